@@ -12,6 +12,9 @@ pub enum DhtCommand {
     FetchEntry(FetchDhtEntryData),
     /// Owner wants us to hold a peer discovery data item.
     HoldPeer(PeerData),
+    /// Owner notifies us that a peer left the space.
+    /// Ignored if we hold a more recent peer discovery data item for it.
+    DropPeer(PeerData),
     /// Owner notifies us that it is holding one or several Aspects for an Entry.
     /// Note: Need an EntryData to know the aspect addresses, but aspects' content can be empty.
     HoldEntryAspectAddress(EntryData),
@@ -45,6 +48,9 @@ pub enum DhtEvent {
     /// Notify owner that we are no longer tracking this entry internally.
     /// Owner should purge this address from storage, but they can, of course, choose not to.
     EntryPruned(Address),
+    /// Notify owner that a `GossipTo` bundle was never acknowledged by its recipient.
+    /// Sent by the gateway owning the DHT, after its last retry.
+    GossipUndelivered(GossipToData),
 }
 
 #[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
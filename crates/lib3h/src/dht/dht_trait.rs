@@ -1,6 +1,7 @@
 use crate::{
     dht::{
         dht_protocol::{DhtCommand, DhtEvent, PeerData},
+        rrdht::StorageArc,
         PeerAddress, PeerAddressRef,
     },
     error::Lib3hResult,
@@ -45,6 +46,12 @@ pub trait Dht {
     /// Entry
     fn get_entry_address_list(&self) -> Vec<&Address>;
     fn get_aspects_of(&self, entry_address: &Address) -> Option<Vec<Address>>;
+    /// Other peers expected to hold an entry
+    fn get_holder_list(&self, entry_address: &Address) -> Vec<PeerData>;
+    /// Portion of the ring this peer holds entries for
+    fn get_storage_arc(&self) -> StorageArc;
+    /// When an aspect was first held locally, in ms since epoch
+    fn get_aspect_hold_time(&self, aspect_address: &Address) -> Option<u64>;
     /// Processing
     fn post(&mut self, cmd: DhtCommand) -> Lib3hResult<()>;
     fn process(&mut self) -> Lib3hResult<(DidWork, Vec<DhtEvent>)>;
@@ -2,6 +2,7 @@ use crate::{
     dht::{
         dht_protocol::*,
         dht_trait::{Dht, DhtConfig},
+        rrdht::{get_location, StorageArc, FULL_ARC_RADIUS},
         PeerAddress, PeerAddressRef,
     },
     error::{ErrorKind, Lib3hError, Lib3hResult},
@@ -21,19 +22,24 @@ type HasTimedOut = bool;
 enum MirrorGossip {
     Entry(EntryData),
     Peer(PeerData),
+    EntryDropped(Address),
 }
 
 /// Mirror DHT implementation: Holds and reflect everything back to other nodes (fullsync)
 ///  - On *HoldRequest, store and gossip data back to every known peer.
-///  - Gossip can only be a *HoldRequest
-///  - Monotonic data
+///  - Gossip is a *HoldRequest or the notice that a peer dropped an entry
+///  - Monotonic data, unless our owner drops an entry
 pub struct MirrorDht {
     /// FIFO of DhtCommands send to us
     inbox: VecDeque<DhtCommand>,
     /// Storage of EntryData with empty aspect content?
     entry_list: HashMap<Address, HashSet<Address>>,
+    /// When each aspect was first held
+    aspect_hold_time_map: HashMap<Address, u64>,
     /// Monotonic Storage of PeerData
     peer_map: HashMap<PeerAddress, PeerData>,
+    /// Peers that told us they dropped an entry
+    dropped_entry_map: HashMap<Address, HashSet<PeerAddress>>,
     /// Track if peer timed out
     timed_out_map: HashMap<PeerAddress, HasTimedOut>,
     /// PeerData of this peer
@@ -58,8 +64,10 @@ impl MirrorDht {
         let this = MirrorDht {
             inbox: VecDeque::new(),
             peer_map: HashMap::new(),
+            dropped_entry_map: HashMap::new(),
             timed_out_map: HashMap::new(),
             entry_list: HashMap::new(),
+            aspect_hold_time_map: HashMap::new(),
             this_peer: PeerData {
                 peer_address: config.this_peer_address.to_owned(),
                 peer_uri: config.this_peer_uri.clone(),
@@ -109,6 +117,30 @@ impl Dht for MirrorDht {
         }
     }
 
+    /// Everybody holds everything, except what they told us they dropped
+    fn get_holder_list(&self, entry_address: &Address) -> Vec<PeerData> {
+        let maybe_dropped_set = self.dropped_entry_map.get(entry_address);
+        self.peer_map
+            .values()
+            .filter(|peer| peer.peer_address != self.this_peer.peer_address)
+            .filter(|peer| {
+                maybe_dropped_set
+                    .map(|dropped_set| !dropped_set.contains(&peer.peer_address))
+                    .unwrap_or(true)
+            })
+            .map(|peer| peer.clone())
+            .collect()
+    }
+
+    /// Everybody holds everything
+    fn get_storage_arc(&self) -> StorageArc {
+        StorageArc::new(get_location(&self.this_peer.peer_address), FULL_ARC_RADIUS)
+    }
+
+    fn get_aspect_hold_time(&self, aspect_address: &Address) -> Option<u64> {
+        self.aspect_hold_time_map.get(aspect_address).cloned()
+    }
+
     // -- Processing -- //
 
     /// Add to inbox
@@ -239,6 +271,8 @@ impl MirrorDht {
                     peer.timestamp,
                 );
                 peer.timestamp = peer_info.timestamp;
+                // Newer info may tell a new uri, e.g. when reached through a relay
+                peer.peer_uri = peer_info.peer_uri.clone();
                 if crate::time::since_epoch_ms() - peer.timestamp < self.config.timeout_threshold {
                     self.timed_out_map
                         .insert(peer_info.peer_address.clone(), false);
@@ -248,6 +282,31 @@ impl MirrorDht {
         }
     }
 
+    /// Forget a peer that left, unless we know of a more recent peer info.
+    /// Return true if it was removed.
+    fn remove_peer(&mut self, peer_info: &PeerData) -> bool {
+        if peer_info.peer_address == self.this_peer.peer_address {
+            return false;
+        }
+        match self.peer_map.get(&peer_info.peer_address) {
+            None => return false,
+            Some(peer) => {
+                // Only a leave newer than the peer's last announcement counts
+                if peer_info.timestamp <= peer.timestamp {
+                    trace!("@MirrorDht@ Removing peer - STALE");
+                    return false;
+                }
+            }
+        }
+        trace!("@MirrorDht@ Removing peer: {:?}", peer_info);
+        self.peer_map.remove(&peer_info.peer_address);
+        self.timed_out_map.remove(&peer_info.peer_address);
+        for dropped_set in self.dropped_entry_map.values_mut() {
+            dropped_set.remove(&peer_info.peer_address);
+        }
+        true
+    }
+
     /// Return aspect addresses diff between
     /// known aspects and aspects in the entry argument
     fn diff_aspects(&self, entry: &EntryData) -> HashSet<Address> {
@@ -277,6 +336,11 @@ impl MirrorDht {
         if diff.len() == 0 {
             return false;
         }
+        let now = time::since_epoch_ms();
+        for aspect_address in diff.iter() {
+            self.aspect_hold_time_map
+                .insert(aspect_address.clone(), now);
+        }
         let maybe_known_aspects = self.entry_list.get(&entry.entry_address);
         let new_aspects: HashSet<_> = match maybe_known_aspects {
             None => diff,
@@ -324,6 +388,12 @@ impl MirrorDht {
                 // Handle gossiped data
                 match maybe_gossip.unwrap() {
                     MirrorGossip::Entry(entry) => {
+                        // Sender holds it (again)
+                        if let Some(dropped_set) =
+                            self.dropped_entry_map.get_mut(&entry.entry_address)
+                        {
+                            dropped_set.remove(&msg.from_peer_address);
+                        }
                         let diff = self.diff_aspects(&entry);
                         if diff.len() > 0 {
                             return Ok(vec![DhtEvent::HoldEntryRequested(
@@ -345,6 +415,13 @@ impl MirrorDht {
                         }
                         return Ok(vec![]);
                     }
+                    MirrorGossip::EntryDropped(entry_address) => {
+                        self.dropped_entry_map
+                            .entry(entry_address)
+                            .or_insert_with(HashSet::new)
+                            .insert(msg.from_peer_address.clone());
+                        return Ok(vec![]);
+                    }
                 }
             }
             // Ask owner to respond to self
@@ -395,6 +472,11 @@ impl MirrorDht {
                 // Done
                 Ok(event_list)
             }
+            // Owner is telling us a peer left the space: stop gossiping with it.
+            DhtCommand::DropPeer(peer_info) => {
+                self.remove_peer(peer_info);
+                Ok(vec![])
+            }
             // Owner is holding some entry. Store its address for bookkeeping.
             // Ask for its data and broadcast it because we want fullsync.
             DhtCommand::HoldEntryAspectAddress(entry) => {
@@ -425,8 +507,30 @@ impl MirrorDht {
                 // Done
                 Ok(vec![gossip_evt])
             }
-            // N/A. Do nothing since this is a monotonic fullsync dht
-            DhtCommand::DropEntryAddress(_) => Ok(vec![]),
+            // Owner stopped holding this entry
+            // Tell everybody so they stop asking us for it.
+            DhtCommand::DropEntryAddress(entry_address) => {
+                let maybe_aspect_address_set = self.entry_list.remove(entry_address);
+                if maybe_aspect_address_set.is_none() {
+                    return Ok(vec![]);
+                }
+                for aspect_address in maybe_aspect_address_set.unwrap() {
+                    self.aspect_hold_time_map.remove(&aspect_address);
+                }
+                let peer_address_list = self.get_other_peer_list();
+                if peer_address_list.is_empty() {
+                    return Ok(vec![]);
+                }
+                let drop_gossip = MirrorGossip::EntryDropped(entry_address.clone());
+                let mut buf = Vec::new();
+                drop_gossip
+                    .serialize(&mut Serializer::new(&mut buf))
+                    .unwrap();
+                Ok(vec![DhtEvent::GossipTo(GossipToData {
+                    peer_address_list,
+                    bundle: buf,
+                })])
+            }
             // EntryDataResponse:
             //   - From a Publish: Forward response back to self
             //   - From a Hold   : Broadcast entry
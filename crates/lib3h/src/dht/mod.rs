@@ -11,7 +11,11 @@ pub type PeerAddressRef = str;
 pub mod tests {
     use crate::{
         dht::{
-            dht_protocol::*, dht_trait::Dht, mirror_dht::MirrorDht, rrdht::RrDht, PeerAddressRef,
+            dht_protocol::*,
+            dht_trait::{Dht, DhtConfig},
+            mirror_dht::MirrorDht,
+            rrdht::*,
+            PeerAddressRef,
         },
         tests::enable_logging_for_test,
     };
@@ -91,7 +95,17 @@ pub mod tests {
         if is_mirror {
             return Box::new(MirrorDht::new(peer_address, &create_test_uri(peer_address)));
         }
-        Box::new(RrDht::new())
+        Box::new(RrDht::new(peer_address, &create_test_uri(peer_address)))
+    }
+
+    fn new_rrdht_with_radius(peer_address: &PeerAddressRef, radius: ArcRadius) -> RrDht {
+        let mut config = DhtConfig::new(peer_address, &create_test_uri(peer_address));
+        config.custom = RrDhtConfig {
+            storage_arc_radius: radius,
+            max_entry_count: 0,
+        }
+        .to_raw();
+        RrDht::new_with_config(&config).unwrap()
     }
 
     #[test]
@@ -153,6 +167,10 @@ pub mod tests {
         let maybe_aspects = dht.get_aspects_of(&ENTRY_ADDRESS_1);
         assert!(maybe_aspects.is_some());
         assert_eq!(maybe_aspects.unwrap().len(), 1);
+        assert!(dht.get_aspect_hold_time(&ASPECT_ADDRESS_1).is_some());
+        // Mirror holds the whole ring
+        let arc = dht.get_storage_arc();
+        assert!(arc.contains(get_location(&ENTRY_ADDRESS_1.to_string())));
         // Fetch it
         let fetch_entry = FetchDhtEntryData {
             msg_id: "fetch_1".to_owned(),
@@ -217,6 +235,40 @@ pub mod tests {
         assert!(peer.timestamp > ref_time);
     }
 
+    #[test]
+    fn test_drop_peer() {
+        enable_logging_for_test(true);
+        for is_mirror in vec![true, false] {
+            let mut dht = new_dht(is_mirror, PEER_A);
+            let peer_b_data = create_PeerData(PEER_B);
+            dht.post(DhtCommand::HoldPeer(peer_b_data.clone())).unwrap();
+            dht.process().unwrap();
+            assert!(dht.get_peer(PEER_B).is_some());
+            // Stale leave notice should be ignored
+            let mut stale_data = peer_b_data.clone();
+            stale_data.timestamp -= 1;
+            dht.post(DhtCommand::DropPeer(stale_data)).unwrap();
+            dht.process().unwrap();
+            assert!(dht.get_peer(PEER_B).is_some());
+            // So should one as old as the held peer info, e.g. a replayed announcement
+            dht.post(DhtCommand::DropPeer(peer_b_data.clone())).unwrap();
+            dht.process().unwrap();
+            assert!(dht.get_peer(PEER_B).is_some());
+            // Newer leave notice should remove it
+            let mut leave_data = peer_b_data.clone();
+            leave_data.timestamp += 1;
+            dht.post(DhtCommand::DropPeer(leave_data)).unwrap();
+            let (_, event_list) = dht.process().unwrap();
+            assert_eq!(event_list.len(), 0);
+            assert!(dht.get_peer(PEER_B).is_none());
+            assert_eq!(dht.get_peer_list().len(), 0);
+            // Can join again
+            dht.post(DhtCommand::HoldPeer(peer_b_data)).unwrap();
+            dht.process().unwrap();
+            assert!(dht.get_peer(PEER_B).is_some());
+        }
+    }
+
     #[test]
     fn test_mirror_broadcast_entry() {
         enable_logging_for_test(true);
@@ -318,4 +370,219 @@ pub mod tests {
         let peer_info = dht_b.get_peer(PEER_C).unwrap();
         assert_eq!(peer_info, peer_c_data);
     }
+
+    #[test]
+    fn test_rrdht_storage_arc() {
+        let arc = StorageArc::new(0, 10);
+        assert!(arc.contains(0));
+        assert!(arc.contains(10));
+        assert!(arc.contains(0xFFFF_FFF6));
+        assert!(!arc.contains(11));
+        assert!(!arc.contains(0xFFFF_FFF5));
+        assert_eq!(ring_distance(0xFFFF_FFFF, 1), 2);
+        let full_arc = StorageArc::new(get_location(PEER_A), FULL_ARC_RADIUS);
+        assert!(full_arc.contains(get_location(PEER_B)));
+        assert!(full_arc.contains(get_location(&ENTRY_ADDRESS_1.to_string())));
+        assert_eq!(get_location(PEER_A), get_location("alex"));
+    }
+
+    #[test]
+    fn test_rrdht_peer_and_entry() {
+        enable_logging_for_test(true);
+        let mut dht = new_dht(false, PEER_A);
+        assert_eq!(dht.this_peer().peer_address, PEER_A);
+        // Add a peer
+        dht.post(DhtCommand::HoldPeer(create_PeerData(PEER_B)))
+            .unwrap();
+        let (did_work, _) = dht.process().unwrap();
+        assert!(did_work);
+        let peer_list = dht.get_peer_list();
+        assert_eq!(peer_list.len(), 1);
+        assert_eq!(peer_list[0].peer_address, PEER_B);
+        // Hold an entry
+        let entry = create_EntryData(&ENTRY_ADDRESS_1, &ASPECT_ADDRESS_1, &ASPECT_CONTENT_1);
+        dht.post(DhtCommand::HoldEntryAspectAddress(entry.clone()))
+            .unwrap();
+        let (_did_work, event_list) = dht.process().unwrap();
+        assert_eq!(event_list.len(), 1);
+        let fetch_entry = unwrap_to!(event_list[0] => DhtEvent::EntryDataRequested);
+        assert_eq!(dht.get_entry_address_list().len(), 1);
+        assert!(dht.get_aspect_hold_time(&ASPECT_ADDRESS_1).is_some());
+        let holder_list = dht.get_holder_list(&ENTRY_ADDRESS_1);
+        assert_eq!(holder_list.len(), 1);
+        assert_eq!(holder_list[0].peer_address, PEER_B);
+        // Providing its data should gossip it to B which has an unknown (full) arc
+        let response = FetchDhtEntryResponseData {
+            msg_id: fetch_entry.msg_id.clone(),
+            entry,
+        };
+        dht.post(DhtCommand::EntryDataResponse(response)).unwrap();
+        let (_did_work, event_list) = dht.process().unwrap();
+        assert_eq!(event_list.len(), 1);
+        let gossip_to = unwrap_to!(event_list[0] => DhtEvent::GossipTo);
+        assert_eq!(gossip_to.peer_address_list, vec![PEER_B.to_string()]);
+        // Drop it: B should be told
+        dht.post(DhtCommand::DropEntryAddress(ENTRY_ADDRESS_1.clone()))
+            .unwrap();
+        let (_did_work, event_list) = dht.process().unwrap();
+        assert_eq!(dht.get_entry_address_list().len(), 0);
+        assert_eq!(dht.get_aspect_hold_time(&ASPECT_ADDRESS_1), None);
+        assert_eq!(event_list.len(), 1);
+        let gossip_to = unwrap_to!(event_list[0] => DhtEvent::GossipTo);
+        assert_eq!(gossip_to.peer_address_list, vec![PEER_B.to_string()]);
+    }
+
+    #[test]
+    fn test_rrdht_shrink_arc_to_fit() {
+        enable_logging_for_test(true);
+        let mut config = DhtConfig::new(PEER_A, &create_test_uri(PEER_A));
+        config.custom = RrDhtConfig {
+            storage_arc_radius: FULL_ARC_RADIUS,
+            max_entry_count: 1,
+        }
+        .to_raw();
+        let mut dht = RrDht::new_with_config(&config).unwrap();
+        let center = dht.this_arc().center;
+        let distance_1 = ring_distance(center, get_location(&ENTRY_ADDRESS_1.to_string()));
+        let distance_2 = ring_distance(center, get_location(&ENTRY_ADDRESS_2.to_string()));
+        assert_ne!(distance_1, distance_2);
+        let (kept, pruned) = if distance_1 < distance_2 {
+            (ENTRY_ADDRESS_1.clone(), ENTRY_ADDRESS_2.clone())
+        } else {
+            (ENTRY_ADDRESS_2.clone(), ENTRY_ADDRESS_1.clone())
+        };
+        // First entry fits
+        let entry = create_EntryData(&ENTRY_ADDRESS_1, &ASPECT_ADDRESS_1, &ASPECT_CONTENT_1);
+        dht.post(DhtCommand::HoldEntryAspectAddress(entry)).unwrap();
+        let (_did_work, event_list) = dht.process().unwrap();
+        assert_eq!(event_list.len(), 1);
+        assert_eq!(dht.this_arc().radius, FULL_ARC_RADIUS);
+        // Second one does not: arc shrinks to only cover the closest one
+        let entry = create_EntryData(&ENTRY_ADDRESS_2, &ASPECT_ADDRESS_2, &ASPECT_CONTENT_2);
+        dht.post(DhtCommand::HoldEntryAspectAddress(entry)).unwrap();
+        let (_did_work, event_list) = dht.process().unwrap();
+        assert!(event_list.contains(&DhtEvent::EntryPruned(pruned.clone())));
+        assert_eq!(dht.get_entry_address_list(), vec![&kept]);
+        assert!(dht.this_arc().contains(get_location(&kept.to_string())));
+        assert!(!dht.this_arc().contains(get_location(&pruned.to_string())));
+    }
+
+    #[test]
+    fn test_mirror_dht_drop_entry() {
+        enable_logging_for_test(true);
+        let mut dht_a = new_dht(true, PEER_A);
+        let mut dht_b = new_dht(true, PEER_B);
+        dht_a
+            .post(DhtCommand::HoldPeer(create_PeerData(PEER_B)))
+            .unwrap();
+        dht_a.process().unwrap();
+        dht_b
+            .post(DhtCommand::HoldPeer(create_PeerData(PEER_A)))
+            .unwrap();
+        dht_b.process().unwrap();
+        assert_eq!(dht_a.get_holder_list(&ENTRY_ADDRESS_1).len(), 1);
+        // B holds then drops an entry
+        let entry = create_EntryData(&ENTRY_ADDRESS_1, &ASPECT_ADDRESS_1, &ASPECT_CONTENT_1);
+        dht_b
+            .post(DhtCommand::HoldEntryAspectAddress(entry))
+            .unwrap();
+        dht_b.process().unwrap();
+        dht_b
+            .post(DhtCommand::DropEntryAddress(ENTRY_ADDRESS_1.clone()))
+            .unwrap();
+        let (_did_work, event_list) = dht_b.process().unwrap();
+        assert_eq!(dht_b.get_entry_address_list().len(), 0);
+        assert_eq!(event_list.len(), 1);
+        let gossip_to = unwrap_to!(event_list[0] => DhtEvent::GossipTo);
+        assert_eq!(gossip_to.peer_address_list, vec![PEER_A.to_string()]);
+        // A should not expect B to hold it anymore
+        let remote_gossip = RemoteGossipBundleData {
+            from_peer_address: PEER_B.to_owned(),
+            bundle: gossip_to.bundle.clone(),
+        };
+        dht_a.post(DhtCommand::HandleGossip(remote_gossip)).unwrap();
+        dht_a.process().unwrap();
+        assert!(dht_a.get_holder_list(&ENTRY_ADDRESS_1).is_empty());
+    }
+
+    #[test]
+    fn test_rrdht_entry_outside_arc() {
+        enable_logging_for_test(true);
+        // B only covers its own location
+        let mut dht_a = new_rrdht_with_radius(PEER_A, FULL_ARC_RADIUS);
+        let mut dht_b = new_rrdht_with_radius(PEER_B, 0);
+        assert_ne!(
+            get_location(&ENTRY_ADDRESS_1.to_string()),
+            dht_b.this_arc().center
+        );
+        // B learns about A and gossips back its arc
+        dht_b
+            .post(DhtCommand::HoldPeer(create_PeerData(PEER_A)))
+            .unwrap();
+        let (_did_work, gossip_list) = dht_b.process().unwrap();
+        assert_eq!(gossip_list.len(), 2);
+        let gossip_to = unwrap_to!(gossip_list[1] => DhtEvent::GossipTo);
+        let remote_gossip = RemoteGossipBundleData {
+            from_peer_address: PEER_B.to_owned(),
+            bundle: gossip_to.bundle.clone(),
+        };
+        dht_a.post(DhtCommand::HandleGossip(remote_gossip)).unwrap();
+        let (_did_work, event_list) = dht_a.process().unwrap();
+        assert_eq!(event_list.len(), 1);
+        let peer_b_data = unwrap_to!(event_list[0] => DhtEvent::HoldPeerRequested);
+        dht_a
+            .post(DhtCommand::HoldPeer(peer_b_data.clone()))
+            .unwrap();
+        dht_a.process().unwrap();
+        // The arc gossiped along an unknown peer is not trusted: assume full arc
+        assert_eq!(dht_a.get_peer_arc(PEER_B).unwrap().radius, FULL_ARC_RADIUS);
+        // B hears of a newer A and gossips back its arc with a newer timestamp
+        let mut newer_peer_a = create_PeerData(PEER_A);
+        newer_peer_a.timestamp += 1;
+        dht_b.post(DhtCommand::HoldPeer(newer_peer_a)).unwrap();
+        let (_did_work, gossip_list) = dht_b.process().unwrap();
+        let gossip_to = unwrap_to!(gossip_list[1] => DhtEvent::GossipTo);
+        let remote_gossip = RemoteGossipBundleData {
+            from_peer_address: PEER_B.to_owned(),
+            bundle: gossip_to.bundle.clone(),
+        };
+        dht_a
+            .post(DhtCommand::HandleGossip(remote_gossip.clone()))
+            .unwrap();
+        dht_a.process().unwrap();
+        assert_eq!(dht_a.get_peer_arc(PEER_B), Some(dht_b.this_arc()));
+        // Replaying that gossip does not count as news
+        dht_a.post(DhtCommand::HandleGossip(remote_gossip)).unwrap();
+        let (_did_work, event_list) = dht_a.process().unwrap();
+        assert_eq!(event_list.len(), 0);
+        // B is not a holder of an entry outside its arc
+        assert!(dht_a.get_holder_list(&ENTRY_ADDRESS_1).is_empty());
+        // A should not gossip an entry outside of B's arc
+        let entry_data = create_EntryData(&ENTRY_ADDRESS_1, &ASPECT_ADDRESS_1, &ASPECT_CONTENT_1);
+        dht_a
+            .post(DhtCommand::BroadcastEntry(entry_data.clone()))
+            .unwrap();
+        let (did_work, event_list) = dht_a.process().unwrap();
+        assert!(did_work);
+        assert_eq!(event_list.len(), 0);
+        assert_eq!(dht_a.get_entry_address_list().len(), 1);
+        // B should ignore gossip of an entry outside of its arc
+        let mut dht_c = new_dht(false, PEER_C);
+        dht_c
+            .post(DhtCommand::HoldPeer(create_PeerData(PEER_B)))
+            .unwrap();
+        dht_c.process().unwrap();
+        dht_c.post(DhtCommand::BroadcastEntry(entry_data)).unwrap();
+        let (_did_work, event_list) = dht_c.process().unwrap();
+        assert_eq!(event_list.len(), 1);
+        let gossip_to = unwrap_to!(event_list[0] => DhtEvent::GossipTo);
+        let remote_gossip = RemoteGossipBundleData {
+            from_peer_address: PEER_C.to_owned(),
+            bundle: gossip_to.bundle.clone(),
+        };
+        dht_b.post(DhtCommand::HandleGossip(remote_gossip)).unwrap();
+        let (did_work, event_list) = dht_b.process().unwrap();
+        assert!(did_work);
+        assert_eq!(event_list.len(), 0);
+    }
 }
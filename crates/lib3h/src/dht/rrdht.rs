@@ -1,48 +1,206 @@
 use crate::{
-    dht::{dht_protocol::*, dht_trait::Dht, PeerAddressRef},
-    error::Lib3hResult,
+    dht::{
+        dht_protocol::*,
+        dht_trait::{Dht, DhtConfig},
+        PeerAddress, PeerAddressRef,
+    },
+    error::{ErrorKind, Lib3hError, Lib3hResult},
+    time,
 };
-use lib3h_protocol::{Address, DidWork};
-use std::collections::VecDeque;
+use lib3h_protocol::{data_types::EntryData, Address, DidWork};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use rmp_serde::{Deserializer, Serializer};
+use serde::{Deserialize, Serialize};
 use url::Url;
 
-/// RedRibbon DHT implementation
-/// TODO #167
+type HasTimedOut = bool;
+
+/// A position on the 32-bit DHT ring
+pub type Location = u32;
+/// Distance from a peer's location within which it holds entries
+pub type ArcRadius = u32;
+
+/// Radius of an arc covering the whole ring
+pub const FULL_ARC_RADIUS: ArcRadius = 0x8000_0000;
+
+/// Compute the location on the ring of a peer or entry address (32-bit FNV-1a).
+/// Must stay stable across nodes and versions since every peer needs to agree on it.
+pub fn get_location(address: &str) -> Location {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in address.as_bytes() {
+        hash ^= u32::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash
+}
+
+/// Shortest distance between two locations on the ring
+pub fn ring_distance(a: Location, b: Location) -> ArcRadius {
+    std::cmp::min(a.wrapping_sub(b), b.wrapping_sub(a))
+}
+
+/// Portion of the ring a peer claims to hold entries for
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct StorageArc {
+    pub center: Location,
+    pub radius: ArcRadius,
+}
+
+impl StorageArc {
+    pub fn new(center: Location, radius: ArcRadius) -> Self {
+        StorageArc { center, radius }
+    }
+
+    pub fn contains(&self, location: Location) -> bool {
+        ring_distance(self.center, location) <= self.radius
+    }
+}
+
+/// RrDht specific settings, serialized in `DhtConfig::custom`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RrDhtConfig {
+    /// Radius of the storage arc this peer claims around its own location
+    pub storage_arc_radius: ArcRadius,
+    /// Maximum number of entries to hold, 0 for no limit.
+    /// The storage arc shrinks to fit and the entries left outside are pruned.
+    #[serde(default)]
+    pub max_entry_count: usize,
+}
+
+impl Default for RrDhtConfig {
+    fn default() -> Self {
+        RrDhtConfig {
+            storage_arc_radius: FULL_ARC_RADIUS,
+            max_entry_count: 0,
+        }
+    }
+}
+
+impl RrDhtConfig {
+    /// Empty raw config means default config
+    pub fn from_raw(raw: &[u8]) -> Lib3hResult<Self> {
+        if raw.is_empty() {
+            return Ok(RrDhtConfig::default());
+        }
+        let mut de = Deserializer::new(raw);
+        let maybe_config: Result<RrDhtConfig, rmp_serde::decode::Error> =
+            Deserialize::deserialize(&mut de);
+        maybe_config.map_err(|e| Lib3hError::new(ErrorKind::RmpSerdeDecodeError(e)))
+    }
+
+    pub fn to_raw(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.serialize(&mut Serializer::new(&mut buf)).unwrap();
+        buf
+    }
+}
+
+/// Enum holding all types of gossip messages used by RrDht
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+enum RrGossip {
+    Entry(EntryData),
+    Peer(PeerData, ArcRadius),
+    EntryDropped(Address),
+}
+
+/// RedRibbon DHT implementation: Sharded DHT on a 32-bit location ring
+///  - Each peer claims a storage arc centered on the location of its address.
+///  - Entries are only held and gossiped to peers whose arc covers the entry's location.
+///  - Peers of unknown arc are assumed to hold everything until they gossip their arc.
+///  - Peers gossip the entries they drop so they are not asked for them anymore.
+///  - Peer info is fully replicated.
 pub struct RrDht {
     /// FIFO of DhtCommands send to us
     inbox: VecDeque<DhtCommand>,
-    ///
+    /// Aspect addresses of the entries we hold
+    entry_list: HashMap<Address, HashSet<Address>>,
+    /// When each aspect was first held
+    aspect_hold_time_map: HashMap<Address, u64>,
+    /// Monotonic Storage of PeerData
+    peer_map: HashMap<PeerAddress, PeerData>,
+    /// Storage arc radius claimed by each known peer
+    peer_arc_map: HashMap<PeerAddress, ArcRadius>,
+    /// Peers that told us they dropped an entry
+    dropped_entry_map: HashMap<Address, HashSet<PeerAddress>>,
+    /// Track if peer timed out
+    timed_out_map: HashMap<PeerAddress, HasTimedOut>,
+    /// PeerData of this peer
     this_peer: PeerData,
+    /// Storage arc claimed by this peer
+    this_arc: StorageArc,
+    /// Maximum number of entries to hold, 0 for no limit
+    max_entry_count: usize,
+    /// Keep track of fetch requests sent to Core
+    pending_fetch_request_list: HashSet<String>,
+    /// Keep track of last time this peer gossiped self to others
+    last_gossip_of_self: u64,
+    /// Store Dht config used by this peer
+    config: DhtConfig,
 }
 
+/// Constructors
 impl RrDht {
-    pub fn new() -> Self {
-        RrDht {
+    pub fn new(this_peer_address: &PeerAddressRef, this_peer_uri: &Url) -> Self {
+        let dht_config = DhtConfig::new(this_peer_address, this_peer_uri);
+        Self::new_with_config(&dht_config).expect("Failed creating default RrDht")
+    }
+
+    pub fn new_with_config(config: &DhtConfig) -> Lib3hResult<Self> {
+        let rr_config = RrDhtConfig::from_raw(&config.custom)?;
+        let timestamp = time::since_epoch_ms();
+        let this = RrDht {
             inbox: VecDeque::new(),
+            entry_list: HashMap::new(),
+            aspect_hold_time_map: HashMap::new(),
+            peer_map: HashMap::new(),
+            peer_arc_map: HashMap::new(),
+            dropped_entry_map: HashMap::new(),
+            timed_out_map: HashMap::new(),
             this_peer: PeerData {
-                peer_address: "FIXME".to_string(),
-                peer_uri: Url::parse("fixme://host:123").expect("a valid transport url"),
-                timestamp: 0, // TODO #166
+                peer_address: config.this_peer_address.to_owned(),
+                peer_uri: config.this_peer_uri.clone(),
+                timestamp,
             },
-        }
+            this_arc: StorageArc::new(
+                get_location(&config.this_peer_address),
+                rr_config.storage_arc_radius,
+            ),
+            max_entry_count: rr_config.max_entry_count,
+            pending_fetch_request_list: HashSet::new(),
+            last_gossip_of_self: timestamp,
+            config: config.clone(),
+        };
+        Ok(this)
     }
+}
 
-    pub fn new_with_raw_config(_config: &[u8]) -> Lib3hResult<Self> {
-        Ok(Self::new())
+/// Arc queries
+impl RrDht {
+    /// Storage arc claimed by this peer
+    pub fn this_arc(&self) -> StorageArc {
+        self.this_arc
+    }
+
+    /// Storage arc claimed by a known peer
+    pub fn get_peer_arc(&self, peer_address: &PeerAddressRef) -> Option<StorageArc> {
+        if !self.peer_map.contains_key(peer_address) {
+            return None;
+        }
+        Some(self.peer_arc(peer_address))
     }
 }
 
+/// Impl Dht interface
 impl Dht for RrDht {
     // -- Peer info -- //
 
     fn get_peer_list(&self) -> Vec<PeerData> {
-        // FIXME
-        vec![]
+        self.peer_map.values().map(|v| v.clone()).collect()
     }
 
-    fn get_peer(&self, _peer_address: &PeerAddressRef) -> Option<PeerData> {
-        // FIXME
-        None
+    fn get_peer(&self, peer_address: &PeerAddressRef) -> Option<PeerData> {
+        self.peer_map.get(peer_address).map(|pd| pd.clone())
     }
 
     fn this_peer(&self) -> &PeerData {
@@ -52,32 +210,506 @@ impl Dht for RrDht {
     // -- Entry -- //
 
     fn get_entry_address_list(&self) -> Vec<&Address> {
-        // FIXME
-        vec![]
+        self.entry_list.iter().map(|kv| kv.0).collect()
     }
-    fn get_aspects_of(&self, _entry_address: &Address) -> Option<Vec<Address>> {
-        // FIXME
-        None
+
+    fn get_aspects_of(&self, entry_address: &Address) -> Option<Vec<Address>> {
+        self.entry_list
+            .get(entry_address)
+            .map(|set| set.iter().map(|addr| addr.clone()).collect())
+    }
+
+    /// Peers whose storage arc covers the entry's location and did not drop it
+    fn get_holder_list(&self, entry_address: &Address) -> Vec<PeerData> {
+        let location = get_location(&entry_address.to_string());
+        let maybe_dropped_set = self.dropped_entry_map.get(entry_address);
+        self.get_covering_peer_list(location)
+            .iter()
+            .filter(|address| {
+                maybe_dropped_set
+                    .map(|dropped_set| !dropped_set.contains(*address))
+                    .unwrap_or(true)
+            })
+            .filter_map(|address| self.peer_map.get(address))
+            .map(|peer| peer.clone())
+            .collect()
     }
+
+    fn get_storage_arc(&self) -> StorageArc {
+        self.this_arc
+    }
+
+    fn get_aspect_hold_time(&self, aspect_address: &Address) -> Option<u64> {
+        self.aspect_hold_time_map.get(aspect_address).cloned()
+    }
+
     // -- Processing -- //
 
+    /// Add to inbox
     fn post(&mut self, cmd: DhtCommand) -> Lib3hResult<()> {
         self.inbox.push_back(cmd);
         Ok(())
     }
 
+    /// Serve each item in inbox
     fn process(&mut self) -> Lib3hResult<(DidWork, Vec<DhtEvent>)> {
-        let outbox = Vec::new();
+        let now = time::since_epoch_ms();
+        let mut outbox = Vec::new();
+        // Process inbox
         let mut did_work = false;
         loop {
             let cmd = match self.inbox.pop_front() {
                 None => break,
                 Some(msg) => msg,
             };
-            did_work = true;
-            trace!("RrDht.process(): {:?}", cmd)
-            // FIXME
+            let res = self.serve_DhtCommand(&cmd);
+            if let Ok(mut output) = res {
+                did_work = true;
+                outbox.append(&mut output);
+            } else {
+                error!("serve_DhtCommand() failed: {:?}", res);
+            }
         }
+        // Check if others timed-out
+        let mut timed_out_list = Vec::new();
+        for (peer_address, peer) in self.peer_map.iter() {
+            // Skip self
+            if peer_address == &self.this_peer.peer_address {
+                continue;
+            }
+            // Skip already timed out
+            let has_timed_out = self
+                .timed_out_map
+                .get(peer_address)
+                .expect("Should always have time_out value for a peer");
+            if *has_timed_out {
+                continue;
+            }
+            // Check if timed-out
+            if now.saturating_sub(peer.timestamp) > self.config.timeout_threshold {
+                debug!("@RrDht@ peer {} timed-out", peer_address);
+                outbox.push(DhtEvent::PeerTimedOut(peer_address.clone()));
+                timed_out_list.push(peer_address.clone());
+                did_work = true;
+            }
+        }
+        // Mark peers that timed out
+        for peer_address in timed_out_list {
+            self.timed_out_map.insert(peer_address, true);
+        }
+        // Check if must gossip self
+        if now - self.last_gossip_of_self > self.config.gossip_interval {
+            self.last_gossip_of_self = now;
+            let gossip_data = self.gossip_self(self.get_other_peer_list());
+            if gossip_data.peer_address_list.len() > 0 {
+                outbox.push(DhtEvent::GossipTo(gossip_data));
+                did_work = true;
+            }
+        }
+        // Done
         Ok((did_work, outbox))
     }
 }
+
+/// Internals
+impl RrDht {
+    // Get all known peers except self
+    fn get_other_peer_list(&self) -> Vec<PeerAddress> {
+        self.peer_map
+            .iter()
+            .filter(|(address, _)| *address != &self.this_peer.peer_address)
+            .map(|(address, _)| address.clone())
+            .collect()
+    }
+
+    /// Storage arc of a peer. Assume full arc if it did not tell us yet.
+    fn peer_arc(&self, peer_address: &PeerAddressRef) -> StorageArc {
+        let radius = self
+            .peer_arc_map
+            .get(peer_address)
+            .cloned()
+            .unwrap_or(FULL_ARC_RADIUS);
+        StorageArc::new(get_location(peer_address), radius)
+    }
+
+    // Get all known peers except self whose storage arc covers the location
+    fn get_covering_peer_list(&self, location: Location) -> Vec<PeerAddress> {
+        self.get_other_peer_list()
+            .into_iter()
+            .filter(|address| self.peer_arc(address).contains(location))
+            .collect()
+    }
+
+    /// Serialize a gossip message into a GossipToData
+    fn create_gossip(gossip: &RrGossip, peer_address_list: Vec<PeerAddress>) -> GossipToData {
+        let mut buf = Vec::new();
+        gossip.serialize(&mut Serializer::new(&mut buf)).unwrap();
+        GossipToData {
+            peer_address_list,
+            bundle: buf,
+        }
+    }
+
+    // Create gossipTo event of your own PeerData and arc (but not to yourself).
+    // Refresh our timestamp so peers accept it as newer than what they hold.
+    fn gossip_self(&mut self, peer_address_list: Vec<PeerAddress>) -> GossipToData {
+        self.this_peer.timestamp =
+            std::cmp::max(time::since_epoch_ms(), self.this_peer.timestamp + 1);
+        trace!(
+            "@RrDht@ gossip_self: {:?} {:?} | to: {:?}",
+            self.this_peer,
+            self.this_arc,
+            peer_address_list,
+        );
+        let gossip = RrGossip::Peer(self.this_peer.clone(), self.this_arc.radius);
+        Self::create_gossip(&gossip, peer_address_list)
+    }
+
+    /// Return true if new peer or updated peer
+    fn add_peer(&mut self, peer_info: &PeerData) -> bool {
+        trace!("@RrDht@ Adding peer: {:?}", peer_info);
+        let maybe_peer = self.peer_map.get_mut(&peer_info.peer_address);
+        match maybe_peer {
+            None => {
+                trace!("@RrDht@ Adding peer - OK NEW");
+                self.peer_map
+                    .insert(peer_info.peer_address.clone(), peer_info.clone());
+                self.timed_out_map
+                    .insert(peer_info.peer_address.clone(), false);
+                true
+            }
+            Some(mut peer) => {
+                if peer_info.timestamp <= peer.timestamp {
+                    trace!("@RrDht@ Adding peer - BAD");
+                    return false;
+                }
+                trace!(
+                    "@RrDht@ Adding peer - OK UPDATED: {} > {}",
+                    peer_info.timestamp,
+                    peer.timestamp,
+                );
+                peer.timestamp = peer_info.timestamp;
+                // Newer info may tell a new uri, e.g. when reached through a relay
+                peer.peer_uri = peer_info.peer_uri.clone();
+                if time::since_epoch_ms().saturating_sub(peer.timestamp)
+                    < self.config.timeout_threshold
+                {
+                    self.timed_out_map
+                        .insert(peer_info.peer_address.clone(), false);
+                }
+                true
+            }
+        }
+    }
+
+    /// Forget a peer that left, unless we know of a more recent peer info.
+    /// Return true if it was removed.
+    fn remove_peer(&mut self, peer_info: &PeerData) -> bool {
+        if peer_info.peer_address == self.this_peer.peer_address {
+            return false;
+        }
+        match self.peer_map.get(&peer_info.peer_address) {
+            None => return false,
+            Some(peer) => {
+                // Only a leave newer than the peer's last announcement counts
+                if peer_info.timestamp <= peer.timestamp {
+                    trace!("@RrDht@ Removing peer - STALE");
+                    return false;
+                }
+            }
+        }
+        trace!("@RrDht@ Removing peer: {:?}", peer_info);
+        self.peer_map.remove(&peer_info.peer_address);
+        self.timed_out_map.remove(&peer_info.peer_address);
+        self.peer_arc_map.remove(&peer_info.peer_address);
+        for dropped_set in self.dropped_entry_map.values_mut() {
+            dropped_set.remove(&peer_info.peer_address);
+        }
+        true
+    }
+
+    /// Return aspect addresses diff between
+    /// known aspects and aspects in the entry argument
+    fn diff_aspects(&self, entry: &EntryData) -> HashSet<Address> {
+        let aspect_address_set: HashSet<_> = entry
+            .aspect_list
+            .iter()
+            .map(|aspect| aspect.aspect_address.clone())
+            .collect();
+        match self.entry_list.get(&entry.entry_address) {
+            None => aspect_address_set,
+            Some(held_aspects) => aspect_address_set
+                .difference(held_aspects)
+                .map(|item| item.clone())
+                .collect(),
+        }
+    }
+
+    /// Add aspect addresses for an entry in our local storage.
+    /// Return true if at least one new aspect address was added.
+    fn add_entry_aspects(&mut self, entry: &EntryData) -> bool {
+        let diff = self.diff_aspects(&entry);
+        if diff.len() == 0 {
+            return false;
+        }
+        let now = time::since_epoch_ms();
+        for aspect_address in diff.iter() {
+            self.aspect_hold_time_map
+                .insert(aspect_address.clone(), now);
+        }
+        self.entry_list
+            .entry(entry.entry_address.clone())
+            .or_insert_with(HashSet::new)
+            .extend(diff);
+        true
+    }
+
+    /// Remove an entry from our local storage.
+    /// Return true if we were holding it.
+    fn remove_entry(&mut self, entry_address: &Address) -> bool {
+        match self.entry_list.remove(entry_address) {
+            None => false,
+            Some(aspect_address_set) => {
+                for aspect_address in aspect_address_set {
+                    self.aspect_hold_time_map.remove(&aspect_address);
+                }
+                true
+            }
+        }
+    }
+
+    /// Shrink our storage arc until we hold no more than `max_entry_count` entries.
+    /// Prune the entries left outside and gossip our new arc to the other peers.
+    fn shrink_arc_to_fit(&mut self) -> Vec<DhtEvent> {
+        if self.max_entry_count == 0 || self.entry_list.len() <= self.max_entry_count {
+            return Vec::new();
+        }
+        let center = self.this_arc.center;
+        let mut distance_list: Vec<ArcRadius> = self
+            .entry_list
+            .keys()
+            .map(|address| ring_distance(center, get_location(&address.to_string())))
+            .collect();
+        distance_list.sort();
+        // Only keep entries closer than the first one in excess
+        self.this_arc.radius = distance_list[self.max_entry_count].saturating_sub(1);
+        debug!("@RrDht@ storage arc shrunk to {:?}", self.this_arc);
+        let pruned_list: Vec<Address> = self
+            .entry_list
+            .keys()
+            .filter(|address| !self.this_arc.contains(get_location(&address.to_string())))
+            .cloned()
+            .collect();
+        let mut event_list = Vec::new();
+        for entry_address in pruned_list {
+            self.remove_entry(&entry_address);
+            event_list.push(DhtEvent::EntryPruned(entry_address));
+        }
+        let gossip_data = self.gossip_self(self.get_other_peer_list());
+        if gossip_data.peer_address_list.len() > 0 {
+            event_list.push(DhtEvent::GossipTo(gossip_data));
+        }
+        event_list
+    }
+
+    /// Create GossipTo event for entry to all known peers covering its location.
+    /// Return None if no such peer is known.
+    fn gossip_entry(&self, entry: &EntryData) -> Option<DhtEvent> {
+        let location = get_location(&entry.entry_address.to_string());
+        let peer_address_list = self.get_covering_peer_list(location);
+        trace!(
+            "@RrDht@ gossip_entry: {} at {:#010x} | to: {:?}",
+            entry.entry_address,
+            location,
+            peer_address_list,
+        );
+        if peer_address_list.is_empty() {
+            return None;
+        }
+        let gossip = RrGossip::Entry(entry.clone());
+        Some(DhtEvent::GossipTo(Self::create_gossip(
+            &gossip,
+            peer_address_list,
+        )))
+    }
+
+    /// Process a DhtEvent Command, sent by our owner.
+    /// Return a list of DhtEvent to owner.
+    #[allow(non_snake_case)]
+    fn serve_DhtCommand(&mut self, cmd: &DhtCommand) -> Lib3hResult<Vec<DhtEvent>> {
+        debug!("@RrDht@ serving cmd: {:?}", cmd);
+        // Note: use same order as the enum
+        match cmd {
+            // Received gossip from remote node. Bundle must be a serialized RrGossip
+            DhtCommand::HandleGossip(msg) => {
+                let mut de = Deserializer::new(&msg.bundle[..]);
+                let maybe_gossip: Result<RrGossip, rmp_serde::decode::Error> =
+                    Deserialize::deserialize(&mut de);
+                let gossip = match maybe_gossip {
+                    Err(e) => {
+                        error!("Failed to deserialize gossip.");
+                        return Err(Lib3hError::new(ErrorKind::RmpSerdeDecodeError(e)));
+                    }
+                    Ok(gossip) => gossip,
+                };
+                // Handle gossiped data
+                match gossip {
+                    RrGossip::Entry(entry) => {
+                        // Sender holds it (again)
+                        if let Some(dropped_set) =
+                            self.dropped_entry_map.get_mut(&entry.entry_address)
+                        {
+                            dropped_set.remove(&msg.from_peer_address);
+                        }
+                        // Ignore entries outside of our arc
+                        let location = get_location(&entry.entry_address.to_string());
+                        if !self.this_arc.contains(location) {
+                            trace!(
+                                "@RrDht@ entry {} not in arc {:?}",
+                                entry.entry_address,
+                                self.this_arc,
+                            );
+                            return Ok(vec![]);
+                        }
+                        if self.diff_aspects(&entry).len() > 0 {
+                            return Ok(vec![DhtEvent::HoldEntryRequested(
+                                self.this_peer.peer_address.clone(),
+                                entry,
+                            )]);
+                        }
+                        Ok(vec![])
+                    }
+                    RrGossip::Peer(gossiped_peer, radius) => {
+                        match self.get_peer(&gossiped_peer.peer_address) {
+                            None => Ok(vec![DhtEvent::HoldPeerRequested(gossiped_peer)]),
+                            Some(known_peer) => {
+                                // Only newer info may update the peer and its arc
+                                if gossiped_peer.timestamp > known_peer.timestamp {
+                                    self.peer_arc_map
+                                        .insert(gossiped_peer.peer_address.clone(), radius);
+                                    let _ = self.add_peer(&gossiped_peer);
+                                }
+                                Ok(vec![])
+                            }
+                        }
+                    }
+                    RrGossip::EntryDropped(entry_address) => {
+                        self.dropped_entry_map
+                            .entry(entry_address)
+                            .or_insert_with(HashSet::new)
+                            .insert(msg.from_peer_address.clone());
+                        Ok(vec![])
+                    }
+                }
+            }
+            // Ask owner to respond to self
+            DhtCommand::FetchEntry(fetch_entry) => {
+                self.pending_fetch_request_list
+                    .insert(fetch_entry.msg_id.clone());
+                Ok(vec![DhtEvent::EntryDataRequested(fetch_entry.clone())])
+            }
+            // Owner is asking us to hold a peer info
+            DhtCommand::HoldPeer(new_peer_data) => {
+                // Get peer_list before adding new peer (to use when doing gossipTo)
+                let others_list = self.get_other_peer_list();
+                // Store it
+                let received_new_content = self.add_peer(new_peer_data);
+                // Bail if peer is known and up to date.
+                if !received_new_content {
+                    return Ok(vec![]);
+                }
+                let mut event_list = Vec::new();
+                // Gossip to everyone to also hold it
+                let peer = self
+                    .peer_map
+                    .get(&new_peer_data.peer_address)
+                    .expect("Should have peer by now");
+                let radius = self.peer_arc(&peer.peer_address).radius;
+                let peer_gossip = RrGossip::Peer(peer.clone(), radius);
+                trace!("@RrDht@ gossiping peer: {:?} to {:?}", peer, others_list);
+                event_list.push(DhtEvent::GossipTo(Self::create_gossip(
+                    &peer_gossip,
+                    others_list,
+                )));
+                // Gossip back your own PeerData (but not to yourself)
+                if new_peer_data.peer_address != self.this_peer.peer_address {
+                    let gossip_data = self.gossip_self(vec![new_peer_data.peer_address.clone()]);
+                    event_list.push(DhtEvent::GossipTo(gossip_data));
+                }
+                // Done
+                Ok(event_list)
+            }
+            // Owner is telling us a peer left the space: stop gossiping with it.
+            DhtCommand::DropPeer(peer_info) => {
+                self.remove_peer(peer_info);
+                Ok(vec![])
+            }
+            // Owner is holding some entry. Store its address for bookkeeping.
+            // Ask for its data so we can gossip it to the peers covering it.
+            DhtCommand::HoldEntryAspectAddress(entry) => {
+                let received_new_content = self.add_entry_aspects(&entry);
+                if !received_new_content {
+                    return Ok(vec![]);
+                }
+                let mut event_list = self.shrink_arc_to_fit();
+                // Bail if it did not fit
+                if !self.entry_list.contains_key(&entry.entry_address) {
+                    return Ok(event_list);
+                }
+                // Use entry_address as request_id
+                let address_str = entry.entry_address.to_string();
+                self.pending_fetch_request_list.insert(address_str.clone());
+                let fetch_entry = FetchDhtEntryData {
+                    msg_id: address_str,
+                    entry_address: entry.entry_address.to_owned(),
+                };
+                event_list.insert(0, DhtEvent::EntryDataRequested(fetch_entry));
+                Ok(event_list)
+            }
+            // Owner has some entry and wants it stored on the network
+            // Bookkeep address and gossip entry to every peer covering it.
+            DhtCommand::BroadcastEntry(entry) => {
+                let received_new_content = self.add_entry_aspects(&entry);
+                if !received_new_content {
+                    return Ok(vec![]);
+                }
+                let mut event_list: Vec<DhtEvent> = self.gossip_entry(entry).into_iter().collect();
+                event_list.append(&mut self.shrink_arc_to_fit());
+                Ok(event_list)
+            }
+            // Owner stopped holding this entry
+            // Tell the peers covering it so they stop asking us for it.
+            DhtCommand::DropEntryAddress(entry_address) => {
+                if !self.remove_entry(entry_address) {
+                    return Ok(vec![]);
+                }
+                let location = get_location(&entry_address.to_string());
+                let peer_address_list = self.get_covering_peer_list(location);
+                if peer_address_list.is_empty() {
+                    return Ok(vec![]);
+                }
+                let gossip = RrGossip::EntryDropped(entry_address.clone());
+                Ok(vec![DhtEvent::GossipTo(Self::create_gossip(
+                    &gossip,
+                    peer_address_list,
+                ))])
+            }
+            // EntryDataResponse:
+            //   - From a Publish: Forward response back to self
+            //   - From a Hold   : Gossip entry to covering peers
+            DhtCommand::EntryDataResponse(response) => {
+                if !self.pending_fetch_request_list.remove(&response.msg_id) {
+                    return Err(Lib3hError::new(ErrorKind::Other(String::from(
+                        "Received response for an unknown request",
+                    ))));
+                }
+                // From a Hold if msg_id matches one set in HoldEntryAspectAddress
+                let address_str: String = response.entry.entry_address.to_string();
+                if address_str == response.msg_id {
+                    return Ok(self.gossip_entry(&response.entry).into_iter().collect());
+                }
+                Ok(vec![DhtEvent::FetchEntryResponse(response.clone())])
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+#![allow(non_snake_case)]
+
+use crate::{
+    dht::{
+        dht_trait::Dht,
+        rrdht::{get_location, StorageArc},
+    },
+    engine::{p2p_protocol::*, ChainId, GspArcSync, RealEngine, RealEngineTrackerData},
+    gateway::{multiplex::ChannelKey, GatewayWrapper},
+    time,
+};
+use lib3h_protocol::Address;
+
+/// Arc gossip related private methods:
+/// Every `gsp_arc_interval`, each of our agents exchanges with one of its peers
+/// the aspect addresses they hold in their overlapping arcs since their last exchange,
+/// then requests the aspects it misses with a MsgGspAspectDataRequest.
+/// Each side only compares hold times with its own clock: the responder filters with the
+/// sync cursor it sent in its previous response, echoed back in `aspect_constraint_time`.
+impl<'engine, D: Dht> RealEngine<'engine, D> {
+    /// Start an arc gossip with a peer of every joined space, if it is time to.
+    pub(crate) fn process_gsp_arc(&mut self) {
+        if self.config.gsp_arc_interval == 0 {
+            return;
+        }
+        let now = time::since_epoch_ms();
+        if now.saturating_sub(self.last_gsp_arc_ms) < self.config.gsp_arc_interval {
+            return;
+        }
+        self.last_gsp_arc_ms = now;
+        let space_gateway_list: Vec<(ChainId, GatewayWrapper<'engine>)> = self
+            .space_gateway_map
+            .iter()
+            .map(|(chain_id, space_gateway)| (chain_id.clone(), space_gateway.clone()))
+            .collect();
+        for (chain_id, space_gateway) in space_gateway_list {
+            // Pick the peer we gossiped with least recently
+            let maybe_peer_agent_id = space_gateway
+                .as_dht_ref()
+                .get_peer_list()
+                .into_iter()
+                .map(|peer| Address::from(peer.peer_address))
+                .filter(|peer_agent_id| *peer_agent_id != chain_id.1)
+                .min_by_key(|peer_agent_id| {
+                    self.gsp_arc_sync_map
+                        .get(&(chain_id.clone(), peer_agent_id.clone()))
+                        .map(|sync| sync.last_sync_ms)
+                        .unwrap_or(0)
+                });
+            let peer_agent_id = match maybe_peer_agent_id {
+                Some(peer_agent_id) => peer_agent_id,
+                None => continue,
+            };
+            let sync = self
+                .gsp_arc_sync_map
+                .entry((chain_id.clone(), peer_agent_id.clone()))
+                .or_insert_with(GspArcSync::default);
+            // A previous unanswered gossip is superseded by this one
+            sync.pending_since_ms = Some(now);
+            let since_ms = sync.last_sync_ms;
+            let peer_cursor_ms = sync.peer_cursor_ms;
+            let our_arc = space_gateway.as_dht_ref().get_storage_arc();
+            let request = MsgGspArc {
+                aspect_constraint_arc: arc_to_wire(&our_arc),
+                aspect_constraint_time: AspectConstraintTime {
+                    gte_epoch_ms: peer_cursor_ms,
+                },
+                // Note: local store counts are not tracked
+                aspect_constraint_count: AspectConstraintCount { gte_local_count: 0 },
+                aspect_hash_list: aspect_hash_list_in_arc(&space_gateway, &our_arc, since_ms),
+            };
+            trace!(
+                "{} - arc gossip of {:?} with {} since {}",
+                self.name,
+                chain_id,
+                peer_agent_id,
+                since_ms,
+            );
+            self.send_to_space_peer(
+                &chain_id.0,
+                &chain_id.1,
+                &peer_agent_id,
+                &P2pMessage::MsgGspArcRequest(request),
+            );
+        }
+    }
+
+    /// A peer started an arc gossip with us on channel `key`:
+    /// respond with what we hold in its arc and request what we miss in ours.
+    pub(crate) fn handle_gsp_arc_request(&mut self, key: &ChannelKey, msg: &MsgGspArc) {
+        let chain_id = (key.space_address.clone(), key.to_agent_id.clone());
+        let space_gateway = match self.space_gateway_map.get(&chain_id) {
+            Some(space_gateway) => space_gateway.clone(),
+            None => {
+                warn!("Received arc gossip for unjoined space: {:?}", key);
+                return;
+            }
+        };
+        let their_arc = arc_from_wire(&msg.aspect_constraint_arc);
+        // Next sync cursor, taken before listing so nothing held meanwhile is skipped
+        let cursor_ms = time::since_epoch_ms();
+        let response = MsgGspArc {
+            aspect_constraint_arc: msg.aspect_constraint_arc,
+            aspect_constraint_time: AspectConstraintTime {
+                gte_epoch_ms: cursor_ms,
+            },
+            aspect_constraint_count: msg.aspect_constraint_count,
+            aspect_hash_list: aspect_hash_list_in_arc(
+                &space_gateway,
+                &their_arc,
+                msg.aspect_constraint_time.gte_epoch_ms,
+            ),
+        };
+        self.send_to_space_peer(
+            &chain_id.0,
+            &chain_id.1,
+            &key.from_agent_id,
+            &P2pMessage::MsgGspArcResponse(response),
+        );
+        self.request_missing_aspects(&chain_id, &key.from_agent_id, &msg.aspect_hash_list);
+    }
+
+    /// A peer responded to our arc gossip on channel `key`: request what we miss.
+    pub(crate) fn handle_gsp_arc_response(&mut self, key: &ChannelKey, msg: &MsgGspArc) {
+        let chain_id = (key.space_address.clone(), key.to_agent_id.clone());
+        let sync_key = (chain_id.clone(), key.from_agent_id.clone());
+        let maybe_pending_since_ms = self
+            .gsp_arc_sync_map
+            .get_mut(&sync_key)
+            .and_then(|sync| sync.pending_since_ms.take());
+        let pending_since_ms = match maybe_pending_since_ms {
+            Some(pending_since_ms) => pending_since_ms,
+            None => {
+                warn!("Received unrequested arc gossip response: {:?}", key);
+                return;
+            }
+        };
+        if let Some(sync) = self.gsp_arc_sync_map.get_mut(&sync_key) {
+            sync.last_sync_ms = pending_since_ms;
+            sync.peer_cursor_ms = msg.aspect_constraint_time.gte_epoch_ms;
+        }
+        self.request_missing_aspects(&chain_id, &key.from_agent_id, &msg.aspect_hash_list);
+    }
+
+    /// Request from `peer_agent_id` the aspects in our arc we do not hold yet.
+    /// Responses are sent to Core as HandleStoreEntryAspect.
+    fn request_missing_aspects(
+        &mut self,
+        chain_id: &ChainId,
+        peer_agent_id: &Address,
+        aspect_hash_list: &[AspectHashList],
+    ) {
+        let space_gateway = match self.space_gateway_map.get(chain_id) {
+            Some(space_gateway) => space_gateway.clone(),
+            None => return,
+        };
+        let our_arc = space_gateway.as_dht_ref().get_storage_arc();
+        let rejected_set = self
+            .rejected_aspect_map
+            .get(chain_id)
+            .cloned()
+            .unwrap_or_default();
+        for hash_list in aspect_hash_list {
+            let (entry_address, maybe_aspect_address_list) = aspect_request_from_wire(hash_list);
+            if !our_arc.contains(get_location(&entry_address.to_string())) {
+                continue;
+            }
+            let known_aspect_list = space_gateway
+                .as_dht_ref()
+                .get_aspects_of(&entry_address)
+                .unwrap_or_default();
+            let missing_aspect_list: Vec<Address> = maybe_aspect_address_list
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|aspect_address| {
+                    !known_aspect_list.contains(aspect_address)
+                        && !rejected_set.contains(aspect_address)
+                })
+                .collect();
+            if missing_aspect_list.is_empty() {
+                continue;
+            }
+            let request_id = self.request_track.reserve();
+            self.request_track.set(
+                &request_id,
+                Some(RealEngineTrackerData::GossipFetchEntry {
+                    chain_id: chain_id.clone(),
+                }),
+            );
+            let request = MsgGspAspectDataRequest {
+                aspect_hash_list: vec![aspect_request_to_wire(
+                    &entry_address,
+                    &Some(missing_aspect_list),
+                )],
+                request_id,
+                space_address: address_to_wire(&chain_id.0),
+                to_agent_id: address_to_wire(peer_agent_id),
+                from_agent_id: address_to_wire(&chain_id.1),
+            };
+            self.send_to_space_peer(
+                &chain_id.0,
+                &chain_id.1,
+                peer_agent_id,
+                &P2pMessage::MsgGspAspectDataRequest(request),
+            );
+        }
+    }
+}
+
+/// Aspect addresses of the entries we hold located in `arc`,
+/// limited to the aspects held since `since_ms`
+fn aspect_hash_list_in_arc(
+    space_gateway: &GatewayWrapper<'_>,
+    arc: &StorageArc,
+    since_ms: u64,
+) -> Vec<AspectHashList> {
+    let dht = space_gateway.as_dht_ref();
+    let mut aspect_hash_list = Vec::new();
+    for entry_address in dht.get_entry_address_list() {
+        if !arc.contains(get_location(&entry_address.to_string())) {
+            continue;
+        }
+        let aspect_address_list: Vec<Address> = dht
+            .get_aspects_of(entry_address)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|aspect_address| {
+                dht.get_aspect_hold_time(aspect_address)
+                    .map(|hold_time| hold_time >= since_ms)
+                    .unwrap_or(false)
+            })
+            .collect();
+        if aspect_address_list.is_empty() {
+            continue;
+        }
+        aspect_hash_list.push(aspect_request_to_wire(
+            entry_address,
+            &Some(aspect_address_list),
+        ));
+    }
+    aspect_hash_list
+}
@@ -0,0 +1,252 @@
+//! LAN discovery: advertise our node as an mDNS service of our network
+//! and connect to the nodes of our network advertised on the LAN.
+//! A node is advertised with a SRV answer named `<transportId>.<service name>`
+//! with its bound URI as target.
+
+use crate::{
+    dht::{dht_protocol::*, dht_trait::Dht},
+    engine::RealEngine,
+    error::Lib3hResult,
+    time,
+    transport::protocol::TransportCommand,
+};
+use lib3h_mdns::{dns, Builder, MulticastDns};
+use lib3h_protocol::DidWork;
+use url::Url;
+
+/// Interval between two announcements of our node on the LAN
+const LAN_ANNOUNCE_INTERVAL_MS: u64 = 10_000;
+/// How long other nodes can consider our announcement valid
+const LAN_ANNOUNCE_TTL_SECONDS: u32 = 120;
+/// Maximum length of a dns name label
+const MAX_LABEL_LEN: usize = 63;
+/// request_id of the Connect commands dialing nodes found on the LAN
+const LAN_DISCOVERY_REQUEST_ID: &str = "lan_discovery";
+
+/// mDNS service name of the nodes of a network
+fn service_name(network_id: &str) -> Vec<u8> {
+    if network_id.is_empty() {
+        return b"_lib3h.local".to_vec();
+    }
+    format!("{}._lib3h.local", network_id).into_bytes()
+}
+
+/// Can `name` be encoded as a dns name, i.e. are its labels non-empty and short enough
+fn is_valid_dns_name(name: &[u8]) -> bool {
+    name.split(|&c| c == b'.')
+        .all(|label| !label.is_empty() && label.len() <= MAX_LABEL_LEN)
+}
+
+/// SRV answer advertising `peer` as a node of service `service_name`,
+/// None if its transportId or URI cannot be encoded as a dns name
+fn peer_to_answer(service_name: &[u8], peer: &PeerData) -> Option<dns::Answer> {
+    let mut name = peer.peer_address.as_bytes().to_vec();
+    name.push(b'.');
+    name.extend_from_slice(service_name);
+    let target = peer.peer_uri.as_str().as_bytes().to_vec();
+    if peer.peer_address.contains('.') || !is_valid_dns_name(&name) || !is_valid_dns_name(&target) {
+        return None;
+    }
+    Some(dns::Answer::Srv(dns::SrvDataA {
+        name,
+        ttl_seconds: LAN_ANNOUNCE_TTL_SECONDS,
+        priority: 0,
+        weight: 0,
+        port: peer.peer_uri.port_or_known_default().unwrap_or(0),
+        target,
+    }))
+}
+
+/// Peer advertised by `answer`, if it is a node of service `service_name`
+fn peer_from_answer(service_name: &[u8], answer: &dns::Answer, now: u64) -> Option<PeerData> {
+    let srv = match answer {
+        dns::Answer::Srv(srv) => srv,
+        _ => return None,
+    };
+    if srv.name.len() <= service_name.len() + 1 || !srv.name.ends_with(service_name) {
+        return None;
+    }
+    let (peer_address, suffix) = srv.name.split_at(srv.name.len() - service_name.len() - 1);
+    if suffix[0] != b'.' || peer_address.contains(&b'.') {
+        return None;
+    }
+    let peer_address = String::from_utf8(peer_address.to_vec()).ok()?;
+    let peer_uri = Url::parse(std::str::from_utf8(&srv.target).ok()?).ok()?;
+    Some(PeerData {
+        peer_address,
+        peer_uri,
+        timestamp: now,
+    })
+}
+
+/// mDNS socket and state of our LAN discovery
+pub(crate) struct LanDiscovery {
+    mdns: MulticastDns,
+    service_name: Vec<u8>,
+    /// Time of our next announcement, 0 before the first one
+    next_announce_ms: u64,
+}
+
+impl LanDiscovery {
+    /// Bind the mDNS socket for discovering the nodes of network `network_id`
+    pub fn new(network_id: &str) -> Lib3hResult<Self> {
+        Ok(LanDiscovery {
+            mdns: Builder::new().build()?,
+            service_name: service_name(network_id),
+            next_announce_ms: 0,
+        })
+    }
+
+    /// Announce `this_peer` if it is time to, answer the queries for our service
+    /// and return the peers announced by other nodes of our network.
+    fn process(&mut self, this_peer: &PeerData) -> Lib3hResult<(DidWork, Vec<PeerData>)> {
+        let now = time::since_epoch_ms();
+        let mut did_work = false;
+        let maybe_announcement = peer_to_answer(&self.service_name, this_peer).map(|answer| {
+            let mut packet = dns::Packet::new();
+            packet.is_query = false;
+            packet.answers.push(answer);
+            packet
+        });
+        if now >= self.next_announce_ms {
+            // On our first announcement, ask the other nodes to announce themselves too
+            if self.next_announce_ms == 0 {
+                let mut query = dns::Packet::new();
+                query.questions.push(dns::Question::Srv(dns::SrvDataQ {
+                    name: self.service_name.clone(),
+                }));
+                self.mdns.send(&query)?;
+            }
+            self.next_announce_ms = now + LAN_ANNOUNCE_INTERVAL_MS;
+            match &maybe_announcement {
+                Some(announcement) => self.mdns.send(announcement)?,
+                None => warn!(
+                    "Cannot announce {} on the LAN: its URI is not a valid dns name",
+                    this_peer.peer_uri,
+                ),
+            }
+            did_work = true;
+        }
+        let mut peer_list = Vec::new();
+        loop {
+            let packet = match self.mdns.recv() {
+                Ok(Some(packet)) => packet,
+                Ok(None) => break,
+                // Other mDNS users of the LAN may send what we cannot parse
+                Err(e) => {
+                    debug!("Ignoring LAN discovery packet: {:?}", e);
+                    break;
+                }
+            };
+            did_work = true;
+            if packet.is_query {
+                let is_for_us = packet.questions.iter().any(|question| match question {
+                    dns::Question::Srv(srv) => srv.name == self.service_name,
+                    _ => false,
+                });
+                if is_for_us {
+                    if let Some(announcement) = &maybe_announcement {
+                        self.mdns.send(announcement)?;
+                    }
+                }
+                continue;
+            }
+            for answer in packet.answers.iter() {
+                if let Some(peer) = peer_from_answer(&self.service_name, answer, now) {
+                    if peer.peer_address != this_peer.peer_address {
+                        peer_list.push(peer);
+                    }
+                }
+            }
+        }
+        Ok((did_work, peer_list))
+    }
+}
+
+/// LAN discovery related private methods
+impl<'engine, D: Dht> RealEngine<'engine, D> {
+    /// Run our LAN discovery, if enabled, and connect to the unknown nodes it found.
+    pub(crate) fn process_lan_discovery(&mut self) -> Lib3hResult<DidWork> {
+        let this_peer = self.network_gateway.as_ref().this_peer().clone();
+        let (did_work, peer_list) = match self.lan_discovery.as_mut() {
+            Some(lan_discovery) => lan_discovery.process(&this_peer)?,
+            None => return Ok(false),
+        };
+        for peer in peer_list {
+            if self
+                .network_gateway
+                .as_dht_ref()
+                .get_peer(&peer.peer_address)
+                .is_some()
+            {
+                continue;
+            }
+            info!(
+                "{} - found peer on the LAN: {} ({})",
+                self.name, peer.peer_address, peer.peer_uri,
+            );
+            let cmd = TransportCommand::Connect(
+                peer.peer_uri.clone(),
+                LAN_DISCOVERY_REQUEST_ID.to_string(),
+            );
+            self.network_gateway
+                .as_dht_mut()
+                .post(DhtCommand::HoldPeer(peer))?;
+            self.network_gateway.as_transport_mut().post(cmd)?;
+        }
+        Ok(did_work)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(peer_address: &str, peer_uri: &str) -> PeerData {
+        PeerData {
+            peer_address: peer_address.to_string(),
+            peer_uri: Url::parse(peer_uri).unwrap(),
+            timestamp: 42,
+        }
+    }
+
+    #[test]
+    fn it_should_read_back_an_announced_peer() {
+        let service_name = service_name("test_net");
+        let alex = peer("HcMalex", "wss://192.168.0.2:64519/");
+        let answer = peer_to_answer(&service_name, &alex).expect("Should be announceable");
+        // Survives the dns encoding
+        let mut packet = dns::Packet::new();
+        packet.is_query = false;
+        packet.answers.push(answer);
+        let packet = dns::Packet::with_raw(&packet.to_raw().unwrap()).unwrap();
+        let found = peer_from_answer(&service_name, &packet.answers[0], 42);
+        assert_eq!(found, Some(alex));
+    }
+
+    #[test]
+    fn it_should_ignore_other_networks() {
+        let alex = peer("HcMalex", "wss://192.168.0.2:64519/");
+        let answer = peer_to_answer(&service_name("test_net"), &alex).unwrap();
+        assert_eq!(
+            peer_from_answer(&service_name("other_net"), &answer, 42),
+            None
+        );
+        assert_eq!(peer_from_answer(&service_name(""), &answer, 42), None);
+    }
+
+    #[test]
+    fn it_should_not_announce_invalid_dns_names() {
+        let service_name = service_name("test_net");
+        let long_path = format!("wss://192.168.0.2:64519/{}", "x".repeat(MAX_LABEL_LEN));
+        assert_eq!(
+            peer_to_answer(&service_name, &peer("HcMalex", &long_path)),
+            None
+        );
+        let empty_label = "wss://192.168.0.2:64519/alex..bound";
+        assert_eq!(
+            peer_to_answer(&service_name, &peer("HcMalex", empty_label)),
+            None
+        );
+    }
+}
@@ -1,20 +1,37 @@
+mod gossip_layer;
+mod lan_discovery;
 mod network_layer;
 pub mod p2p_protocol;
+mod persistence;
 pub mod real_engine;
 mod space_layer;
 
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, RwLock},
+};
 
 use crate::{
     dht::dht_trait::{Dht, DhtFactory},
-    gateway::GatewayWrapper,
+    gateway::{
+        multiplex::{Multiplexer, DEFAULT_MAX_MESSAGE_SIZE},
+        GatewayWrapper,
+    },
     track::Tracker,
     transport::{ConnectionId, TransportWrapper},
-    transport_wss::TlsConfig,
+    transport_wss::{
+        send_queue::{DEFAULT_MAX_SEND_QUEUE_BYTES, DEFAULT_MAX_SEND_QUEUE_LEN},
+        TlsConfig, DEFAULT_HEARTBEAT_MS, DEFAULT_HEARTBEAT_WAIT_MS,
+    },
 };
+use self::{lan_discovery::LanDiscovery, persistence::DhtSnapshot};
 
 use lib3h_crypto_api::{Buffer, CryptoSystem};
-use lib3h_protocol::{protocol_client::Lib3hClientProtocol, Address};
+use lib3h_protocol::{
+    data_types::{EntryAspectData, EntryData, FetchEntryData, QueryEntryData, QueryEntryResultData},
+    protocol_client::Lib3hClientProtocol,
+    Address,
+};
 use url::Url;
 
 /// Identifier of a source chain: SpaceAddress+AgentId
@@ -22,7 +39,7 @@ pub type ChainId = (Address, Address);
 
 pub static NETWORK_GATEWAY_ID: &'static str = "__network__";
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 enum RealEngineTrackerData {
     /// track the actual HandleGetGossipingEntryList request
     GetGossipingEntryList,
@@ -31,23 +48,143 @@ enum RealEngineTrackerData {
     /// once we have the AuthoringEntryListResponse, fetch data for entries
     DataForAuthorEntry,
     /// gossip has requested we store data, send a hold request to core
-    /// core should respond ??
-    HoldEntryRequested,
+    /// core should respond with a SuccessResult to hold it or a FailureResult to reject it
+    HoldEntryRequested {
+        chain_id: ChainId,
+        provider_agent_id: Address,
+        entry_address: Address,
+        aspect: EntryAspectData,
+    },
+    /// our FetchEntry request is served by our own core
+    LocalFetchEntry(FetchEntryData),
+    /// our FetchEntry request has been sent to `pending_count` holders,
+    /// `entry` merges what they responded so far
+    NetworkFetchEntry {
+        request: FetchEntryData,
+        entry: EntryData,
+        pending_count: usize,
+    },
+    /// our QueryEntry request has been sent to `pending_count` responders,
+    /// `result_list` holds what they responded so far.
+    /// Only the remote agents of `holder_list` may respond, once each.
+    QueryEntry {
+        request: QueryEntryData,
+        holder_list: Vec<Address>,
+        result_list: Vec<QueryEntryResultData>,
+        pending_count: usize,
+    },
+    /// a remote agent's QueryEntry is handled by our core
+    RemoteQueryEntry {
+        request_id: String,
+        requester_agent_id: Address,
+        space_address: Address,
+        entry_address: Address,
+        /// our agent it was sent to
+        agent_id: Address,
+    },
+    /// a remote agent requested entry data our core holds
+    RemoteFetchEntry {
+        request_id: String,
+        requester_agent_id: Address,
+        aspect_address_list: Option<Vec<Address>>,
+    },
+    /// arc gossip found aspects we miss, requested from the peer holding them
+    GossipFetchEntry { chain_id: ChainId },
 }
 
-/// Struct holding all config settings for the RealEngine
+/// Progress of the arc gossip of one of our agents with a peer
+#[derive(Debug, Clone, Default)]
+struct GspArcSync {
+    /// Start time of the last completed arc gossip, 0 if none
+    last_sync_ms: u64,
+    /// Sync cursor of the peer's last arc gossip response, in the peer's own clock, 0 if none
+    peer_cursor_ms: u64,
+    /// Start time of the arc gossip waiting for the peer's response
+    pending_since_ms: Option<u64>,
+}
+
+/// Network connection waiting for the MsgHandshake of its remote
+#[derive(Debug, Clone)]
+struct PendingConnection {
+    /// request_id of the Connect that opened it, empty for incoming connections
+    request_id: String,
+    /// Network we dialed it for, None for incoming connections
+    maybe_network_id: Option<String>,
+}
+
+/// Struct holding all config settings for the RealEngine.
+/// Settings added since the first release default to their former behavior
+/// when missing from a serialized config.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct RealEngineConfig {
     pub tls_config: TlsConfig,
     pub socket_type: String,
     pub bootstrap_nodes: Vec<String>,
     pub work_dir: String,
+    /// Passphrase encrypting the TransportKeys stored in work_dir
+    #[serde(default)]
+    pub transport_keys_passphrase: String,
     pub log_level: char,
+    /// Its scheme picks the network transport: `tcp://host:port` for framed TCP,
+    /// `unix:///path` for a Unix domain socket, websockets otherwise
     #[serde(with = "url_serde")]
     pub bind_url: Url,
     pub dht_gossip_interval: u64,
     pub dht_timeout_threshold: u64,
     pub dht_custom_config: Vec<u8>,
+    /// Number of results to wait for before answering a QueryEntry (0 or 1: first result)
+    #[serde(default)]
+    pub query_entry_quorum: usize,
+    /// Accept to relay the channels of peers that cannot be reached directly
+    #[serde(default)]
+    pub accept_relay_duty: bool,
+    /// Ask the peers we connect to to act as our relay, until one accepts
+    #[serde(default)]
+    pub request_relay: bool,
+    /// Interval in ms between arc gossip rounds of each joined space, 0 to disable
+    #[serde(default)]
+    pub gsp_arc_interval: u64,
+    /// Identifier of the network we are part of
+    #[serde(default)]
+    pub network_id: String,
+    /// Advertise ourself and connect to the nodes of our network found on the LAN, with mDNS
+    #[serde(default)]
+    pub enable_lan_discovery: bool,
+    /// Interval in ms between two pings on a connection we receive nothing from
+    #[serde(default = "default_wss_heartbeat_ms")]
+    pub wss_heartbeat_ms: u64,
+    /// Close a connection we received nothing from for this many ms
+    #[serde(default = "default_wss_idle_timeout_ms")]
+    pub wss_idle_timeout_ms: u64,
+    /// Most messages waiting to be sent on a connection, more are refused until it drains
+    #[serde(default = "default_wss_max_send_queue_len")]
+    pub wss_max_send_queue_len: usize,
+    /// Most bytes waiting to be sent on a connection, more are refused until it drains
+    #[serde(default = "default_wss_max_send_queue_bytes")]
+    pub wss_max_send_queue_bytes: usize,
+    /// Most bytes of a message received on a channel, bigger ones are refused
+    #[serde(default = "default_max_message_size")]
+    pub max_message_size: usize,
+}
+
+fn default_wss_heartbeat_ms() -> u64 {
+    DEFAULT_HEARTBEAT_MS
+}
+
+fn default_wss_idle_timeout_ms() -> u64 {
+    DEFAULT_HEARTBEAT_WAIT_MS
+}
+
+fn default_wss_max_send_queue_len() -> usize {
+    DEFAULT_MAX_SEND_QUEUE_LEN
+}
+
+fn default_wss_max_send_queue_bytes() -> usize {
+    DEFAULT_MAX_SEND_QUEUE_BYTES
+}
+
+fn default_max_message_size() -> usize {
+    DEFAULT_MAX_MESSAGE_SIZE
 }
 
 pub struct TransportKeys {
@@ -77,14 +214,48 @@ pub struct RealEngine<'engine, D: Dht + 'engine> {
     network_transport: TransportWrapper<'engine>,
     /// P2p gateway for the network layer
     network_gateway: GatewayWrapper<'engine>,
-    /// Store active connections?
-    network_connections: HashSet<ConnectionId>,
+    /// network_id of each confirmed connection, i.e. whose remote sent us
+    /// a valid MsgHandshake of one of our networks
+    network_connections: HashMap<ConnectionId, String>,
+    /// Connections waiting for the MsgHandshake of their remote
+    pending_connection_map: HashMap<ConnectionId, PendingConnection>,
+    /// Nonce of the MsgHandshake we sent on each connection, to be echoed by its remote
+    handshake_nonce_map: HashMap<ConnectionId, Vec<u8>>,
+    /// network_id of the Connect requests waiting for their connection, by request_id
+    connect_network_map: HashMap<String, String>,
+    /// Networks we are part of: the one of our config and the ones Core connected us to
+    network_id_set: HashSet<String>,
     /// Map of P2p gateway per Space+Agent
     space_gateway_map: HashMap<ChainId, GatewayWrapper<'engine>>,
-    #[allow(dead_code)]
+    /// Channels multiplexed on our network connections, shared with all gateways
+    multiplexer: Arc<RwLock<Multiplexer>>,
+    /// TransportId of each connection, from its MsgHandshake
+    connection_peer_map: HashMap<ConnectionId, String>,
+    /// Connection of the peer announcing each agent, for relaying channels to it
+    relay_route_map: HashMap<ChainId, ConnectionId>,
+    /// TransportIds that announced each agent with a signed MsgJoinSpace,
+    /// the only peers we accept channels from that agent from
+    agent_announcer_map: HashMap<ChainId, HashSet<String>>,
+    /// Aspects rejected by Core per Space+Agent, never to be held or requested again
+    rejected_aspect_map: HashMap<ChainId, HashSet<Address>>,
+    /// Arc gossip progress of each of our agents with each of its peers
+    gsp_arc_sync_map: HashMap<(ChainId, Address), GspArcSync>,
+    /// Time of the last arc gossip round
+    last_gsp_arc_ms: u64,
+    /// Number of times we dialed our bootstrap nodes since we last had a connection
+    bootstrap_attempt_count: u32,
+    /// Time of the next dialing of our bootstrap nodes, if still without connection
+    next_bootstrap_ms: u64,
+    /// mDNS discovery of the nodes of our network on the LAN, if enabled
+    lan_discovery: Option<LanDiscovery>,
+    /// DHT state of spaces loaded from work_dir and not joined yet
+    persisted_space_map: HashMap<ChainId, DhtSnapshot>,
+    /// Time we last stored our DHT state in work_dir
+    last_dht_save_ms: u64,
+    /// Did we do any work since we last stored our DHT state
+    dht_state_changed: bool,
     /// crypto system to use
     crypto: Box<dyn CryptoSystem>,
-    #[allow(dead_code)]
     /// transport_id data, public/private keys, etc
     transport_keys: TransportKeys,
     /// debug: count number of calls to process()
@@ -2,17 +2,93 @@
 
 use crate::{
     dht::{dht_protocol::*, dht_trait::Dht},
-    engine::{p2p_protocol::P2pProtocol, RealEngine, NETWORK_GATEWAY_ID},
-    error::{ErrorKind, Lib3hError, Lib3hResult},
-    transport::{protocol::*, ConnectionIdRef},
+    engine::{
+        p2p_protocol::*,
+        real_engine::{fetch_entry_failure, fetch_entry_result, includes},
+        ChainId, PendingConnection, RealEngine, RealEngineTrackerData, NETWORK_GATEWAY_ID,
+    },
+    error::{Lib3hError, Lib3hResult},
+    gateway::{
+        multiplex::{parse_relay_uri, relay_uri, ChannelKey, Demultiplexed, Multiplexer},
+        GatewayWrapper,
+    },
+    transport::{error::TransportError, protocol::*, ConnectionId, ConnectionIdRef},
 };
-use lib3h_protocol::{data_types::*, protocol_server::Lib3hServerProtocol, DidWork};
+use lib3h_p2p_protocol::multiplex::MultiplexErrorCode;
+use lib3h_protocol::{data_types::*, protocol_server::Lib3hServerProtocol, Address, DidWork};
+use std::collections::{HashMap, HashSet};
+use url::Url;
 
-use rmp_serde::{Deserializer, Serializer};
-use serde::{Deserialize, Serialize};
+/// Delay before dialing our bootstrap nodes again, doubled on every attempt
+const BOOTSTRAP_RETRY_BASE_MS: u64 = 1000;
+/// Maximum delay between two dialings of our bootstrap nodes
+const BOOTSTRAP_RETRY_MAX_MS: u64 = 60_000;
+/// request_id of the Connect commands dialing our bootstrap nodes
+const BOOTSTRAP_REQUEST_ID: &str = "bootstrap";
 
 /// Network layer related private methods
 impl<'engine, D: Dht> RealEngine<'engine, D> {
+    /// Dial every bootstrap node of our config while we have no connection in our network,
+    /// i.e. on startup and whenever we lose our last connection.
+    /// Dial again with an exponential backoff until one of them succeeds.
+    pub(crate) fn process_bootstrap(&mut self) -> Lib3hResult<DidWork> {
+        if self.config.bootstrap_nodes.is_empty() {
+            return Ok(false);
+        }
+        if self.is_connected_to(&self.config.network_id) {
+            self.bootstrap_attempt_count = 0;
+            self.next_bootstrap_ms = 0;
+            return Ok(false);
+        }
+        let now = crate::time::since_epoch_ms();
+        if now < self.next_bootstrap_ms {
+            return Ok(false);
+        }
+        let backoff_ms = (BOOTSTRAP_RETRY_BASE_MS << self.bootstrap_attempt_count.min(16))
+            .min(BOOTSTRAP_RETRY_MAX_MS);
+        self.bootstrap_attempt_count = self.bootstrap_attempt_count.saturating_add(1);
+        self.next_bootstrap_ms = now + backoff_ms;
+        let this_peer_uri = self.network_gateway.as_ref().this_peer().peer_uri.clone();
+        for node in self.config.bootstrap_nodes.clone() {
+            let uri = match Url::parse(&node) {
+                Ok(uri) => uri,
+                Err(e) => {
+                    warn!("{} - invalid bootstrap node {}: {:?}", self.name, node, e);
+                    continue;
+                }
+            };
+            // Skip ourself
+            if uri == this_peer_uri {
+                continue;
+            }
+            info!(
+                "{} - bootstrapping from {} (attempt {})",
+                self.name, uri, self.bootstrap_attempt_count,
+            );
+            let cmd = TransportCommand::Connect(uri, BOOTSTRAP_REQUEST_ID.to_string());
+            self.network_gateway.as_transport_mut().post(cmd)?;
+        }
+        Ok(true)
+    }
+
+    /// Send again the fragment windows the receivers did not acknowledge in time.
+    pub(crate) fn process_transfer_retransmit(&mut self) -> Lib3hResult<DidWork> {
+        let resend_list = self
+            .multiplexer
+            .write()
+            .expect("failed to obtain write lock")
+            .retransmit();
+        for (connection_id, frame_list) in resend_list.iter() {
+            if let Err(e) = self.send_back(connection_id, frame_list) {
+                warn!(
+                    "{} - failed to send fragments again on {}: {:?}",
+                    self.name, connection_id, e
+                );
+            }
+        }
+        Ok(!resend_list.is_empty())
+    }
+
     /// Process whatever the network has in for us.
     pub(crate) fn process_network_gateway(
         &mut self,
@@ -42,9 +118,12 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
                 did_work = true;
             }
         }
+        // A failure on one connection must not keep us from handling the others
         for evt in event_list {
-            let mut output = self.handle_netTransportEvent(&evt)?;
-            outbox.append(&mut output);
+            match self.handle_netTransportEvent(&evt) {
+                Ok(mut output) => outbox.append(&mut output),
+                Err(e) => warn!("{} - failed handling a network event: {:?}", self.name, e),
+            }
         }
         // Process the network gateway as a DHT
         let (dht_did_work, event_list) = self.network_gateway.as_dht_mut().process()?;
@@ -69,9 +148,22 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
             DhtEvent::GossipUnreliablyTo(_data) => {
                 // no-op
             }
+            DhtEvent::GossipUndelivered(gossip_data) => {
+                warn!(
+                    "{} - network gossip undelivered to {:?}",
+                    self.name, gossip_data.peer_address_list,
+                );
+            }
             DhtEvent::HoldPeerRequested(peer_data) => {
                 // TODO #167 - hardcoded for MirrorDHT and thus should not appear here.
                 // Connect to every peer we are requested to hold.
+                if parse_relay_uri(&peer_data.peer_uri).is_some() {
+                    // Reached through its relay: nothing to dial
+                    self.network_gateway
+                        .as_dht_mut()
+                        .post(DhtCommand::HoldPeer(peer_data))?;
+                    return Ok(outbox);
+                }
                 info!(
                     "{} auto-connect to peer: {} ({})",
                     self.name, peer_data.peer_address, peer_data.peer_uri,
@@ -113,48 +205,194 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
         Ok(outbox)
     }
 
+    /// Handle a connection we opened (`maybe_request_id` is set) or accepted:
+    /// wait for the MsgHandshake of its remote before using it.
+    /// Dialing side sends its MsgHandshake first, for the network it dials.
+    /// Each side then answers with a MsgHandshake echoing the other's nonce,
+    /// so a handshake captured on another connection cannot be replayed.
     fn handle_new_connection(
         &mut self,
         id: &ConnectionIdRef,
+        maybe_request_id: Option<&str>,
+    ) -> Lib3hResult<()> {
+        let uri = match self.network_transport.as_ref().get_uri(id) {
+            Some(uri) => uri,
+            None => return Ok(()),
+        };
+        info!("Network Connection opened: {} ({})", id, uri);
+        let pending = match maybe_request_id {
+            Some(request_id) => {
+                let network_id = self
+                    .connect_network_map
+                    .remove(request_id)
+                    .unwrap_or_else(|| self.config.network_id.clone());
+                if !self.send_handshake(id, &network_id, &[])? {
+                    return Ok(());
+                }
+                PendingConnection {
+                    request_id: request_id.to_string(),
+                    maybe_network_id: Some(network_id),
+                }
+            }
+            None => PendingConnection {
+                request_id: "".to_string(),
+                maybe_network_id: None,
+            },
+        };
+        self.pending_connection_map.insert(id.to_owned(), pending);
+        Ok(())
+    }
+
+    /// Send our signed PeerAddress as a node of network `network_id`,
+    /// echoing the nonce of the remote's handshake if we received it.
+    /// Return false if the connection was too congested and got closed.
+    fn send_handshake(
+        &mut self,
+        id: &ConnectionIdRef,
+        network_id: &str,
+        remote_nonce: &[u8],
+    ) -> Lib3hResult<bool> {
+        let this_peer = self.network_gateway.as_ref().this_peer().clone();
+        let nonce = match self.handshake_nonce_map.get(id) {
+            Some(nonce) => nonce.clone(),
+            None => {
+                let nonce = handshake_nonce(self.crypto.as_crypto_system())?;
+                self.handshake_nonce_map
+                    .insert(id.to_owned(), nonce.clone());
+                nonce
+            }
+        };
+        // Note: arc radii are not negotiated yet
+        let mut our_handshake = MsgHandshake {
+            store_arc_radius: 0,
+            query_arc_radius: 0,
+            space_address: NETWORK_GATEWAY_ID.as_bytes().to_vec(),
+            peer_address: this_peer.peer_address.as_bytes().to_vec(),
+            timestamp: crate::time::since_epoch_ms(),
+            signer_pub_key: Vec::new(),
+            signature: Vec::new(),
+            network_id: network_id.as_bytes().to_vec(),
+            nonce,
+            remote_nonce: remote_nonce.to_vec(),
+        };
+        sign_handshake(
+            self.crypto.as_crypto_system(),
+            &self.transport_keys,
+            &mut our_handshake,
+        )?;
+        let buf = Multiplexer::network_frame(&P2pMessage::MsgHandshake(our_handshake).to_bytes());
+        trace!("{} - sending MsgHandshake to {:?}", self.name, id);
+        let result = self.network_transport.as_mut().send(&[id], &buf);
+        if let Err(TransportError::WouldBlock(_)) = result {
+            // Never confirmed without our handshake: the remote may dial again
+            warn!("{} - congested connection {}, closing it", self.name, id);
+            self.network_transport.as_mut().close(id)?;
+            self.forget_connection(id);
+            return Ok(false);
+        }
+        result?;
+        Ok(true)
+    }
+
+    /// The remote of a connection proved to be a node of one of our networks:
+    /// start using the connection.
+    fn confirm_connection(
+        &mut self,
+        id: &ConnectionIdRef,
+        peer_address: &str,
+        network_id: String,
         request_id: String,
     ) -> Lib3hResult<Vec<Lib3hServerProtocol>> {
         let mut outbox = Vec::new();
-        let mut network_gateway = self.network_gateway.as_mut();
-        if let Some(uri) = network_gateway.get_uri(id) {
-            info!("Network Connection opened: {} ({})", id, uri);
-            // TODO #150 - Should do this in next process instead
-            // Send to other node our Joined Spaces
-            let space_list = self.get_all_spaces();
-            let our_joined_space_list = P2pProtocol::AllJoinedSpaceList(space_list);
-            let mut buf = Vec::new();
-            our_joined_space_list
-                .serialize(&mut Serializer::new(&mut buf))
-                .unwrap();
-            trace!(
-                "AllJoinedSpaceList: {:?} to {:?}",
-                our_joined_space_list,
-                id
-            );
-            // id is connectionId but we need a transportId, so search for it in the DHT
-            let peer_list = network_gateway.get_peer_list();
-            trace!("AllJoinedSpaceList: get_peer_list = {:?}", peer_list);
-            let maybe_peer_data = peer_list.iter().find(|pd| pd.peer_uri == uri);
-            if let Some(peer_data) = maybe_peer_data {
-                trace!("AllJoinedSpaceList ; sending back to {:?}", peer_data);
-                network_gateway.send(&[&peer_data.peer_address], &buf)?;
-            }
-            // TODO END
+        let uri = match self.network_transport.as_ref().get_uri(id) {
+            Some(uri) => uri,
+            None => return Ok(outbox),
+        };
+        info!(
+            "{} - connection {} confirmed in network '{}'",
+            self.name, id, network_id,
+        );
+        self.connection_peer_map
+            .insert(id.to_string(), peer_address.to_string());
+        // New session: channels we opened before are gone on the other side
+        let resume_list = self
+            .multiplexer
+            .write()
+            .expect("failed to obtain write lock")
+            .reset_remote(peer_address, id);
+        // TODO #150 - Should do this in next process instead
+        // Send to other node our Joined Spaces
+        let mut peer_list = Vec::new();
+        for (space_address, peer_data) in self.get_all_spaces().iter() {
+            peer_list.push(self.signed_peer_info(space_address, peer_data)?);
+        }
+        let our_joined_space_list = P2pMessage::MsgJoinSpace(MsgJoinSpace { peer_list });
+        let buf = Multiplexer::network_frame(&our_joined_space_list.to_bytes());
+        trace!(
+            "AllJoinedSpaceList: {:?} to {:?}",
+            our_joined_space_list,
+            id
+        );
+        self.send_back(id, &[buf])?;
+        // TODO END
+        // Resume our fragmented messages interrupted by the disconnection
+        self.send_back(id, &resume_list)?;
 
-            // Output a Lib3hServerProtocol::Connected if its the first connection
-            if self.network_connections.is_empty() {
-                let data = ConnectedData { request_id, uri };
-                outbox.push(Lib3hServerProtocol::Connected(data));
+        if self.config.request_relay {
+            let maybe_request = self
+                .multiplexer
+                .write()
+                .expect("failed to obtain write lock")
+                .request_relay(id);
+            if let Some(request) = maybe_request {
+                debug!("{} - asking {} to act as our relay", self.name, id);
+                self.send_back(id, &[request])?;
             }
-            let _ = self.network_connections.insert(id.to_owned());
         }
+
+        // Output a Lib3hServerProtocol::Connected if its the first connection of that network
+        if !self.is_connected_to(&network_id) {
+            let data = ConnectedData {
+                request_id,
+                uri,
+                network_id: network_id.clone(),
+            };
+            outbox.push(Lib3hServerProtocol::Connected(data));
+        }
+        self.network_connections.insert(id.to_owned(), network_id);
         Ok(outbox)
     }
 
+    /// Tell the remote of a connection that it is not part of our network, and close it
+    fn reject_network(
+        &mut self,
+        id: &ConnectionIdRef,
+        network_id: &str,
+    ) -> Lib3hResult<Vec<Lib3hServerProtocol>> {
+        warn!(
+            "{} - closing connection {}: not part of network '{}'",
+            self.name, id, network_id,
+        );
+        let msg = P2pMessage::MsgError(MsgError {
+            error_code: ErrorCode::BadNetworkId,
+            error_text: format!("Not part of network '{}'", network_id),
+        });
+        self.send_back(id, &[Multiplexer::network_frame(&msg.to_bytes())])?;
+        self.network_transport.as_mut().close(id)?;
+        Ok(self
+            .forget_connection(id)
+            .map(Lib3hServerProtocol::Disconnected)
+            .into_iter()
+            .collect())
+    }
+
+    /// Do we have a confirmed connection in network `network_id`
+    fn is_connected_to(&self, network_id: &str) -> bool {
+        self.network_connections
+            .values()
+            .any(|connection_network_id| connection_network_id == network_id)
+    }
+
     /// Handle a TransportEvent sent to us by our network gateway
     fn handle_netTransportEvent(
         &mut self,
@@ -165,90 +403,399 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
         // Note: use same order as the enum
         match evt {
             TransportEvent::ErrorOccured(id, e) => {
-                self.network_connections.remove(id);
                 error!("{} Network error from {} : {:?}", self.name, id, e);
-                // Output a Lib3hServerProtocol::Disconnected if it was the connection
-                if self.network_connections.is_empty() {
-                    let data = DisconnectedData {
-                        network_id: "FIXME".to_string(), // TODO #172
-                    };
+                if let Some(data) = self.forget_connection(id) {
                     outbox.push(Lib3hServerProtocol::Disconnected(data));
                 }
             }
             TransportEvent::ConnectResult(id, request_id) => {
-                let mut output = self.handle_new_connection(id, request_id.clone())?;
-                outbox.append(&mut output);
+                self.handle_new_connection(id, Some(request_id))?;
             }
             TransportEvent::IncomingConnectionEstablished(id) => {
-                let mut output = self.handle_new_connection(id, "".to_string())?;
-                outbox.append(&mut output);
+                self.handle_new_connection(id, None)?;
             }
-            TransportEvent::ConnectionClosed(id) => {
-                self.network_connections.remove(id);
-                // Output a Lib3hServerProtocol::Disconnected if it was the last connection
-                if self.network_connections.is_empty() {
-                    let data = DisconnectedData {
-                        network_id: "FIXME".to_string(), // TODO #172
-                    };
+            TransportEvent::ConnectionClosed(id, reason) => {
+                info!("{} Connection closed: {} ({:?})", self.name, id, reason);
+                if let Some(data) = self.forget_connection(id) {
                     outbox.push(Lib3hServerProtocol::Disconnected(data));
                 }
             }
+            TransportEvent::ConnectionDrained(id) => {
+                debug!("{} Connection drained: {}", self.name, id);
+            }
             TransportEvent::ReceivedData(id, payload) => {
                 debug!("Received message from: {} | {}", id, payload.len());
-                let mut de = Deserializer::new(&payload[..]);
-                let maybe_msg: Result<P2pProtocol, rmp_serde::decode::Error> =
-                    Deserialize::deserialize(&mut de);
-                if let Err(e) = maybe_msg {
-                    error!("Failed deserializing msg: {:?}", e);
-                    return Err(Lib3hError::new(ErrorKind::RmpSerdeDecodeError(e)));
+                let maybe_demultiplexed = {
+                    let space_gateway_map = &self.space_gateway_map;
+                    let relay_route_map = &self.relay_route_map;
+                    let agent_announcer_map = &self.agent_announcer_map;
+                    let network_gateway = &self.network_gateway;
+                    let maybe_peer_address = self.connection_peer_map.get(id);
+                    let mut multiplexer = self
+                        .multiplexer
+                        .write()
+                        .expect("failed to obtain write lock");
+                    let relayed_id_list = multiplexer.relayed_id_list();
+                    let is_from_our_relay = multiplexer.relay_id() == Some(id);
+                    multiplexer.demultiplex(id, payload, |key| {
+                        check_channel(space_gateway_map, key).or_else(|error_code| {
+                            if is_relay_channel(relay_route_map, &relayed_id_list, id, key) {
+                                Ok(())
+                            } else {
+                                Err(error_code)
+                            }
+                        })?;
+                        if is_from_our_relay {
+                            // Our relay checked the channels it forwards to us
+                            return Ok(());
+                        }
+                        check_from_agent(
+                            agent_announcer_map,
+                            space_gateway_map,
+                            network_gateway,
+                            maybe_peer_address,
+                            key,
+                        )
+                    })
+                };
+                // Never let a malformed frame from a remote stop the engine
+                let demultiplexed = match maybe_demultiplexed {
+                    Ok(demultiplexed) => demultiplexed,
+                    Err(e) => {
+                        warn!(
+                            "{} - dropping malformed frame from {}: {:?}",
+                            self.name, id, e
+                        );
+                        return Ok(outbox);
+                    }
+                };
+                let (maybe_key, content) = match demultiplexed {
+                    Demultiplexed::Network(content) => (None, content),
+                    Demultiplexed::Channel(key, content) => (Some(key), content),
+                    Demultiplexed::Reassembled(key, content, ack) => {
+                        self.send_back(id, &[ack])?;
+                        (Some(key), content)
+                    }
+                    Demultiplexed::RelayRequest => {
+                        self.serve_relay_request(id)?;
+                        return Ok(outbox);
+                    }
+                    Demultiplexed::Handled(maybe_reply) => {
+                        if let Some(reply) = maybe_reply {
+                            self.send_back(id, &[reply])?;
+                        }
+                        return Ok(outbox);
+                    }
+                    Demultiplexed::Resend(frame_list) => {
+                        self.send_back(id, &frame_list)?;
+                        return Ok(outbox);
+                    }
+                };
+                if let Some(key) = maybe_key.as_ref() {
+                    let chain_id = (key.space_address.clone(), key.to_agent_id.clone());
+                    if !self.space_gateway_map.contains_key(&chain_id) {
+                        self.relay_channel_message(key, &content)?;
+                        return Ok(outbox);
+                    }
+                    trace!("Received message on channel {:?}", key);
                 }
-                let p2p_msg = maybe_msg.unwrap();
-                let mut output = self.serve_P2pProtocol(id, &p2p_msg)?;
+                let p2p_msg = match P2pMessage::from_bytes(&content) {
+                    Ok(p2p_msg) => p2p_msg,
+                    Err(e) => {
+                        warn!("{} - failed decoding msg from {}: {:?}", self.name, id, e);
+                        return Ok(outbox);
+                    }
+                };
+                let mut output = self.serve_P2pMessage(id, maybe_key.as_ref(), &p2p_msg)?;
                 outbox.append(&mut output);
             }
         };
         Ok(outbox)
     }
 
-    /// Serve a P2pProtocol sent to us by the network.
+    /// Forget everything related to a closed connection.
+    /// Return the Disconnected to output if it was the last connection of its network.
+    fn forget_connection(&mut self, id: &ConnectionIdRef) -> Option<DisconnectedData> {
+        self.pending_connection_map.remove(id);
+        self.handshake_nonce_map.remove(id);
+        let maybe_network_id = self.network_connections.remove(id);
+        self.connection_peer_map.remove(id);
+        self.relay_route_map.retain(|_, route_id| route_id != id);
+        self.multiplexer
+            .write()
+            .expect("failed to obtain write lock")
+            .close_connection(id);
+        match maybe_network_id {
+            Some(network_id) if !self.is_connected_to(&network_id) => {
+                Some(DisconnectedData { network_id })
+            }
+            _ => None,
+        }
+    }
+
+    /// A peer asks us to act as its relay: accept if configured to,
+    /// and advertise it as reachable through us.
+    fn serve_relay_request(&mut self, id: &ConnectionIdRef) -> Lib3hResult<()> {
+        let maybe_peer_address = self.connection_peer_map.get(id).cloned();
+        let peer_address = match maybe_peer_address {
+            Some(peer_address) if self.config.accept_relay_duty => peer_address,
+            _ => {
+                debug!("{} - refusing relay duty for {}", self.name, id);
+                self.send_back(id, &[Multiplexer::refuse_relay()])?;
+                return Ok(());
+            }
+        };
+        let reply = self
+            .multiplexer
+            .write()
+            .expect("failed to obtain write lock")
+            .accept_relay(id, &peer_address);
+        self.send_back(id, &[reply])?;
+        let relay_address = self
+            .network_gateway
+            .as_ref()
+            .this_peer()
+            .peer_address
+            .clone();
+        let peer = PeerData {
+            peer_uri: relay_uri(&relay_address, &peer_address),
+            peer_address,
+            timestamp: crate::time::since_epoch_ms(),
+        };
+        info!("{} - relaying for {}", self.name, peer.peer_uri);
+        self.network_gateway
+            .as_dht_mut()
+            .post(DhtCommand::HoldPeer(peer))?;
+        Ok(())
+    }
+
+    /// Send frames on a connection while handling its events.
+    /// On a congested connection, drop them like relayed frames instead of failing:
+    /// unacknowledged fragments are sent again on a timer, or on reconnection.
+    fn send_back(&mut self, id: &ConnectionIdRef, frame_list: &[Vec<u8>]) -> Lib3hResult<()> {
+        for frame in frame_list {
+            let result = self.network_transport.as_mut().send(&[id], frame);
+            if let Err(TransportError::WouldBlock(_)) = result {
+                warn!("{} - congested connection {}, frame dropped", self.name, id);
+                continue;
+            }
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Forward a message received on a channel we relay
+    fn relay_channel_message(&mut self, key: &ChannelKey, content: &[u8]) -> Lib3hResult<()> {
+        let chain_id = (key.space_address.clone(), key.to_agent_id.clone());
+        let to_id = match self.relay_route_map.get(&chain_id) {
+            Some(to_id) => to_id.clone(),
+            None => {
+                warn!("No route for relayed channel: {:?}", key);
+                return Ok(());
+            }
+        };
+        trace!("Relaying message on channel {:?} to {}", key, to_id);
+        let frame_list = self
+            .multiplexer
+            .write()
+            .expect("failed to obtain write lock")
+            .multiplex(&to_id, key, content)?;
+        for frame in frame_list {
+            let result = self
+                .network_transport
+                .as_mut()
+                .send(&[to_id.as_str()], &frame);
+            if let Err(TransportError::WouldBlock(_)) = result {
+                // the relayed peers handle the loss like any other
+                warn!("Congested relay route, frame dropped for {:?}", key);
+                continue;
+            }
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Sign one of our space PeerData for announcing it to the network
+    pub(crate) fn signed_peer_info(
+        &self,
+        space_address: &str,
+        peer_data: &PeerData,
+    ) -> Lib3hResult<PeerInfo> {
+        let mut peer_info = peer_to_wire(space_address, peer_data);
+        sign_peer_info(
+            self.crypto.as_crypto_system(),
+            &self.transport_keys,
+            &mut peer_info,
+        )?;
+        Ok(peer_info)
+    }
+
+    /// Sign the leave notice of one of our space PeerData
+    pub(crate) fn signed_leave_space(
+        &self,
+        space_address: &str,
+        peer_data: &PeerData,
+    ) -> Lib3hResult<MsgLeaveSpace> {
+        let mut msg = leave_to_wire(space_address, peer_data);
+        sign_leave_space(
+            self.crypto.as_crypto_system(),
+            &self.transport_keys,
+            &mut msg,
+        )?;
+        Ok(msg)
+    }
+
+    /// Drop a connection that sent us a forged peer announcement
+    /// and report it as a network error.
+    fn reject_forged_announcement(
+        &mut self,
+        from_id: &ConnectionIdRef,
+        e: Lib3hError,
+    ) -> Lib3hResult<Vec<Lib3hServerProtocol>> {
+        error!(
+            "{} Rejecting forged peer announcement from {}: {}",
+            self.name, from_id, e,
+        );
+        self.network_transport.as_mut().close(from_id)?;
+        let evt = TransportEvent::ErrorOccured(
+            from_id.to_string(),
+            TransportError::new(format!("Forged peer announcement: {}", e)),
+        );
+        self.handle_netTransportEvent(&evt)
+    }
+
+    /// Serve a P2pMessage sent to us by the network, on a space channel if `maybe_key` is set.
     /// Return a list of Lib3hServerProtocol to send to Core.
-    /// TODO #150
-    fn serve_P2pProtocol(
+    fn serve_P2pMessage(
         &mut self,
-        _from_id: &ConnectionIdRef,
-        p2p_msg: &P2pProtocol,
+        from_id: &ConnectionIdRef,
+        maybe_key: Option<&ChannelKey>,
+        p2p_msg: &P2pMessage,
     ) -> Lib3hResult<Vec<Lib3hServerProtocol>> {
         let mut outbox = Vec::new();
+        // Only handshake on a connection until its remote proved to be of our network
         match p2p_msg {
-            P2pProtocol::Gossip(msg) => {
-                // Prepare remoteGossipTo to post to dht
-                let cmd = DhtCommand::HandleGossip(RemoteGossipBundleData {
-                    from_peer_address: msg.from_peer_address.clone().into(),
-                    bundle: msg.bundle.clone(),
-                });
-                // Check if its for the network_gateway
-                if msg.space_address.to_string() == NETWORK_GATEWAY_ID {
-                    self.network_gateway.as_dht_mut().post(cmd)?;
-                } else {
-                    // otherwise should be for one of our space
-                    let maybe_space_gateway = self
-                        .space_gateway_map
-                        .get_mut(&(msg.space_address.to_owned(), msg.to_peer_address.to_owned()));
-                    if let Some(space_gateway) = maybe_space_gateway {
-                        space_gateway.as_dht_mut().post(cmd)?;
-                    } else {
-                        warn!("received gossip for unjoined space: {}", msg.space_address);
+            P2pMessage::MsgError(_) | P2pMessage::MsgHandshake(_) => (),
+            _ => {
+                if !self.network_connections.contains_key(from_id) {
+                    warn!("Received message on unconfirmed connection {}", from_id);
+                    return Ok(outbox);
+                }
+            }
+        }
+        // Note: use same order as the enum
+        match p2p_msg {
+            P2pMessage::MsgError(msg) => {
+                warn!("Received MsgError: {:?}", msg);
+            }
+            P2pMessage::MsgHandshake(msg) => {
+                let peer_address = str_from_wire(&msg.peer_address);
+                debug!("Received MsgHandshake: {}", peer_address);
+                let our_nonce = self
+                    .handshake_nonce_map
+                    .get(from_id)
+                    .cloned()
+                    .unwrap_or_default();
+                if let Err(e) = verify_handshake(
+                    self.crypto.as_crypto_system(),
+                    msg,
+                    &our_nonce,
+                    crate::time::since_epoch_ms(),
+                ) {
+                    return self.reject_forged_announcement(from_id, e);
+                }
+                if str_from_wire(&msg.space_address) != NETWORK_GATEWAY_ID {
+                    warn!("Received MsgHandshake for unknown gateway: {:?}", msg);
+                    return Ok(outbox);
+                }
+                let network_id = str_from_wire(&msg.network_id);
+                let maybe_pending = match self.network_connections.get(from_id).cloned() {
+                    // Already confirmed, e.g. a refreshed handshake
+                    Some(confirmed_network_id) => {
+                        if confirmed_network_id != network_id {
+                            return self.reject_network(from_id, &network_id);
+                        }
+                        None
+                    }
+                    None => Some(self.pending_connection_map.remove(from_id).unwrap_or(
+                        PendingConnection {
+                            request_id: "".to_string(),
+                            maybe_network_id: None,
+                        },
+                    )),
+                };
+                if let Some(pending) = maybe_pending {
+                    let is_our_network = match &pending.maybe_network_id {
+                        Some(dialed_network_id) => *dialed_network_id == network_id,
+                        None => self.network_id_set.contains(&network_id),
+                    };
+                    if !is_our_network {
+                        return self.reject_network(from_id, &network_id);
+                    }
+                    if msg.remote_nonce.is_empty() {
+                        // Accepting side answers with its MsgHandshake for the same network,
+                        // and waits for the dialing side to echo its nonce back
+                        if !self.send_handshake(from_id, &network_id, &msg.nonce)? {
+                            return Ok(outbox);
+                        }
+                        self.pending_connection_map
+                            .insert(from_id.to_owned(), pending);
+                        return Ok(outbox);
+                    }
+                    // Dialing side proves in turn it received the accepting side's handshake
+                    if pending.maybe_network_id.is_some()
+                        && !self.send_handshake(from_id, &network_id, &msg.nonce)?
+                    {
+                        return Ok(outbox);
                     }
+                    outbox.append(&mut self.confirm_connection(
+                        from_id,
+                        &peer_address,
+                        network_id,
+                        pending.request_id,
+                    )?);
+                }
+                let maybe_peer_uri = self.network_transport.as_ref().get_uri(from_id);
+                if let Some(peer_uri) = maybe_peer_uri {
+                    debug!("peer_uri of: {} = {}", from_id, peer_uri);
+                    let peer = PeerData {
+                        peer_address,
+                        peer_uri,
+                        timestamp: msg.timestamp,
+                    };
+                    self.network_gateway
+                        .as_dht_mut()
+                        .post(DhtCommand::HoldPeer(peer))?;
+                }
+            }
+            P2pMessage::MsgGspArcRequest(msg) => match maybe_key {
+                Some(key) => self.handle_gsp_arc_request(key, msg),
+                None => warn!("Received arc gossip out of a space channel"),
+            },
+            P2pMessage::MsgGspArcResponse(msg) => match maybe_key {
+                Some(key) => self.handle_gsp_arc_response(key, msg),
+                None => warn!("Received arc gossip out of a space channel"),
+            },
+            P2pMessage::MsgGspAspectDataRequest(msg) => {
+                if let Some(request) = self.handle_aspect_data_request(msg)? {
+                    outbox.push(request);
                 }
             }
-            P2pProtocol::DirectMessage(dm_data) => {
+            P2pMessage::MsgGspAspectDataResponse(msg) => {
+                outbox.append(&mut self.handle_aspect_data_response(msg)?);
+            }
+            P2pMessage::MsgGspAspectBroadcast(_) => {
+                warn!("Received unhandled P2pMessage: {:?}", p2p_msg);
+            }
+            P2pMessage::MsgDirectRequest(msg) => {
+                let dm_data = direct_message_from_wire(msg);
                 let maybe_space_gateway = self.space_gateway_map.get(&(
                     dm_data.space_address.to_owned(),
                     dm_data.to_agent_id.to_owned(),
                 ));
                 if let Some(_) = maybe_space_gateway {
                     // Change into Lib3hServerProtocol
-                    let lib3_msg = Lib3hServerProtocol::HandleSendDirectMessage(dm_data.clone());
+                    let lib3_msg = Lib3hServerProtocol::HandleSendDirectMessage(dm_data);
                     outbox.push(lib3_msg);
                 } else {
                     warn!(
@@ -257,13 +804,14 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
                     );
                 }
             }
-            P2pProtocol::DirectMessageResult(dm_data) => {
+            P2pMessage::MsgDirectResponse(msg) => {
+                let dm_data = direct_message_from_wire(msg);
                 let maybe_space_gateway = self.space_gateway_map.get(&(
                     dm_data.space_address.to_owned(),
                     dm_data.to_agent_id.to_owned(),
                 ));
                 if let Some(_) = maybe_space_gateway {
-                    let lib3_msg = Lib3hServerProtocol::SendDirectMessageResult(dm_data.clone());
+                    let lib3_msg = Lib3hServerProtocol::SendDirectMessageResult(dm_data);
                     outbox.push(lib3_msg);
                 } else {
                     warn!(
@@ -272,29 +820,418 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
                     );
                 }
             }
-            P2pProtocol::PeerAddress(_, _, _) => {
-                // no-op
+            P2pMessage::MsgQueryRequest(msg) => {
+                let space_address = address_from_wire(&msg.space_address);
+                let agent_id = address_from_wire(&msg.to_agent_id);
+                if !self
+                    .space_gateway_map
+                    .contains_key(&(space_address.clone(), agent_id.clone()))
+                {
+                    warn!("Received query for unjoined space: {}", space_address);
+                    return Ok(outbox);
+                }
+                // Ask our Core and track the request for HandleQueryEntryResult
+                let requester_agent_id = address_from_wire(&msg.from_agent_id);
+                let entry_address = address_from_wire(&msg.entry_address);
+                let request_id = self.request_track.reserve();
+                self.request_track.set(
+                    &request_id,
+                    Some(RealEngineTrackerData::RemoteQueryEntry {
+                        request_id: msg.request_id.clone(),
+                        requester_agent_id: requester_agent_id.clone(),
+                        space_address: space_address.clone(),
+                        entry_address: entry_address.clone(),
+                        agent_id,
+                    }),
+                );
+                outbox.push(Lib3hServerProtocol::HandleQueryEntry(QueryEntryData {
+                    space_address,
+                    entry_address,
+                    request_id,
+                    requester_agent_id,
+                    query: msg.data.clone(),
+                }));
             }
-            P2pProtocol::BroadcastJoinSpace(gateway_id, peer_data) => {
-                debug!("Received JoinSpace: {} {:?}", gateway_id, peer_data);
-                for (_, space_gateway) in self.space_gateway_map.iter_mut() {
-                    space_gateway
-                        .as_dht_mut()
-                        .post(DhtCommand::HoldPeer(peer_data.clone()))?;
+            P2pMessage::MsgQueryResponse(msg) => {
+                let responder: ChainId = (
+                    address_from_wire(&msg.space_address),
+                    address_from_wire(&msg.from_agent_id),
+                );
+                // Responders answer on their own channel to us
+                let is_responder_channel = maybe_key
+                    .map(|key| key.space_address == responder.0 && key.from_agent_id == responder.1)
+                    .unwrap_or(false);
+                if !is_responder_channel {
+                    warn!(
+                        "Received query response off its responder's channel: {:?}",
+                        maybe_key
+                    );
+                    return Ok(outbox);
                 }
+                if msg.failed {
+                    let reason = String::from_utf8_lossy(&msg.data);
+                    outbox.append(&mut self.add_query_entry_failure(
+                        &msg.request_id,
+                        Some(&responder),
+                        &reason,
+                    ));
+                    return Ok(outbox);
+                }
+                let result = QueryEntryResultData {
+                    space_address: address_from_wire(&msg.space_address),
+                    entry_address: address_from_wire(&msg.entry_address),
+                    request_id: msg.request_id.clone(),
+                    requester_agent_id: address_from_wire(&msg.to_agent_id),
+                    responder_agent_id: address_from_wire(&msg.from_agent_id),
+                    query_result: msg.data.clone(),
+                };
+                outbox.append(&mut self.add_query_entry_result(Some(&responder), result));
             }
-            P2pProtocol::AllJoinedSpaceList(join_list) => {
-                debug!("Received AllJoinedSpaceList: {:?}", join_list);
-                for (space_address, peer_data) in join_list {
-                    let maybe_space_gateway = self.get_first_space_mut(space_address);
-                    if let Some(space_gateway) = maybe_space_gateway {
+            P2pMessage::MsgJoinSpace(msg) => {
+                debug!("Received MsgJoinSpace: {:?}", msg);
+                for peer_info in msg.peer_list.iter() {
+                    let signer_id =
+                        match verify_peer_info(self.crypto.as_crypto_system(), peer_info) {
+                            Ok(signer_id) => signer_id,
+                            Err(e) => return self.reject_forged_announcement(from_id, e),
+                        };
+                    let (space_address, peer_data) = peer_from_wire(peer_info)?;
+                    let is_from_signer = self.connection_peer_map.get(from_id) == Some(&signer_id);
+                    self.agent_announcer_map
+                        .entry((
+                            space_address.clone().into(),
+                            peer_data.peer_address.clone().into(),
+                        ))
+                        .or_insert_with(HashSet::new)
+                        .insert(signer_id);
+                    if self.config.accept_relay_duty && is_from_signer {
+                        // Channels to that agent are relayed on the connection of its node
+                        self.relay_route_map.insert(
+                            (
+                                space_address.clone().into(),
+                                peer_data.peer_address.clone().into(),
+                            ),
+                            from_id.to_string(),
+                        );
+                    }
+                    // Hold peer in every gateway of that space
+                    for (chain_id, space_gateway) in self.space_gateway_map.iter_mut() {
+                        let current_space_address: String = chain_id.0.clone().into();
+                        if current_space_address == space_address {
+                            space_gateway
+                                .as_dht_mut()
+                                .post(DhtCommand::HoldPeer(peer_data.clone()))?;
+                        }
+                    }
+                }
+            }
+            P2pMessage::MsgLeaveSpace(msg) => {
+                debug!("Received MsgLeaveSpace: {:?}", msg);
+                let signer_id = match verify_leave_space(self.crypto.as_crypto_system(), msg) {
+                    Ok(signer_id) => signer_id,
+                    Err(e) => return self.reject_forged_announcement(from_id, e),
+                };
+                let (space_address, peer_data) = leave_from_wire(msg)?;
+                let chain_id: ChainId = (
+                    space_address.clone().into(),
+                    peer_data.peer_address.clone().into(),
+                );
+                self.relay_route_map.remove(&chain_id);
+                if let Some(announcer_set) = self.agent_announcer_map.get_mut(&chain_id) {
+                    announcer_set.remove(&signer_id);
+                    if announcer_set.is_empty() {
+                        self.agent_announcer_map.remove(&chain_id);
+                    }
+                }
+                // Drop peer from every gateway of that space,
+                // unless they hold a PeerData as recent as the leave
+                for (chain_id, space_gateway) in self.space_gateway_map.iter_mut() {
+                    let current_space_address: String = chain_id.0.clone().into();
+                    if current_space_address == space_address {
                         space_gateway
                             .as_dht_mut()
-                            .post(DhtCommand::HoldPeer(peer_data.clone()))?;
+                            .post(DhtCommand::DropPeer(peer_data.clone()))?;
                     }
                 }
             }
+            P2pMessage::MsgDhtGossip(msg) => {
+                let gateway = match self.gossip_gateway(&msg) {
+                    Some(gateway) => gateway,
+                    None => return Ok(outbox),
+                };
+                let from_peer_address = str_from_wire(&msg.from_peer_address);
+                // Ack gossip expecting it, and handle it only once
+                if msg.gossip_id != 0
+                    && !gateway
+                        .as_mut()
+                        .receive_reliable_gossip(&from_peer_address, msg.gossip_id)
+                {
+                    trace!(
+                        "Dropping duplicate gossip {} from {}",
+                        msg.gossip_id,
+                        from_peer_address,
+                    );
+                    return Ok(outbox);
+                }
+                // Prepare remoteGossipTo to post to dht
+                let cmd = DhtCommand::HandleGossip(RemoteGossipBundleData {
+                    from_peer_address,
+                    bundle: msg.bundle.clone(),
+                });
+                gateway.as_dht_mut().post(cmd)?;
+            }
+            P2pMessage::MsgDhtGossipAck(msg) => {
+                if let Some(gateway) = self.gossip_gateway(&msg) {
+                    gateway
+                        .as_mut()
+                        .handle_gossip_ack(&str_from_wire(&msg.from_peer_address), msg.gossip_id);
+                }
+            }
         };
         Ok(outbox)
     }
+
+    /// Gateway of the DHT a gossip is for: the network gateway or one of our space gateways
+    fn gossip_gateway(&self, msg: &MsgDhtGossip) -> Option<GatewayWrapper<'engine>> {
+        let space_address = str_from_wire(&msg.space_address);
+        if space_address == NETWORK_GATEWAY_ID {
+            return Some(self.network_gateway.clone());
+        }
+        let maybe_space_gateway = self
+            .space_gateway_map
+            .get(&(
+                space_address.clone().into(),
+                address_from_wire(&msg.to_peer_address),
+            ))
+            .cloned();
+        if maybe_space_gateway.is_none() {
+            warn!("received gossip for unjoined space: {}", space_address);
+        }
+        maybe_space_gateway
+    }
+
+    /// A remote agent wants entry data from us:
+    /// ask our Core for it, or tell right away that we don't hold it.
+    fn handle_aspect_data_request(
+        &mut self,
+        msg: &MsgGspAspectDataRequest,
+    ) -> Lib3hResult<Option<Lib3hServerProtocol>> {
+        let space_address = address_from_wire(&msg.space_address);
+        let agent_id = address_from_wire(&msg.to_agent_id);
+        let requester_agent_id = address_from_wire(&msg.from_agent_id);
+        let chain_id = (space_address.clone(), agent_id.clone());
+        let maybe_space_gateway = self.space_gateway_map.get(&chain_id).cloned();
+        let space_gateway = match maybe_space_gateway {
+            Some(space_gateway) => space_gateway,
+            None => {
+                warn!(
+                    "Received entry request for unjoined space: {}",
+                    space_address
+                );
+                return Ok(None);
+            }
+        };
+        let (entry_address, aspect_address_list) = match msg.aspect_hash_list.first() {
+            Some(aspect_hash_list) => aspect_request_from_wire(aspect_hash_list),
+            None => {
+                warn!("Received empty entry request: {:?}", msg);
+                return Ok(None);
+            }
+        };
+        if space_gateway
+            .as_ref()
+            .get_aspects_of(&entry_address)
+            .is_none()
+        {
+            // Not a holder: respond with no aspects
+            let entry = EntryData {
+                entry_address,
+                aspect_list: Vec::new(),
+            };
+            let response = MsgGspAspectDataResponse {
+                aspect_data_list: vec![entry_to_wire(&entry)?],
+                request_id: msg.request_id.clone(),
+                space_address: msg.space_address.clone(),
+                to_agent_id: msg.from_agent_id.clone(),
+                from_agent_id: msg.to_agent_id.clone(),
+            };
+            self.send_to_space_peer(
+                &space_address,
+                &agent_id,
+                &requester_agent_id,
+                &P2pMessage::MsgGspAspectDataResponse(response),
+            );
+            return Ok(None);
+        }
+        // Ask our Core and track the request for HandleFetchEntryResult
+        let request_id = self.request_track.reserve();
+        self.request_track.set(
+            &request_id,
+            Some(RealEngineTrackerData::RemoteFetchEntry {
+                request_id: msg.request_id.clone(),
+                requester_agent_id,
+                aspect_address_list: aspect_address_list.clone(),
+            }),
+        );
+        Ok(Some(Lib3hServerProtocol::HandleFetchEntry(
+            FetchEntryData {
+                space_address,
+                entry_address,
+                request_id,
+                provider_agent_id: agent_id,
+                aspect_address_list,
+            },
+        )))
+    }
+
+    /// A holder responded to one of our FetchEntry requests:
+    /// merge its aspects and output the result once complete.
+    /// Aspects requested by arc gossip are sent to Core for storage.
+    fn handle_aspect_data_response(
+        &mut self,
+        msg: &MsgGspAspectDataResponse,
+    ) -> Lib3hResult<Vec<Lib3hServerProtocol>> {
+        let (request, mut entry, pending_count) = match self.request_track.get(&msg.request_id) {
+            Some(RealEngineTrackerData::NetworkFetchEntry {
+                request,
+                entry,
+                pending_count,
+            }) => (request.clone(), entry.clone(), *pending_count),
+            Some(RealEngineTrackerData::GossipFetchEntry { chain_id }) => {
+                let chain_id = chain_id.clone();
+                self.request_track.remove(&msg.request_id);
+                let provider_agent_id = address_from_wire(&msg.from_agent_id);
+                let mut outbox = Vec::new();
+                for aspect_data_list in msg.aspect_data_list.iter() {
+                    let entry = entry_from_wire(aspect_data_list)?;
+                    outbox.append(&mut self.store_entry_aspect_list(
+                        &chain_id,
+                        &provider_agent_id,
+                        entry,
+                    ));
+                }
+                return Ok(outbox);
+            }
+            _ => {
+                warn!("Received untracked entry response: {}", msg.request_id);
+                return Ok(Vec::new());
+            }
+        };
+        for aspect_data_list in msg.aspect_data_list.iter() {
+            let received = entry_from_wire(aspect_data_list)?;
+            if !entry.merge(&received) {
+                warn!(
+                    "Received aspects of another entry: {}",
+                    received.entry_address
+                );
+            }
+        }
+        let pending_count = pending_count.saturating_sub(1);
+        let is_complete = match &request.aspect_address_list {
+            None => false,
+            Some(requested) => {
+                let known: Vec<Address> = entry
+                    .aspect_list
+                    .iter()
+                    .map(|aspect| aspect.aspect_address.clone())
+                    .collect();
+                includes(&known, requested)
+            }
+        };
+        if pending_count > 0 && !is_complete {
+            self.request_track.set(
+                &msg.request_id,
+                Some(RealEngineTrackerData::NetworkFetchEntry {
+                    request,
+                    entry,
+                    pending_count,
+                }),
+            );
+            return Ok(Vec::new());
+        }
+        self.request_track.remove(&msg.request_id);
+        if entry.aspect_list.is_empty() {
+            return Ok(vec![fetch_entry_failure(&request, "Entry not found")]);
+        }
+        Ok(vec![fetch_entry_result(&request, entry)])
+    }
+}
+
+/// Accept channels we relay: from a peer we relay for,
+/// or to an agent announced by a peer we relay for
+fn is_relay_channel(
+    relay_route_map: &HashMap<ChainId, ConnectionId>,
+    relayed_id_list: &[ConnectionId],
+    from_id: &ConnectionIdRef,
+    key: &ChannelKey,
+) -> bool {
+    let chain_id = (key.space_address.clone(), key.to_agent_id.clone());
+    match relay_route_map.get(&chain_id) {
+        None => false,
+        Some(to_id) => {
+            to_id != from_id
+                && relayed_id_list
+                    .iter()
+                    .any(|relayed_id| relayed_id == from_id || relayed_id == to_id)
+        }
+    }
+}
+
+/// Accept channels from an agent announced by the peer on that connection,
+/// or by a node that peer relays for, as advertised in our network DHT
+fn check_from_agent(
+    agent_announcer_map: &HashMap<ChainId, HashSet<String>>,
+    space_gateway_map: &HashMap<ChainId, GatewayWrapper<'_>>,
+    network_gateway: &GatewayWrapper<'_>,
+    maybe_peer_address: Option<&String>,
+    key: &ChannelKey,
+) -> Result<(), MultiplexErrorCode> {
+    let peer_address = match maybe_peer_address {
+        Some(peer_address) => peer_address,
+        None => return Err(MultiplexErrorCode::BadFromId),
+    };
+    let chain_id = (key.space_address.clone(), key.from_agent_id.clone());
+    let is_announced_by_peer = agent_announcer_map
+        .get(&chain_id)
+        .map(|announcer_set| announcer_set.contains(peer_address))
+        .unwrap_or(false);
+    if is_announced_by_peer {
+        return Ok(());
+    }
+    // A relayed agent is only known from gossip by the peers of its relay
+    let from_agent_id: String = key.from_agent_id.clone().into();
+    let maybe_transport_id = space_gateway_map
+        .iter()
+        .find(|(chain_id, _)| chain_id.0 == key.space_address)
+        .and_then(|(_, space_gateway)| space_gateway.as_dht_ref().get_peer(&from_agent_id))
+        .map(|peer| peer.peer_uri.path().to_string());
+    let is_relayed_by_peer = maybe_transport_id
+        .and_then(|transport_id| network_gateway.as_dht_ref().get_peer(&transport_id))
+        .map(|peer| match parse_relay_uri(&peer.peer_uri) {
+            Some((relay_address, _)) => relay_address.eq_ignore_ascii_case(peer_address),
+            None => false,
+        })
+        .unwrap_or(false);
+    if is_relayed_by_peer {
+        return Ok(());
+    }
+    Err(MultiplexErrorCode::BadFromId)
+}
+
+/// Accept channels to one of our agents in a space we joined
+fn check_channel(
+    space_gateway_map: &HashMap<ChainId, GatewayWrapper<'_>>,
+    key: &ChannelKey,
+) -> Result<(), MultiplexErrorCode> {
+    if !space_gateway_map
+        .keys()
+        .any(|(space_address, _)| *space_address == key.space_address)
+    {
+        return Err(MultiplexErrorCode::BadSpaceHash);
+    }
+    let chain_id = (key.space_address.clone(), key.to_agent_id.clone());
+    if !space_gateway_map.contains_key(&chain_id) {
+        return Err(MultiplexErrorCode::BadToId);
+    }
+    Ok(())
 }
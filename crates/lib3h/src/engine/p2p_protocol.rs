@@ -1,30 +1,565 @@
-use crate::dht::{dht_protocol::PeerData, PeerAddress};
-use lib3h_protocol::{data_types::DirectMessageData, Address};
+//! Conversions between lib3h types and the `lib3h_p2p_protocol` wire messages
+//! of the 'network module <-> network module' protocol.
+
+use crate::{
+    dht::{
+        dht_protocol::PeerData,
+        rrdht::{StorageArc, FULL_ARC_RADIUS},
+        PeerAddress,
+    },
+    engine::TransportKeys,
+    error::{Lib3hError, Lib3hResult},
+};
+use lib3h_crypto_api::{Buffer, CryptoSystem};
+use lib3h_protocol::{
+    data_types::{DirectMessageData, EntryAspectData, EntryData},
+    Address,
+};
+use rmp_serde::{Deserializer, Serializer};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+pub use lib3h_p2p_protocol::p2p::*;
 
 pub type SpaceAddress = String;
 pub type GatewayId = String;
 pub type PeerTimestamp = u64;
 
-/// Enum holding all message types in the 'network module <-> network module' protocol.
-/// TODO #150 - replace this with the p2p-protocol crate
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub enum P2pProtocol {
-    Gossip(GossipData),
-    DirectMessage(DirectMessageData),
-    DirectMessageResult(DirectMessageData),
-    /// Notify another node's our identify in a specific gateway/dht
-    PeerAddress(GatewayId, PeerAddress, PeerTimestamp),
-    /// Broadcast JoinSpace to all when joining a space
-    BroadcastJoinSpace(SpaceAddress, PeerData),
-    /// For sending a peer's 'JoinSpace' info to a newly connected peer
-    AllJoinedSpaceList(Vec<(SpaceAddress, PeerData)>),
-}
-
-/// DHT gossip data
-#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
-pub struct GossipData {
-    pub space_address: Address,
-    pub to_peer_address: Address,
-    pub from_peer_address: Address,
-    pub bundle: Vec<u8>,
+/// Length of the random nonce binding a MsgHandshake to its connection
+pub(crate) const HANDSHAKE_NONCE_BYTES: usize = 32;
+/// Handshakes timestamped further than this from our own clock are rejected as stale
+pub(crate) const HANDSHAKE_MAX_CLOCK_DRIFT_MS: u64 = 5 * 60 * 1000;
+
+/// Wire representation of an Address
+pub(crate) fn address_to_wire(address: &Address) -> Vec<u8> {
+    let address: String = address.clone().into();
+    address.into_bytes()
+}
+
+/// Address out of its wire representation
+pub(crate) fn address_from_wire(bytes: &[u8]) -> Address {
+    String::from_utf8_lossy(bytes).to_string().into()
+}
+
+/// String identifier (GatewayId, PeerAddress...) out of its wire representation
+pub(crate) fn str_from_wire(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).to_string()
+}
+
+pub(crate) fn direct_message_to_wire(msg: &DirectMessageData) -> MsgDirect {
+    MsgDirect {
+        request_id: msg.request_id.clone(),
+        data: msg.content.clone(),
+        space_address: address_to_wire(&msg.space_address),
+        to_agent_id: address_to_wire(&msg.to_agent_id),
+        from_agent_id: address_to_wire(&msg.from_agent_id),
+    }
+}
+
+pub(crate) fn direct_message_from_wire(msg: &MsgDirect) -> DirectMessageData {
+    DirectMessageData {
+        space_address: address_from_wire(&msg.space_address),
+        request_id: msg.request_id.clone(),
+        to_agent_id: address_from_wire(&msg.to_agent_id),
+        from_agent_id: address_from_wire(&msg.from_agent_id),
+        content: msg.data.clone(),
+    }
+}
+
+/// Wire request for some aspects of an entry.
+/// An empty aspect_hash_list stands for all the aspects of that entry.
+pub(crate) fn aspect_request_to_wire(
+    entry_address: &Address,
+    aspect_address_list: &Option<Vec<Address>>,
+) -> AspectHashList {
+    let aspect_hash_list = match aspect_address_list {
+        None => Vec::new(),
+        Some(list) => list.iter().map(address_to_wire).collect(),
+    };
+    AspectHashList {
+        entry_address: address_to_wire(entry_address),
+        aspect_hash_list,
+    }
+}
+
+/// Entry address and requested aspect addresses out of an AspectHashList
+pub(crate) fn aspect_request_from_wire(list: &AspectHashList) -> (Address, Option<Vec<Address>>) {
+    let aspect_address_list = if list.aspect_hash_list.is_empty() {
+        None
+    } else {
+        Some(
+            list.aspect_hash_list
+                .iter()
+                .map(|aspect_hash| address_from_wire(aspect_hash))
+                .collect(),
+        )
+    };
+    (address_from_wire(&list.entry_address), aspect_address_list)
+}
+
+/// Wire representation of a storage arc: locations in [arc_start, arc_end).
+/// The full ring is sent as arc_start == arc_end.
+pub(crate) fn arc_to_wire(arc: &StorageArc) -> AspectConstraintArc {
+    if arc.radius >= FULL_ARC_RADIUS {
+        return AspectConstraintArc {
+            arc_start: arc.center,
+            arc_end: arc.center,
+        };
+    }
+    AspectConstraintArc {
+        arc_start: arc.center.wrapping_sub(arc.radius),
+        arc_end: arc.center.wrapping_add(arc.radius).wrapping_add(1),
+    }
+}
+
+pub(crate) fn arc_from_wire(arc: &AspectConstraintArc) -> StorageArc {
+    let length = arc.arc_end.wrapping_sub(arc.arc_start);
+    if length == 0 {
+        return StorageArc::new(arc.arc_start, FULL_ARC_RADIUS);
+    }
+    let radius = (length - 1) / 2;
+    StorageArc::new(arc.arc_start.wrapping_add(radius), radius)
+}
+
+/// Wire representation of an entry: one MessagePack encoded EntryAspectData per aspect
+pub(crate) fn entry_to_wire(entry: &EntryData) -> Lib3hResult<AspectDataList> {
+    let mut aspect_data_list = Vec::with_capacity(entry.aspect_list.len());
+    for aspect in entry.aspect_list.iter() {
+        let mut buf = Vec::new();
+        aspect.serialize(&mut Serializer::new(&mut buf))?;
+        aspect_data_list.push(buf);
+    }
+    Ok(AspectDataList {
+        entry_address: address_to_wire(&entry.entry_address),
+        aspect_data_list,
+    })
+}
+
+pub(crate) fn entry_from_wire(list: &AspectDataList) -> Lib3hResult<EntryData> {
+    let mut aspect_list = Vec::with_capacity(list.aspect_data_list.len());
+    for aspect_data in list.aspect_data_list.iter() {
+        let mut de = Deserializer::new(&aspect_data[..]);
+        let aspect: EntryAspectData = Deserialize::deserialize(&mut de)?;
+        aspect_list.push(aspect);
+    }
+    Ok(EntryData {
+        entry_address: address_from_wire(&list.entry_address),
+        aspect_list,
+    })
+}
+
+pub(crate) fn peer_to_wire(space_address: &str, peer: &PeerData) -> PeerInfo {
+    PeerInfo {
+        space_address: space_address.as_bytes().to_vec(),
+        peer_address: peer.peer_address.as_bytes().to_vec(),
+        peer_uri: peer.peer_uri.to_string(),
+        timestamp: peer.timestamp,
+        signer_pub_key: Vec::new(),
+        signature: Vec::new(),
+    }
+}
+
+/// Return the space address and the PeerData held in a PeerInfo.
+/// Fails if the peer_uri is not a valid Url.
+pub(crate) fn peer_from_wire(info: &PeerInfo) -> Lib3hResult<(SpaceAddress, PeerData)> {
+    let peer_uri = Url::parse(&info.peer_uri)
+        .map_err(|e| Lib3hError::new_other(&format!("Invalid peer_uri: {}", e)))?;
+    let peer_address: PeerAddress = str_from_wire(&info.peer_address);
+    Ok((
+        str_from_wire(&info.space_address),
+        PeerData {
+            peer_address,
+            peer_uri,
+            timestamp: info.timestamp,
+        },
+    ))
+}
+
+pub(crate) fn leave_to_wire(space_address: &str, peer: &PeerData) -> MsgLeaveSpace {
+    MsgLeaveSpace {
+        space_address: space_address.as_bytes().to_vec(),
+        peer_address: peer.peer_address.as_bytes().to_vec(),
+        peer_uri: peer.peer_uri.to_string(),
+        timestamp: peer.timestamp,
+        signer_pub_key: Vec::new(),
+        signature: Vec::new(),
+    }
+}
+
+/// Return the space address and the PeerData of the peer leaving it.
+/// Fails if the peer_uri is not a valid Url.
+pub(crate) fn leave_from_wire(msg: &MsgLeaveSpace) -> Lib3hResult<(SpaceAddress, PeerData)> {
+    let peer_uri = Url::parse(&msg.peer_uri)
+        .map_err(|e| Lib3hError::new_other(&format!("Invalid peer_uri: {}", e)))?;
+    Ok((
+        str_from_wire(&msg.space_address),
+        PeerData {
+            peer_address: str_from_wire(&msg.peer_address),
+            peer_uri,
+            timestamp: msg.timestamp,
+        },
+    ))
+}
+
+/// TransportId matching a transport signing public key
+pub(crate) fn transport_id_from_wire(pub_key: &[u8]) -> Lib3hResult<String> {
+    let hcm0 = hcid::HcidEncoding::with_kind("hcm0")?;
+    Ok(hcm0.encode(pub_key)?)
+}
+
+/// Sign a peer announcement's content with our transport secret key
+fn sign_with_transport_key(
+    crypto: &dyn CryptoSystem,
+    keys: &TransportKeys,
+    data: &[u8],
+) -> Lib3hResult<Vec<u8>> {
+    let message: Box<dyn Buffer> = Box::new(data.to_vec());
+    let mut signature: Box<dyn Buffer> = Box::new(vec![0; crypto.sign_bytes()]);
+    crypto.sign(&mut signature, &message, &keys.transport_secret_key)?;
+    Ok(signature.to_vec())
+}
+
+/// Check a peer announcement's signature and return the TransportId of its signer.
+fn verify_signer(
+    crypto: &dyn CryptoSystem,
+    signature: &[u8],
+    data: &[u8],
+    signer_pub_key: &[u8],
+) -> Lib3hResult<String> {
+    if signature.len() != crypto.sign_bytes()
+        || signer_pub_key.len() != crypto.sign_public_key_bytes()
+    {
+        return Err(Lib3hError::new_other("Malformed peer signature"));
+    }
+    let signature: Box<dyn Buffer> = Box::new(signature.to_vec());
+    let message: Box<dyn Buffer> = Box::new(data.to_vec());
+    let public_key: Box<dyn Buffer> = Box::new(signer_pub_key.to_vec());
+    if !crypto.sign_verify(&signature, &message, &public_key)? {
+        return Err(Lib3hError::new_other("Invalid peer signature"));
+    }
+    transport_id_from_wire(signer_pub_key)
+}
+
+/// Random nonce of our MsgHandshake on a new connection
+pub(crate) fn handshake_nonce(crypto: &dyn CryptoSystem) -> Lib3hResult<Vec<u8>> {
+    let mut nonce: Box<dyn Buffer> = Box::new(vec![0; HANDSHAKE_NONCE_BYTES]);
+    crypto.randombytes_buf(&mut nonce)?;
+    Ok(nonce.to_vec())
+}
+
+/// Sign our network handshake with our transport keys
+pub(crate) fn sign_handshake(
+    crypto: &dyn CryptoSystem,
+    keys: &TransportKeys,
+    handshake: &mut MsgHandshake,
+) -> Lib3hResult<()> {
+    handshake.signer_pub_key = keys.transport_public_key.to_vec();
+    handshake.signature = sign_with_transport_key(crypto, keys, &handshake.signed_bytes())?;
+    Ok(())
+}
+
+/// Accept a handshake only if it is signed by the transport key of the peer_address it claims,
+/// is recent, and echoes `our_nonce`: the nonce of the handshake we sent on the same connection,
+/// empty if we did not send one yet.
+pub(crate) fn verify_handshake(
+    crypto: &dyn CryptoSystem,
+    handshake: &MsgHandshake,
+    our_nonce: &[u8],
+    now_ms: u64,
+) -> Lib3hResult<()> {
+    let signer_id = verify_signer(
+        crypto,
+        &handshake.signature,
+        &handshake.signed_bytes(),
+        &handshake.signer_pub_key,
+    )?;
+    let peer_address = str_from_wire(&handshake.peer_address);
+    if signer_id != peer_address {
+        return Err(Lib3hError::new_other(&format!(
+            "Handshake for {} signed by {}",
+            peer_address, signer_id,
+        )));
+    }
+    if handshake.nonce.len() != HANDSHAKE_NONCE_BYTES || handshake.remote_nonce != our_nonce {
+        return Err(Lib3hError::new_other(&format!(
+            "Handshake of {} not bound to this connection",
+            peer_address,
+        )));
+    }
+    let drift_ms = if handshake.timestamp > now_ms {
+        handshake.timestamp - now_ms
+    } else {
+        now_ms - handshake.timestamp
+    };
+    if drift_ms > HANDSHAKE_MAX_CLOCK_DRIFT_MS {
+        return Err(Lib3hError::new_other(&format!(
+            "Stale handshake of {}: {} ms off",
+            peer_address, drift_ms,
+        )));
+    }
+    Ok(())
+}
+
+/// Sign one of our space PeerInfo with our transport keys
+pub(crate) fn sign_peer_info(
+    crypto: &dyn CryptoSystem,
+    keys: &TransportKeys,
+    info: &mut PeerInfo,
+) -> Lib3hResult<()> {
+    info.signer_pub_key = keys.transport_public_key.to_vec();
+    info.signature = sign_with_transport_key(crypto, keys, &info.signed_bytes())?;
+    Ok(())
+}
+
+/// Accept a PeerInfo only if it is signed by the transport key its peer_uri points to.
+/// Return the transportId of its signer.
+pub(crate) fn verify_peer_info(crypto: &dyn CryptoSystem, info: &PeerInfo) -> Lib3hResult<String> {
+    let signer_id = verify_signer(
+        crypto,
+        &info.signature,
+        &info.signed_bytes(),
+        &info.signer_pub_key,
+    )?;
+    check_peer_uri_signer("PeerInfo", &info.peer_uri, &signer_id)?;
+    Ok(signer_id)
+}
+
+/// Sign the leave notice of one of our space peers with our transport keys
+pub(crate) fn sign_leave_space(
+    crypto: &dyn CryptoSystem,
+    keys: &TransportKeys,
+    msg: &mut MsgLeaveSpace,
+) -> Lib3hResult<()> {
+    msg.signer_pub_key = keys.transport_public_key.to_vec();
+    msg.signature = sign_with_transport_key(crypto, keys, &msg.signed_bytes())?;
+    Ok(())
+}
+
+/// Accept a leave notice only if it is signed by the transport key its peer_uri points to.
+/// Return the transportId of its signer.
+pub(crate) fn verify_leave_space(
+    crypto: &dyn CryptoSystem,
+    msg: &MsgLeaveSpace,
+) -> Lib3hResult<String> {
+    let signer_id = verify_signer(
+        crypto,
+        &msg.signature,
+        &msg.signed_bytes(),
+        &msg.signer_pub_key,
+    )?;
+    check_peer_uri_signer("Leave notice", &msg.peer_uri, &signer_id)?;
+    Ok(signer_id)
+}
+
+/// A peer can only be announced by the node its peer_uri points to
+fn check_peer_uri_signer(what: &str, peer_uri: &str, signer_id: &str) -> Lib3hResult<()> {
+    // TODO #175 - encapsulate this conversion logic
+    let expected_uri = format!("transportId:{}", signer_id);
+    if peer_uri != expected_uri {
+        return Err(Lib3hError::new_other(&format!(
+            "{} for {} signed by {}",
+            what, peer_uri, signer_id,
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lib3h_sodium::SodiumCryptoSystem;
+
+    #[test]
+    fn it_should_convert_direct_message_to_wire_and_back() {
+        let msg = DirectMessageData {
+            space_address: "space_1".to_string().into(),
+            request_id: "req_1".to_string(),
+            to_agent_id: "billy".to_string().into(),
+            from_agent_id: "alex".to_string().into(),
+            content: b"hello".to_vec(),
+        };
+        let wire = P2pMessage::MsgDirectRequest(direct_message_to_wire(&msg)).to_bytes();
+        let decoded = P2pMessage::from_bytes(&wire).unwrap();
+        let decoded = unwrap_to!(decoded => P2pMessage::MsgDirectRequest);
+        assert_eq!(direct_message_from_wire(decoded), msg);
+    }
+
+    #[test]
+    fn it_should_convert_entry_to_wire_and_back() {
+        let entry = EntryData {
+            entry_address: "entry_1".to_string().into(),
+            aspect_list: vec![EntryAspectData {
+                aspect_address: "aspect_1".to_string().into(),
+                type_hint: "hint".to_string(),
+                aspect: b"content".to_vec(),
+                publish_ts: 42,
+            }],
+        };
+        let wire = entry_to_wire(&entry).unwrap();
+        assert_eq!(entry_from_wire(&wire).unwrap(), entry);
+        // Requests
+        let entry_address: Address = "entry_1".to_string().into();
+        let all = aspect_request_to_wire(&entry_address, &None);
+        assert_eq!(
+            aspect_request_from_wire(&all),
+            (entry_address.clone(), None)
+        );
+        let some = Some(vec!["aspect_1".to_string().into()]);
+        let wire = aspect_request_to_wire(&entry_address, &some);
+        assert_eq!(aspect_request_from_wire(&wire), (entry_address, some));
+    }
+
+    #[test]
+    fn it_should_convert_arc_to_wire_and_back() {
+        let arc = StorageArc::new(5, 10);
+        let wire = arc_to_wire(&arc);
+        assert_eq!(wire.arc_start, 0xFFFF_FFFB);
+        assert_eq!(wire.arc_end, 16);
+        assert_eq!(arc_from_wire(&wire), arc);
+        let full_arc = StorageArc::new(42, FULL_ARC_RADIUS);
+        let wire = arc_to_wire(&full_arc);
+        assert_eq!(wire.arc_start, wire.arc_end);
+        assert_eq!(arc_from_wire(&wire), full_arc);
+    }
+
+    #[test]
+    fn it_should_convert_peer_to_wire_and_back() {
+        let peer = PeerData {
+            peer_address: "alex".to_string(),
+            peer_uri: Url::parse("transportId:hcm0_alex").unwrap(),
+            timestamp: 42,
+        };
+        let info = peer_to_wire("space_1", &peer);
+        let (space_address, decoded) = peer_from_wire(&info).unwrap();
+        assert_eq!(space_address, "space_1");
+        assert_eq!(decoded, peer);
+    }
+
+    const NOW_MS: u64 = 1_563_000_000_000;
+
+    fn signed_handshake(crypto: &dyn CryptoSystem, keys: &TransportKeys) -> MsgHandshake {
+        let mut handshake = MsgHandshake {
+            store_arc_radius: 0,
+            query_arc_radius: 0,
+            space_address: b"__network__".to_vec(),
+            peer_address: keys.transport_id.as_bytes().to_vec(),
+            timestamp: NOW_MS,
+            signer_pub_key: Vec::new(),
+            signature: Vec::new(),
+            network_id: b"network_1".to_vec(),
+            nonce: handshake_nonce(crypto).unwrap(),
+            remote_nonce: Vec::new(),
+        };
+        sign_handshake(crypto, keys, &mut handshake).unwrap();
+        handshake
+    }
+
+    #[test]
+    fn it_should_verify_signed_handshake() {
+        let crypto = SodiumCryptoSystem::new();
+        let keys = TransportKeys::new(&crypto).unwrap();
+        let handshake = signed_handshake(&crypto, &keys);
+        assert!(verify_handshake(&crypto, &handshake, &[], NOW_MS).is_ok());
+        // Tampering with the content breaks the signature
+        let mut tampered = handshake.clone();
+        tampered.timestamp = NOW_MS + 1;
+        assert!(verify_handshake(&crypto, &tampered, &[], NOW_MS).is_err());
+        // So does changing its network
+        let mut tampered = handshake.clone();
+        tampered.network_id = b"network_2".to_vec();
+        assert!(verify_handshake(&crypto, &tampered, &[], NOW_MS).is_err());
+    }
+
+    #[test]
+    fn it_should_reject_handshake_of_another_connection() {
+        let crypto = SodiumCryptoSystem::new();
+        let keys = TransportKeys::new(&crypto).unwrap();
+        let our_nonce = handshake_nonce(&crypto).unwrap();
+        let mut handshake = signed_handshake(&crypto, &keys);
+        handshake.remote_nonce = our_nonce.clone();
+        sign_handshake(&crypto, &keys, &mut handshake).unwrap();
+        assert!(verify_handshake(&crypto, &handshake, &our_nonce, NOW_MS).is_ok());
+        // Replayed on a connection where we sent another nonce
+        let other_nonce = handshake_nonce(&crypto).unwrap();
+        assert!(verify_handshake(&crypto, &handshake, &other_nonce, NOW_MS).is_err());
+        // Or where we did not send ours yet
+        assert!(verify_handshake(&crypto, &handshake, &[], NOW_MS).is_err());
+        // The echoed nonce is covered by the signature
+        let mut tampered = handshake.clone();
+        tampered.remote_nonce = other_nonce.clone();
+        assert!(verify_handshake(&crypto, &tampered, &other_nonce, NOW_MS).is_err());
+    }
+
+    #[test]
+    fn it_should_reject_stale_handshake() {
+        let crypto = SodiumCryptoSystem::new();
+        let keys = TransportKeys::new(&crypto).unwrap();
+        let handshake = signed_handshake(&crypto, &keys);
+        let later_ms = NOW_MS + HANDSHAKE_MAX_CLOCK_DRIFT_MS;
+        assert!(verify_handshake(&crypto, &handshake, &[], later_ms).is_ok());
+        assert!(verify_handshake(&crypto, &handshake, &[], later_ms + 1).is_err());
+        // Timestamps too far in the future are rejected as well
+        let earlier_ms = NOW_MS - HANDSHAKE_MAX_CLOCK_DRIFT_MS - 1;
+        assert!(verify_handshake(&crypto, &handshake, &[], earlier_ms).is_err());
+    }
+
+    #[test]
+    fn it_should_reject_forged_handshake() {
+        let crypto = SodiumCryptoSystem::new();
+        let alex_keys = TransportKeys::new(&crypto).unwrap();
+        let mallory_keys = TransportKeys::new(&crypto).unwrap();
+        // Mallory announces alex's transportId with its own key
+        let mut forged = signed_handshake(&crypto, &mallory_keys);
+        forged.peer_address = alex_keys.transport_id.as_bytes().to_vec();
+        sign_handshake(&crypto, &mallory_keys, &mut forged).unwrap();
+        assert!(verify_handshake(&crypto, &forged, &[], NOW_MS).is_err());
+    }
+
+    #[test]
+    fn it_should_verify_peer_info_and_reject_forged_ones() {
+        let crypto = SodiumCryptoSystem::new();
+        let alex_keys = TransportKeys::new(&crypto).unwrap();
+        let mallory_keys = TransportKeys::new(&crypto).unwrap();
+        let peer = PeerData {
+            peer_address: "alex".to_string(),
+            peer_uri: Url::parse(&format!("transportId:{}", alex_keys.transport_id)).unwrap(),
+            timestamp: 42,
+        };
+        let mut info = peer_to_wire("space_1", &peer);
+        sign_peer_info(&crypto, &alex_keys, &mut info).unwrap();
+        assert_eq!(
+            verify_peer_info(&crypto, &info).unwrap(),
+            alex_keys.transport_id
+        );
+        let mut forged = peer_to_wire("space_1", &peer);
+        sign_peer_info(&crypto, &mallory_keys, &mut forged).unwrap();
+        assert!(verify_peer_info(&crypto, &forged).is_err());
+        // Unsigned PeerInfo are rejected too
+        let unsigned = peer_to_wire("space_1", &peer);
+        assert!(verify_peer_info(&crypto, &unsigned).is_err());
+    }
+
+    #[test]
+    fn it_should_not_accept_a_peer_info_signature_as_a_leave() {
+        let crypto = SodiumCryptoSystem::new();
+        let alex_keys = TransportKeys::new(&crypto).unwrap();
+        let peer = PeerData {
+            peer_address: "alex".to_string(),
+            peer_uri: Url::parse(&format!("transportId:{}", alex_keys.transport_id)).unwrap(),
+            timestamp: 42,
+        };
+        let mut leave = leave_to_wire("space_1", &peer);
+        sign_leave_space(&crypto, &alex_keys, &mut leave).unwrap();
+        assert!(verify_leave_space(&crypto, &leave).is_ok());
+        let (space_address, decoded) = leave_from_wire(&leave).unwrap();
+        assert_eq!(space_address, "space_1");
+        assert_eq!(decoded, peer);
+        // A relayed join announcement replayed as a leave
+        let mut info = peer_to_wire("space_1", &peer);
+        sign_peer_info(&crypto, &alex_keys, &mut info).unwrap();
+        let mut replayed = leave_to_wire("space_1", &peer);
+        replayed.signer_pub_key = info.signer_pub_key;
+        replayed.signature = info.signature;
+        assert!(verify_leave_space(&crypto, &replayed).is_err());
+    }
 }
@@ -0,0 +1,375 @@
+//! Persistence of a RealEngine's identity and DHT state in its `work_dir`.
+//! An empty `work_dir` disables persistence.
+
+use crate::{
+    dht::{dht_protocol::*, dht_trait::Dht},
+    engine::{ChainId, RealEngine, TransportKeys},
+    error::{Lib3hError, Lib3hResult},
+    transport::protocol::TransportCommand,
+};
+use lib3h_crypto_api::{Buffer, CryptoSystem};
+use lib3h_protocol::{
+    data_types::{EntryAspectData, EntryData},
+    Address,
+};
+use rmp_serde::{Deserializer, Serializer};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fs, path::Path};
+
+static TRANSPORT_KEYS_FILE: &'static str = "transport_keys.bin";
+static DHT_STATE_FILE: &'static str = "dht_state.bin";
+
+/// Minimum interval in ms between two stores of our DHT state while we are running
+const DHT_STATE_SAVE_INTERVAL_MS: u64 = 30_000;
+
+/// TransportKeys as stored on disk.
+/// The secret key is encrypted with a secret derived from the passphrase.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct EncryptedTransportKeys {
+    transport_public_key: Vec<u8>,
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    cipher: Vec<u8>,
+}
+
+/// Snapshot of a gateway's DHT: known peers and held entry aspects
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) struct DhtSnapshot {
+    pub peer_list: Vec<PeerData>,
+    pub entry_list: Vec<(Address, Vec<Address>)>,
+}
+
+/// Snapshot of all the DHTs of a RealEngine
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) struct EngineSnapshot {
+    pub network: DhtSnapshot,
+    pub space_list: Vec<(ChainId, DhtSnapshot)>,
+}
+
+impl DhtSnapshot {
+    /// Capture the state of a DHT, without its own peer
+    pub fn capture(dht: &dyn Dht) -> Self {
+        let this_peer_address = dht.this_peer().peer_address.clone();
+        let peer_list = dht
+            .get_peer_list()
+            .into_iter()
+            .filter(|peer| peer.peer_address != this_peer_address)
+            .collect();
+        let mut entry_list = Vec::new();
+        for entry_address in dht.get_entry_address_list() {
+            let aspect_list = dht.get_aspects_of(entry_address).unwrap_or_default();
+            entry_list.push((entry_address.clone(), aspect_list));
+        }
+        DhtSnapshot {
+            peer_list,
+            entry_list,
+        }
+    }
+
+    /// Have a DHT hold everything in this snapshot
+    pub fn restore(&self, dht: &mut dyn Dht) -> Lib3hResult<()> {
+        for peer in self.peer_list.iter() {
+            dht.post(DhtCommand::HoldPeer(peer.clone()))?;
+        }
+        for (entry_address, aspect_address_list) in self.entry_list.iter() {
+            // Restore a "fake" entry, in the sense an entry with no actual content,
+            // but valid addresses.
+            let aspect_list = aspect_address_list
+                .iter()
+                .map(|aspect_address| EntryAspectData {
+                    aspect_address: aspect_address.clone(),
+                    type_hint: String::new(),
+                    aspect: vec![],
+                    publish_ts: 0,
+                })
+                .collect();
+            let fake_entry = EntryData {
+                entry_address: entry_address.clone(),
+                aspect_list,
+            };
+            dht.post(DhtCommand::HoldEntryAspectAddress(fake_entry))?;
+        }
+        Ok(())
+    }
+}
+
+/// Persistence related private methods
+impl<'engine, D: Dht> RealEngine<'engine, D> {
+    /// Reload the DHT state stored in our work_dir:
+    /// Network peers are held and reconnected to right away,
+    /// space state is kept until that space is joined again.
+    pub(crate) fn restore_dht_state(&mut self) -> Lib3hResult<()> {
+        let snapshot = match load_engine_snapshot(&self.config.work_dir)? {
+            None => return Ok(()),
+            Some(snapshot) => snapshot,
+        };
+        debug!(
+            "{} - restoring {} network peers and {} spaces",
+            self.name,
+            snapshot.network.peer_list.len(),
+            snapshot.space_list.len(),
+        );
+        snapshot
+            .network
+            .restore(&mut *self.network_gateway.as_dht_mut())?;
+        for peer in snapshot.network.peer_list.iter() {
+            let cmd = TransportCommand::Connect(peer.peer_uri.clone(), "".to_string());
+            self.network_gateway.as_transport_mut().post(cmd)?;
+        }
+        self.persisted_space_map = snapshot.space_list.into_iter().collect();
+        Ok(())
+    }
+
+    /// Store our DHT state if we did any work since we last stored it,
+    /// at most every DHT_STATE_SAVE_INTERVAL_MS, so that a crash does not lose it all.
+    pub(crate) fn process_dht_state_save(&mut self, did_work: bool) {
+        if self.config.work_dir.is_empty() {
+            return;
+        }
+        self.dht_state_changed |= did_work;
+        let now = crate::time::since_epoch_ms();
+        if !self.dht_state_changed || now < self.last_dht_save_ms + DHT_STATE_SAVE_INTERVAL_MS {
+            return;
+        }
+        self.last_dht_save_ms = now;
+        self.dht_state_changed = false;
+        if let Err(e) = self.save_dht_state() {
+            error!("{} - Storing DHT state failed: {:?}", self.name, e);
+        }
+    }
+
+    /// Store the state of all our DHTs in our work_dir
+    pub(crate) fn save_dht_state(&self) -> Lib3hResult<()> {
+        if self.config.work_dir.is_empty() {
+            return Ok(());
+        }
+        let mut snapshot = EngineSnapshot {
+            network: DhtSnapshot::capture(&*self.network_gateway.as_dht_ref()),
+            space_list: Vec::new(),
+        };
+        for (chain_id, space_gateway) in self.space_gateway_map.iter() {
+            let space_snapshot = DhtSnapshot::capture(&*space_gateway.as_dht_ref());
+            snapshot.space_list.push((chain_id.clone(), space_snapshot));
+        }
+        // Keep the state of spaces we did not join again this time
+        for (chain_id, space_snapshot) in self.persisted_space_map.iter() {
+            if !self.space_gateway_map.contains_key(chain_id) {
+                snapshot
+                    .space_list
+                    .push((chain_id.clone(), space_snapshot.clone()));
+            }
+        }
+        save_engine_snapshot(&self.config.work_dir, &snapshot)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// TransportKeys
+//--------------------------------------------------------------------------------------------------
+
+/// Load our TransportKeys from `work_dir`, or generate and store new ones if there are none.
+pub(crate) fn load_or_new_transport_keys(
+    crypto: &dyn CryptoSystem,
+    work_dir: &str,
+    passphrase: &str,
+) -> Lib3hResult<TransportKeys> {
+    if work_dir.is_empty() {
+        return TransportKeys::new(crypto);
+    }
+    let path = Path::new(work_dir).join(TRANSPORT_KEYS_FILE);
+    if path.exists() {
+        let stored: EncryptedTransportKeys = read_file(&path)?;
+        let keys = decrypt_transport_keys(crypto, &stored, passphrase)?;
+        debug!("Loaded TransportKeys {} from {:?}", keys.transport_id, path);
+        return Ok(keys);
+    }
+    let keys = TransportKeys::new(crypto)?;
+    let stored = encrypt_transport_keys(crypto, &keys, passphrase)?;
+    write_file(&path, &stored)?;
+    debug!("Stored new TransportKeys {} in {:?}", keys.transport_id, path);
+    Ok(keys)
+}
+
+/// Derive the aead secret protecting our transport secret key from the passphrase
+fn passphrase_secret(
+    crypto: &dyn CryptoSystem,
+    passphrase: &str,
+    salt: &[u8],
+) -> Lib3hResult<Box<dyn Buffer>> {
+    let password: Box<dyn Buffer> = Box::new(passphrase.as_bytes().to_vec());
+    let salt: Box<dyn Buffer> = Box::new(salt.to_vec());
+    let mut hash = crypto.buf_new_secure(crypto.pwhash_bytes());
+    crypto.pwhash(&mut hash, &password, &salt)?;
+    if hash.len() == crypto.aead_secret_bytes() {
+        return Ok(hash);
+    }
+    let mut secret = crypto.buf_new_secure(crypto.aead_secret_bytes());
+    crypto.generic_hash(&mut secret, &hash, None)?;
+    Ok(secret)
+}
+
+fn encrypt_transport_keys(
+    crypto: &dyn CryptoSystem,
+    keys: &TransportKeys,
+    passphrase: &str,
+) -> Lib3hResult<EncryptedTransportKeys> {
+    let mut salt: Box<dyn Buffer> = Box::new(vec![0; crypto.pwhash_salt_bytes()]);
+    crypto.randombytes_buf(&mut salt)?;
+    let mut nonce: Box<dyn Buffer> = Box::new(vec![0; crypto.aead_nonce_bytes()]);
+    crypto.randombytes_buf(&mut nonce)?;
+    let secret = passphrase_secret(crypto, passphrase, &salt)?;
+    let cipher_len = keys.transport_secret_key.len() + crypto.aead_auth_bytes();
+    let mut cipher: Box<dyn Buffer> = Box::new(vec![0; cipher_len]);
+    // Bind the secret key to its public key
+    let adata: Box<dyn Buffer> = Box::new(keys.transport_public_key.to_vec());
+    crypto.aead_encrypt(
+        &mut cipher,
+        &keys.transport_secret_key,
+        Some(&adata),
+        &nonce,
+        &secret,
+    )?;
+    Ok(EncryptedTransportKeys {
+        transport_public_key: keys.transport_public_key.to_vec(),
+        salt: salt.to_vec(),
+        nonce: nonce.to_vec(),
+        cipher: cipher.to_vec(),
+    })
+}
+
+fn decrypt_transport_keys(
+    crypto: &dyn CryptoSystem,
+    stored: &EncryptedTransportKeys,
+    passphrase: &str,
+) -> Lib3hResult<TransportKeys> {
+    if stored.cipher.len() != crypto.sign_secret_key_bytes() + crypto.aead_auth_bytes() {
+        return Err(Lib3hError::new_other("Corrupted TransportKeys file"));
+    }
+    let secret = passphrase_secret(crypto, passphrase, &stored.salt)?;
+    let cipher: Box<dyn Buffer> = Box::new(stored.cipher.clone());
+    let nonce: Box<dyn Buffer> = Box::new(stored.nonce.clone());
+    let adata: Box<dyn Buffer> = Box::new(stored.transport_public_key.clone());
+    let mut secret_key = crypto.buf_new_secure(crypto.sign_secret_key_bytes());
+    crypto
+        .aead_decrypt(&mut secret_key, &cipher, Some(&adata), &nonce, &secret)
+        .map_err(|_| Lib3hError::new_other("Failed to decrypt TransportKeys: wrong passphrase?"))?;
+    let hcm0 = hcid::HcidEncoding::with_kind("hcm0")?;
+    Ok(TransportKeys {
+        transport_id: hcm0.encode(&stored.transport_public_key)?,
+        transport_public_key: Box::new(stored.transport_public_key.clone()),
+        transport_secret_key: secret_key,
+    })
+}
+
+//--------------------------------------------------------------------------------------------------
+// DHT state
+//--------------------------------------------------------------------------------------------------
+
+/// Load the DHT state snapshot stored in `work_dir`, if any
+pub(crate) fn load_engine_snapshot(work_dir: &str) -> Lib3hResult<Option<EngineSnapshot>> {
+    if work_dir.is_empty() {
+        return Ok(None);
+    }
+    let path = Path::new(work_dir).join(DHT_STATE_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(read_file(&path)?))
+}
+
+/// Store a DHT state snapshot in `work_dir`
+pub(crate) fn save_engine_snapshot(work_dir: &str, snapshot: &EngineSnapshot) -> Lib3hResult<()> {
+    if work_dir.is_empty() {
+        return Ok(());
+    }
+    write_file(&Path::new(work_dir).join(DHT_STATE_FILE), snapshot)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Files
+//--------------------------------------------------------------------------------------------------
+
+fn read_file<T: DeserializeOwned>(path: &Path) -> Lib3hResult<T> {
+    let raw = fs::read(path)?;
+    let mut de = Deserializer::new(&raw[..]);
+    Ok(Deserialize::deserialize(&mut de)?)
+}
+
+/// Write to a temporary file first so a crash never leaves a truncated file behind
+fn write_file<T: Serialize>(path: &Path, value: &T) -> Lib3hResult<()> {
+    let mut raw = Vec::new();
+    value.serialize(&mut Serializer::new(&mut raw))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, &raw)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dht::{dht_trait::DhtConfig, mirror_dht::MirrorDht};
+    use lib3h_sodium::SodiumCryptoSystem;
+    use url::Url;
+
+    fn test_work_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("lib3h_{}_{}", name, nanoid::simple()));
+        dir.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn it_should_reload_transport_keys() {
+        let crypto = SodiumCryptoSystem::new().set_pwhash_interactive();
+        let work_dir = test_work_dir("keys");
+        let keys = load_or_new_transport_keys(&crypto, &work_dir, "passphrase").unwrap();
+        let reloaded = load_or_new_transport_keys(&crypto, &work_dir, "passphrase").unwrap();
+        assert_eq!(reloaded.transport_id, keys.transport_id);
+        assert_eq!(
+            reloaded.transport_secret_key.read_lock().to_vec(),
+            keys.transport_secret_key.read_lock().to_vec(),
+        );
+        // Wrong passphrase
+        assert!(load_or_new_transport_keys(&crypto, &work_dir, "oops").is_err());
+        fs::remove_dir_all(&work_dir).unwrap();
+    }
+
+    #[test]
+    fn it_should_generate_new_transport_keys_without_work_dir() {
+        let crypto = SodiumCryptoSystem::new().set_pwhash_interactive();
+        let keys_a = load_or_new_transport_keys(&crypto, "", "").unwrap();
+        let keys_b = load_or_new_transport_keys(&crypto, "", "").unwrap();
+        assert_ne!(keys_a.transport_id, keys_b.transport_id);
+    }
+
+    #[test]
+    fn it_should_save_and_restore_dht_snapshot() {
+        let work_dir = test_work_dir("dht");
+        let this_uri = Url::parse("mem://alex").unwrap();
+        let config = DhtConfig::new("alex", &this_uri);
+        let mut dht = MirrorDht::new_with_config(&config).unwrap();
+        let peer = PeerData {
+            peer_address: "billy".to_string(),
+            peer_uri: Url::parse("mem://billy").unwrap(),
+            timestamp: 42,
+        };
+        dht.post(DhtCommand::HoldPeer(peer.clone())).unwrap();
+        dht.process().unwrap();
+        let snapshot = EngineSnapshot {
+            network: DhtSnapshot::capture(&dht),
+            space_list: vec![],
+        };
+        assert_eq!(snapshot.network.peer_list, vec![peer.clone()]);
+        save_engine_snapshot(&work_dir, &snapshot).unwrap();
+        let reloaded = load_engine_snapshot(&work_dir).unwrap().unwrap();
+        assert_eq!(reloaded, snapshot);
+        // Restore in a fresh DHT
+        let mut restored_dht = MirrorDht::new_with_config(&config).unwrap();
+        reloaded.network.restore(&mut restored_dht).unwrap();
+        restored_dht.process().unwrap();
+        assert_eq!(restored_dht.get_peer("billy"), Some(peer));
+        fs::remove_dir_all(&work_dir).unwrap();
+    }
+}
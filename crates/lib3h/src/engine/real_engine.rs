@@ -2,22 +2,32 @@
 
 //#[cfg(test)]
 use crate::transport::memory_mock::transport_memory::TransportMemory;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, RwLock},
+};
 use url::Url;
 
 use super::RealEngineTrackerData;
 use crate::{
-    dht::{
-        dht_protocol::{self, *},
-        dht_trait::*,
-    },
+    dht::{dht_protocol::*, dht_trait::*},
     engine::{
-        p2p_protocol::P2pProtocol, RealEngine, RealEngineConfig, TransportKeys, NETWORK_GATEWAY_ID,
+        lan_discovery::LanDiscovery,
+        p2p_protocol::{
+            address_to_wire, aspect_request_to_wire, direct_message_to_wire, entry_to_wire,
+            MsgGspAspectDataRequest, MsgGspAspectDataResponse, MsgJoinSpace, MsgQuery, P2pMessage,
+        },
+        persistence::load_or_new_transport_keys,
+        ChainId, RealEngine, RealEngineConfig, TransportKeys, NETWORK_GATEWAY_ID,
     },
     error::Lib3hResult,
-    gateway::{GatewayWrapper, P2pGateway},
+    gateway::{
+        multiplex::{Multiplexer, FRAME_OVERHEAD},
+        GatewayWrapper, P2pGateway,
+    },
     track::Tracker,
     transport::{protocol::TransportCommand, TransportWrapper},
+    transport_framed::{TransportFramed, TCP_SCHEME},
     transport_wss::TransportWss,
 };
 use lib3h_crypto_api::{Buffer, CryptoSystem};
@@ -25,8 +35,9 @@ use lib3h_protocol::{
     data_types::*, error::Lib3hProtocolResult, network_engine::NetworkEngine,
     protocol_client::Lib3hClientProtocol, protocol_server::Lib3hServerProtocol, Address, DidWork,
 };
-use rmp_serde::{Deserializer, Serializer};
-use serde::{Deserialize, Serialize};
+
+/// Number of holders a FetchEntry is sent to when we don't hold the entry ourselves
+const FETCH_ENTRY_HOLDER_COUNT: usize = 3;
 
 impl TransportKeys {
     pub fn new(crypto: &dyn CryptoSystem) -> Lib3hResult<Self> {
@@ -43,20 +54,24 @@ impl TransportKeys {
 }
 
 impl<'engine, D: Dht> RealEngine<'engine, D> {
-    /// Constructor with TransportWss
+    /// Constructor with the network transport of the `bind_url` scheme:
+    /// TransportFramed for tcp:// and unix:// uris, TransportWss otherwise
     pub fn new(
         crypto: Box<dyn CryptoSystem>,
         config: RealEngineConfig,
         name: &str,
         dht_factory: DhtFactory<D>,
     ) -> Lib3hResult<Self> {
+        // Load keys from work_dir or generate new ones
+        let transport_keys = load_or_new_transport_keys(
+            crypto.as_crypto_system(),
+            &config.work_dir,
+            &config.transport_keys_passphrase,
+        )?;
         // Create Transport and bind
         let network_transport =
-            TransportWrapper::new(TransportWss::with_std_tcp_stream(config.tls_config.clone()));
+            Self::new_network_transport(crypto.as_crypto_system(), &config, &transport_keys)?;
         let binding = network_transport.as_mut().bind(&config.bind_url)?;
-        // Generate keys
-        // TODO #209 - Check persistence first before generating
-        let transport_keys = TransportKeys::new(crypto.as_crypto_system())?;
         // Generate DHT config and create network_gateway
         let dht_config = DhtConfig {
             this_peer_address: transport_keys.transport_id.clone(),
@@ -65,14 +80,24 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
             gossip_interval: config.dht_gossip_interval,
             timeout_threshold: config.dht_timeout_threshold,
         };
+        let multiplexer = Arc::new(RwLock::new(Multiplexer::new(
+            crypto.box_clone(),
+            config.max_message_size,
+        )));
         let network_gateway = GatewayWrapper::new(P2pGateway::new(
             NETWORK_GATEWAY_ID,
             network_transport.clone(),
+            multiplexer.clone(),
             dht_factory,
             &dht_config,
         ));
-        // Done
-        Ok(RealEngine {
+        let network_id_set = vec![config.network_id.clone()].into_iter().collect();
+        let lan_discovery = if config.enable_lan_discovery {
+            Some(LanDiscovery::new(&config.network_id)?)
+        } else {
+            None
+        };
+        let mut engine = RealEngine {
             crypto,
             config,
             inbox: VecDeque::new(),
@@ -81,11 +106,30 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
             request_track: Tracker::new("real_engine_", 2000),
             network_transport,
             network_gateway,
-            network_connections: HashSet::new(),
+            network_connections: HashMap::new(),
+            pending_connection_map: HashMap::new(),
+            handshake_nonce_map: HashMap::new(),
+            connect_network_map: HashMap::new(),
+            network_id_set,
             space_gateway_map: HashMap::new(),
+            multiplexer,
+            connection_peer_map: HashMap::new(),
+            relay_route_map: HashMap::new(),
+            agent_announcer_map: HashMap::new(),
+            rejected_aspect_map: HashMap::new(),
+            gsp_arc_sync_map: HashMap::new(),
+            last_gsp_arc_ms: 0,
+            bootstrap_attempt_count: 0,
+            next_bootstrap_ms: 0,
+            lan_discovery,
+            persisted_space_map: HashMap::new(),
+            last_dht_save_ms: 0,
+            dht_state_changed: false,
             transport_keys,
             process_count: 0,
-        })
+        };
+        engine.restore_dht_state()?;
+        Ok(engine)
     }
 }
 
@@ -106,17 +150,28 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
             .as_mut()
             .bind(&config.bind_url)
             .expect("TransportMemory.bind() failed. bind-url might not be unique?");
+        // Load keys from work_dir or generate new ones
+        let transport_keys = load_or_new_transport_keys(
+            crypto.as_crypto_system(),
+            &config.work_dir,
+            &config.transport_keys_passphrase,
+        )?;
         let dht_config = DhtConfig {
-            this_peer_address: format!("{}_tId", name),
+            this_peer_address: transport_keys.transport_id.clone(),
             this_peer_uri: binding,
             custom: config.dht_custom_config.clone(),
             gossip_interval: config.dht_gossip_interval,
             timeout_threshold: config.dht_timeout_threshold,
         };
         // Create network gateway
+        let multiplexer = Arc::new(RwLock::new(Multiplexer::new(
+            crypto.box_clone(),
+            config.max_message_size,
+        )));
         let network_gateway = GatewayWrapper::new(P2pGateway::new(
             NETWORK_GATEWAY_ID,
             network_transport.clone(),
+            multiplexer.clone(),
             dht_factory,
             &dht_config,
         ));
@@ -125,8 +180,13 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
             name,
             network_gateway.as_ref().this_peer()
         );
-        let transport_keys = TransportKeys::new(crypto.as_crypto_system())?;
-        Ok(RealEngine {
+        let network_id_set = vec![config.network_id.clone()].into_iter().collect();
+        let lan_discovery = if config.enable_lan_discovery {
+            Some(LanDiscovery::new(&config.network_id)?)
+        } else {
+            None
+        };
+        let mut engine = RealEngine {
             crypto,
             config,
             inbox: VecDeque::new(),
@@ -135,11 +195,78 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
             request_track: Tracker::new("real_engine_", 2000),
             network_transport,
             network_gateway,
-            network_connections: HashSet::new(),
+            network_connections: HashMap::new(),
+            pending_connection_map: HashMap::new(),
+            handshake_nonce_map: HashMap::new(),
+            connect_network_map: HashMap::new(),
+            network_id_set,
             space_gateway_map: HashMap::new(),
+            multiplexer,
+            connection_peer_map: HashMap::new(),
+            relay_route_map: HashMap::new(),
+            agent_announcer_map: HashMap::new(),
+            rejected_aspect_map: HashMap::new(),
+            gsp_arc_sync_map: HashMap::new(),
+            last_gsp_arc_ms: 0,
+            bootstrap_attempt_count: 0,
+            next_bootstrap_ms: 0,
+            lan_discovery,
+            persisted_space_map: HashMap::new(),
+            last_dht_save_ms: 0,
+            dht_state_changed: false,
             transport_keys,
             process_count: 0,
-        })
+        };
+        engine.restore_dht_state()?;
+        Ok(engine)
+    }
+
+    /// Network transport of the `bind_url` scheme, not bound yet
+    fn new_network_transport(
+        crypto: &dyn CryptoSystem,
+        config: &RealEngineConfig,
+        transport_keys: &TransportKeys,
+    ) -> Lib3hResult<TransportWrapper<'engine>> {
+        // a frame carries a multiplexed message, or a fragment of one
+        let max_frame_size = config.max_message_size + FRAME_OVERHEAD;
+        match config.bind_url.scheme() {
+            TCP_SCHEME => {
+                let mut transport_tcp = TransportFramed::with_std_tcp_stream();
+                transport_tcp.set_heartbeat(config.wss_heartbeat_ms, config.wss_idle_timeout_ms);
+                transport_tcp.set_send_queue_limits(
+                    config.wss_max_send_queue_len,
+                    config.wss_max_send_queue_bytes,
+                );
+                transport_tcp.set_max_frame_size(max_frame_size);
+                Ok(TransportWrapper::new(transport_tcp))
+            }
+            #[cfg(unix)]
+            crate::transport_framed::UNIX_SCHEME => {
+                let mut transport_unix = TransportFramed::with_unix_stream();
+                transport_unix.set_heartbeat(config.wss_heartbeat_ms, config.wss_idle_timeout_ms);
+                transport_unix.set_send_queue_limits(
+                    config.wss_max_send_queue_len,
+                    config.wss_max_send_queue_bytes,
+                );
+                transport_unix.set_max_frame_size(max_frame_size);
+                Ok(TransportWrapper::new(transport_unix))
+            }
+            _ => {
+                let mut transport_wss =
+                    TransportWss::with_std_tcp_stream(config.tls_config.clone());
+                transport_wss.set_heartbeat(config.wss_heartbeat_ms, config.wss_idle_timeout_ms);
+                transport_wss.set_send_queue_limits(
+                    config.wss_max_send_queue_len,
+                    config.wss_max_send_queue_bytes,
+                );
+                transport_wss.set_transport_keys(
+                    crypto,
+                    &transport_keys.transport_id,
+                    &transport_keys.transport_secret_key,
+                )?;
+                Ok(TransportWrapper::new(transport_wss))
+            }
+        }
     }
 }
 
@@ -172,12 +299,20 @@ impl<'engine, D: Dht> NetworkEngine for RealEngine<'engine, D> {
         trace!("{} - process() START - {}", self.name, self.process_count);
         // Process all received Lib3hClientProtocol messages from Core
         let (inbox_did_work, mut outbox) = self.process_inbox()?;
+        // Dial bootstrap nodes while we have no connection
+        let bootstrap_did_work = self.process_bootstrap()?;
+        // Find the nodes of our network on the LAN
+        let lan_did_work = self.process_lan_discovery()?;
         // Process the network layer
         let (net_did_work, mut net_outbox) = self.process_network_gateway()?;
         outbox.append(&mut net_outbox);
+        // Send again the fragments lost on the way
+        let retransmit_did_work = self.process_transfer_retransmit()?;
         // Process the space layer
         let mut p2p_output = self.process_space_gateways()?;
         outbox.append(&mut p2p_output);
+        // Start arc gossip rounds
+        self.process_gsp_arc();
         trace!(
             "process() END - {} (outbox: {})\n",
             self.process_count,
@@ -185,11 +320,56 @@ impl<'engine, D: Dht> NetworkEngine for RealEngine<'engine, D> {
         );
 
         for (timeout_id, timeout_data) in self.request_track.process_timeouts() {
-            error!("timeout {:?} {:?}", timeout_id, timeout_data);
+            match timeout_data {
+                Some(RealEngineTrackerData::LocalFetchEntry(request)) => {
+                    outbox.push(fetch_entry_failure(&request, "Timed out fetching entry"));
+                }
+                // Return whatever the holders responded so far
+                Some(RealEngineTrackerData::QueryEntry {
+                    request,
+                    result_list,
+                    ..
+                }) => {
+                    if result_list.is_empty() {
+                        outbox.push(query_entry_failure(&request, "Timed out querying entry"));
+                    } else {
+                        outbox.append(&mut query_entry_result_list(&request, result_list));
+                    }
+                }
+                Some(RealEngineTrackerData::NetworkFetchEntry { request, entry, .. }) => {
+                    if entry.aspect_list.is_empty() {
+                        outbox.push(fetch_entry_failure(&request, "Timed out fetching entry"));
+                    } else {
+                        outbox.push(fetch_entry_result(&request, entry));
+                    }
+                }
+                Some(RealEngineTrackerData::HoldEntryRequested {
+                    entry_address,
+                    aspect,
+                    ..
+                }) => {
+                    warn!(
+                        "Core did not answer HandleStoreEntryAspect of {} for entry {}",
+                        aspect.aspect_address, entry_address,
+                    );
+                }
+                Some(RealEngineTrackerData::GossipFetchEntry { .. }) => {
+                    debug!("Arc gossip request timed out: {:?}", timeout_id);
+                }
+                timeout_data => error!("timeout {:?} {:?}", timeout_id, timeout_data),
+            }
         }
 
+        let did_work = inbox_did_work
+            || bootstrap_did_work
+            || lan_did_work
+            || net_did_work
+            || retransmit_did_work;
+        // Store our DHT state from time to time, not only on shutdown
+        self.process_dht_state_save(did_work);
+
         // Done
-        Ok((inbox_did_work || net_did_work, outbox))
+        Ok((did_work, outbox))
     }
 }
 
@@ -207,7 +387,11 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
     /// Called on drop.
     /// Close all connections gracefully
     fn shutdown(&mut self) -> Lib3hResult<()> {
-        let mut result: Lib3hResult<()> = Ok(());
+        // Store our DHT state before closing everything
+        let mut result: Lib3hResult<()> = self.save_dht_state().map_err(|e| {
+            error!("Storing DHT state failed: {:?}", e);
+            e
+        });
 
         for space_gatway in self.space_gateway_map.values_mut() {
             let res = space_gatway.as_transport_mut().close_all();
@@ -259,13 +443,26 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
             Lib3hClientProtocol::Shutdown => {
                 // TODO
             }
-            Lib3hClientProtocol::SuccessResult(_msg) => {
-                // TODO #168
+            // SuccessResult: Core accepted an aspect we asked it to store
+            Lib3hClientProtocol::SuccessResult(msg) => {
+                self.serve_StoreEntryAspectResult(&msg, true)?;
             }
-            Lib3hClientProtocol::FailureResult(_msg) => {
-                // TODO #168
+            // FailureResult: Core rejected an aspect we asked it to store,
+            // or could not serve one of our requests
+            Lib3hClientProtocol::FailureResult(msg) => {
+                let mut output = self.serve_FailureResult(&msg)?;
+                outbox.append(&mut output);
             }
             Lib3hClientProtocol::Connect(msg) => {
+                // Connect to our network if none is specified
+                let network_id = if msg.network_id.is_empty() {
+                    self.config.network_id.clone()
+                } else {
+                    msg.network_id
+                };
+                self.network_id_set.insert(network_id.clone());
+                self.connect_network_map
+                    .insert(msg.request_id.clone(), network_id);
                 // Convert into TransportCommand & post to network gateway
                 let cmd = TransportCommand::Connect(msg.peer_uri, msg.request_id);
                 self.network_gateway.as_transport_mut().post(cmd)?;
@@ -275,7 +472,7 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
                 outbox.append(&mut output);
             }
             Lib3hClientProtocol::LeaveSpace(msg) => {
-                let srv_msg = self.serve_LeaveSpace(&msg);
+                let srv_msg = self.serve_LeaveSpace(&msg)?;
                 outbox.push(srv_msg);
             }
             Lib3hClientProtocol::SendDirectMessage(msg) => {
@@ -286,47 +483,17 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
                 let srv_msg = self.serve_DirectMessage(msg, true);
                 outbox.push(srv_msg);
             }
-            Lib3hClientProtocol::FetchEntry(_msg) => {
-                // TODO #169
+            Lib3hClientProtocol::FetchEntry(msg) => {
+                let mut output = self.serve_FetchEntry(msg)?;
+                outbox.append(&mut output);
             }
             // HandleFetchEntryResult:
             //   - From GetAuthoringList      : Convert to DhtCommand::BroadcastEntry
+            //   - From our own FetchEntry    : Convert to FetchEntryResult
+            //   - From a remote FetchEntry   : Send back a MsgGspAspectDataResponse
             //   - From DHT EntryDataRequested: Convert to DhtCommand::EntryDataResponse
             Lib3hClientProtocol::HandleFetchEntryResult(msg) => {
-                let mut is_data_for_author_list = false;
-                if self.request_track.has(&msg.request_id) {
-                    match self.request_track.remove(&msg.request_id) {
-                        Some(data) => match data {
-                            RealEngineTrackerData::DataForAuthorEntry => {
-                                is_data_for_author_list = true;
-                            }
-                            _ => (),
-                        },
-                        None => (),
-                    };
-                }
-                let maybe_space = self.get_space_or_fail(
-                    &msg.space_address,
-                    &msg.provider_agent_id,
-                    &msg.request_id,
-                    None,
-                );
-                match maybe_space {
-                    Err(res) => outbox.push(res),
-                    Ok(space_gateway) => {
-                        if is_data_for_author_list {
-                            let cmd = DhtCommand::BroadcastEntry(msg.entry);
-                            space_gateway.as_dht_mut().post(cmd)?;
-                        } else {
-                            let response = FetchDhtEntryResponseData {
-                                msg_id: msg.request_id.clone(),
-                                entry: msg.entry.clone(),
-                            };
-                            let cmd = DhtCommand::EntryDataResponse(response);
-                            space_gateway.as_dht_mut().post(cmd)?;
-                        }
-                    }
-                }
+                self.serve_Lib3hClientProtocol_HandleFetchEntryResult(&mut outbox, msg)?;
             }
             // PublishEntry: Broadcast on the space DHT
             Lib3hClientProtocol::PublishEntry(msg) => {
@@ -360,51 +527,15 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
                     }
                 }
             }
-            // QueryEntry: Converting to DHT FetchEntry for now
-            // TODO #169
             Lib3hClientProtocol::QueryEntry(msg) => {
-                let maybe_space = self.get_space_or_fail(
-                    &msg.space_address,
-                    &msg.requester_agent_id,
-                    &msg.request_id,
-                    None,
-                );
-                match maybe_space {
-                    Err(res) => outbox.push(res),
-                    Ok(space_gateway) => {
-                        let msg = dht_protocol::FetchDhtEntryData {
-                            msg_id: msg.request_id,
-                            entry_address: msg.entry_address,
-                        };
-                        let cmd = DhtCommand::FetchEntry(msg);
-                        space_gateway.as_dht_mut().post(cmd)?;
-                    }
-                }
+                let mut output = self.serve_QueryEntry(msg)?;
+                outbox.append(&mut output);
             }
-            // HandleQueryEntryResult: Convert into DhtCommand::ProvideEntryResponse
-            // TODO #169
+            // HandleQueryEntryResult:
+            //   - From our own QueryEntry  : Aggregate into QueryEntryResult
+            //   - From a remote QueryEntry : Send back a MsgQueryResponse
             Lib3hClientProtocol::HandleQueryEntryResult(msg) => {
-                let maybe_space = self.get_space_or_fail(
-                    &msg.space_address,
-                    &msg.responder_agent_id,
-                    &msg.request_id,
-                    None,
-                );
-                let mut de = Deserializer::new(&msg.query_result[..]);
-                let maybe_entry: Result<EntryData, rmp_serde::decode::Error> =
-                    Deserialize::deserialize(&mut de);
-                let entry = maybe_entry.expect("Deserialization should always work");
-                match maybe_space {
-                    Err(res) => outbox.push(res),
-                    Ok(space_gateway) => {
-                        let msg = dht_protocol::FetchDhtEntryResponseData {
-                            msg_id: msg.request_id,
-                            entry,
-                        };
-                        let cmd = DhtCommand::EntryDataResponse(msg);
-                        space_gateway.as_dht_mut().post(cmd)?;
-                    }
-                }
+                self.serve_Lib3hClientProtocol_HandleQueryEntryResult(&mut outbox, msg);
             }
             // Our request for the publish_list has returned
             Lib3hClientProtocol::HandleGetAuthoringEntryListResult(msg) => {
@@ -418,6 +549,70 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
         Ok(outbox)
     }
 
+    fn serve_Lib3hClientProtocol_HandleFetchEntryResult(
+        &mut self,
+        outbox: &mut Vec<Lib3hServerProtocol>,
+        msg: FetchEntryResultData,
+    ) -> Lib3hResult<()> {
+        let maybe_track = self.request_track.remove(&msg.request_id);
+        let mut is_data_for_author_list = false;
+        match maybe_track {
+            Some(RealEngineTrackerData::DataForAuthorEntry) => {
+                is_data_for_author_list = true;
+            }
+            Some(RealEngineTrackerData::LocalFetchEntry(request)) => {
+                outbox.push(fetch_entry_result(&request, msg.entry));
+                return Ok(());
+            }
+            Some(RealEngineTrackerData::RemoteFetchEntry {
+                request_id,
+                requester_agent_id,
+                aspect_address_list,
+            }) => {
+                let mut entry = msg.entry;
+                filter_aspects(&mut entry, &aspect_address_list);
+                let response = MsgGspAspectDataResponse {
+                    aspect_data_list: vec![entry_to_wire(&entry)?],
+                    request_id,
+                    space_address: address_to_wire(&msg.space_address),
+                    to_agent_id: address_to_wire(&requester_agent_id),
+                    from_agent_id: address_to_wire(&msg.provider_agent_id),
+                };
+                self.send_to_space_peer(
+                    &msg.space_address,
+                    &msg.provider_agent_id,
+                    &requester_agent_id,
+                    &P2pMessage::MsgGspAspectDataResponse(response),
+                );
+                return Ok(());
+            }
+            _ => (),
+        };
+        let maybe_space = self.get_space_or_fail(
+            &msg.space_address,
+            &msg.provider_agent_id,
+            &msg.request_id,
+            None,
+        );
+        match maybe_space {
+            Err(res) => outbox.push(res),
+            Ok(space_gateway) => {
+                if is_data_for_author_list {
+                    let cmd = DhtCommand::BroadcastEntry(msg.entry);
+                    space_gateway.as_dht_mut().post(cmd)?;
+                } else {
+                    let response = FetchDhtEntryResponseData {
+                        msg_id: msg.request_id.clone(),
+                        entry: msg.entry.clone(),
+                    };
+                    let cmd = DhtCommand::EntryDataResponse(response);
+                    space_gateway.as_dht_mut().post(cmd)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn serve_Lib3hClientProtocol_HandleGetAuthoringEntryListResult(
         &mut self,
         outbox: &mut Vec<Lib3hServerProtocol>,
@@ -567,19 +762,23 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
         let new_space_gateway: GatewayWrapper<'engine> =
             GatewayWrapper::new(P2pGateway::new_with_space(
                 self.network_gateway.as_transport(),
+                self.multiplexer.clone(),
                 &join_msg.space_address,
                 self.dht_factory,
                 &dht_config,
             ));
+        // Reload what this space's DHT held before our last shutdown
+        if let Some(snapshot) = self.persisted_space_map.remove(&chain_id) {
+            snapshot.restore(&mut *new_space_gateway.as_dht_mut())?;
+        }
 
-        // TODO #150 - Send JoinSpace to all known peers
+        // Announce our signed PeerData for this space to every connected node
         let space_address: String = join_msg.space_address.clone().into();
         let peer = new_space_gateway.as_ref().this_peer().to_owned();
-        let mut payload = Vec::new();
-        let p2p_msg = P2pProtocol::BroadcastJoinSpace(space_address.clone(), peer.clone());
-        p2p_msg
-            .serialize(&mut Serializer::new(&mut payload))
-            .unwrap();
+        let p2p_msg = P2pMessage::MsgJoinSpace(MsgJoinSpace {
+            peer_list: vec![self.signed_peer_info(&space_address, &peer)?],
+        });
+        let payload = Multiplexer::network_frame(&p2p_msg.to_bytes());
         trace!(
             "{} - Broadcasting JoinSpace: {}, {}",
             self.name,
@@ -590,7 +789,6 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
             .as_transport_mut()
             .send_all(&payload)
             .ok();
-        // TODO END
 
         // Add it to space map
         self.space_gateway_map
@@ -655,17 +853,14 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
             response.result_info = "Messaging self".as_bytes().to_vec();
             return Lib3hServerProtocol::FailureResult(response);
         }
-        // Change into P2pProtocol
+        // Change into P2pMessage
         let net_msg = if is_response {
-            P2pProtocol::DirectMessageResult(msg.clone())
+            P2pMessage::MsgDirectResponse(direct_message_to_wire(&msg))
         } else {
-            P2pProtocol::DirectMessage(msg.clone())
+            P2pMessage::MsgDirectRequest(direct_message_to_wire(&msg))
         };
         // Serialize payload
-        let mut payload = Vec::new();
-        net_msg
-            .serialize(&mut Serializer::new(&mut payload))
-            .unwrap();
+        let payload = net_msg.to_bytes();
         // Send
         let peer_address: String = msg.to_agent_id.clone().into();
         let res = space_gateway
@@ -678,29 +873,416 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
         Lib3hServerProtocol::SuccessResult(response)
     }
 
-    /// Destroy gateway for this agent in this space, if part of it.
-    /// Respond with FailureResult if space was not already joined.
-    fn serve_LeaveSpace(&mut self, join_msg: &SpaceData) -> Lib3hServerProtocol {
-        // Try remove
-        let chain_id = (join_msg.space_address.clone(), join_msg.agent_id.clone());
-        let res = self.space_gateway_map.remove(&chain_id);
-        // Create response according to remove result
-        let response = GenericResultData {
-            request_id: join_msg.request_id.clone(),
-            space_address: join_msg.space_address.clone(),
-            to_agent_id: join_msg.agent_id.clone(),
-            result_info: match res {
-                None => "Agent is not part of the space".to_string().into_bytes(),
-                Some(_) => vec![],
+    /// Fetch an entry from our own Core if we hold the requested aspects,
+    /// otherwise request them from holders in the space.
+    fn serve_FetchEntry(&mut self, msg: FetchEntryData) -> Lib3hResult<Vec<Lib3hServerProtocol>> {
+        let maybe_space = self.get_space_or_fail(
+            &msg.space_address,
+            &msg.provider_agent_id,
+            &msg.request_id,
+            None,
+        );
+        let space_gateway = match maybe_space {
+            Err(res) => return Ok(vec![res]),
+            Ok(space_gateway) => space_gateway,
+        };
+        // Check if we hold the requested aspects
+        let maybe_known_aspects = space_gateway.as_ref().get_aspects_of(&msg.entry_address);
+        let holds_entry = match (&maybe_known_aspects, &msg.aspect_address_list) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(known_aspects), Some(requested)) => includes(known_aspects, requested),
+        };
+        let request_id = self.request_track.reserve();
+        if holds_entry {
+            let local_msg = FetchEntryData {
+                request_id: request_id.clone(),
+                ..msg.clone()
+            };
+            self.request_track.set(
+                &request_id,
+                Some(RealEngineTrackerData::LocalFetchEntry(msg)),
+            );
+            return Ok(vec![Lib3hServerProtocol::HandleFetchEntry(local_msg)]);
+        }
+        // Request it from holders in the space
+        let pending_count = send_to_holders(
+            &space_gateway,
+            &msg.entry_address,
+            FETCH_ENTRY_HOLDER_COUNT,
+            |holder_address| {
+                P2pMessage::MsgGspAspectDataRequest(MsgGspAspectDataRequest {
+                    aspect_hash_list: vec![aspect_request_to_wire(
+                        &msg.entry_address,
+                        &msg.aspect_address_list,
+                    )],
+                    request_id: request_id.clone(),
+                    space_address: address_to_wire(&msg.space_address),
+                    to_agent_id: holder_address.as_bytes().to_vec(),
+                    from_agent_id: address_to_wire(&msg.provider_agent_id),
+                })
             },
+        )
+        .len();
+        if pending_count == 0 {
+            self.request_track.remove(&request_id);
+            return Ok(vec![fetch_entry_failure(
+                &msg,
+                "No entry holder could be reached",
+            )]);
+        }
+        let entry = EntryData {
+            entry_address: msg.entry_address.clone(),
+            aspect_list: Vec::new(),
         };
-        // Done
-        match res {
-            None => Lib3hServerProtocol::FailureResult(response),
-            Some(_) => Lib3hServerProtocol::SuccessResult(response),
+        self.request_track.set(
+            &request_id,
+            Some(RealEngineTrackerData::NetworkFetchEntry {
+                request: msg,
+                entry,
+                pending_count,
+            }),
+        );
+        Ok(vec![])
+    }
+
+    /// Query an entry from `query_entry_quorum` responders:
+    /// our own Core first if we hold the entry, then agents whose arc holds it.
+    fn serve_QueryEntry(&mut self, msg: QueryEntryData) -> Lib3hResult<Vec<Lib3hServerProtocol>> {
+        let maybe_space = self.get_space_or_fail(
+            &msg.space_address,
+            &msg.requester_agent_id,
+            &msg.request_id,
+            None,
+        );
+        let space_gateway = match maybe_space {
+            Err(res) => return Ok(vec![res]),
+            Ok(space_gateway) => space_gateway,
+        };
+        let quorum = self.query_entry_quorum();
+        // Responses are tracked with our own request_id, mapped back to Core's on output
+        let request_id = self.request_track.reserve();
+        let mut outbox = Vec::new();
+        let mut pending_count = 0;
+        if space_gateway
+            .as_ref()
+            .get_aspects_of(&msg.entry_address)
+            .is_some()
+        {
+            outbox.push(Lib3hServerProtocol::HandleQueryEntry(QueryEntryData {
+                request_id: request_id.clone(),
+                ..msg.clone()
+            }));
+            pending_count += 1;
+        }
+        let mut holder_list = Vec::new();
+        if pending_count < quorum {
+            holder_list = send_to_holders(
+                &space_gateway,
+                &msg.entry_address,
+                quorum - pending_count,
+                |holder_address| {
+                    P2pMessage::MsgQueryRequest(MsgQuery {
+                        request_id: request_id.clone(),
+                        entry_address: address_to_wire(&msg.entry_address),
+                        data: msg.query.clone(),
+                        space_address: address_to_wire(&msg.space_address),
+                        to_agent_id: holder_address.as_bytes().to_vec(),
+                        from_agent_id: address_to_wire(&msg.requester_agent_id),
+                        failed: false,
+                    })
+                },
+            );
+            pending_count += holder_list.len();
+        }
+        if pending_count == 0 {
+            self.request_track.remove(&request_id);
+            return Ok(vec![query_entry_failure(
+                &msg,
+                "No entry holder could be reached",
+            )]);
+        }
+        self.request_track.set(
+            &request_id,
+            Some(RealEngineTrackerData::QueryEntry {
+                request: msg,
+                holder_list,
+                result_list: Vec::new(),
+                pending_count,
+            }),
+        );
+        Ok(outbox)
+    }
+
+    /// Number of results to aggregate before answering a QueryEntry
+    fn query_entry_quorum(&self) -> usize {
+        std::cmp::max(1, self.config.query_entry_quorum)
+    }
+
+    /// Aggregate a result of one of our QueryEntry requests,
+    /// from our own Core if `maybe_responder` is None, else from that remote (space, agent).
+    /// Output all results once the quorum is reached or every responder answered.
+    pub(crate) fn add_query_entry_result(
+        &mut self,
+        maybe_responder: Option<&ChainId>,
+        result: QueryEntryResultData,
+    ) -> Vec<Lib3hServerProtocol> {
+        let request_id = result.request_id.clone();
+        self.add_query_entry_response(&request_id, maybe_responder, Ok(result))
+    }
+
+    /// A responder could not answer one of our QueryEntry requests.
+    /// Fail the request if no responder answered it at all.
+    pub(crate) fn add_query_entry_failure(
+        &mut self,
+        request_id: &str,
+        maybe_responder: Option<&ChainId>,
+        reason: &str,
+    ) -> Vec<Lib3hServerProtocol> {
+        self.add_query_entry_response(request_id, maybe_responder, Err(reason.to_string()))
+    }
+
+    fn add_query_entry_response(
+        &mut self,
+        request_id: &str,
+        maybe_responder: Option<&ChainId>,
+        response: Result<QueryEntryResultData, String>,
+    ) -> Vec<Lib3hServerProtocol> {
+        let (request, mut holder_list, mut result_list, pending_count) =
+            match self.request_track.get(request_id) {
+                Some(RealEngineTrackerData::QueryEntry {
+                    request,
+                    holder_list,
+                    result_list,
+                    pending_count,
+                }) => (
+                    request.clone(),
+                    holder_list.clone(),
+                    result_list.clone(),
+                    *pending_count,
+                ),
+                _ => {
+                    warn!("Received untracked query result: {}", request_id);
+                    return Vec::new();
+                }
+            };
+        // Only the remote holders we asked may respond, once each
+        if let Some((space_address, agent_id)) = maybe_responder {
+            let maybe_index = holder_list.iter().position(|holder| holder == agent_id);
+            match maybe_index {
+                Some(index) if *space_address == request.space_address => {
+                    holder_list.remove(index);
+                }
+                _ => {
+                    warn!(
+                        "Received query result for {} from unexpected agent: {}",
+                        request_id, agent_id,
+                    );
+                    return Vec::new();
+                }
+            }
+        }
+        let mut failure_reason = String::new();
+        match response {
+            Ok(result) => result_list.push(result),
+            Err(reason) => failure_reason = reason,
+        }
+        let pending_count = pending_count.saturating_sub(1);
+        if pending_count > 0 && result_list.len() < self.query_entry_quorum() {
+            self.request_track.set(
+                request_id,
+                Some(RealEngineTrackerData::QueryEntry {
+                    request,
+                    holder_list,
+                    result_list,
+                    pending_count,
+                }),
+            );
+            return Vec::new();
+        }
+        self.request_track.remove(request_id);
+        if result_list.is_empty() {
+            return vec![query_entry_failure(&request, &failure_reason)];
+        }
+        query_entry_result_list(&request, result_list)
+    }
+
+    /// Route a FailureResult from Core to the request it answers
+    fn serve_FailureResult(
+        &mut self,
+        result: &GenericResultData,
+    ) -> Lib3hResult<Vec<Lib3hServerProtocol>> {
+        let reason = String::from_utf8_lossy(&result.result_info).to_string();
+        match self.request_track.get(&result.request_id) {
+            Some(RealEngineTrackerData::HoldEntryRequested { .. }) => {
+                self.serve_StoreEntryAspectResult(result, false)?;
+                return Ok(Vec::new());
+            }
+            Some(RealEngineTrackerData::QueryEntry { .. }) => {
+                return Ok(self.add_query_entry_failure(&result.request_id, None, &reason));
+            }
+            _ => (),
+        }
+        match self.request_track.remove(&result.request_id) {
+            Some(RealEngineTrackerData::LocalFetchEntry(request)) => {
+                return Ok(vec![fetch_entry_failure(&request, &reason)]);
+            }
+            // Tell the requester right away instead of letting it time out
+            Some(RealEngineTrackerData::RemoteQueryEntry {
+                request_id,
+                requester_agent_id,
+                space_address,
+                entry_address,
+                agent_id,
+            }) => {
+                let response = MsgQuery {
+                    request_id,
+                    entry_address: address_to_wire(&entry_address),
+                    data: result.result_info.clone(),
+                    space_address: address_to_wire(&space_address),
+                    to_agent_id: address_to_wire(&requester_agent_id),
+                    from_agent_id: address_to_wire(&agent_id),
+                    failed: true,
+                };
+                self.send_to_space_peer(
+                    &space_address,
+                    &agent_id,
+                    &requester_agent_id,
+                    &P2pMessage::MsgQueryResponse(response),
+                );
+            }
+            Some(track) => warn!("Received FailureResult for {:?}: {}", track, reason),
+            None => debug!("Received untracked result: {:?}", result),
+        }
+        Ok(Vec::new())
+    }
+
+    fn serve_Lib3hClientProtocol_HandleQueryEntryResult(
+        &mut self,
+        outbox: &mut Vec<Lib3hServerProtocol>,
+        msg: QueryEntryResultData,
+    ) {
+        if let Some(RealEngineTrackerData::QueryEntry { .. }) =
+            self.request_track.get(&msg.request_id)
+        {
+            outbox.append(&mut self.add_query_entry_result(None, msg));
+            return;
+        }
+        match self.request_track.remove(&msg.request_id) {
+            Some(RealEngineTrackerData::RemoteQueryEntry {
+                request_id,
+                requester_agent_id,
+                ..
+            }) => {
+                let response = MsgQuery {
+                    request_id,
+                    entry_address: address_to_wire(&msg.entry_address),
+                    data: msg.query_result,
+                    space_address: address_to_wire(&msg.space_address),
+                    to_agent_id: address_to_wire(&requester_agent_id),
+                    from_agent_id: address_to_wire(&msg.responder_agent_id),
+                    failed: false,
+                };
+                self.send_to_space_peer(
+                    &msg.space_address,
+                    &msg.responder_agent_id,
+                    &requester_agent_id,
+                    &P2pMessage::MsgQueryResponse(response),
+                );
+            }
+            _ => warn!(
+                "Received untracked HandleQueryEntryResult: {}",
+                msg.request_id
+            ),
+        }
+    }
+
+    /// Send a P2pMessage to another agent of a space we joined.
+    /// Failures are only logged: the other side will time out.
+    pub(crate) fn send_to_space_peer(
+        &mut self,
+        space_address: &Address,
+        agent_id: &Address,
+        to_agent_id: &Address,
+        p2p_msg: &P2pMessage,
+    ) {
+        let chain_id = (space_address.clone(), agent_id.clone());
+        let to_agent_id: String = to_agent_id.clone().into();
+        let res = match self.space_gateway_map.get(&chain_id) {
+            None => {
+                warn!(
+                    "Can't send to {}: space {} not joined",
+                    to_agent_id, space_address
+                );
+                return;
+            }
+            Some(space_gateway) => space_gateway
+                .as_transport_mut()
+                .send(&[to_agent_id.as_str()], &p2p_msg.to_bytes()),
+        };
+        if let Err(e) = res {
+            warn!("Failed sending to {}: {}", to_agent_id, e);
         }
     }
 
+    /// Destroy gateway for this agent in this space, if part of it,
+    /// and tell connected peers that the agent left.
+    /// Respond with FailureResult if space was not already joined,
+    /// or with SuccessResult once everything related to that space is cleaned up.
+    fn serve_LeaveSpace(&mut self, leave_msg: &SpaceData) -> Lib3hResult<Lib3hServerProtocol> {
+        let mut response = GenericResultData {
+            request_id: leave_msg.request_id.clone(),
+            space_address: leave_msg.space_address.clone(),
+            to_agent_id: leave_msg.agent_id.clone(),
+            result_info: vec![],
+        };
+        // Try remove
+        let chain_id = (leave_msg.space_address.clone(), leave_msg.agent_id.clone());
+        let space_gateway = match self.space_gateway_map.remove(&chain_id) {
+            Some(space_gateway) => space_gateway,
+            None => {
+                response.result_info = "Agent is not part of the space".to_string().into_bytes();
+                return Ok(Lib3hServerProtocol::FailureResult(response));
+            }
+        };
+        // Send LeaveSpace to all known peers.
+        // Note: Timestamp it after our last announcement so it is not taken as stale.
+        let space_address: String = leave_msg.space_address.clone().into();
+        let mut peer = space_gateway.as_ref().this_peer().to_owned();
+        peer.timestamp = std::cmp::max(crate::time::since_epoch_ms(), peer.timestamp + 1);
+        let p2p_msg = P2pMessage::MsgLeaveSpace(self.signed_leave_space(&space_address, &peer)?);
+        let payload = Multiplexer::network_frame(&p2p_msg.to_bytes());
+        trace!(
+            "{} - Broadcasting LeaveSpace: {}, {}",
+            self.name,
+            space_address,
+            peer.peer_address,
+        );
+        self.network_gateway
+            .as_transport_mut()
+            .send_all(&payload)
+            .ok();
+        // Close the channels of that agent in that space
+        let close_list = self
+            .multiplexer
+            .write()
+            .expect("failed to obtain write lock")
+            .close_agent_channels(&chain_id.0, &chain_id.1);
+        for (remote_id, frame) in close_list {
+            self.network_gateway
+                .as_transport_mut()
+                .send(&[remote_id.as_str()], &frame)
+                .ok();
+        }
+        // Forget what we knew of that space, so joining it again starts fresh
+        self.gsp_arc_sync_map
+            .retain(|(sync_chain_id, _), _| *sync_chain_id != chain_id);
+        self.rejected_aspect_map.remove(&chain_id);
+        self.persisted_space_map.remove(&chain_id);
+        // Done
+        Ok(Lib3hServerProtocol::SuccessResult(response))
+    }
+
     /// Get a space_gateway for the specified space+agent.
     /// If agent did not join that space, respond with a FailureResult instead.
     fn get_space_or_fail(
@@ -732,8 +1314,92 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
     }
 }
 
+/// FetchEntryResult for a FetchEntry request, holding only the requested aspects
+pub(crate) fn fetch_entry_result(
+    request: &FetchEntryData,
+    mut entry: EntryData,
+) -> Lib3hServerProtocol {
+    filter_aspects(&mut entry, &request.aspect_address_list);
+    Lib3hServerProtocol::FetchEntryResult(FetchEntryResultData {
+        space_address: request.space_address.clone(),
+        provider_agent_id: request.provider_agent_id.clone(),
+        request_id: request.request_id.clone(),
+        entry,
+    })
+}
+
+pub(crate) fn fetch_entry_failure(request: &FetchEntryData, reason: &str) -> Lib3hServerProtocol {
+    Lib3hServerProtocol::FailureResult(GenericResultData {
+        request_id: request.request_id.clone(),
+        space_address: request.space_address.clone(),
+        to_agent_id: request.provider_agent_id.clone(),
+        result_info: reason.as_bytes().to_vec(),
+    })
+}
+
+/// One QueryEntryResult per result, answering the original QueryEntry request
+fn query_entry_result_list(
+    request: &QueryEntryData,
+    result_list: Vec<QueryEntryResultData>,
+) -> Vec<Lib3hServerProtocol> {
+    result_list
+        .into_iter()
+        .map(|result| {
+            Lib3hServerProtocol::QueryEntryResult(QueryEntryResultData {
+                request_id: request.request_id.clone(),
+                requester_agent_id: request.requester_agent_id.clone(),
+                ..result
+            })
+        })
+        .collect()
+}
+
+fn query_entry_failure(request: &QueryEntryData, reason: &str) -> Lib3hServerProtocol {
+    Lib3hServerProtocol::FailureResult(GenericResultData {
+        request_id: request.request_id.clone(),
+        space_address: request.space_address.clone(),
+        to_agent_id: request.requester_agent_id.clone(),
+        result_info: reason.as_bytes().to_vec(),
+    })
+}
+
+/// Send a P2pMessage to up to `max_count` other agents expected to hold an entry.
+/// Return the agents it was sent to.
+fn send_to_holders(
+    space_gateway: &GatewayWrapper<'_>,
+    entry_address: &Address,
+    max_count: usize,
+    build_msg: impl Fn(&str) -> P2pMessage,
+) -> Vec<Address> {
+    let holder_list = space_gateway.as_ref().get_holder_list(entry_address);
+    let mut sent_list = Vec::new();
+    for holder in holder_list {
+        if sent_list.len() >= max_count {
+            break;
+        }
+        let payload = build_msg(&holder.peer_address).to_bytes();
+        let res = space_gateway
+            .as_transport_mut()
+            .send(&[holder.peer_address.as_str()], &payload);
+        match res {
+            Ok(()) => sent_list.push(holder.peer_address.into()),
+            Err(e) => warn!("Failed sending to holder {}: {}", holder.peer_address, e),
+        }
+    }
+    sent_list
+}
+
+/// Keep only the requested aspects of an entry. `None` stands for all aspects.
+pub(crate) fn filter_aspects(entry: &mut EntryData, aspect_address_list: &Option<Vec<Address>>) {
+    if let Some(aspect_address_list) = aspect_address_list {
+        entry
+            .aspect_list
+            .retain(|aspect| aspect_address_list.contains(&aspect.aspect_address));
+    }
+}
+
 /// Return true if all elements of list_b are found in list_a
-fn includes(list_a: &[Address], list_b: &[Address]) -> bool {
+pub(crate) fn includes(list_a: &[Address], list_b: &[Address]) -> bool {
     let set_a: HashSet<_> = list_a.iter().map(|addr| addr).collect();
     let set_b: HashSet<_> = list_b.iter().map(|addr| addr).collect();
     set_b.is_subset(&set_a)
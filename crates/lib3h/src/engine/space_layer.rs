@@ -3,15 +3,14 @@
 use super::RealEngineTrackerData;
 use crate::{
     dht::{dht_protocol::*, dht_trait::Dht},
-    engine::{p2p_protocol::SpaceAddress, ChainId, RealEngine},
+    engine::{p2p_protocol::*, ChainId, RealEngine},
+    error::Lib3hResult,
     gateway::GatewayWrapper,
 };
 use lib3h_protocol::{
-    data_types::*, error::Lib3hProtocolResult, protocol_server::Lib3hServerProtocol,
+    data_types::*, error::Lib3hProtocolResult, protocol_server::Lib3hServerProtocol, Address,
 };
-use rmp_serde::Serializer;
-use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Space layer related private methods
 /// Engine does not process a space gateway's Transport because it is shared with the network layer
@@ -37,6 +36,115 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
         None
     }
 
+    /// Send each aspect of an entry to Core for validation and storage,
+    /// except the ones it already rejected
+    pub(crate) fn store_entry_aspect_list(
+        &mut self,
+        chain_id: &ChainId,
+        provider_agent_id: &Address,
+        entry: EntryData,
+    ) -> Vec<Lib3hServerProtocol> {
+        let mut outbox = Vec::new();
+        for aspect in entry.aspect_list {
+            let is_rejected = self
+                .rejected_aspect_map
+                .get(chain_id)
+                .map(|rejected_set| rejected_set.contains(&aspect.aspect_address))
+                .unwrap_or(false);
+            if is_rejected {
+                trace!("Skipping rejected aspect {}", aspect.aspect_address);
+                continue;
+            }
+            let lib3h_msg = StoreEntryAspectData {
+                request_id: self.request_track.reserve(),
+                space_address: chain_id.0.clone(),
+                provider_agent_id: provider_agent_id.clone(),
+                entry_address: entry.entry_address.clone(),
+                entry_aspect: aspect.clone(),
+            };
+            // Core should answer with a SuccessResult or a FailureResult
+            self.request_track.set(
+                &lib3h_msg.request_id,
+                Some(RealEngineTrackerData::HoldEntryRequested {
+                    chain_id: chain_id.clone(),
+                    provider_agent_id: provider_agent_id.clone(),
+                    entry_address: entry.entry_address.clone(),
+                    aspect,
+                }),
+            );
+            outbox.push(Lib3hServerProtocol::HandleStoreEntryAspect(lib3h_msg))
+        }
+        outbox
+    }
+
+    /// Core answered one of our HandleStoreEntryAspect.
+    /// Accepted: hold the aspect in the space DHT.
+    /// Rejected: never hold nor request that aspect again and tell its provider.
+    pub(crate) fn serve_StoreEntryAspectResult(
+        &mut self,
+        result: &GenericResultData,
+        is_accepted: bool,
+    ) -> Lib3hResult<()> {
+        let (chain_id, provider_agent_id, entry_address, aspect) =
+            match self.request_track.get(&result.request_id) {
+                Some(RealEngineTrackerData::HoldEntryRequested {
+                    chain_id,
+                    provider_agent_id,
+                    entry_address,
+                    aspect,
+                }) => (
+                    chain_id.clone(),
+                    provider_agent_id.clone(),
+                    entry_address.clone(),
+                    aspect.clone(),
+                ),
+                _ => {
+                    debug!("Received untracked result: {:?}", result);
+                    return Ok(());
+                }
+            };
+        self.request_track.remove(&result.request_id);
+        if is_accepted {
+            if let Some(space_gateway) = self.space_gateway_map.get_mut(&chain_id) {
+                let entry = EntryData {
+                    entry_address,
+                    aspect_list: vec![aspect],
+                };
+                space_gateway
+                    .as_dht_mut()
+                    .post(DhtCommand::HoldEntryAspectAddress(entry))?;
+            }
+            return Ok(());
+        }
+        info!(
+            "{} - Core rejected aspect {} of entry {}",
+            self.name, aspect.aspect_address, entry_address,
+        );
+        self.rejected_aspect_map
+            .entry(chain_id.clone())
+            .or_insert_with(HashSet::new)
+            .insert(aspect.aspect_address.clone());
+        if provider_agent_id == chain_id.1 {
+            return Ok(());
+        }
+        let error_text = format!(
+            "Rejected aspect {} of entry {}: {}",
+            aspect.aspect_address,
+            entry_address,
+            String::from_utf8_lossy(&result.result_info),
+        );
+        self.send_to_space_peer(
+            &chain_id.0,
+            &chain_id.1,
+            &provider_agent_id,
+            &P2pMessage::MsgError(MsgError {
+                error_code: ErrorCode::Unknown,
+                error_text,
+            }),
+        );
+        Ok(())
+    }
+
     /// Process all space gateways
     pub(crate) fn process_space_gateways(
         &mut self,
@@ -83,6 +191,12 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
             DhtEvent::GossipUnreliablyTo(_data) => {
                 // n/a - should have been handled by gateway
             }
+            DhtEvent::GossipUndelivered(gossip_data) => {
+                warn!(
+                    "{} - gossip of {:?} undelivered to {:?}",
+                    self.name, chain_id, gossip_data.peer_address_list,
+                );
+            }
             // HoldPeerRequested from gossip
             DhtEvent::HoldPeerRequested(peer_data) => {
                 debug!(
@@ -102,43 +216,20 @@ impl<'engine, D: Dht> RealEngine<'engine, D> {
             // HoldEntryRequested from gossip
             // -> Send each aspect to Core for validation
             DhtEvent::HoldEntryRequested(from, entry) => {
-                for aspect in entry.aspect_list {
-                    let lib3h_msg = StoreEntryAspectData {
-                        request_id: self.request_track.reserve(),
-                        space_address: chain_id.0.clone(),
-                        provider_agent_id: from.clone().into(),
-                        entry_address: entry.entry_address.clone(),
-                        entry_aspect: aspect,
-                    };
-                    // TODO - not sure what core is expected to send back here
-                    //      - right now these tracks will timeout
-                    self.request_track.set(
-                        &lib3h_msg.request_id,
-                        Some(RealEngineTrackerData::HoldEntryRequested),
-                    );
-                    outbox.push(Lib3hServerProtocol::HandleStoreEntryAspect(lib3h_msg))
-                }
+                outbox.append(&mut self.store_entry_aspect_list(chain_id, &from.into(), entry));
+            }
+            DhtEvent::FetchEntryResponse(_response) => {
+                // n/a - QueryEntry and FetchEntry are served by the engine
             }
-            // FetchEntryResponse: Send back as a query response to Core
-            // TODO #169 - Discern Fetch from Query
-            DhtEvent::FetchEntryResponse(response) => {
-                let mut query_result = Vec::new();
-                response
-                    .entry
-                    .serialize(&mut Serializer::new(&mut query_result))
-                    .unwrap();
-                let msg_data = QueryEntryResultData {
+            // EntryPruned: DHT is no longer responsible for that entry,
+            // tell Core it does not need to hold it anymore.
+            DhtEvent::EntryPruned(entry_address) => {
+                let msg_data = DropEntryData {
                     space_address: chain_id.0.clone(),
-                    entry_address: response.entry.entry_address.clone(),
-                    request_id: response.msg_id.clone(),
-                    requester_agent_id: chain_id.1.clone(), // TODO #150 - get requester from channel from p2p-protocol
-                    responder_agent_id: chain_id.1.clone(),
-                    query_result,
+                    request_id: self.request_track.gen_id(),
+                    entry_address,
                 };
-                outbox.push(Lib3hServerProtocol::QueryEntryResult(msg_data))
-            }
-            DhtEvent::EntryPruned(_address) => {
-                // TODO #174
+                outbox.push(Lib3hServerProtocol::HandleDropEntry(msg_data));
             }
             // EntryDataRequested: Change it into a Lib3hServerProtocol::HandleFetchEntry.
             DhtEvent::EntryDataRequested(fetch_entry) => {
@@ -2,8 +2,10 @@
 
 use crate::transport::error::TransportError;
 use lib3h_crypto_api::CryptoError;
+use lib3h_mdns::MulticastDnsError;
+use lib3h_p2p_protocol::error::P2pProtocolError;
 use lib3h_protocol::error::{ErrorKind as Lib3hProtocolErrorKind, Lib3hProtocolError};
-use rmp_serde::decode::Error as RMPSerdeDecodeError;
+use rmp_serde::{decode::Error as RMPSerdeDecodeError, encode::Error as RMPSerdeEncodeError};
 use std::{error::Error as StdError, fmt, io, result};
 
 /// A type alias for `Result<T, Lib3hError>`.
@@ -50,12 +52,18 @@ pub enum ErrorKind {
     TransportError(TransportError),
     /// Error originating from [lib3h_protocol] crate.
     Lib3hProtocolError(Lib3hProtocolError),
+    /// Error originating from [lib3h_p2p_protocol] crate, usually a malformed wire message.
+    P2pProtocolError(P2pProtocolError),
     /// Error occuring from [Hcid](hcid) crate.
     HcId(hcid::HcidError),
     /// Error originating from [MessagePack](rmp_serde) deserializing crate.
     RmpSerdeDecodeError(RMPSerdeDecodeError),
+    /// Error originating from [MessagePack](rmp_serde) serializing crate.
+    RmpSerdeEncodeError(RMPSerdeEncodeError),
     /// Error from the [lib3h_crypto_api] crate.
     CryptoApiError(CryptoError),
+    /// Error from the [lib3h_mdns] crate, used for LAN discovery.
+    MulticastDnsError(MulticastDnsError),
     /// Error occuring when the key is not present in the Map.
     KeyNotFound(String),
     /// Yet undefined error.
@@ -76,9 +84,12 @@ impl StdError for Lib3hError {
             ErrorKind::Io(ref err) => Some(err),
             ErrorKind::TransportError(ref err) => Some(err),
             ErrorKind::Lib3hProtocolError(ref err) => Some(err),
+            ErrorKind::P2pProtocolError(ref err) => Some(err),
             ErrorKind::HcId(ref err) => Some(err),
             ErrorKind::RmpSerdeDecodeError(ref err) => Some(err),
+            ErrorKind::RmpSerdeEncodeError(ref err) => Some(err),
             ErrorKind::CryptoApiError(ref err) => Some(err),
+            ErrorKind::MulticastDnsError(ref err) => Some(err),
             ErrorKind::Other(ref _s) | ErrorKind::KeyNotFound(ref _s) => None,
             _ => unreachable!(),
         }
@@ -91,9 +102,12 @@ impl fmt::Display for Lib3hError {
             ErrorKind::Io(ref err) => err.fmt(f),
             ErrorKind::TransportError(ref err) => err.fmt(f),
             ErrorKind::Lib3hProtocolError(ref err) => err.fmt(f),
+            ErrorKind::P2pProtocolError(ref err) => err.fmt(f),
             ErrorKind::HcId(ref err) => err.fmt(f),
             ErrorKind::RmpSerdeDecodeError(ref err) => err.fmt(f),
+            ErrorKind::RmpSerdeEncodeError(ref err) => err.fmt(f),
             ErrorKind::CryptoApiError(ref err) => err.fmt(f),
+            ErrorKind::MulticastDnsError(ref err) => err.fmt(f),
             ErrorKind::KeyNotFound(ref s) => write!(f, "Key: '{}' not found", s),
             ErrorKind::Other(ref s) => write!(f, "Unknown error encountered: '{}'.", s),
             _ => unreachable!(),
@@ -125,18 +139,36 @@ impl From<Lib3hProtocolError> for Lib3hError {
     }
 }
 
+impl From<P2pProtocolError> for Lib3hError {
+    fn from(err: P2pProtocolError) -> Self {
+        Lib3hError::new(ErrorKind::P2pProtocolError(err))
+    }
+}
+
 impl From<RMPSerdeDecodeError> for Lib3hError {
     fn from(err: RMPSerdeDecodeError) -> Self {
         Lib3hError::new(ErrorKind::RmpSerdeDecodeError(err))
     }
 }
 
+impl From<RMPSerdeEncodeError> for Lib3hError {
+    fn from(err: RMPSerdeEncodeError) -> Self {
+        Lib3hError::new(ErrorKind::RmpSerdeEncodeError(err))
+    }
+}
+
 impl From<CryptoError> for Lib3hError {
     fn from(err: CryptoError) -> Self {
         Lib3hError::new(ErrorKind::CryptoApiError(err))
     }
 }
 
+impl From<MulticastDnsError> for Lib3hError {
+    fn from(err: MulticastDnsError) -> Self {
+        Lib3hError::new(ErrorKind::MulticastDnsError(err))
+    }
+}
+
 // I'm not so sure about this...
 impl From<Lib3hError> for Lib3hProtocolError {
     fn from(err: Lib3hError) -> Self {
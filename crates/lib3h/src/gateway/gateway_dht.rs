@@ -1,14 +1,12 @@
 #![allow(non_snake_case)]
 
 use crate::{
-    dht::{dht_protocol::*, dht_trait::Dht},
+    dht::{dht_protocol::*, dht_trait::Dht, rrdht::StorageArc},
     engine::{p2p_protocol::*, NETWORK_GATEWAY_ID},
     error::Lib3hResult,
     gateway::{Gateway, P2pGateway},
 };
 use lib3h_protocol::{Address, DidWork};
-use rmp_serde::Serializer;
-use serde::Serialize;
 
 /// Compose DHT
 impl<'gateway, D: Dht> Dht for P2pGateway<'gateway, D> {
@@ -29,6 +27,15 @@ impl<'gateway, D: Dht> Dht for P2pGateway<'gateway, D> {
     fn get_aspects_of(&self, entry_address: &Address) -> Option<Vec<Address>> {
         self.inner_dht.get_aspects_of(entry_address)
     }
+    fn get_holder_list(&self, entry_address: &Address) -> Vec<PeerData> {
+        self.inner_dht.get_holder_list(entry_address)
+    }
+    fn get_storage_arc(&self) -> StorageArc {
+        self.inner_dht.get_storage_arc()
+    }
+    fn get_aspect_hold_time(&self, aspect_address: &Address) -> Option<u64> {
+        self.inner_dht.get_aspect_hold_time(aspect_address)
+    }
 
     /// Processing
     fn post(&mut self, cmd: DhtCommand) -> Lib3hResult<()> {
@@ -55,6 +62,18 @@ impl<'gateway, D: Dht> Dht for P2pGateway<'gateway, D> {
                     );
                 }
             }
+            if let DhtCommand::DropPeer(peer_data) = &cmd {
+                // Keep it unless the leave is newer than its last announcement
+                let is_stale = self
+                    .inner_dht
+                    .get_peer(&peer_data.peer_address)
+                    .map(|peer| peer.timestamp >= peer_data.timestamp)
+                    .unwrap_or(false);
+                if !is_stale {
+                    self.connection_map.remove(&peer_data.peer_uri);
+                    self.gossip_delivery.forget_peer(&peer_data.peer_address);
+                }
+            }
         }
         self.inner_dht.post(cmd)
     }
@@ -73,6 +92,24 @@ impl<'gateway, D: Dht> Dht for P2pGateway<'gateway, D> {
                 self.handle_DhtEvent(evt)?;
             }
         }
+        // Resend unacknowledged gossip
+        let mut dht_event_list = dht_event_list;
+        let (resend_list, given_up_list) =
+            self.gossip_delivery.process(crate::time::since_epoch_ms());
+        let did_work = did_work || !resend_list.is_empty() || !given_up_list.is_empty();
+        for pending in resend_list {
+            debug!(
+                "({}) resending gossip {} to {}",
+                self.identifier, pending.gossip_id, pending.to_peer_address,
+            );
+            self.send_gossip(&pending.to_peer_address, &pending.bundle, pending.gossip_id);
+        }
+        for pending in given_up_list {
+            dht_event_list.push(DhtEvent::GossipUndelivered(GossipToData {
+                peer_address_list: vec![pending.to_peer_address],
+                bundle: pending.bundle,
+            }));
+        }
         // TODO #173: Check for timeouts of own requests here?
         // Done
         Ok((did_work, dht_event_list))
@@ -94,24 +131,13 @@ impl<'gateway, D: Dht> P2pGateway<'gateway, D> {
                         continue;
                     }
                     // TODO END
-                    // Convert DHT Gossip to P2P Gossip
-                    let p2p_gossip = P2pProtocol::Gossip(GossipData {
-                        space_address: self.identifier().into(),
-                        to_peer_address: to_peer_address.clone().into(),
-                        from_peer_address: self.this_peer().peer_address.clone().into(),
-                        bundle: data.bundle.clone(),
-                    });
-                    let mut payload = Vec::new();
-                    p2p_gossip
-                        .serialize(&mut Serializer::new(&mut payload))
-                        .expect("P2pProtocol::Gossip serialization failed");
-                    let to_conn_id = self
-                        .get_connection_id(&to_peer_address)
-                        .expect("Should gossip to a known peer");
-                    // Forward gossip to the inner_transport
-                    self.inner_transport
-                        .as_mut()
-                        .send(&[&to_conn_id], &payload)?;
+                    // Resent until acknowledged
+                    let gossip_id = self.gossip_delivery.track(
+                        &to_peer_address,
+                        &data.bundle,
+                        crate::time::since_epoch_ms(),
+                    );
+                    self.send_gossip(&to_peer_address, &data.bundle, gossip_id);
                 }
             }
             DhtEvent::GossipUnreliablyTo(_data) => {
@@ -132,10 +158,41 @@ impl<'gateway, D: Dht> P2pGateway<'gateway, D> {
             DhtEvent::EntryPruned(_address) => {
                 // no-op
             }
+            DhtEvent::GossipUndelivered(_data) => {
+                // no-op
+            }
             DhtEvent::EntryDataRequested(_) => {
                 // no-op
             }
         }
         Ok(())
     }
+
+    /// Convert DHT Gossip to P2P Gossip and forward it to the inner_transport.
+    /// Failures are only logged: unacknowledged gossip is resent later.
+    fn send_gossip(&mut self, to_peer_address: &str, bundle: &[u8], gossip_id: u64) {
+        let p2p_gossip = P2pMessage::MsgDhtGossip(MsgDhtGossip {
+            space_address: self.identifier().as_bytes().to_vec(),
+            to_peer_address: to_peer_address.as_bytes().to_vec(),
+            from_peer_address: self.this_peer().peer_address.as_bytes().to_vec(),
+            bundle: bundle.to_vec(),
+            gossip_id,
+        });
+        let to_conn_id = match self.get_connection_id(to_peer_address) {
+            Some(to_conn_id) => to_conn_id,
+            None => {
+                debug!(
+                    "({}) no connection to {} for gossip {}",
+                    self.identifier, to_peer_address, gossip_id,
+                );
+                return;
+            }
+        };
+        if let Err(e) = self.send_on_channel(to_peer_address, &to_conn_id, &p2p_gossip.to_bytes()) {
+            warn!(
+                "({}) failed sending gossip {} to {}: {:?}",
+                self.identifier, gossip_id, to_peer_address, e,
+            );
+        }
+    }
 }
@@ -1,19 +1,20 @@
 #![allow(non_snake_case)]
 
 use crate::{
-    dht::{dht_protocol::*, dht_trait::Dht},
-    engine::p2p_protocol::P2pProtocol,
-    gateway::{Gateway, P2pGateway},
+    dht::dht_trait::Dht,
+    engine::NETWORK_GATEWAY_ID,
+    gateway::{
+        multiplex::{ChannelKey, Multiplexer},
+        P2pGateway,
+    },
     transport::{
         error::{TransportError, TransportResult},
-        protocol::{TransportCommand, TransportEvent},
+        protocol::{CloseReason, TransportCommand, TransportEvent},
         transport_trait::Transport,
         ConnectionId, ConnectionIdRef,
     },
 };
 use lib3h_protocol::DidWork;
-use rmp_serde::{Deserializer, Serializer};
-use serde::{Deserialize, Serialize};
 use url::Url;
 
 /// Compose Transport
@@ -41,8 +42,8 @@ impl<'gateway, D: Dht> Transport for P2pGateway<'gateway, D> {
     }
 
     /// id_list =
-    ///   - Network : transportId
-    ///   - space   : agentId
+    ///   - Network : transportId, payload must already be a multiplexed frame
+    ///   - space   : agentId, payload is sent on the channel to each agent
     fn send(&mut self, dht_id_list: &[&ConnectionIdRef], payload: &[u8]) -> TransportResult<()> {
         // get connectionId from the inner dht first
         let dht_uri_list = self.dht_address_to_uri_list(dht_id_list)?;
@@ -57,18 +58,37 @@ impl<'gateway, D: Dht> Transport for P2pGateway<'gateway, D> {
         // Get connectionIds for the inner Transport.
         let mut conn_list = Vec::new();
         for dht_uri in dht_uri_list {
-            let net_uri = self.connection_map.get(&dht_uri).expect("unknown dht_uri");
-            conn_list.push(net_uri);
+            let net_uri = self
+                .resolve_connection_id(&dht_uri)
+                .ok_or_else(|| TransportError::new(format!("No connection to: {}", dht_uri)))?;
             trace!(
                 "({}).send() reversed mapped dht_uri {:?} to net_uri {:?}",
                 self.identifier,
                 dht_uri,
                 net_uri,
-            )
+            );
+            conn_list.push(net_uri);
+        }
+        if self.identifier == NETWORK_GATEWAY_ID {
+            // Answers to our transfers come back on these connections
+            {
+                let mut multiplexer = self
+                    .multiplexer
+                    .write()
+                    .expect("failed to obtain write lock");
+                for (dht_id, net_id) in dht_id_list.iter().zip(conn_list.iter()) {
+                    multiplexer.set_route(dht_id, net_id);
+                }
+            }
+            let ref_list: Vec<&str> = conn_list.iter().map(|v| v.as_str()).collect();
+            // Send on the inner Transport
+            return self.inner_transport.as_mut().send(&ref_list, payload);
+        }
+        // Send on each agent's channel
+        for (dht_id, net_id) in dht_id_list.iter().zip(conn_list.iter()) {
+            self.send_on_channel(dht_id, net_id, payload)?;
         }
-        let ref_list: Vec<&str> = conn_list.iter().map(|v| v.as_str()).collect();
-        // Send on the inner Transport
-        self.inner_transport.as_mut().send(&ref_list, payload)
+        Ok(())
     }
 
     ///
@@ -142,6 +162,47 @@ impl<'gateway, D: Dht> Transport for P2pGateway<'gateway, D> {
 
 /// Private internals
 impl<'gateway, D: Dht> P2pGateway<'gateway, D> {
+    /// Send a payload to a peer of this gateway on the inner Transport:
+    ///   - Network : on the network channel of the connection
+    ///   - space   : on the channel from this agent to the peer
+    pub(crate) fn send_on_channel(
+        &mut self,
+        peer_address: &str,
+        inner_id: &ConnectionIdRef,
+        payload: &[u8],
+    ) -> TransportResult<()> {
+        let frame_list = {
+            let mut multiplexer = self
+                .multiplexer
+                .write()
+                .expect("failed to obtain write lock");
+            // Assume the remote refuses what we would refuse
+            if payload.len() > multiplexer.max_message_size() {
+                return Err(TransportError::new(format!(
+                    "Message of {} bytes is bigger than the maximum message size",
+                    payload.len()
+                )));
+            }
+            if self.identifier == NETWORK_GATEWAY_ID {
+                vec![Multiplexer::network_frame(payload)]
+            } else {
+                let key = ChannelKey {
+                    space_address: self.identifier.clone().into(),
+                    from_agent_id: self.inner_dht.this_peer().peer_address.clone().into(),
+                    to_agent_id: peer_address.to_string().into(),
+                };
+                multiplexer
+                    .multiplex(inner_id, &key, payload)
+                    .map_err(|e| TransportError::new(e.to_string()))?
+            }
+        };
+        let mut inner_transport = self.inner_transport.as_mut();
+        for frame in frame_list {
+            inner_transport.send(&[inner_id], &frame)?;
+        }
+        Ok(())
+    }
+
     /// Get Uris from DHT peer_address'
     pub(crate) fn dht_address_to_uri_list(
         &self,
@@ -176,25 +237,8 @@ impl<'gateway, D: Dht> P2pGateway<'gateway, D> {
         if let Some(previous_cId) = maybe_previous {
             debug!("Replaced connectionId for {} ; was: {}", uri, previous_cId,);
         }
-
-        // Send to other node our PeerAddress
-        let this_peer = self.this_peer().clone();
-        let our_peer_address = P2pProtocol::PeerAddress(
-            self.identifier().to_string(),
-            this_peer.peer_address,
-            this_peer.timestamp,
-        );
-        let mut buf = Vec::new();
-        our_peer_address
-            .serialize(&mut Serializer::new(&mut buf))
-            .unwrap();
-        trace!(
-            "({}) sending P2pProtocol::PeerAddress: {:?} to {:?}",
-            self.identifier,
-            our_peer_address,
-            id,
-        );
-        return self.inner_transport.as_mut().send(&[&id], &buf);
+        // Note: our signed MsgHandshake is sent by the engine which owns the transport keys
+        Ok(())
     }
 
     /// Process a transportEvent received from our internal connection.
@@ -220,41 +264,15 @@ impl<'gateway, D: Dht> P2pGateway<'gateway, D> {
                 info!("({}) Incoming connection opened: {}", self.identifier, id);
                 self.handle_new_connection(id)?;
             }
-            TransportEvent::ConnectionClosed(_id) => {
+            TransportEvent::ConnectionClosed(_id, _reason) => {
                 // TODO #176
             }
-            TransportEvent::ReceivedData(connection_id, payload) => {
+            TransportEvent::ConnectionDrained(id) => {
+                debug!("({}) Connection drained: {}", self.identifier, id);
+            }
+            TransportEvent::ReceivedData(connection_id, _payload) => {
+                // Note: MsgHandshake is verified and held by the engine
                 debug!("Received message from: {}", connection_id);
-                // trace!("Deserialize msg: {:?}", payload);
-                let mut de = Deserializer::new(&payload[..]);
-                let maybe_p2p_msg: Result<P2pProtocol, rmp_serde::decode::Error> =
-                    Deserialize::deserialize(&mut de);
-                if let Ok(p2p_msg) = maybe_p2p_msg {
-                    if let P2pProtocol::PeerAddress(gateway_id, peer_address, peer_timestamp) =
-                        p2p_msg
-                    {
-                        debug!(
-                            "Received PeerAddress: {} | {} ({})",
-                            peer_address, gateway_id, self.identifier
-                        );
-                        let peer_uri = self
-                            .inner_transport
-                            .as_mut()
-                            .get_uri(connection_id)
-                            .expect("FIXME"); // TODO #58
-                        debug!("peer_uri of: {} = {}", connection_id, peer_uri);
-                        if self.identifier == gateway_id {
-                            let peer = PeerData {
-                                peer_address: peer_address.clone(),
-                                peer_uri,
-                                timestamp: peer_timestamp,
-                            };
-                            Dht::post(self, DhtCommand::HoldPeer(peer)).expect("FIXME"); // TODO #58
-                                                                                         // TODO #150 - Should not call process manually
-                            Dht::process(self).expect("HACK");
-                        }
-                    }
-                }
             }
         };
         Ok(())
@@ -270,11 +288,17 @@ impl<'gateway, D: Dht> P2pGateway<'gateway, D> {
         trace!("({}) serving transport cmd: {:?}", self.identifier, cmd);
         // Note: use same order as the enum
         match cmd {
-            TransportCommand::Connect(url, request_id) => {
-                let id = self.connect(url)?;
-                let evt = TransportEvent::ConnectResult(id, request_id.clone());
-                Ok(vec![evt])
-            }
+            TransportCommand::Connect(url, request_id) => match self.connect(url) {
+                Ok(id) => Ok(vec![TransportEvent::ConnectResult(id, request_id.clone())]),
+                // Report it for that url only, e.g. a dead bootstrap node
+                Err(e) => {
+                    warn!(
+                        "({}) failed to connect to {}: {:?}",
+                        self.identifier, url, e
+                    );
+                    Ok(vec![TransportEvent::ErrorOccured(url.to_string(), e)])
+                }
+            },
             TransportCommand::Send(id_list, payload) => {
                 let mut id_ref_list = Vec::with_capacity(id_list.len());
                 for id in id_list {
@@ -289,7 +313,7 @@ impl<'gateway, D: Dht> P2pGateway<'gateway, D> {
             }
             TransportCommand::Close(id) => {
                 self.close(id)?;
-                let evt = TransportEvent::ConnectionClosed(id.to_string());
+                let evt = TransportEvent::ConnectionClosed(id.to_string(), CloseReason::Local);
                 Ok(vec![evt])
             }
             TransportCommand::CloseAll => {
@@ -0,0 +1,218 @@
+//! Reliable delivery of the gossip bundles a DHT asks to send with `DhtEvent::GossipTo`.
+//! Each bundle is sent with a gossipId and resent with an exponential backoff
+//! until its recipient acknowledges it with a `MsgDhtGossipAck`,
+//! or given up after `GOSSIP_MAX_ATTEMPTS`.
+//! Recipients always acknowledge, but only handle a gossipId once.
+//! GossipIds start at a random value, so a sender that restarted or rejoined
+//! does not reuse the ids its recipients remember from its previous session.
+
+use crate::dht::PeerAddress;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Delay before the first resend of an unacknowledged gossip, doubled on every resend
+pub const GOSSIP_RETRY_BASE_MS: u64 = 500;
+/// Number of sends of a gossip before giving up on it
+pub const GOSSIP_MAX_ATTEMPTS: u32 = 5;
+/// Number of gossipIds remembered per sender for dropping duplicates
+const RECEIVED_HISTORY_SIZE: usize = 256;
+
+/// A gossip waiting for its acknowledgement
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingGossip {
+    pub gossip_id: u64,
+    pub to_peer_address: PeerAddress,
+    pub bundle: Vec<u8>,
+    attempt_count: u32,
+    next_attempt_ms: u64,
+}
+
+pub struct GossipDelivery {
+    next_gossip_id: u64,
+    pending_map: HashMap<u64, PendingGossip>,
+    /// Recently received gossipIds per sender, oldest first
+    received_map: HashMap<PeerAddress, (VecDeque<u64>, HashSet<u64>)>,
+}
+
+impl Default for GossipDelivery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Random non-zero gossipId to start a session with
+fn random_gossip_id() -> u64 {
+    const HEX_ALPHABET: [char; 16] = [
+        '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f',
+    ];
+    let gossip_id = u64::from_str_radix(&nanoid::custom(16, &HEX_ALPHABET), 16).unwrap_or(0);
+    // Note: 0 is for gossip that does not expect an ack
+    std::cmp::max(gossip_id, 1)
+}
+
+impl GossipDelivery {
+    pub fn new() -> Self {
+        GossipDelivery {
+            next_gossip_id: random_gossip_id(),
+            pending_map: HashMap::new(),
+            received_map: HashMap::new(),
+        }
+    }
+
+    /// Track a gossip we are sending for the first time.
+    /// Return the gossipId to send it with.
+    pub fn track(&mut self, to_peer_address: &str, bundle: &[u8], now_ms: u64) -> u64 {
+        let gossip_id = self.next_gossip_id;
+        self.next_gossip_id = self.next_gossip_id.checked_add(1).unwrap_or(1);
+        self.pending_map.insert(
+            gossip_id,
+            PendingGossip {
+                gossip_id,
+                to_peer_address: to_peer_address.to_string(),
+                bundle: bundle.to_vec(),
+                attempt_count: 1,
+                next_attempt_ms: now_ms + GOSSIP_RETRY_BASE_MS,
+            },
+        );
+        gossip_id
+    }
+
+    /// `from_peer_address` acknowledged a gossip.
+    /// Return false if we were not waiting for that acknowledgement.
+    pub fn acknowledge(&mut self, from_peer_address: &str, gossip_id: u64) -> bool {
+        match self.pending_map.get(&gossip_id) {
+            Some(pending) if pending.to_peer_address == from_peer_address => {
+                self.pending_map.remove(&gossip_id);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Gossips to resend now, and gossips given up on
+    pub fn process(&mut self, now_ms: u64) -> (Vec<PendingGossip>, Vec<PendingGossip>) {
+        let mut resend_list = Vec::new();
+        let mut given_up_id_list = Vec::new();
+        for (gossip_id, pending) in self.pending_map.iter_mut() {
+            if now_ms < pending.next_attempt_ms {
+                continue;
+            }
+            if pending.attempt_count >= GOSSIP_MAX_ATTEMPTS {
+                given_up_id_list.push(*gossip_id);
+                continue;
+            }
+            pending.next_attempt_ms = now_ms + (GOSSIP_RETRY_BASE_MS << pending.attempt_count);
+            pending.attempt_count += 1;
+            resend_list.push(pending.clone());
+        }
+        let given_up_list = given_up_id_list
+            .iter()
+            .filter_map(|gossip_id| self.pending_map.remove(gossip_id))
+            .collect();
+        (resend_list, given_up_list)
+    }
+
+    /// We received a gossip expecting an acknowledgement.
+    /// Return false if we already received it.
+    pub fn receive(&mut self, from_peer_address: &str, gossip_id: u64) -> bool {
+        let (id_queue, id_set) = self
+            .received_map
+            .entry(from_peer_address.to_string())
+            .or_insert_with(|| (VecDeque::new(), HashSet::new()));
+        if !id_set.insert(gossip_id) {
+            return false;
+        }
+        id_queue.push_back(gossip_id);
+        if id_queue.len() > RECEIVED_HISTORY_SIZE {
+            if let Some(oldest_id) = id_queue.pop_front() {
+                id_set.remove(&oldest_id);
+            }
+        }
+        true
+    }
+
+    /// Forget the gossips pending for a peer, e.g. when it left
+    pub fn forget_peer(&mut self, peer_address: &str) {
+        self.pending_map
+            .retain(|_, pending| pending.to_peer_address != peer_address);
+        self.received_map.remove(peer_address);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_resend_with_backoff_until_acknowledged() {
+        let mut delivery = GossipDelivery::new();
+        let gossip_id = delivery.track("billy", b"bundle", 0);
+        assert_ne!(gossip_id, 0);
+        // Not due yet
+        let (resend_list, given_up_list) = delivery.process(GOSSIP_RETRY_BASE_MS - 1);
+        assert!(resend_list.is_empty() && given_up_list.is_empty());
+        // First resend
+        let (resend_list, _) = delivery.process(GOSSIP_RETRY_BASE_MS);
+        assert_eq!(resend_list.len(), 1);
+        assert_eq!(resend_list[0].gossip_id, gossip_id);
+        assert_eq!(resend_list[0].bundle, b"bundle".to_vec());
+        // Backoff doubles
+        let (resend_list, _) = delivery.process(GOSSIP_RETRY_BASE_MS * 2);
+        assert!(resend_list.is_empty());
+        let (resend_list, _) = delivery.process(GOSSIP_RETRY_BASE_MS * 3);
+        assert_eq!(resend_list.len(), 1);
+        // Only the recipient can acknowledge it
+        assert!(!delivery.acknowledge("camille", gossip_id));
+        assert!(delivery.acknowledge("billy", gossip_id));
+        assert!(!delivery.acknowledge("billy", gossip_id));
+        let (resend_list, given_up_list) = delivery.process(std::u64::MAX / 2);
+        assert!(resend_list.is_empty() && given_up_list.is_empty());
+    }
+
+    #[test]
+    fn it_should_give_up_after_max_attempts() {
+        let mut delivery = GossipDelivery::new();
+        let gossip_id = delivery.track("billy", b"bundle", 0);
+        let mut now_ms = 0;
+        for _ in 1..GOSSIP_MAX_ATTEMPTS {
+            now_ms += GOSSIP_RETRY_BASE_MS << GOSSIP_MAX_ATTEMPTS;
+            let (resend_list, given_up_list) = delivery.process(now_ms);
+            assert_eq!(resend_list.len(), 1);
+            assert!(given_up_list.is_empty());
+        }
+        now_ms += GOSSIP_RETRY_BASE_MS << GOSSIP_MAX_ATTEMPTS;
+        let (resend_list, given_up_list) = delivery.process(now_ms);
+        assert!(resend_list.is_empty());
+        assert_eq!(given_up_list.len(), 1);
+        assert_eq!(given_up_list[0].gossip_id, gossip_id);
+        assert_eq!(given_up_list[0].to_peer_address, "billy");
+        assert!(!delivery.acknowledge("billy", gossip_id));
+    }
+
+    #[test]
+    fn it_should_drop_duplicates() {
+        let mut delivery = GossipDelivery::new();
+        assert!(delivery.receive("alex", 1));
+        assert!(!delivery.receive("alex", 1));
+        // Same id from another sender is another gossip
+        assert!(delivery.receive("camille", 1));
+        // Oldest ids are forgotten
+        for gossip_id in 2..=(RECEIVED_HISTORY_SIZE as u64 + 1) {
+            assert!(delivery.receive("alex", gossip_id));
+        }
+        assert!(delivery.receive("alex", 1));
+    }
+
+    #[test]
+    fn it_should_not_reuse_gossip_ids_after_a_restart() {
+        let mut receiver = GossipDelivery::new();
+        let mut sender = GossipDelivery::new();
+        let gossip_id = sender.track("billy", b"bundle-1", 0);
+        assert!(receiver.receive("alex", gossip_id));
+        // Alex restarts, its first gossip is not taken for a duplicate
+        let mut sender = GossipDelivery::new();
+        let restarted_gossip_id = sender.track("billy", b"bundle-2", 0);
+        assert_ne!(restarted_gossip_id, 0);
+        assert_ne!(restarted_gossip_id, gossip_id);
+        assert!(receiver.receive("alex", restarted_gossip_id));
+    }
+}
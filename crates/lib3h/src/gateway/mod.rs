@@ -1,7 +1,10 @@
 pub mod gateway_dht;
 pub mod gateway_transport;
+pub mod gossip_delivery;
+pub mod multiplex;
 pub mod p2p_gateway;
 
+use self::{gossip_delivery::GossipDelivery, multiplex::Multiplexer};
 use crate::{
     dht::dht_trait::Dht,
     transport::{protocol::*, transport_trait::Transport, ConnectionId, TransportWrapper},
@@ -19,6 +22,11 @@ pub trait Gateway: Transport + Dht {
     fn identifier(&self) -> &str;
     fn transport_inject_event(&mut self, evt: TransportEvent);
     fn get_connection_id(&self, peer_address: &str) -> Option<String>;
+    /// Acknowledge a gossip expecting it from a peer.
+    /// Return false if it was already received and must not be handled again.
+    fn receive_reliable_gossip(&mut self, from_peer_address: &str, gossip_id: u64) -> bool;
+    /// A peer acknowledged one of our gossips
+    fn handle_gossip_ack(&mut self, from_peer_address: &str, gossip_id: u64);
 }
 
 /// since rust doesn't suport upcasting to supertraits
@@ -102,4 +110,8 @@ pub struct P2pGateway<'gateway, D: Dht> {
     /// Own inbox for TransportCommands which is processed during Transport::process()
     transport_inbox: VecDeque<TransportCommand>,
     transport_inject_events: Vec<TransportEvent>,
+    /// Channels multiplexed on the network connections, shared by all gateways of an engine
+    multiplexer: Arc<RwLock<Multiplexer>>,
+    /// Acks and retries of the gossip our DHT sends with GossipTo
+    gossip_delivery: GossipDelivery,
 }
@@ -0,0 +1,1220 @@
+//! Multiplexing of space/agent channels on a single network connection.
+//! Every frame sent on a network connection is a `MultiplexMessage`:
+//!   - connection level messages are sent on the always open `NETWORK_CHANNEL_ID`
+//!   - space level messages are sent on a channel per (space, from-agent, to-agent),
+//!     created with a `MsgChannelCreate` before its first message.
+//! A node that cannot be reached directly can ask a peer to relay its channels
+//! with a `MsgRelayRequest`, and is then advertised as `holorelay://<relay>/<transportId>`.
+//! Payloads bigger than `FRAGMENT_SIZE` are sent as numbered `MsgChannelFragment`s,
+//! a window at a time, the receiver acknowledging each window with a `MsgFragmentResume`.
+//! A window left unacknowledged is sent again after `FRAGMENT_RETRANSMIT_MS`,
+//! and a transfer interrupted by a disconnection is sent again on the new connection.
+
+use crate::{
+    engine::p2p_protocol::{address_from_wire, address_to_wire},
+    error::{Lib3hError, Lib3hResult},
+    transport::{ConnectionId, ConnectionIdRef},
+};
+use lib3h_crypto_api::{Buffer, CryptoResult, CryptoSystem};
+use lib3h_p2p_protocol::multiplex::*;
+use lib3h_protocol::Address;
+use std::collections::HashMap;
+use url::Url;
+
+/// Channel of connection level messages, open on every connection
+pub const NETWORK_CHANNEL_ID: u32 = 0;
+/// First channel id available for space channels
+const FIRST_CHANNEL_ID: u32 = 1;
+/// Scheme of the uri of a peer reached through a relay
+pub const RELAY_SCHEME: &str = "holorelay";
+/// Payloads bigger than this are sent as fragments of this size
+pub const FRAGMENT_SIZE: usize = 64 * 1024;
+/// Fragments sent before waiting for the receiver to acknowledge them
+const FRAGMENT_WINDOW: u32 = 16;
+/// Transfers that did not progress for this long are dropped
+const TRANSFER_TIMEOUT_MS: u64 = 60_000;
+/// Windows the receiver did not acknowledge for this long are sent again
+const FRAGMENT_RETRANSMIT_MS: u64 = 5_000;
+/// Default maximum size of a message received on a channel
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+/// Fragmented messages of the maximum size we receive at once on a channel
+const MAX_IN_TRANSFER_PER_CHANNEL: usize = 2;
+/// Fragmented messages of the maximum size we receive at once on a connection
+const MAX_IN_TRANSFER_PER_CONNECTION: usize = 4;
+/// Room for the multiplexing header of a frame
+pub const FRAME_OVERHEAD: usize = 1024;
+
+/// Uri of a peer reached through the peer `relay_address`
+pub fn relay_uri(relay_address: &str, transport_id: &str) -> Url {
+    Url::parse(&format!(
+        "{}://{}/{}",
+        RELAY_SCHEME, relay_address, transport_id
+    ))
+    .expect("transportIds should make a valid relay uri")
+}
+
+/// Relay address and transportId of a peer uri, if it is a relay uri.
+/// Note: hosts are lowercased by `Url`, so compare the relay address ignoring case.
+pub fn parse_relay_uri(uri: &Url) -> Option<(&str, &str)> {
+    if uri.scheme() != RELAY_SCHEME {
+        return None;
+    }
+    let relay_address = uri.host_str()?;
+    Some((relay_address, uri.path().trim_start_matches('/')))
+}
+
+/// What a channel is established for
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChannelKey {
+    pub space_address: Address,
+    pub from_agent_id: Address,
+    pub to_agent_id: Address,
+}
+
+/// Result of demultiplexing a received frame
+#[derive(Debug, Clone, PartialEq)]
+pub enum Demultiplexed {
+    /// Payload received on the network channel
+    Network(Vec<u8>),
+    /// Payload received on a space channel
+    Channel(ChannelKey, Vec<u8>),
+    /// Remote asks us to act as its relay
+    RelayRequest,
+    /// Frame consumed by the multiplexer, with an optional frame to send back
+    Handled(Option<Vec<u8>>),
+    /// Last fragment of a payload received on a space channel,
+    /// with the frame acknowledging the whole payload to send back
+    Reassembled(ChannelKey, Vec<u8>, Vec<u8>),
+    /// Fragments the receiver of our payload asks for, to send back
+    Resend(Vec<Vec<u8>>),
+}
+
+/// A fragmented payload we are sending
+struct OutTransfer {
+    /// Remote id we send to
+    remote_id: ConnectionId,
+    key: ChannelKey,
+    payload: Vec<u8>,
+    message_hash: Vec<u8>,
+    fragment_count: u32,
+    /// Index of the first fragment the receiver did not acknowledge
+    acked_index: u32,
+    last_active_ms: u64,
+    /// Time we last sent its current window
+    last_sent_ms: u64,
+}
+
+impl OutTransfer {
+    /// Frames of the fragments following the acknowledged ones
+    fn window(&self, channel_id: u32) -> Vec<Vec<u8>> {
+        let end = std::cmp::min(self.acked_index + FRAGMENT_WINDOW, self.fragment_count);
+        (self.acked_index..end)
+            .map(|index| {
+                let start = index as usize * FRAGMENT_SIZE;
+                let end = std::cmp::min(start + FRAGMENT_SIZE, self.payload.len());
+                MultiplexMessage::MsgChannelFragment(MsgChannelFragment {
+                    channel_id,
+                    index,
+                    count: self.fragment_count,
+                    message_size: self.payload.len() as u64,
+                    message_hash: self.message_hash.clone(),
+                    content: self.payload[start..end].to_vec(),
+                })
+                .to_bytes()
+            })
+            .collect()
+    }
+}
+
+/// A fragmented payload we are receiving
+struct InTransfer {
+    payload: Vec<u8>,
+    message_size: usize,
+    fragment_count: u32,
+    /// Index of the next fragment expected
+    next_index: u32,
+    /// Index we last asked the sender to resume from, to ask only once
+    resume_index: Option<u32>,
+    last_active_ms: u64,
+}
+
+pub struct Multiplexer {
+    /// Channels we opened, per remote id we send to
+    out_channel_map: HashMap<ConnectionId, HashMap<ChannelKey, u32>>,
+    /// Channels opened by remotes, per connection we receive from
+    in_channel_map: HashMap<ConnectionId, HashMap<u32, ChannelKey>>,
+    next_channel_id: u32,
+    /// Connection we asked to act as our relay, until it answers
+    pending_relay_id: Option<ConnectionId>,
+    /// Connection of the peer relaying for us
+    relay_id: Option<ConnectionId>,
+    /// TransportId of each connection we act as a relay for
+    relayed_map: HashMap<ConnectionId, String>,
+    /// Connection we last sent to each remote id on,
+    /// the only one its receiver may answer our transfers on
+    route_map: HashMap<String, ConnectionId>,
+    /// Fragmented payloads we are sending, per channel id and message hash
+    out_transfer_map: HashMap<(u32, Vec<u8>), OutTransfer>,
+    /// Fragmented payloads we are receiving, per connection, channel and message hash.
+    /// Per connection, so no other remote can add fragments to a transfer:
+    /// one interrupted by a disconnection starts over on the new connection.
+    in_transfer_map: HashMap<(ConnectionId, ChannelKey, Vec<u8>), InTransfer>,
+    /// Hashes the fragmented payloads
+    crypto: Box<dyn CryptoSystem>,
+    /// Bigger messages are refused, and so are fragmented messages beyond
+    /// `MAX_IN_TRANSFER_PER_CHANNEL` / `MAX_IN_TRANSFER_PER_CONNECTION` times this size
+    max_message_size: usize,
+}
+
+impl Multiplexer {
+    pub fn new(crypto: Box<dyn CryptoSystem>, max_message_size: usize) -> Self {
+        Multiplexer {
+            out_channel_map: HashMap::new(),
+            in_channel_map: HashMap::new(),
+            next_channel_id: FIRST_CHANNEL_ID,
+            pending_relay_id: None,
+            relay_id: None,
+            relayed_map: HashMap::new(),
+            route_map: HashMap::new(),
+            out_transfer_map: HashMap::new(),
+            in_transfer_map: HashMap::new(),
+            crypto,
+            max_message_size,
+        }
+    }
+
+    /// Size of the biggest message we accept
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+
+    /// Connection of the peer relaying for us, if any
+    pub fn relay_id(&self) -> Option<&ConnectionIdRef> {
+        self.relay_id.as_ref().map(|id| id.as_str())
+    }
+
+    /// Frame asking `connection_id` to act as our relay.
+    /// None if we already have or are waiting for a relay.
+    pub fn request_relay(&mut self, connection_id: &ConnectionIdRef) -> Option<Vec<u8>> {
+        if self.relay_id.is_some() || self.pending_relay_id.is_some() {
+            return None;
+        }
+        self.pending_relay_id = Some(connection_id.to_string());
+        Some(MultiplexMessage::MsgRelayRequest.to_bytes())
+    }
+
+    /// Act as the relay of `transport_id` on `connection_id`.
+    /// Return the frame answering its `Demultiplexed::RelayRequest`.
+    pub fn accept_relay(&mut self, connection_id: &ConnectionIdRef, transport_id: &str) -> Vec<u8> {
+        self.relayed_map
+            .insert(connection_id.to_string(), transport_id.to_string());
+        MultiplexMessage::MsgRelayAccept.to_bytes()
+    }
+
+    /// Frame refusing a `Demultiplexed::RelayRequest`
+    pub fn refuse_relay() -> Vec<u8> {
+        MultiplexMessage::MsgError(MsgMultiplexError {
+            channel_id: NO_CHANNEL_ID,
+            error_code: MultiplexErrorCode::Unknown,
+            error_text: "Relay duty refused".to_string(),
+        })
+        .to_bytes()
+    }
+
+    /// Connections we act as a relay for
+    pub fn relayed_id_list(&self) -> Vec<ConnectionId> {
+        self.relayed_map.keys().cloned().collect()
+    }
+
+    /// Connection of a peer we act as a relay for
+    pub fn relayed_connection_id(&self, transport_id: &str) -> Option<&ConnectionIdRef> {
+        self.relayed_map
+            .iter()
+            .find(|(_, relayed_id)| relayed_id.as_str() == transport_id)
+            .map(|(connection_id, _)| connection_id.as_str())
+    }
+
+    /// Frames to `remote_id` are sent on `connection_id`, directly or through a relay
+    pub fn set_route(&mut self, remote_id: &str, connection_id: &ConnectionIdRef) {
+        if self.route_map.get(remote_id).map(|id| id.as_str()) != Some(connection_id) {
+            self.route_map
+                .insert(remote_id.to_string(), connection_id.to_string());
+        }
+    }
+
+    /// Frame a payload for the network channel
+    pub fn network_frame(payload: &[u8]) -> Vec<u8> {
+        MultiplexMessage::MsgChannelMessage(MsgChannelMessage {
+            channel_id: NETWORK_CHANNEL_ID,
+            content: payload.to_vec(),
+        })
+        .to_bytes()
+    }
+
+    /// Frames to send to `remote_id` for a payload on channel `key`.
+    /// Starts with a MsgChannelCreate if that channel is not open yet.
+    /// A payload bigger than `FRAGMENT_SIZE` is sent as fragments:
+    /// only the first window of them, the next ones are sent as the receiver asks for them.
+    /// Fails on payloads bigger than the maximum message size, which the receiver would refuse.
+    pub fn multiplex(
+        &mut self,
+        remote_id: &ConnectionIdRef,
+        key: &ChannelKey,
+        payload: &[u8],
+    ) -> Lib3hResult<Vec<Vec<u8>>> {
+        if payload.len() > self.max_message_size {
+            return Err(Lib3hError::new_other(&format!(
+                "Message of {} bytes is bigger than the maximum message size",
+                payload.len()
+            )));
+        }
+        let mut frame_list = Vec::new();
+        let channel_id = self.out_channel_id(remote_id, key, &mut frame_list);
+        if payload.len() <= FRAGMENT_SIZE {
+            frame_list.push(
+                MultiplexMessage::MsgChannelMessage(MsgChannelMessage {
+                    channel_id,
+                    content: payload.to_vec(),
+                })
+                .to_bytes(),
+            );
+            return Ok(frame_list);
+        }
+        let now = crate::time::since_epoch_ms();
+        self.out_transfer_map.retain(|_, transfer| {
+            now.saturating_sub(transfer.last_active_ms) < TRANSFER_TIMEOUT_MS
+        });
+        let transfer = OutTransfer {
+            remote_id: remote_id.to_string(),
+            key: key.clone(),
+            payload: payload.to_vec(),
+            message_hash: self.hash(payload)?,
+            fragment_count: ((payload.len() + FRAGMENT_SIZE - 1) / FRAGMENT_SIZE) as u32,
+            acked_index: 0,
+            last_active_ms: now,
+            last_sent_ms: now,
+        };
+        trace!(
+            "Sending {} bytes in {} fragments on channel {} to {}",
+            payload.len(),
+            transfer.fragment_count,
+            channel_id,
+            remote_id,
+        );
+        frame_list.append(&mut transfer.window(channel_id));
+        self.out_transfer_map
+            .insert((channel_id, transfer.message_hash.clone()), transfer);
+        Ok(frame_list)
+    }
+
+    /// Demultiplex a frame received on a connection.
+    /// `check_channel` tells if a remote may open a channel for that key.
+    pub fn demultiplex(
+        &mut self,
+        connection_id: &ConnectionIdRef,
+        frame: &[u8],
+        check_channel: impl Fn(&ChannelKey) -> Result<(), MultiplexErrorCode>,
+    ) -> Lib3hResult<Demultiplexed> {
+        // Refuse oversized frames before decoding them
+        let max_frame_size = self.max_message_size + FRAME_OVERHEAD;
+        if frame.len() > max_frame_size {
+            warn!(
+                "Refusing frame of {} bytes from {}",
+                frame.len(),
+                connection_id
+            );
+            return Ok(error_reply(
+                NO_CHANNEL_ID,
+                MultiplexErrorCode::MessageTooBig,
+                "Frame too big",
+            ));
+        }
+        // Note: use same order as the enum
+        let result = match MultiplexMessage::from_bytes_limited(frame, max_frame_size)? {
+            MultiplexMessage::MsgError(msg) => {
+                warn!("Received multiplex error from {}: {:?}", connection_id, msg);
+                if msg.channel_id == NO_CHANNEL_ID && self.is_pending_relay(connection_id) {
+                    // Relay refused: ask the next peer we connect to
+                    self.pending_relay_id = None;
+                }
+                // Forget the rejected channel: it will be created again on next message
+                let route_map = &self.route_map;
+                for (remote_id, channel_map) in self.out_channel_map.iter_mut() {
+                    if is_routed_on(route_map, remote_id, connection_id) {
+                        channel_map.retain(|_, channel_id| *channel_id != msg.channel_id);
+                    }
+                }
+                self.out_transfer_map.retain(|(channel_id, _), transfer| {
+                    *channel_id != msg.channel_id
+                        || !is_routed_on(route_map, &transfer.remote_id, connection_id)
+                });
+                Demultiplexed::Handled(None)
+            }
+            MultiplexMessage::MsgChannelCreate(msg) => {
+                let key = ChannelKey {
+                    space_address: address_from_wire(&msg.space_hash),
+                    from_agent_id: address_from_wire(&msg.from_id),
+                    to_agent_id: address_from_wire(&msg.to_id),
+                };
+                if msg.channel_id == NETWORK_CHANNEL_ID || msg.channel_id == NO_CHANNEL_ID {
+                    return Ok(error_reply(
+                        msg.channel_id,
+                        MultiplexErrorCode::BadChannelId,
+                        "Reserved channelId",
+                    ));
+                }
+                if let Err(error_code) = check_channel(&key) {
+                    debug!("Rejecting channel from {}: {:?}", connection_id, key);
+                    return Ok(error_reply(msg.channel_id, error_code, "Channel rejected"));
+                }
+                self.in_channel_map
+                    .entry(connection_id.to_string())
+                    .or_insert_with(HashMap::new)
+                    .insert(msg.channel_id, key);
+                Demultiplexed::Handled(None)
+            }
+            MultiplexMessage::MsgChannelClose(channel_id) => {
+                if let Some(channel_map) = self.in_channel_map.get_mut(connection_id) {
+                    channel_map.remove(&channel_id);
+                }
+                Demultiplexed::Handled(None)
+            }
+            MultiplexMessage::MsgChannelMessage(msg) => {
+                if msg.content.len() > self.max_message_size {
+                    return Ok(error_reply(
+                        msg.channel_id,
+                        MultiplexErrorCode::MessageTooBig,
+                        "Message too big",
+                    ));
+                }
+                if msg.channel_id == NETWORK_CHANNEL_ID {
+                    return Ok(Demultiplexed::Network(msg.content));
+                }
+                match self.in_channel_key(connection_id, msg.channel_id) {
+                    Some(key) => Demultiplexed::Channel(key, msg.content),
+                    None => error_reply(
+                        msg.channel_id,
+                        MultiplexErrorCode::BadChannelId,
+                        "Unknown channelId",
+                    ),
+                }
+            }
+            MultiplexMessage::MsgRelayRequest => Demultiplexed::RelayRequest,
+            MultiplexMessage::MsgRelayAccept => {
+                if self.is_pending_relay(connection_id) {
+                    info!("{} accepted to act as our relay", connection_id);
+                    self.pending_relay_id = None;
+                    self.relay_id = Some(connection_id.to_string());
+                } else {
+                    warn!("Received unrequested MsgRelayAccept from {}", connection_id);
+                }
+                Demultiplexed::Handled(None)
+            }
+            MultiplexMessage::MsgChannelFragment(msg) => {
+                match self.in_channel_key(connection_id, msg.channel_id) {
+                    Some(key) => self.receive_fragment(connection_id, key, msg)?,
+                    None => error_reply(
+                        msg.channel_id,
+                        MultiplexErrorCode::BadChannelId,
+                        "Unknown channelId",
+                    ),
+                }
+            }
+            MultiplexMessage::MsgFragmentResume(msg) => self.resume_transfer(connection_id, msg),
+        };
+        Ok(result)
+    }
+
+    /// Store a fragment of a payload sent on channel `key`.
+    /// Return the payload once all its fragments are received.
+    fn receive_fragment(
+        &mut self,
+        connection_id: &ConnectionIdRef,
+        key: ChannelKey,
+        msg: MsgChannelFragment,
+    ) -> Lib3hResult<Demultiplexed> {
+        let transfer_key = (connection_id.to_string(), key, msg.message_hash.clone());
+        // Refuse before allocating anything
+        if msg.message_size > self.max_message_size as u64 {
+            warn!(
+                "Refusing message of {} bytes on channel {:?}",
+                msg.message_size, transfer_key.1
+            );
+            self.in_transfer_map.remove(&transfer_key);
+            return Ok(error_reply(
+                msg.channel_id,
+                MultiplexErrorCode::MessageTooBig,
+                "Message too big",
+            ));
+        }
+        let now = crate::time::since_epoch_ms();
+        self.in_transfer_map.retain(|_, transfer| {
+            now.saturating_sub(transfer.last_active_ms) < TRANSFER_TIMEOUT_MS
+        });
+        // Bound what concurrent transfers can make us buffer,
+        // counting the announced size of each as it will grow to it
+        if !self.in_transfer_map.contains_key(&transfer_key) {
+            let message_size = msg.message_size as usize;
+            let (channel_bytes, connection_bytes) =
+                self.in_transfer_bytes(&transfer_key.1, connection_id);
+            if channel_bytes + message_size > MAX_IN_TRANSFER_PER_CHANNEL * self.max_message_size
+                || connection_bytes + message_size
+                    > MAX_IN_TRANSFER_PER_CONNECTION * self.max_message_size
+            {
+                warn!(
+                    "Refusing message of {} bytes from {}: too many bytes in transfer",
+                    message_size, connection_id
+                );
+                return Ok(error_reply(
+                    msg.channel_id,
+                    MultiplexErrorCode::MessageTooBig,
+                    "Too many messages in transfer",
+                ));
+            }
+        }
+        let transfer = self
+            .in_transfer_map
+            .entry(transfer_key.clone())
+            .or_insert_with(|| InTransfer {
+                // Grows with the fragments received, not with the announced size
+                payload: Vec::new(),
+                message_size: msg.message_size as usize,
+                fragment_count: msg.count,
+                next_index: 0,
+                resume_index: None,
+                last_active_ms: now,
+            });
+        if msg.count != transfer.fragment_count
+            || msg.message_size as usize != transfer.message_size
+            || msg.index >= msg.count
+            || transfer.payload.len() + msg.content.len() > transfer.message_size
+        {
+            self.in_transfer_map.remove(&transfer_key);
+            return Ok(error_reply(
+                msg.channel_id,
+                MultiplexErrorCode::Unknown,
+                "Inconsistent fragment",
+            ));
+        }
+        if msg.index != transfer.next_index {
+            // Fragment lost, or sent again after a reconnection:
+            // ask the sender to resume from the one we expect, once.
+            if transfer.resume_index == Some(transfer.next_index) {
+                return Ok(Demultiplexed::Handled(None));
+            }
+            transfer.resume_index = Some(transfer.next_index);
+            let reply = resume_frame(msg.channel_id, &msg.message_hash, transfer.next_index);
+            return Ok(Demultiplexed::Handled(Some(reply)));
+        }
+        transfer.payload.extend_from_slice(&msg.content);
+        transfer.next_index += 1;
+        transfer.resume_index = None;
+        transfer.last_active_ms = now;
+        if transfer.next_index < transfer.fragment_count {
+            if transfer.next_index % FRAGMENT_WINDOW != 0 {
+                return Ok(Demultiplexed::Handled(None));
+            }
+            // Acknowledge the window
+            let reply = resume_frame(msg.channel_id, &msg.message_hash, transfer.next_index);
+            return Ok(Demultiplexed::Handled(Some(reply)));
+        }
+        let transfer = self
+            .in_transfer_map
+            .remove(&transfer_key)
+            .expect("transfer should be stored");
+        if transfer.payload.len() != transfer.message_size
+            || self.hash(&transfer.payload)? != msg.message_hash
+        {
+            warn!("Bad checksum of message on channel {:?}", transfer_key.1);
+            return Ok(error_reply(
+                msg.channel_id,
+                MultiplexErrorCode::Unknown,
+                "Bad message checksum",
+            ));
+        }
+        let ack = resume_frame(msg.channel_id, &msg.message_hash, transfer.fragment_count);
+        Ok(Demultiplexed::Reassembled(
+            transfer_key.1,
+            transfer.payload,
+            ack,
+        ))
+    }
+
+    /// Announced bytes of the transfers we receive on channel `key`,
+    /// and of the ones we receive from `connection_id`
+    fn in_transfer_bytes(
+        &self,
+        key: &ChannelKey,
+        connection_id: &ConnectionIdRef,
+    ) -> (usize, usize) {
+        let mut channel_bytes = 0;
+        let mut connection_bytes = 0;
+        for ((transfer_connection_id, transfer_channel_key, _), transfer) in
+            self.in_transfer_map.iter()
+        {
+            if transfer_channel_key == key {
+                channel_bytes += transfer.message_size;
+            }
+            if transfer_connection_id == connection_id {
+                connection_bytes += transfer.message_size;
+            }
+        }
+        (channel_bytes, connection_bytes)
+    }
+
+    /// The receiver of a fragmented payload asks for the fragments following `next_index`
+    fn resume_transfer(
+        &mut self,
+        connection_id: &ConnectionIdRef,
+        msg: MsgFragmentResume,
+    ) -> Demultiplexed {
+        let transfer_key = (msg.channel_id, msg.message_hash);
+        let transfer = match self.out_transfer_map.get_mut(&transfer_key) {
+            Some(transfer) => transfer,
+            None => {
+                debug!(
+                    "Resume of an unknown transfer on channel {}",
+                    msg.channel_id
+                );
+                return Demultiplexed::Handled(None);
+            }
+        };
+        if !is_routed_on(&self.route_map, &transfer.remote_id, connection_id) {
+            warn!(
+                "Ignoring resume of a transfer to {} from {}",
+                transfer.remote_id, connection_id
+            );
+            return Demultiplexed::Handled(None);
+        }
+        if msg.next_index < transfer.fragment_count {
+            let now = crate::time::since_epoch_ms();
+            transfer.acked_index = msg.next_index;
+            transfer.last_active_ms = now;
+            transfer.last_sent_ms = now;
+            return Demultiplexed::Resend(transfer.window(msg.channel_id));
+        }
+        trace!("Fragmented message received on channel {}", msg.channel_id);
+        self.out_transfer_map.remove(&transfer_key);
+        Demultiplexed::Handled(None)
+    }
+
+    /// Forget the channels and relay contract of a closed connection
+    pub fn close_connection(&mut self, connection_id: &ConnectionIdRef) {
+        self.in_channel_map.remove(connection_id);
+        self.in_transfer_map
+            .retain(|(transfer_connection_id, _, _), _| transfer_connection_id != connection_id);
+        // Channels we relay are keyed by connectionId
+        self.out_channel_map.remove(connection_id);
+        self.relayed_map.remove(connection_id);
+        self.route_map
+            .retain(|_, route_id| route_id != connection_id);
+        self.out_transfer_map
+            .retain(|_, transfer| transfer.remote_id != connection_id);
+        if self.is_pending_relay(connection_id) {
+            self.pending_relay_id = None;
+        }
+        if self.relay_id() == Some(connection_id) {
+            warn!("Lost our relay {}", connection_id);
+            self.relay_id = None;
+        }
+    }
+
+    /// Close every channel of `agent_id` in `space_address`, e.g. when it leaves that space.
+    /// Return the `MsgChannelClose` frames to send, with the remote id to send each to.
+    pub fn close_agent_channels(
+        &mut self,
+        space_address: &Address,
+        agent_id: &Address,
+    ) -> Vec<(ConnectionId, Vec<u8>)> {
+        let is_agent_channel = |key: &ChannelKey| {
+            key.space_address == *space_address
+                && (key.from_agent_id == *agent_id || key.to_agent_id == *agent_id)
+        };
+        let mut frame_list = Vec::new();
+        for (remote_id, channel_map) in self.out_channel_map.iter_mut() {
+            channel_map.retain(|key, channel_id| {
+                if !is_agent_channel(key) {
+                    return true;
+                }
+                let frame = MultiplexMessage::MsgChannelClose(*channel_id).to_bytes();
+                frame_list.push((remote_id.clone(), frame));
+                false
+            });
+        }
+        for channel_map in self.in_channel_map.values_mut() {
+            channel_map.retain(|_, key| !is_agent_channel(key));
+        }
+        self.out_transfer_map
+            .retain(|_, transfer| !is_agent_channel(&transfer.key));
+        self.in_transfer_map
+            .retain(|(_, key, _), _| !is_agent_channel(key));
+        frame_list
+    }
+
+    /// Forget the channels we opened to a remote, e.g. when reconnecting to it on `connection_id`.
+    /// Return the frames resuming our unfinished transfers to it on new channels.
+    pub fn reset_remote(
+        &mut self,
+        remote_id: &ConnectionIdRef,
+        connection_id: &ConnectionIdRef,
+    ) -> Vec<Vec<u8>> {
+        self.set_route(remote_id, connection_id);
+        self.out_channel_map.remove(remote_id);
+        let transfer_key_list: Vec<(u32, Vec<u8>)> = self
+            .out_transfer_map
+            .iter()
+            .filter(|(_, transfer)| transfer.remote_id == remote_id)
+            .map(|(transfer_key, _)| transfer_key.clone())
+            .collect();
+        let mut frame_list = Vec::new();
+        for transfer_key in transfer_key_list {
+            let mut transfer = self
+                .out_transfer_map
+                .remove(&transfer_key)
+                .expect("transfer should be stored");
+            transfer.last_sent_ms = crate::time::since_epoch_ms();
+            let channel_id = self.out_channel_id(remote_id, &transfer.key, &mut frame_list);
+            debug!(
+                "Resuming transfer to {} at fragment {} on channel {}",
+                remote_id, transfer.acked_index, channel_id
+            );
+            frame_list.append(&mut transfer.window(channel_id));
+            self.out_transfer_map
+                .insert((channel_id, transfer_key.1), transfer);
+        }
+        frame_list
+    }
+
+    /// Windows of our transfers the receiver did not acknowledge in time,
+    /// with the connection to send each again on.
+    /// Drop the transfers that stopped progressing.
+    pub fn retransmit(&mut self) -> Vec<(ConnectionId, Vec<Vec<u8>>)> {
+        let now = crate::time::since_epoch_ms();
+        self.out_transfer_map.retain(|_, transfer| {
+            now.saturating_sub(transfer.last_active_ms) < TRANSFER_TIMEOUT_MS
+        });
+        let mut resend_list = Vec::new();
+        for ((channel_id, _), transfer) in self.out_transfer_map.iter_mut() {
+            if now.saturating_sub(transfer.last_sent_ms) < FRAGMENT_RETRANSMIT_MS {
+                continue;
+            }
+            // Relayed transfers are sent to a connection
+            let connection_id = self
+                .route_map
+                .get(&transfer.remote_id)
+                .unwrap_or(&transfer.remote_id)
+                .clone();
+            debug!(
+                "Sending again fragment {} to {} on channel {}",
+                transfer.acked_index, transfer.remote_id, channel_id
+            );
+            transfer.last_sent_ms = now;
+            resend_list.push((connection_id, transfer.window(*channel_id)));
+        }
+        resend_list
+    }
+
+    /// Channel id of channel `key` to `remote_id`.
+    /// Push a MsgChannelCreate to `frame_list` if that channel is not open yet.
+    fn out_channel_id(
+        &mut self,
+        remote_id: &ConnectionIdRef,
+        key: &ChannelKey,
+        frame_list: &mut Vec<Vec<u8>>,
+    ) -> u32 {
+        let maybe_channel_id = self
+            .out_channel_map
+            .get(remote_id)
+            .and_then(|channel_map| channel_map.get(key))
+            .cloned();
+        if let Some(channel_id) = maybe_channel_id {
+            return channel_id;
+        }
+        let channel_id = self.new_channel_id();
+        trace!(
+            "Creating channel {} to {}: {:?}",
+            channel_id,
+            remote_id,
+            key
+        );
+        frame_list.push(
+            MultiplexMessage::MsgChannelCreate(MsgChannelCreate {
+                channel_id,
+                space_hash: address_to_wire(&key.space_address),
+                to_id: address_to_wire(&key.to_agent_id),
+                from_id: address_to_wire(&key.from_agent_id),
+            })
+            .to_bytes(),
+        );
+        self.out_channel_map
+            .entry(remote_id.to_string())
+            .or_insert_with(HashMap::new)
+            .insert(key.clone(), channel_id);
+        channel_id
+    }
+
+    /// Key of a channel opened by the remote of `connection_id`
+    fn in_channel_key(
+        &self,
+        connection_id: &ConnectionIdRef,
+        channel_id: u32,
+    ) -> Option<ChannelKey> {
+        self.in_channel_map
+            .get(connection_id)
+            .and_then(|channel_map| channel_map.get(&channel_id))
+            .cloned()
+    }
+
+    /// Sha256 checksum of a fragmented payload
+    fn hash(&self, payload: &[u8]) -> CryptoResult<Vec<u8>> {
+        let mut hash: Box<dyn Buffer> = Box::new(vec![0; self.crypto.hash_sha256_bytes()]);
+        let data: Box<dyn Buffer> = Box::new(payload.to_vec());
+        self.crypto.hash_sha256(&mut hash, &data)?;
+        let hash = hash.read_lock().to_vec();
+        Ok(hash)
+    }
+
+    fn is_pending_relay(&self, connection_id: &ConnectionIdRef) -> bool {
+        self.pending_relay_id.as_ref().map(|id| id.as_str()) == Some(connection_id)
+    }
+
+    fn new_channel_id(&mut self) -> u32 {
+        let channel_id = self.next_channel_id;
+        self.next_channel_id = if channel_id >= NO_CHANNEL_ID - 1 {
+            FIRST_CHANNEL_ID
+        } else {
+            channel_id + 1
+        };
+        channel_id
+    }
+}
+
+/// Do we send to `remote_id` on `connection_id`: a remote id of a relayed transfer is
+/// the connection itself.
+fn is_routed_on(
+    route_map: &HashMap<String, ConnectionId>,
+    remote_id: &str,
+    connection_id: &ConnectionIdRef,
+) -> bool {
+    remote_id == connection_id
+        || route_map.get(remote_id).map(|id| id.as_str()) == Some(connection_id)
+}
+
+/// Frame asking the sender of a fragmented message to send the fragments following `next_index`
+fn resume_frame(channel_id: u32, message_hash: &[u8], next_index: u32) -> Vec<u8> {
+    MultiplexMessage::MsgFragmentResume(MsgFragmentResume {
+        channel_id,
+        message_hash: message_hash.to_vec(),
+        next_index,
+    })
+    .to_bytes()
+}
+
+fn error_reply(channel_id: u32, error_code: MultiplexErrorCode, error_text: &str) -> Demultiplexed {
+    let reply = MultiplexMessage::MsgError(MsgMultiplexError {
+        channel_id,
+        error_code,
+        error_text: error_text.to_string(),
+    });
+    Demultiplexed::Handled(Some(reply.to_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lib3h_sodium::SodiumCryptoSystem;
+    use std::collections::VecDeque;
+
+    fn new_multiplexer() -> Multiplexer {
+        Multiplexer::new(
+            Box::new(SodiumCryptoSystem::new()),
+            DEFAULT_MAX_MESSAGE_SIZE,
+        )
+    }
+
+    fn test_key() -> ChannelKey {
+        ChannelKey {
+            space_address: "space".to_string().into(),
+            from_agent_id: "alex".to_string().into(),
+            to_agent_id: "billy".to_string().into(),
+        }
+    }
+
+    fn accept_all(_key: &ChannelKey) -> Result<(), MultiplexErrorCode> {
+        Ok(())
+    }
+
+    fn reply_error_code(demultiplexed: Demultiplexed) -> MultiplexErrorCode {
+        match demultiplexed {
+            Demultiplexed::Handled(Some(reply)) => match MultiplexMessage::from_bytes(&reply) {
+                Ok(MultiplexMessage::MsgError(msg)) => msg.error_code,
+                other => panic!("Expected MsgError, got {:?}", other),
+            },
+            other => panic!("Expected a reply, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_should_open_a_channel_and_reuse_it() {
+        let mut alex = new_multiplexer();
+        let mut billy = new_multiplexer();
+        let key = test_key();
+        // First message creates the channel
+        let frame_list = alex.multiplex("billy_tId", &key, b"hello").unwrap();
+        assert_eq!(frame_list.len(), 2);
+        let res = billy
+            .demultiplex("alex_cId", &frame_list[0], accept_all)
+            .unwrap();
+        assert_eq!(res, Demultiplexed::Handled(None));
+        let res = billy
+            .demultiplex("alex_cId", &frame_list[1], accept_all)
+            .unwrap();
+        assert_eq!(res, Demultiplexed::Channel(key.clone(), b"hello".to_vec()));
+        // Next message reuses it
+        let frame_list = alex.multiplex("billy_tId", &key, b"again").unwrap();
+        assert_eq!(frame_list.len(), 1);
+        let res = billy
+            .demultiplex("alex_cId", &frame_list[0], accept_all)
+            .unwrap();
+        assert_eq!(res, Demultiplexed::Channel(key, b"again".to_vec()));
+        // Network channel is always open
+        let res = billy
+            .demultiplex("alex_cId", &Multiplexer::network_frame(b"net"), accept_all)
+            .unwrap();
+        assert_eq!(res, Demultiplexed::Network(b"net".to_vec()));
+    }
+
+    #[test]
+    fn it_should_reject_unknown_channels() {
+        let mut alex = new_multiplexer();
+        let mut billy = new_multiplexer();
+        let key = test_key();
+        let frame_list = alex.multiplex("billy_tId", &key, b"hello").unwrap();
+        alex.set_route("billy_tId", "billy_cId");
+        // Billy did not join that space
+        let res = billy
+            .demultiplex("alex_cId", &frame_list[0], |_| {
+                Err(MultiplexErrorCode::BadSpaceHash)
+            })
+            .unwrap();
+        assert_eq!(
+            reply_error_code(res.clone()),
+            MultiplexErrorCode::BadSpaceHash
+        );
+        // So the message is on an unknown channel
+        let res_msg = billy
+            .demultiplex("alex_cId", &frame_list[1], accept_all)
+            .unwrap();
+        assert_eq!(reply_error_code(res_msg), MultiplexErrorCode::BadChannelId);
+        // Alex forgets the rejected channel and creates it again on next message
+        if let Demultiplexed::Handled(Some(reply)) = res {
+            alex.demultiplex("billy_cId", &reply, accept_all).unwrap();
+        }
+        assert_eq!(
+            alex.multiplex("billy_tId", &key, b"retry").unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn it_should_close_the_channels_of_a_leaving_agent() {
+        let mut alex = new_multiplexer();
+        let mut billy = new_multiplexer();
+        let key = test_key();
+        let frame_list = alex.multiplex("billy_tId", &key, b"hello").unwrap();
+        for frame in frame_list.iter() {
+            billy.demultiplex("alex_cId", frame, accept_all).unwrap();
+        }
+        let close_list = alex.close_agent_channels(&key.space_address, &key.from_agent_id);
+        assert_eq!(close_list.len(), 1);
+        assert_eq!(close_list[0].0, "billy_tId");
+        billy
+            .demultiplex("alex_cId", &close_list[0].1, accept_all)
+            .unwrap();
+        // Billy does not know that channel anymore
+        let res = billy
+            .demultiplex("alex_cId", &frame_list[1], accept_all)
+            .unwrap();
+        assert_eq!(reply_error_code(res), MultiplexErrorCode::BadChannelId);
+        // Alex creates it again on next message
+        assert!(alex
+            .close_agent_channels(&key.space_address, &key.from_agent_id)
+            .is_empty());
+        assert_eq!(
+            alex.multiplex("billy_tId", &key, b"again").unwrap().len(),
+            2
+        );
+    }
+
+    fn big_payload() -> Vec<u8> {
+        (0..40 * FRAGMENT_SIZE + 123)
+            .map(|i| (i % 251) as u8)
+            .collect()
+    }
+
+    /// Deliver frames from alex to billy, and the replies of each to the other,
+    /// until billy reassembles a payload
+    fn transfer(
+        alex: &mut Multiplexer,
+        billy: &mut Multiplexer,
+        frame_list: Vec<Vec<u8>>,
+    ) -> Option<(ChannelKey, Vec<u8>)> {
+        alex.set_route("billy_tId", "billy_cId");
+        let mut to_billy: VecDeque<Vec<u8>> = frame_list.into_iter().collect();
+        while let Some(frame) = to_billy.pop_front() {
+            let reply = match billy.demultiplex("alex_cId", &frame, accept_all).unwrap() {
+                Demultiplexed::Handled(None) => continue,
+                Demultiplexed::Handled(Some(reply)) => reply,
+                Demultiplexed::Reassembled(key, payload, ack) => {
+                    let res = alex.demultiplex("billy_cId", &ack, accept_all).unwrap();
+                    assert_eq!(res, Demultiplexed::Handled(None));
+                    return Some((key, payload));
+                }
+                _ => panic!("Unexpected demultiplexed frame"),
+            };
+            match alex.demultiplex("billy_cId", &reply, accept_all).unwrap() {
+                Demultiplexed::Resend(frame_list) => to_billy.extend(frame_list),
+                _ => panic!("Expected fragments to resend"),
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn it_should_fragment_big_payloads() {
+        let mut alex = new_multiplexer();
+        let mut billy = new_multiplexer();
+        let key = test_key();
+        let payload = big_payload();
+        let frame_list = alex.multiplex("billy_tId", &key, &payload).unwrap();
+        // MsgChannelCreate and the first window of fragments
+        assert_eq!(frame_list.len(), 1 + FRAGMENT_WINDOW as usize);
+        let res = transfer(&mut alex, &mut billy, frame_list);
+        assert_eq!(res, Some((key, payload)));
+        // Both forget the finished transfer
+        assert!(alex.out_transfer_map.is_empty());
+        assert!(billy.in_transfer_map.is_empty());
+    }
+
+    #[test]
+    fn it_should_resume_a_transfer_after_a_reconnection() {
+        let mut alex = new_multiplexer();
+        let mut billy = new_multiplexer();
+        let key = test_key();
+        let payload = big_payload();
+        let frame_list = alex.multiplex("billy_tId", &key, &payload).unwrap();
+        // Connection lost after a few fragments
+        for frame in frame_list.iter().take(5) {
+            let res = billy.demultiplex("alex_cId", frame, accept_all).unwrap();
+            assert_eq!(res, Demultiplexed::Handled(None));
+        }
+        billy.close_connection("alex_cId");
+        // On reconnection, alex resends the fragments billy did not acknowledge on a new channel,
+        // and billy, which dropped the interrupted transfer, receives them all again
+        let frame_list = alex.reset_remote("billy_tId", "billy_cId");
+        assert_eq!(frame_list.len(), 1 + FRAGMENT_WINDOW as usize);
+        let res = transfer(&mut alex, &mut billy, frame_list);
+        assert_eq!(res, Some((key, payload)));
+        assert!(alex.out_transfer_map.is_empty());
+    }
+
+    #[test]
+    fn it_should_not_mix_the_fragments_of_two_connections() {
+        let mut alex = new_multiplexer();
+        let mut billy = new_multiplexer();
+        let key = test_key();
+        let frame_list = alex.multiplex("billy_tId", &key, &big_payload()).unwrap();
+        for frame in frame_list.iter().take(3) {
+            billy.demultiplex("alex_cId", frame, accept_all).unwrap();
+        }
+        // The same fragments replayed on another connection start another transfer
+        for frame in frame_list.iter().take(2) {
+            billy.demultiplex("mallory_cId", frame, accept_all).unwrap();
+        }
+        assert_eq!(billy.in_transfer_map.len(), 2);
+        billy.close_connection("mallory_cId");
+        assert_eq!(billy.in_transfer_map.len(), 1);
+    }
+
+    #[test]
+    fn it_should_only_take_answers_to_a_transfer_from_its_route() {
+        let mut alex = new_multiplexer();
+        let key = test_key();
+        alex.multiplex("billy_tId", &key, &big_payload()).unwrap();
+        alex.set_route("billy_tId", "billy_cId");
+        let (channel_id, message_hash) = alex.out_transfer_map.keys().next().unwrap().clone();
+        // Mallory can neither ask for the fragments nor cancel the transfer
+        let resume = resume_frame(channel_id, &message_hash, 0);
+        let res = alex
+            .demultiplex("mallory_cId", &resume, accept_all)
+            .unwrap();
+        assert_eq!(res, Demultiplexed::Handled(None));
+        if let Demultiplexed::Handled(Some(error)) =
+            error_reply(channel_id, MultiplexErrorCode::Unknown, "cancel")
+        {
+            alex.demultiplex("mallory_cId", &error, accept_all).unwrap();
+        }
+        assert_eq!(alex.out_transfer_map.len(), 1);
+        // Billy can
+        match alex.demultiplex("billy_cId", &resume, accept_all).unwrap() {
+            Demultiplexed::Resend(frame_list) => {
+                assert_eq!(frame_list.len(), FRAGMENT_WINDOW as usize)
+            }
+            other => panic!("Expected fragments to resend, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_should_send_again_unacknowledged_windows() {
+        let mut alex = new_multiplexer();
+        let key = test_key();
+        alex.multiplex("billy_tId", &key, &big_payload()).unwrap();
+        alex.set_route("billy_tId", "billy_cId");
+        assert!(alex.retransmit().is_empty());
+        // Billy did not acknowledge the first window in time
+        for transfer in alex.out_transfer_map.values_mut() {
+            transfer.last_sent_ms -= FRAGMENT_RETRANSMIT_MS;
+        }
+        let resend_list = alex.retransmit();
+        assert_eq!(resend_list.len(), 1);
+        assert_eq!(resend_list[0].0, "billy_cId");
+        assert_eq!(resend_list[0].1.len(), FRAGMENT_WINDOW as usize);
+        assert!(alex.retransmit().is_empty());
+        // Until the transfer times out
+        for transfer in alex.out_transfer_map.values_mut() {
+            transfer.last_active_ms -= TRANSFER_TIMEOUT_MS;
+        }
+        assert!(alex.retransmit().is_empty());
+        assert!(alex.out_transfer_map.is_empty());
+    }
+
+    #[test]
+    fn it_should_refuse_messages_over_the_size_limit() {
+        let mut alex = new_multiplexer();
+        let mut billy = Multiplexer::new(Box::new(SodiumCryptoSystem::new()), 2 * FRAGMENT_SIZE);
+        let key = test_key();
+        // The first fragment announces the size of the whole message
+        let frame_list = alex.multiplex("billy_tId", &key, &big_payload()).unwrap();
+        alex.set_route("billy_tId", "billy_cId");
+        billy
+            .demultiplex("alex_cId", &frame_list[0], accept_all)
+            .unwrap();
+        let res = billy
+            .demultiplex("alex_cId", &frame_list[1], accept_all)
+            .unwrap();
+        assert_eq!(
+            reply_error_code(res.clone()),
+            MultiplexErrorCode::MessageTooBig
+        );
+        assert!(billy.in_transfer_map.is_empty());
+        // Alex drops the refused transfer
+        if let Demultiplexed::Handled(Some(reply)) = res {
+            alex.demultiplex("billy_cId", &reply, accept_all).unwrap();
+        }
+        assert!(alex.out_transfer_map.is_empty());
+        // Oversized frames are refused before being decoded
+        let frame = Multiplexer::network_frame(&vec![0xff; 3 * FRAGMENT_SIZE]);
+        let res = billy.demultiplex("alex_cId", &frame, accept_all).unwrap();
+        assert_eq!(reply_error_code(res), MultiplexErrorCode::MessageTooBig);
+        // And billy does not send what it would refuse
+        assert!(billy.multiplex("alex_tId", &key, &big_payload()).is_err());
+        assert!(billy.out_transfer_map.is_empty());
+        assert!(billy.out_channel_map.is_empty());
+    }
+
+    /// Deliver the frames of a big payload from alex to billy up to its first fragment.
+    /// Return billy's answer to that fragment.
+    fn start_transfer(
+        alex: &mut Multiplexer,
+        billy: &mut Multiplexer,
+        connection_id: &str,
+        key: &ChannelKey,
+        seed: u8,
+    ) -> Demultiplexed {
+        let mut payload = big_payload();
+        payload[0] = seed;
+        let frame_list = alex.multiplex(connection_id, key, &payload).unwrap();
+        let first_fragment = frame_list.len() - FRAGMENT_WINDOW as usize;
+        let mut res = Demultiplexed::Handled(None);
+        for frame in frame_list.iter().take(first_fragment + 1) {
+            res = billy.demultiplex(connection_id, frame, accept_all).unwrap();
+        }
+        res
+    }
+
+    #[test]
+    fn it_should_cap_the_messages_in_transfer() {
+        let mut alex = new_multiplexer();
+        let mut billy = Multiplexer::new(Box::new(SodiumCryptoSystem::new()), big_payload().len());
+        let key = test_key();
+        let other_key = |to_agent_id: &str| ChannelKey {
+            to_agent_id: to_agent_id.to_string().into(),
+            ..test_key()
+        };
+        // Per channel
+        for seed in 0..MAX_IN_TRANSFER_PER_CHANNEL {
+            let res = start_transfer(&mut alex, &mut billy, "alex_cId", &key, seed as u8);
+            assert_eq!(res, Demultiplexed::Handled(None));
+        }
+        let res = start_transfer(&mut alex, &mut billy, "alex_cId", &key, 0xff);
+        assert_eq!(reply_error_code(res), MultiplexErrorCode::MessageTooBig);
+        // Per connection
+        let camille_key = other_key("camille");
+        for seed in MAX_IN_TRANSFER_PER_CHANNEL..MAX_IN_TRANSFER_PER_CONNECTION {
+            let res = start_transfer(&mut alex, &mut billy, "alex_cId", &camille_key, seed as u8);
+            assert_eq!(res, Demultiplexed::Handled(None));
+        }
+        let res = start_transfer(&mut alex, &mut billy, "alex_cId", &other_key("david"), 0xff);
+        assert_eq!(reply_error_code(res), MultiplexErrorCode::MessageTooBig);
+        assert_eq!(billy.in_transfer_map.len(), MAX_IN_TRANSFER_PER_CONNECTION);
+        // Other connections are not affected
+        let res = start_transfer(
+            &mut alex,
+            &mut billy,
+            "alex_cId2",
+            &other_key("david"),
+            0xff,
+        );
+        assert_eq!(res, Demultiplexed::Handled(None));
+    }
+
+    #[test]
+    fn it_should_negotiate_a_relay() {
+        let mut alex = new_multiplexer();
+        let mut billy = new_multiplexer();
+        let request = alex.request_relay("billy_cId").unwrap();
+        // Only one relay request at a time
+        assert_eq!(alex.request_relay("camille_cId"), None);
+        let res = billy.demultiplex("alex_cId", &request, accept_all).unwrap();
+        assert_eq!(res, Demultiplexed::RelayRequest);
+        // Refused: alex can ask another peer
+        alex.demultiplex("billy_cId", &Multiplexer::refuse_relay(), accept_all)
+            .unwrap();
+        assert_eq!(alex.relay_id(), None);
+        let request = alex.request_relay("camille_cId").unwrap();
+        // Accepted
+        let mut camille = new_multiplexer();
+        let res = camille
+            .demultiplex("alex_cId", &request, accept_all)
+            .unwrap();
+        assert_eq!(res, Demultiplexed::RelayRequest);
+        let reply = camille.accept_relay("alex_cId", "alex_tId");
+        assert_eq!(camille.relayed_connection_id("alex_tId"), Some("alex_cId"));
+        alex.demultiplex("camille_cId", &reply, accept_all).unwrap();
+        assert_eq!(alex.relay_id(), Some("camille_cId"));
+        // Contract ends with the connection
+        alex.close_connection("camille_cId");
+        assert_eq!(alex.relay_id(), None);
+        camille.close_connection("alex_cId");
+        assert!(camille.relayed_id_list().is_empty());
+    }
+
+    #[test]
+    fn it_should_build_relay_uris() {
+        let uri = relay_uri("hcrelay", "HcMalex");
+        assert_eq!(uri.scheme(), RELAY_SCHEME);
+        assert_eq!(parse_relay_uri(&uri), Some(("hcrelay", "HcMalex")));
+        let uri = Url::parse("wss://127.0.0.1:64519/").unwrap();
+        assert_eq!(parse_relay_uri(&uri), None);
+    }
+}
@@ -2,11 +2,21 @@
 
 use crate::{
     dht::dht_trait::{Dht, DhtConfig, DhtFactory},
-    gateway::{Gateway, P2pGateway},
-    transport::{protocol::*, TransportWrapper},
+    engine::p2p_protocol::{MsgDhtGossip, P2pMessage},
+    engine::NETWORK_GATEWAY_ID,
+    gateway::{
+        gossip_delivery::GossipDelivery,
+        multiplex::{parse_relay_uri, Multiplexer},
+        Gateway, P2pGateway,
+    },
+    transport::{protocol::*, ConnectionId, TransportWrapper},
 };
 use lib3h_protocol::Address;
-use std::collections::{HashMap, VecDeque};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, RwLock},
+};
+use url::Url;
 
 //--------------------------------------------------------------------------------------------------
 // Constructors
@@ -19,6 +29,7 @@ impl<'gateway, D: Dht> P2pGateway<'gateway, D> {
     pub fn new(
         identifier: &str,
         inner_transport: TransportWrapper<'gateway>,
+        multiplexer: Arc<RwLock<Multiplexer>>,
         dht_factory: DhtFactory<D>,
         dht_config: &DhtConfig,
     ) -> Self {
@@ -29,6 +40,8 @@ impl<'gateway, D: Dht> P2pGateway<'gateway, D> {
             connection_map: HashMap::new(),
             transport_inbox: VecDeque::new(),
             transport_inject_events: Vec::new(),
+            multiplexer,
+            gossip_delivery: GossipDelivery::new(),
         }
     }
 }
@@ -58,11 +71,11 @@ impl<'gateway, D: Dht> Gateway for P2pGateway<'gateway, D> {
             peer_uri,
         );
         // get connection_id
-        let maybe_connection_id = self.connection_map.get(&peer_uri);
+        let maybe_connection_id = self.resolve_connection_id(&peer_uri);
         if maybe_connection_id.is_none() {
             return None;
         }
-        let conn_id = maybe_connection_id.unwrap().clone();
+        let conn_id = maybe_connection_id.unwrap();
         trace!(
             "({}) get_connection_id: {} -> {} -> {}",
             self.identifier,
@@ -72,6 +85,87 @@ impl<'gateway, D: Dht> Gateway for P2pGateway<'gateway, D> {
         );
         Some(conn_id)
     }
+
+    /// Acknowledge a gossip expecting it from a peer.
+    /// Return false if it was already received and must not be handled again.
+    fn receive_reliable_gossip(&mut self, from_peer_address: &str, gossip_id: u64) -> bool {
+        // Always ack: our previous ack may have been lost
+        let ack = P2pMessage::MsgDhtGossipAck(MsgDhtGossip {
+            space_address: self.identifier.as_bytes().to_vec(),
+            to_peer_address: from_peer_address.as_bytes().to_vec(),
+            from_peer_address: self.inner_dht.this_peer().peer_address.as_bytes().to_vec(),
+            bundle: Vec::new(),
+            gossip_id,
+        });
+        match self.get_connection_id(from_peer_address) {
+            Some(to_conn_id) => {
+                if let Err(e) =
+                    self.send_on_channel(from_peer_address, &to_conn_id, &ack.to_bytes())
+                {
+                    warn!(
+                        "({}) failed to ack gossip to {}: {:?}",
+                        self.identifier, from_peer_address, e
+                    );
+                }
+            }
+            None => debug!(
+                "({}) cannot ack gossip to unknown peer {}",
+                self.identifier, from_peer_address
+            ),
+        }
+        self.gossip_delivery.receive(from_peer_address, gossip_id)
+    }
+
+    /// A peer acknowledged one of our gossips
+    fn handle_gossip_ack(&mut self, from_peer_address: &str, gossip_id: u64) {
+        if !self
+            .gossip_delivery
+            .acknowledge(from_peer_address, gossip_id)
+        {
+            trace!(
+                "({}) unexpected gossip ack from {}: {}",
+                self.identifier,
+                from_peer_address,
+                gossip_id
+            );
+        }
+    }
+}
+
+/// Private internals
+impl<'gateway, D: Dht> P2pGateway<'gateway, D> {
+    /// Connection to send to for reaching a peer_uri:
+    /// our connection to it, or else the connection to its relay or to our own relay
+    pub(crate) fn resolve_connection_id(&self, peer_uri: &Url) -> Option<ConnectionId> {
+        if let Some(connection_id) = self.connection_map.get(peer_uri) {
+            return Some(connection_id.clone());
+        }
+        // Space gateways know all their peers' transportIds
+        if self.identifier != NETWORK_GATEWAY_ID {
+            return None;
+        }
+        let multiplexer = self.multiplexer.read().expect("failed to obtain read lock");
+        if let Some((relay_address, transport_id)) = parse_relay_uri(peer_uri) {
+            // A peer we relay for is reached on its own connection
+            if relay_address.eq_ignore_ascii_case(&self.inner_dht.this_peer().peer_address) {
+                return multiplexer
+                    .relayed_connection_id(transport_id)
+                    .map(|connection_id| connection_id.to_string());
+            }
+            let maybe_relay_uri = self
+                .inner_dht
+                .get_peer_list()
+                .into_iter()
+                .find(|peer| peer.peer_address.eq_ignore_ascii_case(relay_address))
+                .map(|peer| peer.peer_uri);
+            if let Some(relay_connection_id) =
+                maybe_relay_uri.and_then(|relay_uri| self.connection_map.get(&relay_uri))
+            {
+                return Some(relay_connection_id.clone());
+            }
+        }
+        multiplexer.relay_id().map(|relay_id| relay_id.to_string())
+    }
 }
 
 /// P2pGateway Constructor
@@ -79,6 +173,7 @@ impl<'gateway, D: Dht> P2pGateway<'gateway, D> {
     /// Constructors
     pub fn new_with_space(
         network_gateway: TransportWrapper<'gateway>,
+        multiplexer: Arc<RwLock<Multiplexer>>,
         space_address: &Address,
         dht_factory: DhtFactory<D>,
         dht_config: &DhtConfig,
@@ -91,6 +186,8 @@ impl<'gateway, D: Dht> P2pGateway<'gateway, D> {
             connection_map: HashMap::new(),
             transport_inbox: VecDeque::new(),
             transport_inject_events: Vec::new(),
+            multiplexer,
+            gossip_delivery: GossipDelivery::new(),
         }
     }
 }
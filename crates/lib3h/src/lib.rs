@@ -1,9 +1,12 @@
 extern crate backtrace;
 extern crate hcid;
 extern crate lib3h_crypto_api;
+extern crate lib3h_mdns;
+extern crate lib3h_p2p_protocol;
 extern crate lib3h_protocol;
 extern crate nanoid;
 extern crate native_tls;
+extern crate openssl;
 extern crate tungstenite;
 extern crate url_serde;
 #[macro_use]
@@ -27,6 +30,7 @@ pub mod gateway;
 pub mod time;
 pub mod track;
 pub mod transport;
+pub mod transport_framed;
 pub mod transport_wss;
 
 #[cfg(test)]
@@ -1,5 +1,6 @@
 //! Connection Error struct and TransportResult type
 
+use crate::transport::ConnectionId;
 use tungstenite::handshake::{
     server::{NoCallback, ServerHandshake},
     HandshakeError,
@@ -10,11 +11,17 @@ pub type TransportResult<T> = Result<T, TransportError>;
 
 /// represents an error generated by a connection instance
 #[derive(Debug, PartialEq, Clone)]
-pub struct TransportError(pub String);
+pub enum TransportError {
+    /// The send queues of these connections are full, nothing was queued for them.
+    /// Retry once a ConnectionDrained event was received.
+    WouldBlock(Vec<ConnectionId>),
+    /// Any other error
+    Other(String),
+}
 
 impl TransportError {
     pub fn new(msg: String) -> Self {
-        TransportError(msg)
+        TransportError::Other(msg)
     }
 }
 
@@ -26,7 +33,10 @@ impl std::fmt::Display for TransportError {
 
 impl std::error::Error for TransportError {
     fn description(&self) -> &str {
-        &self.0
+        match self {
+            TransportError::WouldBlock(_) => "send queue full",
+            TransportError::Other(msg) => msg,
+        }
     }
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         None
@@ -35,37 +45,37 @@ impl std::error::Error for TransportError {
 /*
 impl From<std::io::Error> for TransportError {
     fn from(error: std::io::Error) -> Self {
-        Self(format!("{:?}", error))
+        Self::new(format!("{:?}", error))
     }
 }
 */
 impl From<Vec<TransportError>> for TransportError {
     fn from(errors: Vec<TransportError>) -> Self {
-        Self(format!("{:?}", errors))
+        Self::new(format!("{:?}", errors))
     }
 }
 
 impl From<url::ParseError> for TransportError {
     fn from(error: url::ParseError) -> Self {
-        Self(format!("{:?}", error))
+        Self::new(format!("{:?}", error))
     }
 }
 
 impl From<std::io::Error> for TransportError {
     fn from(error: std::io::Error) -> Self {
-        Self(format!("{:?}", error))
+        Self::new(format!("{:?}", error))
     }
 }
 
 impl From<tungstenite::Error> for TransportError {
     fn from(error: tungstenite::Error) -> Self {
-        Self(format!("{:?}", error))
+        Self::new(format!("{:?}", error))
     }
 }
 
 impl From<native_tls::Error> for TransportError {
     fn from(error: native_tls::Error) -> Self {
-        Self(format!("{:?}", error))
+        Self::new(format!("{:?}", error))
     }
 }
 
@@ -73,7 +83,7 @@ impl<S: std::fmt::Debug + std::io::Read + std::io::Write>
     From<HandshakeError<ServerHandshake<S, NoCallback>>> for TransportError
 {
     fn from(error: HandshakeError<ServerHandshake<S, NoCallback>>) -> Self {
-        Self(format!("{:?}", error))
+        Self::new(format!("{:?}", error))
     }
 }
 
@@ -81,7 +91,7 @@ impl<T: std::io::Read + std::io::Write + std::fmt::Debug> From<native_tls::Hands
     for TransportError
 {
     fn from(error: native_tls::HandshakeError<T>) -> Self {
-        Self(format!("{:?}", error))
+        Self::new(format!("{:?}", error))
     }
 }
 
@@ -89,7 +99,31 @@ impl<T: std::io::Read + std::io::Write + std::fmt::Debug>
     From<tungstenite::HandshakeError<tungstenite::ClientHandshake<T>>> for TransportError
 {
     fn from(error: tungstenite::HandshakeError<tungstenite::ClientHandshake<T>>) -> Self {
-        Self(format!("{:?}", error))
+        Self::new(format!("{:?}", error))
+    }
+}
+
+impl From<openssl::error::ErrorStack> for TransportError {
+    fn from(error: openssl::error::ErrorStack) -> Self {
+        Self::new(format!("{:?}", error))
+    }
+}
+
+impl From<hcid::HcidError> for TransportError {
+    fn from(error: hcid::HcidError) -> Self {
+        Self::new(format!("{:?}", error))
+    }
+}
+
+impl From<lib3h_crypto_api::CryptoError> for TransportError {
+    fn from(error: lib3h_crypto_api::CryptoError) -> Self {
+        Self::new(format!("{:?}", error))
+    }
+}
+
+impl From<lib3h_p2p_protocol::error::P2pProtocolError> for TransportError {
+    fn from(error: lib3h_p2p_protocol::error::P2pProtocolError) -> Self {
+        Self::new(format!("{:?}", error))
     }
 }
 
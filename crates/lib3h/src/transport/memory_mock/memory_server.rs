@@ -1,6 +1,6 @@
 use crate::transport::{
     error::{TransportError, TransportResult},
-    protocol::TransportEvent,
+    protocol::{CloseReason, TransportEvent},
 };
 use lib3h_protocol::DidWork;
 use std::{
@@ -160,7 +160,7 @@ impl MemoryServer {
             let event = if *is_new {
                 TransportEvent::IncomingConnectionEstablished(in_uri.to_string())
             } else {
-                TransportEvent::ConnectionClosed(in_uri.to_string())
+                TransportEvent::ConnectionClosed(in_uri.to_string(), CloseReason::Remote)
             };
             trace!("(MemoryServer {}). connection: {:?}", self.this_uri, event);
             outbox.push(event);
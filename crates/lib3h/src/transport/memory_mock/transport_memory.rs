@@ -1,7 +1,7 @@
 use crate::transport::{
     error::{TransportError, TransportResult},
     memory_mock::memory_server,
-    protocol::{TransportCommand, TransportEvent},
+    protocol::{CloseReason, TransportCommand, TransportEvent},
     transport_trait::Transport,
     ConnectionId, ConnectionIdRef,
 };
@@ -296,10 +296,10 @@ impl Transport for TransportMemory {
         // process other messages
         for event in output {
             match event {
-                TransportEvent::ConnectionClosed(in_cid) => {
+                TransportEvent::ConnectionClosed(in_cid, reason) => {
                     // close will fail as other side isn't there anymore
                     let _ = self.close(&in_cid);
-                    outbox.push(TransportEvent::ConnectionClosed(in_cid.to_string()));
+                    outbox.push(TransportEvent::ConnectionClosed(in_cid.to_string(), reason));
                 }
                 TransportEvent::ReceivedData(in_cid, data) => {
                     outbox.push(TransportEvent::ReceivedData(
@@ -346,14 +346,18 @@ impl TransportMemory {
             }
             TransportCommand::Close(uri_as_cid) => {
                 self.close(uri_as_cid)?;
-                let evt = TransportEvent::ConnectionClosed(uri_as_cid.to_string());
+                let evt =
+                    TransportEvent::ConnectionClosed(uri_as_cid.to_string(), CloseReason::Local);
                 Ok(vec![evt])
             }
             TransportCommand::CloseAll => {
                 self.close_all()?;
                 let mut outbox = Vec::new();
                 for remote_uri in &self.connections {
-                    let evt = TransportEvent::ConnectionClosed(remote_uri.to_string());
+                    let evt = TransportEvent::ConnectionClosed(
+                        remote_uri.to_string(),
+                        CloseReason::Local,
+                    );
                     outbox.push(evt);
                 }
                 Ok(outbox)
@@ -52,10 +52,17 @@ pub mod tests {
 
     use crate::{
         transport::{
-            memory_mock::transport_memory, protocol::TransportEvent, transport_trait::Transport,
+            error::TransportError,
+            memory_mock::transport_memory,
+            protocol::{CloseReason, TransportEvent},
+            transport_crypto::TransportCrypto,
+            transport_trait::Transport,
         },
+        transport_framed::TransportFramed,
         transport_wss::{TlsConfig, TransportWss},
     };
+    use lib3h_crypto_api::{Buffer, CryptoSystem};
+    use lib3h_sodium::SodiumCryptoSystem;
 
     use crate::tests::enable_logging_for_test;
     use url::Url;
@@ -76,6 +83,31 @@ pub mod tests {
         send_test(&mut node_A, &mut node_B, &uri_A, &uri_B);
     }
 
+    #[test]
+    fn crypto_memory_send_test() {
+        enable_logging_for_test(true);
+        let mut node_A = new_crypto_memory_transport(b"crypto_send_test");
+        let mut node_B = new_crypto_memory_transport(b"crypto_send_test");
+        let uri_A = Url::parse("mem://crypto_a").unwrap();
+        let uri_B = Url::parse("mem://crypto_b").unwrap();
+
+        send_test(&mut node_A, &mut node_B, &uri_A, &uri_B);
+    }
+
+    pub fn new_crypto_memory_transport(network_id: &[u8]) -> TransportCrypto {
+        let crypto = SodiumCryptoSystem::new();
+        let mut public_key: Box<dyn Buffer> = Box::new(vec![0; crypto.sign_public_key_bytes()]);
+        let mut secret_key = crypto.buf_new_secure(crypto.sign_secret_key_bytes());
+        crypto.sign_keypair(&mut public_key, &mut secret_key).unwrap();
+        TransportCrypto::new(
+            Box::new(transport_memory::TransportMemory::new()),
+            Box::new(crypto),
+            network_id,
+            public_key,
+            secret_key,
+        )
+    }
+
     #[test]
     fn wss_send_test() {
         enable_logging_for_test(true);
@@ -98,6 +130,316 @@ pub mod tests {
         send_test(&mut node_A, &mut node_B, &uri_A, &uri_B);
     }
 
+    fn new_transport_id_tls_transport() -> TransportWss<std::net::TcpStream> {
+        let crypto = SodiumCryptoSystem::new();
+        let mut public_key: Box<dyn Buffer> = Box::new(vec![0; crypto.sign_public_key_bytes()]);
+        let mut secret_key = crypto.buf_new_secure(crypto.sign_secret_key_bytes());
+        crypto.sign_keypair(&mut public_key, &mut secret_key).unwrap();
+        let transport_id = hcid::HcidEncoding::with_kind("hcm0")
+            .unwrap()
+            .encode(&public_key)
+            .unwrap();
+        let mut transport = TransportWss::with_std_tcp_stream(TlsConfig::TransportIdCertificate);
+        transport
+            .set_transport_keys(&crypto, &transport_id, &secret_key)
+            .unwrap();
+        transport
+    }
+
+    #[test]
+    fn wss_send_test_transport_id_tls() {
+        enable_logging_for_test(true);
+        let mut node_A = new_transport_id_tls_transport();
+        let mut node_B = new_transport_id_tls_transport();
+        let uri_A = Url::parse("wss://127.0.0.1:64533/TLS_A").unwrap();
+        let uri_B = Url::parse("wss://127.0.0.1:64534/TLS_B").unwrap();
+
+        send_test(&mut node_A, &mut node_B, &uri_A, &uri_B);
+    }
+
+    #[test]
+    fn wss_transport_id_tls_should_reject_other_transport_id() {
+        enable_logging_for_test(true);
+        let mut node_A = new_transport_id_tls_transport();
+        let mut node_B = new_transport_id_tls_transport();
+        let mut node_C = new_transport_id_tls_transport();
+        let uri_B = Url::parse("wss://127.0.0.1:64535/TLS_B").unwrap();
+        let uri_C = Url::parse("wss://127.0.0.1:64536/TLS_C").unwrap();
+        // Dial B, expecting the TransportId of C
+        let mut uri_C_at_B = node_B.bind(&uri_B).unwrap();
+        let bound_uri_c = node_C.bind(&uri_C).unwrap();
+        uri_C_at_B.set_query(bound_uri_c.query());
+        let id = node_A.connect(&uri_C_at_B).unwrap();
+
+        let mut event_list = Vec::new();
+        for _x in 0..NUM_PROCESS_LOOPS {
+            let (_did_work_A, mut event_list_A) = node_A.process().unwrap();
+            let (_did_work_B, _event_list_B) = node_B.process().unwrap();
+            event_list.append(&mut event_list_A);
+        }
+        assert!(event_list.iter().any(|event| match event {
+            TransportEvent::ErrorOccured(error_id, _) => *error_id == id,
+            _ => false,
+        }));
+        assert!(!event_list.iter().any(|event| match event {
+            TransportEvent::ConnectResult(_, _) => true,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn wss_should_close_silent_connection() {
+        enable_logging_for_test(true);
+        let mut node_A = TransportWss::with_std_tcp_stream(TlsConfig::Unencrypted);
+        let mut node_B = TransportWss::with_std_tcp_stream(TlsConfig::Unencrypted);
+        node_A.set_heartbeat(10, 50);
+        let uri_B = Url::parse("wss://127.0.0.1:64537/B").unwrap();
+        let bound_uri_b = node_B.bind(&uri_B).unwrap();
+        let idAB = node_A.connect(&bound_uri_b).unwrap();
+        for _x in 0..NUM_PROCESS_LOOPS {
+            node_A.process().unwrap();
+            node_B.process().unwrap();
+        }
+        // B stops answering our pings
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let (_did_work, event_list) = node_A.process().unwrap();
+        assert_eq!(
+            event_list,
+            vec![TransportEvent::ConnectionClosed(
+                idAB,
+                CloseReason::HeartbeatTimeout
+            )]
+        );
+    }
+
+    #[test]
+    fn wss_should_keep_pinged_connection_open() {
+        enable_logging_for_test(true);
+        let mut node_A = TransportWss::with_std_tcp_stream(TlsConfig::Unencrypted);
+        let mut node_B = TransportWss::with_std_tcp_stream(TlsConfig::Unencrypted);
+        node_A.set_heartbeat(10, 100);
+        node_B.set_heartbeat(10, 100);
+        let uri_B = Url::parse("wss://127.0.0.1:64538/B").unwrap();
+        let bound_uri_b = node_B.bind(&uri_B).unwrap();
+        let idAB = node_A.connect(&bound_uri_b).unwrap();
+        let mut event_list = Vec::new();
+        for _x in 0..60 {
+            event_list.append(&mut node_A.process().unwrap().1);
+            event_list.append(&mut node_B.process().unwrap().1);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert!(!event_list.iter().any(|event| match event {
+            TransportEvent::ConnectionClosed(_, _) => true,
+            _ => false,
+        }));
+
+        // B is told A closed the connection
+        node_A.close(&idAB).unwrap();
+        let mut event_list = Vec::new();
+        for _x in 0..NUM_PROCESS_LOOPS {
+            event_list.append(&mut node_B.process().unwrap().1);
+        }
+        assert!(event_list.iter().any(|event| match event {
+            TransportEvent::ConnectionClosed(_, reason) => *reason == CloseReason::Remote,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn wss_send_should_block_until_drained() {
+        enable_logging_for_test(true);
+        let mut node_A = TransportWss::with_std_tcp_stream(TlsConfig::Unencrypted);
+        let mut node_B = TransportWss::with_std_tcp_stream(TlsConfig::Unencrypted);
+        node_A.set_send_queue_limits(2, 1024);
+        let uri_B = Url::parse("wss://127.0.0.1:64539/B").unwrap();
+        let bound_uri_b = node_B.bind(&uri_B).unwrap();
+        let idAB = node_A.connect(&bound_uri_b).unwrap();
+        // Queued until the handshake is done
+        node_A.send(&[&idAB], &[1]).unwrap();
+        node_A.send(&[&idAB], &[2]).unwrap();
+        assert_eq!(
+            node_A.send(&[&idAB], &[3]),
+            Err(TransportError::WouldBlock(vec![idAB.clone()]))
+        );
+
+        let mut event_list_A = Vec::new();
+        let mut event_list_B = Vec::new();
+        for _x in 0..NUM_PROCESS_LOOPS {
+            event_list_A.append(&mut node_A.process().unwrap().1);
+            event_list_B.append(&mut node_B.process().unwrap().1);
+        }
+        assert!(event_list_A.contains(&TransportEvent::ConnectionDrained(idAB.clone())));
+        let payload_list: Vec<Vec<u8>> = event_list_B
+            .into_iter()
+            .filter_map(|event| match event {
+                TransportEvent::ReceivedData(_, payload) => Some(payload),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(payload_list, vec![vec![1], vec![2]]);
+        node_A.send(&[&idAB], &[3]).unwrap();
+    }
+
+    #[test]
+    fn crypto_send_should_block_until_handshake_done() {
+        enable_logging_for_test(true);
+        let mut node_A = new_crypto_memory_transport(b"crypto_queue_test");
+        let mut node_B = new_crypto_memory_transport(b"crypto_queue_test");
+        node_A.set_send_queue_limits(2, 1024);
+        let uri_B = Url::parse("mem://crypto_queue_b").unwrap();
+        let bound_uri_b = node_B.bind(&uri_B).unwrap();
+        let idAB = node_A.connect(&bound_uri_b).unwrap();
+        // Queued until the handshake is done
+        node_A.send(&[&idAB], &[1]).unwrap();
+        node_A.send(&[&idAB], &[2]).unwrap();
+        assert_eq!(
+            node_A.send(&[&idAB], &[3]),
+            Err(TransportError::WouldBlock(vec![idAB.clone()]))
+        );
+
+        let mut event_list_A = Vec::new();
+        let mut event_list_B = Vec::new();
+        for _x in 0..NUM_PROCESS_LOOPS {
+            event_list_A.append(&mut node_A.process().unwrap().1);
+            event_list_B.append(&mut node_B.process().unwrap().1);
+        }
+        assert!(event_list_A.contains(&TransportEvent::ConnectionDrained(idAB.clone())));
+        let payload_list: Vec<Vec<u8>> = event_list_B
+            .into_iter()
+            .filter_map(|event| match event {
+                TransportEvent::ReceivedData(_, payload) => Some(payload),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(payload_list, vec![vec![1], vec![2]]);
+        node_A.send(&[&idAB], &[3]).unwrap();
+    }
+
+    #[test]
+    fn tcp_send_test() {
+        enable_logging_for_test(true);
+        let mut node_A = TransportFramed::with_std_tcp_stream();
+        let mut node_B = TransportFramed::with_std_tcp_stream();
+        let uri_A = Url::parse("tcp://127.0.0.1:64540").unwrap();
+        let uri_B = Url::parse("tcp://127.0.0.1:64541").unwrap();
+
+        send_test(&mut node_A, &mut node_B, &uri_A, &uri_B);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_send_test() {
+        enable_logging_for_test(true);
+        let dir = std::env::temp_dir();
+        let mut node_A = TransportFramed::with_unix_stream();
+        let mut node_B = TransportFramed::with_unix_stream();
+        let uri_A = Url::parse(&format!(
+            "unix://{}/lib3h_{}_A.sock",
+            dir.display(),
+            std::process::id()
+        ))
+        .unwrap();
+        let uri_B = Url::parse(&format!(
+            "unix://{}/lib3h_{}_B.sock",
+            dir.display(),
+            std::process::id()
+        ))
+        .unwrap();
+
+        send_test(&mut node_A, &mut node_B, &uri_A, &uri_B);
+        std::fs::remove_file(uri_A.path()).unwrap();
+        std::fs::remove_file(uri_B.path()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_should_give_accepted_connections_distinct_uris() {
+        enable_logging_for_test(true);
+        let mut node_A = TransportFramed::with_unix_stream();
+        let mut node_B = TransportFramed::with_unix_stream();
+        let uri_B = Url::parse(&format!(
+            "unix://{}/lib3h_{}_distinct_B.sock",
+            std::env::temp_dir().display(),
+            std::process::id()
+        ))
+        .unwrap();
+        let bound_uri_b = node_B.bind(&uri_B).unwrap();
+        node_A.connect(&bound_uri_b).unwrap();
+        node_A.connect(&bound_uri_b).unwrap();
+        let mut event_list = Vec::new();
+        for _x in 0..NUM_PROCESS_LOOPS {
+            node_A.process().unwrap();
+            event_list.append(&mut node_B.process().unwrap().1);
+        }
+        let accepted_uri_list: Vec<Url> = event_list
+            .iter()
+            .filter_map(|event| match event {
+                TransportEvent::IncomingConnectionEstablished(id) => node_B.get_uri(id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(accepted_uri_list.len(), 2);
+        assert_ne!(accepted_uri_list[0], accepted_uri_list[1]);
+        for accepted_uri in accepted_uri_list {
+            assert_eq!(accepted_uri.path(), bound_uri_b.path());
+            assert!(accepted_uri.fragment().is_some());
+        }
+        std::fs::remove_file(uri_B.path()).unwrap();
+    }
+
+    #[test]
+    fn tcp_should_keep_connection_open_with_heartbeats() {
+        enable_logging_for_test(true);
+        let mut node_A = TransportFramed::with_std_tcp_stream();
+        let mut node_B = TransportFramed::with_std_tcp_stream();
+        node_A.set_heartbeat(10, 100);
+        node_B.set_heartbeat(10, 100);
+        let uri_B = Url::parse("tcp://127.0.0.1:64543").unwrap();
+        let bound_uri_b = node_B.bind(&uri_B).unwrap();
+        node_A.connect(&bound_uri_b).unwrap();
+        let mut event_list = Vec::new();
+        for _x in 0..60 {
+            event_list.append(&mut node_A.process().unwrap().1);
+            event_list.append(&mut node_B.process().unwrap().1);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert!(!event_list.iter().any(|event| match event {
+            TransportEvent::ConnectionClosed(_, _) | TransportEvent::ReceivedData(_, _) => true,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn tcp_should_close_connection_sending_oversized_frame() {
+        enable_logging_for_test(true);
+        let mut node_A = TransportFramed::with_std_tcp_stream();
+        let mut node_B = TransportFramed::with_std_tcp_stream();
+        node_B.set_max_frame_size(4);
+        let uri_B = Url::parse("tcp://127.0.0.1:64542").unwrap();
+        let bound_uri_b = node_B.bind(&uri_B).unwrap();
+        let idAB = node_A.connect(&bound_uri_b).unwrap();
+        node_A.send(&[&idAB], &[1, 2, 3, 4]).unwrap();
+        node_A.send(&[&idAB], &[1, 2, 3, 4, 5]).unwrap();
+
+        let mut event_list = Vec::new();
+        for _x in 0..NUM_PROCESS_LOOPS {
+            node_A.process().unwrap();
+            event_list.append(&mut node_B.process().unwrap().1);
+        }
+        let payload_list: Vec<&Vec<u8>> = event_list
+            .iter()
+            .filter_map(|event| match event {
+                TransportEvent::ReceivedData(_, payload) => Some(payload),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(payload_list, vec![&vec![1, 2, 3, 4]]);
+        assert!(event_list.iter().any(|event| match event {
+            TransportEvent::ConnectionClosed(_, reason) => *reason == CloseReason::IoError,
+            _ => false,
+        }));
+    }
+
     fn send_test(
         node_A: &mut impl Transport,
         node_B: &mut impl Transport,
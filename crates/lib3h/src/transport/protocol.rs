@@ -23,6 +23,23 @@ pub enum TransportEvent {
     IncomingConnectionEstablished(ConnectionId),
     /// We have received data from a connection
     ReceivedData(ConnectionId, Vec<u8>),
-    /// A connection closed for whatever reason
-    ConnectionClosed(ConnectionId),
+    /// A connection closed, for the given reason
+    ConnectionClosed(ConnectionId, CloseReason),
+    /// A connection whose send queue was full can be sent to again
+    ConnectionDrained(ConnectionId),
+}
+
+/// Why a connection closed
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CloseReason {
+    /// We closed it, with a Close or CloseAll command
+    Local,
+    /// The remote closed it
+    Remote,
+    /// Nothing was received from the remote for too long
+    HeartbeatTimeout,
+    /// An I/O error broke it, reported first with an ErrorOccured event
+    IoError,
+    /// Its handshake failed, reported first with an ErrorOccured event
+    HandshakeFailed,
 }
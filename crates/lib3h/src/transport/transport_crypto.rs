@@ -1,65 +1,955 @@
+#![allow(non_snake_case)]
+
 use crate::transport::{
-    error::TransportResult,
-    protocol::{TransportCommand, TransportEvent},
+    error::{TransportError, TransportResult},
+    protocol::{CloseReason, TransportCommand, TransportEvent},
     transport_trait::Transport,
     ConnectionId, ConnectionIdRef,
 };
+use crate::transport_wss::send_queue::{SendQueue, SendQueueLimits};
+use lib3h_crypto_api::{Buffer, CryptoError, CryptoResult, CryptoSystem};
+use lib3h_p2p_protocol::transit_encoding::*;
 use lib3h_protocol::DidWork;
-
+use std::collections::{HashMap, VecDeque};
 use url::Url;
 
-/// Wraps any transport and adds cryptography
+/// Where a connection is at in the transit encoding handshake
+/// (see `crates/p2p_protocol/doc/transit_encoding_protocol.md`)
+enum HandshakeState {
+    /// Connecting node: sent MsgStep1FromConnect, waiting for MsgStep2FromListen
+    SentStep1 {
+        kx_public_key: Box<dyn Buffer>,
+        kx_secret_key: Box<dyn Buffer>,
+    },
+    /// Connecting node: sent MsgStep3FromConnect, waiting for MsgStep4FromListen
+    SentStep3(CryptoSession),
+    /// Listening node: waiting for MsgStep1FromConnect
+    WaitStep1,
+    /// Listening node: sent MsgStep2FromListen, waiting for MsgStep3FromConnect
+    SentStep2 {
+        kx_rx_key: Box<dyn Buffer>,
+        l2c_session_key: Box<dyn Buffer>,
+    },
+    /// Listening node: sent MsgStep4FromListen, waiting for MsgStep5FromConnect
+    SentStep4(CryptoSession),
+    /// Handshake is done, every frame is an encrypted EncodedMessage
+    Ready(CryptoSession),
+}
+
+/// Session keys and sequential nonces of a channel
+struct CryptoSession {
+    remote_sig_public_key: Vec<u8>,
+    tx_session_key: Box<dyn Buffer>,
+    rx_session_key: Box<dyn Buffer>,
+    /// Note: nonce-0 of each session key is used by the handshake
+    tx_nonce: u64,
+    rx_nonce: u64,
+}
+
+/// Per connection bookkeeping
+struct CryptoConnection {
+    /// true if we initiated the connection, i.e. our owner already knows its ConnectionId
+    is_outgoing: bool,
+    /// request_id of the Connect command, to return in ConnectResult
+    request_id: String,
+    state: HandshakeState,
+    /// Payloads sent by owner before the handshake completed
+    send_queue: SendQueue,
+}
+
+impl CryptoConnection {
+    fn new(is_outgoing: bool, state: HandshakeState) -> Self {
+        CryptoConnection {
+            is_outgoing,
+            request_id: String::new(),
+            state,
+            send_queue: SendQueue::new(),
+        }
+    }
+}
+
+/// Why a handshake did not go through
+#[derive(Debug)]
+enum HandshakeFailure {
+    /// We are halting the handshake
+    Halt(Halt),
+    /// Remote node halted the handshake
+    RemoteHalt(Halt),
+}
+
+fn halt(reason_code: HaltReasonCode, reason_text: &str) -> HandshakeFailure {
+    HandshakeFailure::Halt(Halt {
+        reason_code,
+        reason_text: reason_text.to_string(),
+    })
+}
+
+impl From<CryptoError> for HandshakeFailure {
+    fn from(err: CryptoError) -> Self {
+        halt(HaltReasonCode::Unspecified, &format!("{:?}", err))
+    }
+}
+
+impl From<TransportError> for HandshakeFailure {
+    fn from(err: TransportError) -> Self {
+        halt(HaltReasonCode::Unspecified, &format!("{:?}", err))
+    }
+}
+
+/// Wraps any transport and adds cryptography.
+/// Every new connection runs the transit encoding handshake: key exchange and
+/// mutual proof of ownership of the signature keys. ConnectResult and
+/// IncomingConnectionEstablished are held back until the handshake is done.
+/// Afterwards every frame is encrypted with the session keys.
 pub struct TransportCrypto {
     inner_transport: Box<dyn Transport>,
+    crypto: Box<dyn CryptoSystem>,
+    /// Network we are part of. Remotes from another network are halted.
+    network_id: Vec<u8>,
+    /// Our signature keypair, i.e. TransportId keys
+    sig_public_key: Box<dyn Buffer>,
+    sig_secret_key: Box<dyn Buffer>,
+    /// Commands sent to us by owner for async processing
+    cmd_inbox: VecDeque<TransportCommand>,
+    connection_map: HashMap<ConnectionId, CryptoConnection>,
+    /// Limits of the payloads queued for a connection during its handshake
+    send_queue_limits: SendQueueLimits,
 }
 
 /// Constructor
 /// TODO #177 - Consume inner_tranport or have it be a reference?
 impl TransportCrypto {
-    pub fn new(inner_transport: Box<dyn Transport>) -> Self {
-        TransportCrypto { inner_transport }
+    pub fn new(
+        inner_transport: Box<dyn Transport>,
+        crypto: Box<dyn CryptoSystem>,
+        network_id: &[u8],
+        sig_public_key: Box<dyn Buffer>,
+        sig_secret_key: Box<dyn Buffer>,
+    ) -> Self {
+        TransportCrypto {
+            inner_transport,
+            crypto,
+            network_id: network_id.to_vec(),
+            sig_public_key,
+            sig_secret_key,
+            cmd_inbox: VecDeque::new(),
+            connection_map: HashMap::new(),
+            send_queue_limits: SendQueueLimits::default(),
+        }
+    }
+
+    /// Refuse to queue more than `max_len` messages or `max_bytes` bytes
+    /// for a connection during its handshake
+    pub fn set_send_queue_limits(&mut self, max_len: usize, max_bytes: usize) {
+        self.send_queue_limits = SendQueueLimits { max_len, max_bytes };
+    }
+
+    /// Signature public key the remote node proved owning during the handshake
+    pub fn get_remote_sig_public_key(&self, id: &ConnectionIdRef) -> Option<Vec<u8>> {
+        match self.connection_map.get(id).map(|connection| &connection.state) {
+            Some(HandshakeState::Ready(session)) => Some(session.remote_sig_public_key.clone()),
+            _ => None,
+        }
     }
 }
 
 /// Implement Transport trait by composing inner transport
-/// TODO #177 - passthrough for now
 impl Transport for TransportCrypto {
+    /// Connect with inner transport and start the handshake
     fn connect(&mut self, uri: &Url) -> TransportResult<ConnectionId> {
-        self.inner_transport.connect(&uri)
+        let id = self.inner_transport.connect(&uri)?;
+        if self.connection_map.contains_key(&id) {
+            return Ok(id);
+        }
+        let crypto = self.crypto.as_crypto_system();
+        let mut kx_public_key: Box<dyn Buffer> = Box::new(vec![0; crypto.kx_public_key_bytes()]);
+        let mut kx_secret_key = crypto.buf_new_secure(crypto.kx_secret_key_bytes());
+        crypto.kx_keypair(&mut kx_public_key, &mut kx_secret_key)?;
+        let step1 = MsgStep1FromConnect {
+            magic: TRANSIT_ENCODING_MAGIC,
+            encoding: Encoding::SodiumPacked,
+            network_id: self.network_id.clone(),
+            kx_pub_key: kx_public_key.to_vec(),
+        };
+        self.inner_transport.send(&[&id], &step1.to_bytes())?;
+        let state = HandshakeState::SentStep1 {
+            kx_public_key,
+            kx_secret_key,
+        };
+        self.connection_map
+            .insert(id.clone(), CryptoConnection::new(true, state));
+        Ok(id)
     }
+
+    /// Note: closing an unknown connection is a no-op, as it might have been
+    /// closed already because of a failed handshake.
     fn close(&mut self, id: &ConnectionIdRef) -> TransportResult<()> {
+        if self.connection_map.remove(id).is_none() {
+            return Ok(());
+        }
         self.inner_transport.close(id)
     }
 
     fn close_all(&mut self) -> TransportResult<()> {
+        self.connection_map.clear();
         self.inner_transport.close_all()
     }
 
+    /// Encrypt and send payload, or queue it if the handshake is not done yet
     fn send(&mut self, id_list: &[&ConnectionIdRef], payload: &[u8]) -> TransportResult<()> {
-        self.inner_transport.send(id_list, payload)
+        let mut blocked_id_list = Vec::new();
+        for id in id_list {
+            let connection = self
+                .connection_map
+                .get_mut(*id)
+                .ok_or_else(|| TransportError::new(format!("Unknown connectionId: {}", id)))?;
+            match &mut connection.state {
+                HandshakeState::Ready(session) => {
+                    session.tx_nonce += 1;
+                    let frame = encode_frame(
+                        self.crypto.as_crypto_system(),
+                        payload,
+                        session.tx_nonce,
+                        &session.tx_session_key,
+                    )?;
+                    match self.inner_transport.send(&[*id], &frame) {
+                        Err(TransportError::WouldBlock(_)) => {
+                            // the frame was not sent, its nonce will be used by the next one
+                            session.tx_nonce -= 1;
+                            blocked_id_list.push(id.to_string());
+                        }
+                        result => result?,
+                    }
+                }
+                _ => {
+                    if !connection.send_queue.push(payload, &self.send_queue_limits) {
+                        blocked_id_list.push(id.to_string());
+                    }
+                }
+            }
+        }
+        if !blocked_id_list.is_empty() {
+            return Err(TransportError::WouldBlock(blocked_id_list));
+        }
+        Ok(())
     }
 
     fn send_all(&mut self, payload: &[u8]) -> TransportResult<()> {
-        self.inner_transport.send_all(payload)
+        let id_list = self.connection_id_list()?;
+        let id_ref_list: Vec<&ConnectionIdRef> = id_list.iter().map(|id| id.as_str()).collect();
+        self.send(&id_ref_list, payload)
     }
 
     fn bind(&mut self, url: &Url) -> TransportResult<Url> {
         self.inner_transport.bind(url)
     }
 
+    /// Add Command to inbox
     fn post(&mut self, command: TransportCommand) -> TransportResult<()> {
-        self.inner_transport.post(command)
+        self.cmd_inbox.push_back(command);
+        Ok(())
     }
 
+    /// Process our TransportCommand inbox and the events of the inner transport
     fn process(&mut self) -> TransportResult<(DidWork, Vec<TransportEvent>)> {
-        self.inner_transport.process()
+        let mut outbox = Vec::new();
+        let mut did_work = false;
+        // Process TransportCommand inbox
+        loop {
+            let cmd = match self.cmd_inbox.pop_front() {
+                None => break,
+                Some(msg) => msg,
+            };
+            let res = self.serve_TransportCommand(&cmd);
+            if let Ok(mut output) = res {
+                did_work = true;
+                outbox.append(&mut output);
+            }
+        }
+        // Process inner transport
+        let (inner_did_work, event_list) = self.inner_transport.process()?;
+        did_work |= inner_did_work;
+        for evt in event_list {
+            let mut output = self.handle_TransportEvent(evt)?;
+            outbox.append(&mut output);
+        }
+        Ok((did_work, outbox))
     }
 
+    /// Only list connections which completed the handshake
     fn connection_id_list(&self) -> TransportResult<Vec<ConnectionId>> {
-        self.inner_transport.connection_id_list()
+        Ok(self
+            .connection_map
+            .iter()
+            .filter(|(_, connection)| match connection.state {
+                HandshakeState::Ready(_) => true,
+                _ => false,
+            })
+            .map(|(id, _)| id.clone())
+            .collect())
     }
 
     fn get_uri(&self, id: &ConnectionIdRef) -> Option<Url> {
         self.inner_transport.get_uri(id)
     }
 }
+
+/// Private internals
+impl TransportCrypto {
+    /// Process a TransportCommand: Call the corresponding method and possibily return some Events.
+    /// Return a list of TransportEvents to owner.
+    fn serve_TransportCommand(
+        &mut self,
+        cmd: &TransportCommand,
+    ) -> TransportResult<Vec<TransportEvent>> {
+        debug!(">>> '(TransportCrypto)' recv cmd: {:?}", cmd);
+        // Note: use same order as the enum
+        match cmd {
+            TransportCommand::Connect(uri, request_id) => match self.connect(uri) {
+                Ok(id) => {
+                    if let Some(connection) = self.connection_map.get_mut(&id) {
+                        connection.request_id = request_id.clone();
+                    }
+                    Ok(vec![])
+                }
+                // Report it for that uri only, other commands still get served
+                Err(e) => {
+                    warn!("failed to connect to {}: {:?}", uri, e);
+                    Ok(vec![TransportEvent::ErrorOccured(uri.to_string(), e)])
+                }
+            },
+            TransportCommand::Send(id_list, payload) => {
+                let id_ref_list: Vec<&ConnectionIdRef> =
+                    id_list.iter().map(|id| id.as_str()).collect();
+                self.send(&id_ref_list, payload)?;
+                Ok(vec![])
+            }
+            TransportCommand::SendAll(payload) => {
+                self.send_all(payload)?;
+                Ok(vec![])
+            }
+            TransportCommand::Close(id) => {
+                self.close(id)?;
+                Ok(vec![TransportEvent::ConnectionClosed(
+                    id.to_string(),
+                    CloseReason::Local,
+                )])
+            }
+            TransportCommand::CloseAll => {
+                let id_list = self.connection_id_list()?;
+                self.close_all()?;
+                Ok(id_list
+                    .into_iter()
+                    .map(|id| TransportEvent::ConnectionClosed(id, CloseReason::Local))
+                    .collect())
+            }
+            TransportCommand::Bind(url) => {
+                self.bind(url)?;
+                Ok(vec![])
+            }
+        }
+    }
+
+    /// Handle an event from the inner transport.
+    /// Return the events to forward to our owner.
+    fn handle_TransportEvent(
+        &mut self,
+        evt: TransportEvent,
+    ) -> TransportResult<Vec<TransportEvent>> {
+        trace!("(TransportCrypto) inner event: {:?}", evt);
+        // Note: use same order as the enum
+        match evt {
+            TransportEvent::ErrorOccured(id, e) => match self.connection_map.remove(&id) {
+                Some(connection) => {
+                    if is_announced(&connection) {
+                        Ok(vec![TransportEvent::ErrorOccured(id, e)])
+                    } else {
+                        warn!("(TransportCrypto) error during handshake: {} {:?}", id, e);
+                        Ok(vec![])
+                    }
+                }
+                None => Ok(vec![]),
+            },
+            TransportEvent::ConnectResult(_, _) => {
+                // Handshake has already been started by connect()
+                Ok(vec![])
+            }
+            TransportEvent::IncomingConnectionEstablished(id) => {
+                self.connection_map
+                    .entry(id)
+                    .or_insert_with(|| CryptoConnection::new(false, HandshakeState::WaitStep1));
+                Ok(vec![])
+            }
+            TransportEvent::ReceivedData(id, payload) => self.handle_received_data(id, &payload),
+            TransportEvent::ConnectionClosed(id, reason) => match self.connection_map.remove(&id) {
+                Some(connection) => {
+                    if is_announced(&connection) {
+                        Ok(vec![TransportEvent::ConnectionClosed(id, reason)])
+                    } else {
+                        Ok(vec![])
+                    }
+                }
+                None => Ok(vec![]),
+            },
+            TransportEvent::ConnectionDrained(id) => match self.connection_map.get(&id) {
+                Some(connection) if is_announced(connection) => {
+                    Ok(vec![TransportEvent::ConnectionDrained(id)])
+                }
+                _ => Ok(vec![]),
+            },
+        }
+    }
+
+    /// Decrypt a frame or advance the handshake
+    fn handle_received_data(
+        &mut self,
+        id: ConnectionId,
+        payload: &[u8],
+    ) -> TransportResult<Vec<TransportEvent>> {
+        // Data from an unknown connection should be a MsgStep1FromConnect
+        let mut connection = self
+            .connection_map
+            .remove(&id)
+            .unwrap_or_else(|| CryptoConnection::new(false, HandshakeState::WaitStep1));
+        // Established channel: decrypt
+        let maybe_res = match &mut connection.state {
+            HandshakeState::Ready(session) => {
+                session.rx_nonce += 1;
+                Some(decode_frame(
+                    self.crypto.as_crypto_system(),
+                    payload,
+                    session.rx_nonce,
+                    &session.rx_session_key,
+                ))
+            }
+            _ => None,
+        };
+        match maybe_res {
+            Some(Ok(content)) => {
+                self.connection_map.insert(id.clone(), connection);
+                return Ok(vec![TransportEvent::ReceivedData(id, content)]);
+            }
+            Some(Err(e)) => {
+                error!("(TransportCrypto) failed decoding frame from {}: {:?}", id, e);
+                self.inner_transport.close(&id).ok();
+                return Ok(vec![TransportEvent::ErrorOccured(id, e)]);
+            }
+            None => (),
+        }
+        // Handshake: process step according to state
+        let state = std::mem::replace(&mut connection.state, HandshakeState::WaitStep1);
+        let res = match state {
+            HandshakeState::SentStep1 {
+                kx_public_key,
+                kx_secret_key,
+            } => self
+                .priv_connect_step2(&id, payload, &kx_public_key, &kx_secret_key)
+                .map_err(|failure| {
+                    if let HandshakeFailure::Halt(halt) = &failure {
+                        let msg = MsgStep3FromConnect::Halt(halt.clone());
+                        self.inner_transport.send(&[&id], &msg.to_bytes()).ok();
+                    }
+                    failure
+                }),
+            HandshakeState::SentStep3(session) => self.priv_connect_step4(&id, payload, session),
+            HandshakeState::WaitStep1 => {
+                self.priv_listen_step1(&id, payload).map_err(|failure| {
+                    if let HandshakeFailure::Halt(halt) = &failure {
+                        let msg = MsgStep2FromListen::Halt(halt.clone());
+                        self.inner_transport.send(&[&id], &msg.to_bytes()).ok();
+                    }
+                    failure
+                })
+            }
+            HandshakeState::SentStep2 {
+                kx_rx_key,
+                l2c_session_key,
+            } => self.priv_listen_step3(&id, payload, &kx_rx_key, &l2c_session_key),
+            HandshakeState::SentStep4(session) => self.priv_listen_step5(payload, session),
+            HandshakeState::Ready(_) => unreachable!(),
+        };
+        match res {
+            Err(failure) => {
+                let e = TransportError::new(format!("Handshake failed: {:?}", failure));
+                warn!("(TransportCrypto) {} with {}", e, id);
+                self.inner_transport.close(&id).ok();
+                if connection.is_outgoing {
+                    Ok(vec![TransportEvent::ErrorOccured(id, e)])
+                } else {
+                    Ok(vec![])
+                }
+            }
+            Ok(HandshakeState::Ready(session)) => {
+                debug!("(TransportCrypto) handshake done with: {}", id);
+                let evt = if connection.is_outgoing {
+                    TransportEvent::ConnectResult(id.clone(), connection.request_id.clone())
+                } else {
+                    TransportEvent::IncomingConnectionEstablished(id.clone())
+                };
+                let mut send_queue =
+                    std::mem::replace(&mut connection.send_queue, SendQueue::new());
+                connection.state = HandshakeState::Ready(session);
+                self.connection_map.insert(id.clone(), connection);
+                // Send what owner has sent in the meantime
+                while let Some(payload) = send_queue.pop() {
+                    match self.send(&[&id], &payload) {
+                        Err(TransportError::WouldBlock(_)) => warn!(
+                            "(TransportCrypto) send queue full, payload dropped for: {}",
+                            id
+                        ),
+                        result => result?,
+                    }
+                }
+                let mut event_list = vec![evt];
+                // Owner was told to wait for the handshake queue to drain
+                if send_queue.take_drained(&self.send_queue_limits) {
+                    event_list.push(TransportEvent::ConnectionDrained(id));
+                }
+                Ok(event_list)
+            }
+            Ok(next_state) => {
+                connection.state = next_state;
+                self.connection_map.insert(id, connection);
+                Ok(vec![])
+            }
+        }
+    }
+
+    /// Connecting node: Receive listening node's kx pubkey, sig pubkey and l2c session key.
+    /// Respond with our sig pubkey, c2l session key and signature of the l2c session key.
+    fn priv_connect_step2(
+        &mut self,
+        id: &ConnectionIdRef,
+        payload: &[u8],
+        kx_public_key: &Box<dyn Buffer>,
+        kx_secret_key: &Box<dyn Buffer>,
+    ) -> Result<HandshakeState, HandshakeFailure> {
+        let msg = match MsgStep2FromListen::from_bytes(payload)
+            .map_err(|e| halt(HaltReasonCode::Unspecified, &format!("{:?}", e)))?
+        {
+            MsgStep2FromListen::Halt(remote_halt) => {
+                return Err(HandshakeFailure::RemoteHalt(remote_halt))
+            }
+            MsgStep2FromListen::Continue(msg) => msg,
+        };
+        let crypto = self.crypto.as_crypto_system();
+        // Derive session keys & decrypt kx secret
+        let (kx_rx_key, kx_tx_key) = kx_session_keys(
+            crypto,
+            true,
+            kx_public_key,
+            kx_secret_key,
+            &msg.kx_pub_key,
+        )?;
+        let step2_kx = aead_decrypt(crypto, &msg.kx_secret, &msg.kx_nonce, &kx_rx_key)
+            .ok()
+            .and_then(|bytes| MsgStep2FromListenKxEncoded::from_bytes(&bytes).ok())
+            .ok_or_else(|| halt(HaltReasonCode::KxDecodeFail, "Could not decode kxSecret"))?;
+        // Prove we own our sig key by signing l2c session key
+        let c2l_signature = sign(crypto, &step2_kx.l2c_session_key, &self.sig_secret_key)?;
+        let c2l_session_key = random_secure(crypto, crypto.aead_secret_bytes())?;
+        let step3_kx = MsgStep3FromConnectKxEncoded {
+            padding: vec![],
+            sig_pub_key: self.sig_public_key.to_vec(),
+            c2l_session_key: c2l_session_key.read_lock().to_vec(),
+            c2l_signature,
+        };
+        let kx_nonce = random(crypto, crypto.aead_nonce_bytes())?;
+        let kx_secret = aead_encrypt(crypto, &step3_kx.to_bytes(), &kx_nonce, &kx_tx_key)?;
+        let step3 = MsgStep3FromConnect::Continue(MsgStep3Continue {
+            kx_nonce,
+            kx_secret,
+        });
+        let session = CryptoSession {
+            remote_sig_public_key: step2_kx.sig_pub_key,
+            tx_session_key: c2l_session_key,
+            rx_session_key: secure_from(crypto, &step2_kx.l2c_session_key)?,
+            tx_nonce: 0,
+            rx_nonce: 0,
+        };
+        self.inner_transport.send(&[id], &step3.to_bytes())?;
+        Ok(HandshakeState::SentStep3(session))
+    }
+
+    /// Connecting node: Verify listening node's signature of our c2l session key.
+    /// Accept it with MsgStep5FromConnect.
+    fn priv_connect_step4(
+        &mut self,
+        id: &ConnectionIdRef,
+        payload: &[u8],
+        session: CryptoSession,
+    ) -> Result<HandshakeState, HandshakeFailure> {
+        let crypto = self.crypto.as_crypto_system();
+        let step4 = aead_decrypt(crypto, payload, &nonce(crypto, 0), &session.rx_session_key)
+            .ok()
+            .and_then(|bytes| MsgStep4FromListenEncoded::from_bytes(&bytes).ok())
+            .ok_or_else(|| halt(HaltReasonCode::KxDecodeFail, "Could not decode MsgStep4"))?;
+        let c2l_session_key = session.tx_session_key.read_lock().to_vec();
+        if !sign_verify(
+            crypto,
+            &step4.l2c_signature,
+            &c2l_session_key,
+            &session.remote_sig_public_key,
+        )? {
+            return Err(halt(HaltReasonCode::BadSignature, "Bad l2cSignature"));
+        }
+        let step5 = MsgStep5FromConnectEncoded { padding: vec![] };
+        let frame = aead_encrypt(
+            crypto,
+            &step5.to_bytes(),
+            &nonce(crypto, 0),
+            &session.tx_session_key,
+        )?;
+        self.inner_transport.send(&[id], &frame)?;
+        Ok(HandshakeState::Ready(session))
+    }
+
+    /// Listening node: Check connecting node's config.
+    /// Respond with our kx pubkey, and kx-encrypted sig pubkey and l2c session key.
+    fn priv_listen_step1(
+        &mut self,
+        id: &ConnectionIdRef,
+        payload: &[u8],
+    ) -> Result<HandshakeState, HandshakeFailure> {
+        let step1 = MsgStep1FromConnect::from_bytes(payload)
+            .map_err(|e| halt(HaltReasonCode::Unspecified, &format!("{:?}", e)))?;
+        if step1.magic != TRANSIT_ENCODING_MAGIC {
+            return Err(halt(HaltReasonCode::BadMagic, "Unknown protocol"));
+        }
+        if step1.encoding != Encoding::SodiumPacked {
+            return Err(halt(
+                HaltReasonCode::BadEncoding,
+                &format!("Unsupported encoding: {:?}", step1.encoding),
+            ));
+        }
+        if step1.network_id != self.network_id {
+            return Err(halt(HaltReasonCode::BadNetworkId, "Unknown networkId"));
+        }
+        let crypto = self.crypto.as_crypto_system();
+        // Derive session keys
+        let mut kx_public_key: Box<dyn Buffer> = Box::new(vec![0; crypto.kx_public_key_bytes()]);
+        let mut kx_secret_key = crypto.buf_new_secure(crypto.kx_secret_key_bytes());
+        crypto.kx_keypair(&mut kx_public_key, &mut kx_secret_key)?;
+        let (kx_rx_key, kx_tx_key) = kx_session_keys(
+            crypto,
+            false,
+            &kx_public_key,
+            &kx_secret_key,
+            &step1.kx_pub_key,
+        )?;
+        // Send our sig pubkey and l2c session key
+        let l2c_session_key = random_secure(crypto, crypto.aead_secret_bytes())?;
+        let step2_kx = MsgStep2FromListenKxEncoded {
+            padding: vec![],
+            sig_pub_key: self.sig_public_key.to_vec(),
+            l2c_session_key: l2c_session_key.read_lock().to_vec(),
+        };
+        let kx_nonce = random(crypto, crypto.aead_nonce_bytes())?;
+        let kx_secret = aead_encrypt(crypto, &step2_kx.to_bytes(), &kx_nonce, &kx_tx_key)?;
+        let step2 = MsgStep2FromListen::Continue(MsgStep2Continue {
+            kx_pub_key: kx_public_key.to_vec(),
+            kx_nonce,
+            kx_secret,
+        });
+        self.inner_transport.send(&[id], &step2.to_bytes())?;
+        Ok(HandshakeState::SentStep2 {
+            kx_rx_key,
+            l2c_session_key,
+        })
+    }
+
+    /// Listening node: Verify connecting node's signature of our l2c session key.
+    /// Respond with our signature of its c2l session key.
+    fn priv_listen_step3(
+        &mut self,
+        id: &ConnectionIdRef,
+        payload: &[u8],
+        kx_rx_key: &Box<dyn Buffer>,
+        l2c_session_key: &Box<dyn Buffer>,
+    ) -> Result<HandshakeState, HandshakeFailure> {
+        let msg = match MsgStep3FromConnect::from_bytes(payload)
+            .map_err(|e| halt(HaltReasonCode::Unspecified, &format!("{:?}", e)))?
+        {
+            MsgStep3FromConnect::Halt(remote_halt) => {
+                return Err(HandshakeFailure::RemoteHalt(remote_halt))
+            }
+            MsgStep3FromConnect::Continue(msg) => msg,
+        };
+        let crypto = self.crypto.as_crypto_system();
+        let step3_kx = aead_decrypt(crypto, &msg.kx_secret, &msg.kx_nonce, kx_rx_key)
+            .ok()
+            .and_then(|bytes| MsgStep3FromConnectKxEncoded::from_bytes(&bytes).ok())
+            .ok_or_else(|| halt(HaltReasonCode::KxDecodeFail, "Could not decode kxSecret"))?;
+        let l2c_bytes = l2c_session_key.read_lock().to_vec();
+        if !sign_verify(
+            crypto,
+            &step3_kx.c2l_signature,
+            &l2c_bytes,
+            &step3_kx.sig_pub_key,
+        )? {
+            return Err(halt(HaltReasonCode::BadSignature, "Bad c2lSignature"));
+        }
+        // Prove we own our sig key by signing c2l session key
+        let step4 = MsgStep4FromListenEncoded {
+            padding: vec![],
+            l2c_signature: sign(crypto, &step3_kx.c2l_session_key, &self.sig_secret_key)?,
+        };
+        let frame = aead_encrypt(
+            crypto,
+            &step4.to_bytes(),
+            &nonce(crypto, 0),
+            l2c_session_key,
+        )?;
+        let session = CryptoSession {
+            remote_sig_public_key: step3_kx.sig_pub_key,
+            tx_session_key: secure_from(crypto, &l2c_bytes)?,
+            rx_session_key: secure_from(crypto, &step3_kx.c2l_session_key)?,
+            tx_nonce: 0,
+            rx_nonce: 0,
+        };
+        self.inner_transport.send(&[id], &frame)?;
+        Ok(HandshakeState::SentStep4(session))
+    }
+
+    /// Listening node: Connecting node accepted our signature, channel is ready.
+    fn priv_listen_step5(
+        &mut self,
+        payload: &[u8],
+        session: CryptoSession,
+    ) -> Result<HandshakeState, HandshakeFailure> {
+        let crypto = self.crypto.as_crypto_system();
+        aead_decrypt(crypto, payload, &nonce(crypto, 0), &session.rx_session_key)
+            .ok()
+            .and_then(|bytes| MsgStep5FromConnectEncoded::from_bytes(&bytes).ok())
+            .ok_or_else(|| halt(HaltReasonCode::KxDecodeFail, "Could not decode MsgStep5"))?;
+        Ok(HandshakeState::Ready(session))
+    }
+}
+
+/// Return true if our owner knows about this connection
+fn is_announced(connection: &CryptoConnection) -> bool {
+    match connection.state {
+        HandshakeState::Ready(_) => true,
+        _ => connection.is_outgoing,
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Crypto helpers
+//--------------------------------------------------------------------------------------------------
+
+/// Sequential nonce: little-endian counter padded with zeros
+fn nonce(crypto: &dyn CryptoSystem, n: u64) -> Vec<u8> {
+    let mut nonce = vec![0; crypto.aead_nonce_bytes()];
+    nonce[..8].copy_from_slice(&n.to_le_bytes());
+    nonce
+}
+
+fn random(crypto: &dyn CryptoSystem, size: usize) -> CryptoResult<Vec<u8>> {
+    let mut buffer: Box<dyn Buffer> = Box::new(vec![0; size]);
+    crypto.randombytes_buf(&mut buffer)?;
+    Ok(buffer.to_vec())
+}
+
+fn random_secure(crypto: &dyn CryptoSystem, size: usize) -> CryptoResult<Box<dyn Buffer>> {
+    let mut buffer = crypto.buf_new_secure(size);
+    crypto.randombytes_buf(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn secure_from(crypto: &dyn CryptoSystem, data: &[u8]) -> CryptoResult<Box<dyn Buffer>> {
+    let mut buffer = crypto.buf_new_secure(data.len());
+    buffer.write(0, data)?;
+    Ok(buffer)
+}
+
+/// Derive (rx, tx) session keys from "client" or "server" perspective
+fn kx_session_keys(
+    crypto: &dyn CryptoSystem,
+    is_client: bool,
+    public_key: &Box<dyn Buffer>,
+    secret_key: &Box<dyn Buffer>,
+    remote_public_key: &[u8],
+) -> Result<(Box<dyn Buffer>, Box<dyn Buffer>), HandshakeFailure> {
+    if remote_public_key.len() != crypto.kx_public_key_bytes() {
+        return Err(halt(HaltReasonCode::KxDecodeFail, "Bad kxPubKey size"));
+    }
+    let remote_public_key: Box<dyn Buffer> = Box::new(remote_public_key.to_vec());
+    let mut rx = crypto.buf_new_secure(crypto.kx_session_key_bytes());
+    let mut tx = crypto.buf_new_secure(crypto.kx_session_key_bytes());
+    if is_client {
+        crypto.kx_client_session_keys(
+            &mut rx,
+            &mut tx,
+            public_key,
+            secret_key,
+            &remote_public_key,
+        )?;
+    } else {
+        crypto.kx_server_session_keys(
+            &mut rx,
+            &mut tx,
+            public_key,
+            secret_key,
+            &remote_public_key,
+        )?;
+    }
+    Ok((rx, tx))
+}
+
+fn sign(
+    crypto: &dyn CryptoSystem,
+    data: &[u8],
+    secret_key: &Box<dyn Buffer>,
+) -> CryptoResult<Vec<u8>> {
+    let message: Box<dyn Buffer> = Box::new(data.to_vec());
+    let mut signature: Box<dyn Buffer> = Box::new(vec![0; crypto.sign_bytes()]);
+    crypto.sign(&mut signature, &message, secret_key)?;
+    Ok(signature.to_vec())
+}
+
+fn sign_verify(
+    crypto: &dyn CryptoSystem,
+    signature: &[u8],
+    data: &[u8],
+    public_key: &[u8],
+) -> CryptoResult<bool> {
+    if signature.len() != crypto.sign_bytes()
+        || public_key.len() != crypto.sign_public_key_bytes()
+    {
+        return Ok(false);
+    }
+    let signature: Box<dyn Buffer> = Box::new(signature.to_vec());
+    let message: Box<dyn Buffer> = Box::new(data.to_vec());
+    let public_key: Box<dyn Buffer> = Box::new(public_key.to_vec());
+    crypto.sign_verify(&signature, &message, &public_key)
+}
+
+fn aead_encrypt(
+    crypto: &dyn CryptoSystem,
+    data: &[u8],
+    nonce: &[u8],
+    secret: &Box<dyn Buffer>,
+) -> CryptoResult<Vec<u8>> {
+    let message: Box<dyn Buffer> = Box::new(data.to_vec());
+    let nonce: Box<dyn Buffer> = Box::new(nonce.to_vec());
+    let mut cipher: Box<dyn Buffer> = Box::new(vec![0; data.len() + crypto.aead_auth_bytes()]);
+    crypto.aead_encrypt(&mut cipher, &message, None, &nonce, secret)?;
+    Ok(cipher.to_vec())
+}
+
+fn aead_decrypt(
+    crypto: &dyn CryptoSystem,
+    cipher: &[u8],
+    nonce: &[u8],
+    secret: &Box<dyn Buffer>,
+) -> CryptoResult<Vec<u8>> {
+    if cipher.len() < crypto.aead_auth_bytes() {
+        return Err(CryptoError::BadCipherSize);
+    }
+    if nonce.len() != crypto.aead_nonce_bytes() {
+        return Err(CryptoError::BadNonceSize);
+    }
+    let cipher: Box<dyn Buffer> = Box::new(cipher.to_vec());
+    let nonce: Box<dyn Buffer> = Box::new(nonce.to_vec());
+    let mut message: Box<dyn Buffer> =
+        Box::new(vec![0; cipher.len() - crypto.aead_auth_bytes()]);
+    crypto.aead_decrypt(&mut message, &cipher, None, &nonce, secret)?;
+    Ok(message.to_vec())
+}
+
+/// Wrap payload into an EncodedMessage encrypted with the n-th nonce of the session key
+fn encode_frame(
+    crypto: &dyn CryptoSystem,
+    payload: &[u8],
+    n: u64,
+    session_key: &Box<dyn Buffer>,
+) -> TransportResult<Vec<u8>> {
+    let msg = EncodedMessage {
+        padding: vec![],
+        content: payload.to_vec(),
+    };
+    Ok(aead_encrypt(
+        crypto,
+        &msg.to_bytes(),
+        &nonce(crypto, n),
+        session_key,
+    )?)
+}
+
+/// Decrypt an EncodedMessage with the n-th nonce of the session key and return its content
+fn decode_frame(
+    crypto: &dyn CryptoSystem,
+    frame: &[u8],
+    n: u64,
+    session_key: &Box<dyn Buffer>,
+) -> TransportResult<Vec<u8>> {
+    let bytes = aead_decrypt(crypto, frame, &nonce(crypto, n), session_key)?;
+    Ok(EncodedMessage::from_bytes(&bytes)?.content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::tests::new_crypto_memory_transport;
+
+    fn process_both(
+        node_A: &mut TransportCrypto,
+        node_B: &mut TransportCrypto,
+    ) -> Vec<TransportEvent> {
+        let mut event_list = Vec::new();
+        for _ in 0..6 {
+            let (_, mut event_list_A) = node_A.process().unwrap();
+            let (_, mut event_list_B) = node_B.process().unwrap();
+            event_list.append(&mut event_list_A);
+            event_list.append(&mut event_list_B);
+        }
+        event_list
+    }
+
+    #[test]
+    fn it_should_authenticate_remote() {
+        let mut node_A = new_crypto_memory_transport(b"auth_test");
+        let mut node_B = new_crypto_memory_transport(b"auth_test");
+        node_A.bind(&Url::parse("mem://auth_a").unwrap()).unwrap();
+        let uri_B = node_B.bind(&Url::parse("mem://auth_b").unwrap()).unwrap();
+        node_A
+            .post(TransportCommand::Connect(uri_B, "req_1".to_string()))
+            .unwrap();
+        let event_list = process_both(&mut node_A, &mut node_B);
+        let idAB = match &event_list[0] {
+            TransportEvent::ConnectResult(id, request_id) => {
+                assert_eq!(request_id, "req_1");
+                id.clone()
+            }
+            e => panic!("Received wrong TransportEvent type: {:?}", e),
+        };
+        assert_eq!(
+            node_A.get_remote_sig_public_key(&idAB).unwrap(),
+            node_B.sig_public_key.to_vec(),
+        );
+        assert_eq!(node_B.connection_id_list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn it_should_halt_on_bad_network_id() {
+        let mut node_A = new_crypto_memory_transport(b"network_1");
+        let mut node_B = new_crypto_memory_transport(b"network_2");
+        node_A.bind(&Url::parse("mem://halt_a").unwrap()).unwrap();
+        let uri_B = node_B.bind(&Url::parse("mem://halt_b").unwrap()).unwrap();
+        let idAB = node_A.connect(&uri_B).unwrap();
+        // Queued until handshake is done, which never happens
+        node_A.send(&[&idAB], &[1, 2, 3]).unwrap();
+        let event_list = process_both(&mut node_A, &mut node_B);
+        assert_eq!(event_list.len(), 1);
+        match &event_list[0] {
+            TransportEvent::ErrorOccured(id, e) => {
+                assert_eq!(id, &idAB);
+                assert!(e.to_string().contains("BadNetworkId"));
+            }
+            e => panic!("Received wrong TransportEvent type: {:?}", e),
+        }
+        assert!(node_A.connection_id_list().unwrap().is_empty());
+        assert!(node_B.connection_id_list().unwrap().is_empty());
+    }
+}
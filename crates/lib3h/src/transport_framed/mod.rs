@@ -0,0 +1,495 @@
+//! abstraction for working with length-prefixed frames
+//! over any rust io Read/Write stream, without the websocket protocol:
+//! for links inside a datacenter, or between a local process and lib3h.
+//! Each frame is the length of its payload as a big-endian u32, followed by the payload.
+//! Empty frames are heartbeats, and are not reported as received data.
+
+mod tcp;
+#[cfg(unix)]
+mod unix;
+
+use crate::{
+    transport::{
+        error::{TransportError, TransportResult},
+        protocol::{CloseReason, TransportCommand, TransportEvent},
+        transport_trait::Transport,
+        ConnectionId, ConnectionIdRef,
+    },
+    transport_wss::{
+        drop_if_blocked,
+        send_queue::{SendQueue, SendQueueLimits},
+        ConnectionIdFactory, IdGenerator, DEFAULT_HEARTBEAT_MS, DEFAULT_HEARTBEAT_WAIT_MS,
+    },
+};
+use lib3h_protocol::DidWork;
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{ErrorKind, Read, Write},
+};
+use url::Url;
+
+/// Scheme of the uris of the framed TCP transport, e.g. `tcp://10.0.0.2:64519`
+pub const TCP_SCHEME: &str = "tcp";
+
+/// Scheme of the uris of the Unix domain socket transport, e.g. `unix:///run/lib3h.sock`
+pub const UNIX_SCHEME: &str = "unix";
+
+/// Bigger frames are refused, and their connection closed
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+/// size of the length prefix of a frame
+const LENGTH_PREFIX_SIZE: usize = 4;
+
+/// most bytes read from a stream at once
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A factory callback opening a stream to a uri
+pub type StreamFactory<T> = fn(uri: &Url) -> TransportResult<T>;
+
+/// A function that accepts the next pending connection, with the uri of its remote
+pub type Acceptor<T> = Box<dyn FnMut() -> TransportResult<Option<(T, Url)>>>;
+
+/// A function that binds to a url, and returns the bound url with its acceptor
+pub type Bind<T> = Box<dyn FnMut(&Url) -> TransportResult<(Url, Acceptor<T>)>>;
+
+/// Represents an individual connection
+struct FramedConnection<T: Read + Write> {
+    id: ConnectionId,
+    request_id: String,
+    url: Url,
+    stream: T,
+    is_server: bool,
+    /// the connection is not reported to our owner yet
+    is_new: bool,
+    /// when we last read from, and started a frame on the stream
+    last_msg: std::time::Instant,
+    last_write: std::time::Instant,
+    /// a heartbeat is to be written before the next queued message
+    is_ping_pending: bool,
+    send_queue: SendQueue,
+    /// the frame being written, and how much of it is written
+    write_buf: Vec<u8>,
+    write_offset: usize,
+    /// the bytes read that do not make a complete frame yet
+    read_buf: Vec<u8>,
+}
+
+impl<T: Read + Write> FramedConnection<T> {
+    fn new(id: ConnectionId, url: Url, stream: T, is_server: bool) -> Self {
+        FramedConnection {
+            id,
+            request_id: "".to_string(),
+            url,
+            stream,
+            is_server,
+            is_new: true,
+            last_msg: std::time::Instant::now(),
+            last_write: std::time::Instant::now(),
+            is_ping_pending: false,
+            send_queue: SendQueue::new(),
+            write_buf: Vec::new(),
+            write_offset: 0,
+            read_buf: Vec::new(),
+        }
+    }
+}
+
+/// Length-prefixed frame of a payload
+fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(LENGTH_PREFIX_SIZE + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// A "Transport" implementation sending length-prefixed frames,
+/// any rust io Read/Write stream should be able to serve as the base
+pub struct TransportFramed<T: Read + Write> {
+    /// scheme of the uris we bind and connect to
+    scheme: &'static str,
+    heartbeat_ms: u64,
+    heartbeat_wait_ms: u64,
+    max_frame_size: usize,
+    send_queue_limits: SendQueueLimits,
+    stream_factory: StreamFactory<T>,
+    connection_map: HashMap<ConnectionId, FramedConnection<T>>,
+    event_queue: Vec<TransportEvent>,
+    n_id: ConnectionIdFactory,
+    inbox: VecDeque<TransportCommand>,
+    bind: Bind<T>,
+    acceptor: Option<Acceptor<T>>,
+}
+
+impl<T: Read + Write> Transport for TransportFramed<T> {
+    /// open a stream to a remote node
+    fn connect(&mut self, uri: &Url) -> TransportResult<ConnectionId> {
+        if uri.scheme() != self.scheme {
+            return Err(TransportError::new(format!(
+                "Cannot connect to {}, expecting a {}:// uri",
+                uri, self.scheme
+            )));
+        }
+        let stream = (self.stream_factory)(uri)?;
+        let id = self.n_id.next_id();
+        let connection = FramedConnection::new(id.clone(), uri.clone(), stream, false);
+        self.connection_map.insert(id.clone(), connection);
+        Ok(id)
+    }
+
+    /// close a currently tracked connection
+    fn close(&mut self, id: &ConnectionIdRef) -> TransportResult<()> {
+        // dropping the stream closes it
+        self.connection_map.remove(id);
+        Ok(())
+    }
+
+    /// close all currently tracked connections
+    fn close_all(&mut self) -> TransportResult<()> {
+        self.connection_map.clear();
+        Ok(())
+    }
+
+    /// send a message to one or more remote connected nodes
+    /// fails with WouldBlock listing the connections whose send queue is full
+    fn send(&mut self, id_list: &[&ConnectionIdRef], payload: &[u8]) -> TransportResult<()> {
+        if payload.len() > u32::max_value() as usize {
+            return Err(TransportError::new(format!(
+                "Payload of {} bytes does not fit in a frame",
+                payload.len()
+            )));
+        }
+        let mut blocked_id_list = Vec::new();
+        for id in id_list {
+            if let Some(connection) = self.connection_map.get_mut(*id) {
+                if !connection.send_queue.push(payload, &self.send_queue_limits) {
+                    blocked_id_list.push(id.to_string());
+                }
+            }
+        }
+        if !blocked_id_list.is_empty() {
+            return Err(TransportError::WouldBlock(blocked_id_list));
+        }
+        Ok(())
+    }
+
+    /// send a message to all remote nodes
+    /// fails with WouldBlock listing the connections whose send queue is full
+    fn send_all(&mut self, payload: &[u8]) -> TransportResult<()> {
+        let id_list = self.connection_id_list()?;
+        let id_ref_list: Vec<&ConnectionIdRef> = id_list.iter().map(|id| id.as_str()).collect();
+        self.send(&id_ref_list, payload)
+    }
+
+    fn bind(&mut self, url: &Url) -> TransportResult<Url> {
+        if url.scheme() != self.scheme {
+            return Err(TransportError::new(format!(
+                "Cannot bind to {}, expecting a {}:// uri",
+                url, self.scheme
+            )));
+        }
+        let (bound_url, acceptor) = (self.bind)(url)?;
+        self.acceptor = Some(acceptor);
+        Ok(bound_url)
+    }
+
+    fn post(&mut self, command: TransportCommand) -> TransportResult<()> {
+        self.inbox.push_back(command);
+        Ok(())
+    }
+
+    /// this should be called frequently on the event loop
+    /// accepts connections, writes the queued frames and reads the received ones
+    fn process(&mut self) -> TransportResult<(DidWork, Vec<TransportEvent>)> {
+        let mut did_work = false;
+
+        while let Some(ref cmd) = self.inbox.pop_front() {
+            did_work = true;
+            self.serve_TransportCommand(cmd)?;
+        }
+
+        if self.priv_process_connections() {
+            did_work = true
+        }
+
+        Ok((did_work, self.event_queue.drain(..).collect()))
+    }
+
+    /// get a list of all open transport ids
+    fn connection_id_list(&self) -> TransportResult<Vec<ConnectionId>> {
+        Ok(self.connection_map.keys().cloned().collect())
+    }
+
+    /// get uri from a connectionId
+    fn get_uri(&self, id: &ConnectionIdRef) -> Option<Url> {
+        self.connection_map
+            .get(id)
+            .map(|connection| connection.url.clone())
+    }
+}
+
+impl<T: Read + Write> TransportFramed<T> {
+    pub fn new(scheme: &'static str, stream_factory: StreamFactory<T>, bind: Bind<T>) -> Self {
+        TransportFramed {
+            scheme,
+            heartbeat_ms: DEFAULT_HEARTBEAT_MS,
+            heartbeat_wait_ms: DEFAULT_HEARTBEAT_WAIT_MS,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            send_queue_limits: SendQueueLimits::default(),
+            stream_factory,
+            connection_map: HashMap::new(),
+            event_queue: Vec::new(),
+            n_id: ConnectionIdFactory::new(),
+            inbox: VecDeque::new(),
+            bind,
+            acceptor: None,
+        }
+    }
+
+    /// Send a heartbeat on the connections we have not written a frame to for `heartbeat_ms`,
+    /// close them once we have not received anything for `heartbeat_wait_ms`
+    pub fn set_heartbeat(&mut self, heartbeat_ms: u64, heartbeat_wait_ms: u64) {
+        self.heartbeat_ms = heartbeat_ms;
+        self.heartbeat_wait_ms = heartbeat_wait_ms;
+    }
+
+    /// Refuse to queue more than `max_len` messages or `max_bytes` bytes
+    /// for a connection, until it drained
+    pub fn set_send_queue_limits(&mut self, max_len: usize, max_bytes: usize) {
+        self.send_queue_limits = SendQueueLimits { max_len, max_bytes };
+    }
+
+    /// Close the connections announcing a frame bigger than `max_frame_size`,
+    /// before buffering it
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.max_frame_size = max_frame_size;
+    }
+
+    // -- private -- //
+
+    #[allow(non_snake_case)]
+    fn serve_TransportCommand(&mut self, cmd: &TransportCommand) -> TransportResult<()> {
+        match cmd {
+            TransportCommand::Connect(url, request_id) => match self.connect(url) {
+                Ok(id) => {
+                    if let Some(connection) = self.connection_map.get_mut(&id) {
+                        connection.request_id = request_id.clone();
+                    }
+                }
+                // Report it for that url only, other commands still get served
+                Err(e) => {
+                    warn!("failed to connect to {}: {:?}", url, e);
+                    self.event_queue
+                        .push(TransportEvent::ErrorOccured(url.to_string(), e));
+                }
+            },
+            TransportCommand::Send(id_list, payload) => {
+                let id_ref_list: Vec<&ConnectionIdRef> =
+                    id_list.iter().map(|id| id.as_str()).collect();
+                drop_if_blocked(self.send(&id_ref_list, payload))?;
+            }
+            TransportCommand::SendAll(payload) => {
+                drop_if_blocked(self.send_all(payload))?;
+            }
+            TransportCommand::Close(id) => {
+                self.close(id)?;
+                self.event_queue.push(TransportEvent::ConnectionClosed(
+                    id.to_string(),
+                    CloseReason::Local,
+                ));
+            }
+            TransportCommand::CloseAll => {
+                for id in self.connection_id_list()? {
+                    self.event_queue
+                        .push(TransportEvent::ConnectionClosed(id, CloseReason::Local));
+                }
+                self.close_all()?;
+            }
+            TransportCommand::Bind(url) => {
+                self.bind(url)?;
+            }
+        }
+        Ok(())
+    }
+
+    // accept the pending incoming connections
+    fn priv_process_accept(&mut self) -> DidWork {
+        let mut did_work = false;
+        if let Some(acceptor) = self.acceptor.as_mut() {
+            loop {
+                match acceptor() {
+                    Ok(Some((stream, url))) => {
+                        let id = self.n_id.next_id();
+                        let connection = FramedConnection::new(id.clone(), url, stream, true);
+                        self.connection_map.insert(id, connection);
+                        did_work = true;
+                    }
+                    Ok(None) => break,
+                    Err(err) => {
+                        warn!("did not accept a connection: {:?}", err);
+                        break;
+                    }
+                }
+            }
+        }
+        did_work
+    }
+
+    // see if any work needs to be done on our connections
+    fn priv_process_connections(&mut self) -> DidWork {
+        let mut did_work = self.priv_process_accept();
+
+        // take connections out, so we can mut ref into self and them at same time
+        let connection_list: Vec<(ConnectionId, FramedConnection<T>)> =
+            self.connection_map.drain().collect();
+
+        for (id, mut connection) in connection_list {
+            match self.priv_process_connection(&mut did_work, &mut connection) {
+                Err(e) => {
+                    self.event_queue
+                        .push(TransportEvent::ErrorOccured(id.clone(), e));
+                    self.event_queue
+                        .push(TransportEvent::ConnectionClosed(id, CloseReason::IoError));
+                    continue;
+                }
+                Ok(false) => {
+                    did_work = true;
+                    self.event_queue
+                        .push(TransportEvent::ConnectionClosed(id, CloseReason::Remote));
+                    continue;
+                }
+                Ok(true) => (),
+            }
+            let silence_ms = connection.last_msg.elapsed().as_millis() as u64;
+            if silence_ms > self.heartbeat_wait_ms {
+                did_work = true;
+                self.event_queue.push(TransportEvent::ConnectionClosed(
+                    id,
+                    CloseReason::HeartbeatTimeout,
+                ));
+                continue;
+            }
+            // the remote closes the connection if we stay silent, like we do
+            if connection.last_write.elapsed().as_millis() as u64 > self.heartbeat_ms {
+                connection.last_write = std::time::Instant::now();
+                connection.is_ping_pending = true;
+            }
+            self.connection_map.insert(id, connection);
+        }
+
+        did_work
+    }
+
+    // write and read what the stream of a connection allows,
+    // Ok(false) once the remote closed it
+    fn priv_process_connection(
+        &mut self,
+        did_work: &mut bool,
+        connection: &mut FramedConnection<T>,
+    ) -> TransportResult<bool> {
+        if connection.is_new {
+            connection.is_new = false;
+            *did_work = true;
+            let event = if connection.is_server {
+                TransportEvent::IncomingConnectionEstablished(connection.id.clone())
+            } else {
+                TransportEvent::ConnectResult(connection.id.clone(), connection.request_id.clone())
+            };
+            self.event_queue.push(event);
+        }
+
+        self.priv_write_send_queue(did_work, connection)?;
+
+        let mut chunk = vec![0; READ_CHUNK_SIZE];
+        loop {
+            match connection.stream.read(&mut chunk) {
+                Ok(0) => return Ok(false),
+                Ok(size) => {
+                    *did_work = true;
+                    connection.last_msg = std::time::Instant::now();
+                    connection.read_buf.extend_from_slice(&chunk[..size]);
+                    self.priv_read_frames(connection)?;
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(true)
+    }
+
+    // report the complete frames of the read buffer,
+    // fail on a frame announcing more than max_frame_size
+    fn priv_read_frames(&mut self, connection: &mut FramedConnection<T>) -> TransportResult<()> {
+        let mut start = 0;
+        while connection.read_buf.len() - start >= LENGTH_PREFIX_SIZE {
+            let mut prefix = [0; LENGTH_PREFIX_SIZE];
+            prefix.copy_from_slice(&connection.read_buf[start..start + LENGTH_PREFIX_SIZE]);
+            let size = u32::from_be_bytes(prefix) as usize;
+            if size > self.max_frame_size {
+                return Err(TransportError::new(format!(
+                    "Refusing frame of {} bytes, the maximum is {}",
+                    size, self.max_frame_size
+                )));
+            }
+            let end = start + LENGTH_PREFIX_SIZE + size;
+            if connection.read_buf.len() < end {
+                break;
+            }
+            if size > 0 {
+                let payload = connection.read_buf[start + LENGTH_PREFIX_SIZE..end].to_vec();
+                self.event_queue
+                    .push(TransportEvent::ReceivedData(connection.id.clone(), payload));
+            }
+            start = end;
+        }
+        connection.read_buf.drain(..start);
+        Ok(())
+    }
+
+    // write the queued frames until the stream blocks,
+    // notify our owner if the queue drained after a congestion
+    fn priv_write_send_queue(
+        &mut self,
+        did_work: &mut bool,
+        connection: &mut FramedConnection<T>,
+    ) -> TransportResult<()> {
+        loop {
+            if connection.write_offset == connection.write_buf.len() {
+                let payload = if connection.is_ping_pending {
+                    connection.is_ping_pending = false;
+                    Vec::new()
+                } else {
+                    match connection.send_queue.pop() {
+                        Some(payload) => payload,
+                        None => break,
+                    }
+                };
+                connection.write_buf = frame(&payload);
+                connection.write_offset = 0;
+                connection.last_write = std::time::Instant::now();
+            }
+            match connection
+                .stream
+                .write(&connection.write_buf[connection.write_offset..])
+            {
+                Ok(0) => return Err(std::io::Error::from(ErrorKind::WriteZero).into()),
+                Ok(size) => {
+                    *did_work = true;
+                    connection.write_offset += size;
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        match connection.stream.flush() {
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => (),
+            result => result?,
+        }
+        if connection.send_queue.take_drained(&self.send_queue_limits) {
+            self.event_queue
+                .push(TransportEvent::ConnectionDrained(connection.id.clone()));
+        }
+        Ok(())
+    }
+}
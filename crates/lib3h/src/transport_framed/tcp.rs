@@ -0,0 +1,61 @@
+//! abstraction for working with length-prefixed frames
+//! TcpStream specific functions
+
+use crate::{
+    transport::error::{TransportError, TransportResult},
+    transport_framed::{Acceptor, Bind, TransportFramed, TCP_SCHEME},
+    transport_wss::tcp::connect_with_timeout,
+};
+
+use std::net::{TcpListener, TcpStream};
+
+impl TransportFramed<TcpStream> {
+    /// convenience constructor for creating a framed "Transport"
+    /// instance that is based of the rust std TcpStream
+    pub fn with_std_tcp_stream() -> Self {
+        let bind: Bind<TcpStream> = Box::new(move |url| Self::tcp_bind(url));
+        TransportFramed::new(
+            TCP_SCHEME,
+            |uri| {
+                let socket = connect_with_timeout(&Self::socket_address(uri)?)?;
+                socket.set_nonblocking(true)?;
+                socket.set_nodelay(true)?;
+                Ok(socket)
+            },
+            bind,
+        )
+    }
+
+    /// `host:port` of a tcp:// uri
+    fn socket_address(uri: &url::Url) -> TransportResult<String> {
+        match (uri.host_str(), uri.port()) {
+            (Some(host), Some(port)) => Ok(format!("{}:{}", host, port)),
+            _ => Err(TransportError::new(format!(
+                "{} lacks a host or a port",
+                uri
+            ))),
+        }
+    }
+
+    fn tcp_bind(url: &url::Url) -> TransportResult<(url::Url, Acceptor<TcpStream>)> {
+        let listener = TcpListener::bind(Self::socket_address(url)?)?;
+        listener.set_nonblocking(true)?;
+        // the port is picked by the os when binding to port 0
+        let mut bound_url = url.clone();
+        bound_url
+            .set_port(Some(listener.local_addr()?.port()))
+            .map_err(|()| TransportError::new(format!("cannot set the port of {}", url)))?;
+        let acceptor: Acceptor<TcpStream> = Box::new(move || match listener.accept() {
+            Ok((tcp_stream, socket_address)) => {
+                tcp_stream.set_nonblocking(true)?;
+                tcp_stream.set_nodelay(true)?;
+                let url = url::Url::parse(&format!("{}://{}", TCP_SCHEME, socket_address))?;
+                debug!("transport_framed::tcp accepted for url {}", url);
+                Ok(Some((tcp_stream, url)))
+            }
+            Err(ref err) if err.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(err.into()),
+        });
+        Ok((bound_url, acceptor))
+    }
+}
@@ -0,0 +1,76 @@
+//! abstraction for working with length-prefixed frames
+//! UnixStream specific functions
+
+use crate::{
+    transport::error::{TransportError, TransportResult},
+    transport_framed::{Acceptor, Bind, TransportFramed, UNIX_SCHEME},
+};
+
+use std::{
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+};
+
+impl TransportFramed<UnixStream> {
+    /// convenience constructor for creating a framed "Transport"
+    /// instance that is based of the rust std UnixStream,
+    /// with uris such as `unix:///run/lib3h.sock`.
+    /// Accepted connections get the uri of the bound socket with a fragment
+    /// numbering them, e.g. `unix:///run/lib3h.sock#3`: remote unix sockets are unnamed,
+    /// so that uri only tells them apart and cannot be dialed back.
+    pub fn with_unix_stream() -> Self {
+        let bind: Bind<UnixStream> = Box::new(move |url| Self::unix_bind(url));
+        TransportFramed::new(
+            UNIX_SCHEME,
+            |uri| {
+                let socket = UnixStream::connect(Self::socket_path(uri)?)?;
+                socket.set_nonblocking(true)?;
+                Ok(socket)
+            },
+            bind,
+        )
+    }
+
+    /// path of the socket file of a unix:// uri
+    fn socket_path(uri: &url::Url) -> TransportResult<PathBuf> {
+        let path = PathBuf::from(uri.path());
+        if !path.is_absolute() || uri.host_str().map_or(false, |host| !host.is_empty()) {
+            return Err(TransportError::new(format!(
+                "{} is not a unix:///absolute/path uri",
+                uri
+            )));
+        }
+        Ok(path)
+    }
+
+    fn unix_bind(url: &url::Url) -> TransportResult<(url::Url, Acceptor<UnixStream>)> {
+        let path = Self::socket_path(url)?;
+        // a socket left by a previous run that nobody listens on anymore
+        if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+            if metadata.file_type().is_socket() && UnixStream::connect(&path).is_err() {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+        // remote unix sockets are unnamed, they are known by the socket we bound
+        // and the number of their connection
+        let bound_url = url.clone();
+        let mut accepted_count: u64 = 0;
+        let acceptor: Acceptor<UnixStream> = Box::new(move || match listener.accept() {
+            Ok((unix_stream, _socket_address)) => {
+                unix_stream.set_nonblocking(true)?;
+                accepted_count += 1;
+                let mut remote_url = bound_url.clone();
+                remote_url.set_fragment(Some(&accepted_count.to_string()));
+                Ok(Some((unix_stream, remote_url)))
+            }
+            Err(ref err) if err.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(err.into()),
+        });
+        Ok((url.clone(), acceptor))
+    }
+}
@@ -1,15 +1,19 @@
 //! abstraction for working with Websocket connections
 //! based on any rust io Read/Write Stream
 
-mod tcp;
+pub(crate) mod send_queue;
+pub(crate) mod tcp;
+mod tls;
 
 use crate::transport::{
     error::{TransportError, TransportResult},
-    protocol::{TransportCommand, TransportEvent},
+    protocol::{CloseReason, TransportCommand, TransportEvent},
     transport_trait::Transport,
     ConnectionId, ConnectionIdRef,
 };
+use lib3h_crypto_api::{Buffer, CryptoSystem};
 use lib3h_protocol::DidWork;
+use send_queue::{SendQueue, SendQueueLimits};
 use std::{
     collections::VecDeque,
     io::{Read, Write},
@@ -83,10 +87,13 @@ enum WebsocketStreamState<T: Read + Write + std::fmt::Debug> {
 }
 
 /// how often should we send a heartbeat if we have not received msgs
-pub const DEFAULT_HEARTBEAT_MS: usize = 2000;
+pub const DEFAULT_HEARTBEAT_MS: u64 = 2000;
 
 /// when should we close a connection due to not receiving remote msgs
-pub const DEFAULT_HEARTBEAT_WAIT_MS: usize = 5000;
+pub const DEFAULT_HEARTBEAT_WAIT_MS: u64 = 5000;
+
+/// how long to wait for an unreachable host before giving up on connecting to it
+pub const CONNECT_TIMEOUT_MS: u64 = 3000;
 
 /// Represents an individual connection
 #[derive(Debug)]
@@ -95,7 +102,8 @@ pub struct WssInfo<T: Read + Write + std::fmt::Debug> {
     request_id: String,
     url: url::Url,
     last_msg: std::time::Instant,
-    send_queue: Vec<Vec<u8>>,
+    last_ping: std::time::Instant,
+    send_queue: SendQueue,
     stateful_socket: WebsocketStreamState<T>,
 }
 
@@ -115,7 +123,8 @@ impl<T: Read + Write + std::fmt::Debug> WssInfo<T> {
             request_id: "".to_string(),
             url,
             last_msg: std::time::Instant::now(),
-            send_queue: Vec::new(),
+            last_ping: std::time::Instant::now(),
+            send_queue: SendQueue::new(),
             stateful_socket: match is_server {
                 false => WebsocketStreamState::Connecting(socket),
                 true => WebsocketStreamState::ConnectingSrv(socket),
@@ -123,6 +132,31 @@ impl<T: Read + Write + std::fmt::Debug> WssInfo<T> {
         }
     }
 
+    /// is the websocket handshake done
+    fn is_ready(&self) -> bool {
+        match &self.stateful_socket {
+            WebsocketStreamState::ReadyWs(_) | WebsocketStreamState::ReadyWss(_) => true,
+            _ => false,
+        }
+    }
+
+    /// send a heartbeat ping, if the websocket handshake is done
+    fn ping(&mut self) -> TransportResult<()> {
+        self.last_ping = std::time::Instant::now();
+        let result = match &mut self.stateful_socket {
+            WebsocketStreamState::ReadyWs(socket) => {
+                socket.write_message(tungstenite::Message::Ping(vec![]))
+            }
+            WebsocketStreamState::ReadyWss(socket) => {
+                socket.write_message(tungstenite::Message::Ping(vec![]))
+            }
+            _ => return Ok(()),
+        };
+        // if blocked, queued and written by a later write or read
+        would_block(result)?;
+        Ok(())
+    }
+
     pub fn client(id: ConnectionId, url: url::Url, socket: BaseStream<T>) -> Self {
         Self::new(id, url, socket, false)
     }
@@ -138,11 +172,30 @@ pub struct TlsCertificate {
     passphrase: String,
 }
 
+/// Note: FakeServer and SuppliedCertificate accept any certificate from the peers we connect to
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum TlsConfig {
     Unencrypted,
     FakeServer,
     SuppliedCertificate(TlsCertificate),
+    /// Serve a self-signed certificate bound to our TransportKeys, see `set_transport_keys()`,
+    /// and only accept peers whose certificate is bound to the TransportId of their URI,
+    /// e.g. `wss://192.168.0.2:64519/?a=HcMyadayada`
+    TransportIdCertificate,
+    /// Serve a supplied certificate, and only accept peers whose certificate is valid
+    /// for their host and issued by one of the PEM encoded CA certificates of `ca_bundle`
+    CaVerified {
+        certificate: TlsCertificate,
+        ca_bundle: Vec<u8>,
+    },
+}
+
+/// What a TransportWss in TlsConfig::TransportIdCertificate mode needs from our TransportKeys
+struct TransportIdTls {
+    transport_id: String,
+    certificate: TlsCertificate,
+    /// for checking the certificates of our peers
+    crypto: Box<dyn CryptoSystem>,
 }
 
 /// A factory callback for generating base streams of type T
@@ -182,6 +235,29 @@ impl ConnectionIdFactory {
 /// A function that produces accepted sockets of type R wrapped in a TransportInfo
 pub type Acceptor<T> = Box<dyn FnMut(ConnectionIdFactory) -> TransportResult<WssInfo<T>>>;
 
+/// Did a write on a non-blocking socket block, or fail
+fn would_block(result: tungstenite::error::Result<()>) -> TransportResult<bool> {
+    match result {
+        Ok(()) => Ok(false),
+        Err(tungstenite::error::Error::Io(ref e)) if e.kind() == std::io::ErrorKind::WouldBlock => {
+            Ok(true)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// A posted payload cannot be retried by its sender:
+/// it is dropped for the connections whose send queue is full
+pub(crate) fn drop_if_blocked(result: TransportResult<()>) -> TransportResult<()> {
+    match result {
+        Err(TransportError::WouldBlock(id_list)) => {
+            warn!("send queue full, payload dropped for: {:?}", id_list);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
 /// A function that binds to a url and produces sockt acceptors of type T
 pub type Bind<T> = Box<dyn FnMut(&Url) -> TransportResult<Acceptor<T>>>;
 
@@ -189,6 +265,10 @@ pub type Bind<T> = Box<dyn FnMut(&Url) -> TransportResult<Acceptor<T>>>;
 /// any rust io Read/Write stream should be able to serve as the base
 pub struct TransportWss<T: Read + Write + std::fmt::Debug> {
     tls_config: TlsConfig,
+    transport_id_tls: Option<TransportIdTls>,
+    heartbeat_ms: u64,
+    heartbeat_wait_ms: u64,
+    send_queue_limits: SendQueueLimits,
     stream_factory: StreamFactory<T>,
     stream_sockets: SocketMap<T>,
     event_queue: Vec<TransportEvent>,
@@ -204,9 +284,9 @@ impl<T: Read + Write + std::fmt::Debug> Transport for TransportWss<T> {
         let host_port = format!(
             "{}:{}",
             uri.host_str()
-                .ok_or_else(|| TransportError("bad connect host".into()))?,
+                .ok_or_else(|| TransportError::new("bad connect host".into()))?,
             uri.port()
-                .ok_or_else(|| TransportError("bad connect port".into()))?,
+                .ok_or_else(|| TransportError::new("bad connect port".into()))?,
         );
         let socket = (self.stream_factory)(&host_port)?;
         let id = self.priv_next_id();
@@ -282,29 +362,41 @@ impl<T: Read + Write + std::fmt::Debug> Transport for TransportWss<T> {
     }
 
     /// send a message to one or more remote connected nodes
+    /// fails with WouldBlock listing the connections whose send queue is full
     fn send(&mut self, id_list: &[&ConnectionIdRef], payload: &[u8]) -> TransportResult<()> {
+        let mut blocked_id_list = Vec::new();
         for id in id_list {
             if let Some(info) = self.stream_sockets.get_mut(&id.to_string()) {
-                info.send_queue.push(payload.to_vec());
+                if !info.send_queue.push(payload, &self.send_queue_limits) {
+                    blocked_id_list.push(id.to_string());
+                }
             }
         }
-
+        if !blocked_id_list.is_empty() {
+            return Err(TransportError::WouldBlock(blocked_id_list));
+        }
         Ok(())
     }
 
     /// send a message to all remote nodes
+    /// fails with WouldBlock listing the connections whose send queue is full
     fn send_all(&mut self, payload: &[u8]) -> TransportResult<()> {
-        for info in self.stream_sockets.values_mut() {
-            info.send_queue.push(payload.to_vec());
-        }
-        Ok(())
+        let id_list = self.connection_id_list()?;
+        let id_ref_list: Vec<&ConnectionIdRef> = id_list.iter().map(|id| id.as_str()).collect();
+        self.send(&id_ref_list, payload)
     }
 
     fn bind(&mut self, url: &Url) -> TransportResult<Url> {
         let acceptor = (self.bind)(&url.clone());
         acceptor.map(|acceptor| {
             self.acceptor = Ok(acceptor);
-            url.clone()
+            // Tell our peers which TransportId to expect
+            let mut url = url.clone();
+            if let Some(transport_id_tls) = &self.transport_id_tls {
+                url.query_pairs_mut()
+                    .append_pair(tls::TRANSPORT_ID_QUERY_KEY, &transport_id_tls.transport_id);
+            }
+            url
         })
     }
 }
@@ -313,14 +405,52 @@ impl<T: Read + Write + std::fmt::Debug + std::marker::Sized> TransportWss<T> {
     pub fn new(stream_factory: StreamFactory<T>, bind: Bind<T>, tls_config: TlsConfig) -> Self {
         TransportWss {
             tls_config,
+            transport_id_tls: None,
+            heartbeat_ms: DEFAULT_HEARTBEAT_MS,
+            heartbeat_wait_ms: DEFAULT_HEARTBEAT_WAIT_MS,
+            send_queue_limits: SendQueueLimits::default(),
             stream_factory,
             stream_sockets: std::collections::HashMap::new(),
             event_queue: Vec::new(),
             n_id: ConnectionIdFactory::new(),
             inbox: VecDeque::new(),
             bind,
-            acceptor: Err(TransportError("acceptor not initialized".into())),
+            acceptor: Err(TransportError::new("acceptor not initialized".into())),
+        }
+    }
+
+    /// Generate the certificate we serve in TlsConfig::TransportIdCertificate mode,
+    /// no-op in the other modes. Must be called before bind().
+    pub fn set_transport_keys(
+        &mut self,
+        crypto: &dyn CryptoSystem,
+        transport_id: &str,
+        transport_secret_key: &Box<dyn Buffer>,
+    ) -> TransportResult<()> {
+        if self.tls_config != TlsConfig::TransportIdCertificate {
+            return Ok(());
         }
+        let certificate =
+            TlsCertificate::with_transport_keys(crypto, transport_id, transport_secret_key)?;
+        self.transport_id_tls = Some(TransportIdTls {
+            transport_id: transport_id.to_string(),
+            certificate,
+            crypto: crypto.box_clone(),
+        });
+        Ok(())
+    }
+
+    /// Ping the connections we have not received msgs from for `heartbeat_ms`,
+    /// close them once we have not received msgs for `heartbeat_wait_ms`
+    pub fn set_heartbeat(&mut self, heartbeat_ms: u64, heartbeat_wait_ms: u64) {
+        self.heartbeat_ms = heartbeat_ms;
+        self.heartbeat_wait_ms = heartbeat_wait_ms;
+    }
+
+    /// Refuse to queue more than `max_len` messages or `max_bytes` bytes
+    /// for a connection, until it drained
+    pub fn set_send_queue_limits(&mut self, max_len: usize, max_bytes: usize) {
+        self.send_queue_limits = SendQueueLimits { max_len, max_bytes };
     }
 
     /// connect and wait for a Connect event response
@@ -331,7 +461,7 @@ impl<T: Read + Write + std::fmt::Debug + std::marker::Sized> TransportWss<T> {
         // Wait for a successful response
         let mut out = Vec::new();
         let start = std::time::Instant::now();
-        while (start.elapsed().as_millis() as usize) < DEFAULT_HEARTBEAT_WAIT_MS {
+        while (start.elapsed().as_millis() as u64) < self.heartbeat_wait_ms {
             let (_did_work, evt_lst) = self.process()?;
             for evt in evt_lst {
                 match evt {
@@ -357,33 +487,40 @@ impl<T: Read + Write + std::fmt::Debug + std::marker::Sized> TransportWss<T> {
     #[allow(non_snake_case)]
     fn serve_TransportCommand(&mut self, cmd: &TransportCommand) -> TransportResult<()> {
         match cmd {
-            TransportCommand::Connect(url, request_id) => {
-                let id = self.connect(url)?;
-                match self.stream_sockets.get_mut(&id) {
+            TransportCommand::Connect(url, request_id) => match self.connect(url) {
+                Ok(id) => match self.stream_sockets.get_mut(&id) {
                     Some(r) => r.request_id = request_id.clone(),
                     None => (),
+                },
+                // Report it for that url only, other commands still get served
+                Err(e) => {
+                    warn!("failed to connect to {}: {:?}", url, e);
+                    self.event_queue
+                        .push(TransportEvent::ErrorOccured(url.to_string(), e));
                 }
-            }
+            },
             TransportCommand::Send(id_list, payload) => {
                 let mut id_ref_list = Vec::with_capacity(id_list.len());
                 for id in id_list {
                     id_ref_list.push(id.as_str());
                 }
-                let _id = self.send(&id_ref_list, payload)?;
+                drop_if_blocked(self.send(&id_ref_list, payload))?;
             }
             TransportCommand::SendAll(payload) => {
-                let _id = self.send_all(payload)?;
+                drop_if_blocked(self.send_all(payload))?;
             }
             TransportCommand::Close(id) => {
                 self.close(id)?;
-                self.event_queue
-                    .push(TransportEvent::ConnectionClosed(id.to_string()));
+                self.event_queue.push(TransportEvent::ConnectionClosed(
+                    id.to_string(),
+                    CloseReason::Local,
+                ));
             }
             TransportCommand::CloseAll => {
                 self.close_all()?;
                 /*
                 for (id, _url) in &self.outbound_connection_map {
-                    self.event_queue.push(TransportEvent::ConnectionClosed(id.to_string()));
+                    self.event_queue.push(TransportEvent::ConnectionClosed(id.to_string(), CloseReason::Local));
                 }
                 */
             }
@@ -429,28 +566,50 @@ impl<T: Read + Write + std::fmt::Debug + std::marker::Sized> TransportWss<T> {
         let sockets: Vec<(String, WssInfo<T>)> = self.stream_sockets.drain().collect();
 
         for (id, mut info) in sockets {
+            let was_ready = info.is_ready();
             if let Err(e) = self.priv_process_socket(&mut did_work, &mut info) {
                 self.event_queue
                     .push(TransportEvent::ErrorOccured(info.id.clone(), e));
+                let reason = if was_ready {
+                    CloseReason::IoError
+                } else {
+                    CloseReason::HandshakeFailed
+                };
+                self.event_queue
+                    .push(TransportEvent::ConnectionClosed(info.id, reason));
+                continue;
             }
             if let WebsocketStreamState::None = info.stateful_socket {
-                self.event_queue
-                    .push(TransportEvent::ConnectionClosed(info.id));
+                self.event_queue.push(TransportEvent::ConnectionClosed(
+                    info.id,
+                    CloseReason::Remote,
+                ));
                 continue;
             }
-            if info.last_msg.elapsed().as_millis() as usize > DEFAULT_HEARTBEAT_MS {
-                if let WebsocketStreamState::ReadyWss(socket) = &mut info.stateful_socket {
-                    socket.write_message(tungstenite::Message::Ping(vec![]))?;
-                }
-                if let WebsocketStreamState::ReadyWs(socket) = &mut info.stateful_socket {
-                    socket.write_message(tungstenite::Message::Ping(vec![]))?;
-                }
-            } else if info.last_msg.elapsed().as_millis() as usize > DEFAULT_HEARTBEAT_WAIT_MS {
-                self.event_queue
-                    .push(TransportEvent::ConnectionClosed(info.id));
-                info.stateful_socket = WebsocketStreamState::None;
+            let silence_ms = info.last_msg.elapsed().as_millis() as u64;
+            if silence_ms > self.heartbeat_wait_ms {
+                // the remote is gone, no need to tell it
+                info.close().ok();
+                self.event_queue.push(TransportEvent::ConnectionClosed(
+                    info.id,
+                    CloseReason::HeartbeatTimeout,
+                ));
+                did_work = true;
                 continue;
             }
+            if silence_ms > self.heartbeat_ms
+                && info.last_ping.elapsed().as_millis() as u64 > self.heartbeat_ms
+            {
+                if let Err(e) = info.ping() {
+                    self.event_queue
+                        .push(TransportEvent::ErrorOccured(info.id.clone(), e));
+                    self.event_queue.push(TransportEvent::ConnectionClosed(
+                        info.id,
+                        CloseReason::IoError,
+                    ));
+                    continue;
+                }
+            }
             self.stream_sockets.insert(id, info);
         }
 
@@ -486,13 +645,10 @@ impl<T: Read + Write + std::fmt::Debug + std::marker::Sized> TransportWss<T> {
                         )?;
                     }
                     _ => {
-                        let connector = native_tls::TlsConnector::builder()
-                            .danger_accept_invalid_certs(true)
-                            .danger_accept_invalid_hostnames(true)
-                            .build()
-                            .expect("failed to build TlsConnector");
+                        let connector = self.priv_tls_connector()?;
+                        let domain = info.url.host_str().unwrap_or("");
                         info.stateful_socket =
-                            self.priv_tls_handshake(connector.connect(info.url.as_str(), socket))?;
+                            self.priv_tls_handshake(connector.connect(domain, socket))?;
                     }
                 }
                 Ok(())
@@ -510,7 +666,12 @@ impl<T: Read + Write + std::fmt::Debug + std::marker::Sized> TransportWss<T> {
                     TlsConfig::FakeServer => {
                         native_tls::Identity::from_pkcs12(FAKE_PKCS12, FAKE_PASS)?
                     }
-                    TlsConfig::SuppliedCertificate(cert) => {
+                    TlsConfig::SuppliedCertificate(cert)
+                    | TlsConfig::CaVerified {
+                        certificate: cert, ..
+                    } => native_tls::Identity::from_pkcs12(&cert.pkcs12_data, &cert.passphrase)?,
+                    TlsConfig::TransportIdCertificate => {
+                        let cert = &self.priv_transport_id_tls()?.certificate;
                         native_tls::Identity::from_pkcs12(&cert.pkcs12_data, &cert.passphrase)?
                     }
                 };
@@ -531,6 +692,7 @@ impl<T: Read + Write + std::fmt::Debug + std::marker::Sized> TransportWss<T> {
             WebsocketStreamState::TlsReady(socket) => {
                 info.last_msg = std::time::Instant::now();
                 *did_work = true;
+                self.priv_check_peer_certificate(&info.url, &socket)?;
                 info.stateful_socket = self.priv_wss_handshake(
                     &info.id,
                     &info.request_id,
@@ -564,12 +726,7 @@ impl<T: Read + Write + std::fmt::Debug + std::marker::Sized> TransportWss<T> {
                 Ok(())
             }
             WebsocketStreamState::ReadyWs(mut socket) => {
-                // This seems to be wrong. Messages shouldn't be drained.
-                let msgs: Vec<Vec<u8>> = info.send_queue.drain(..).collect();
-                for msg in msgs {
-                    // TODO: fix this line! if there is an error, all the remaining messages will be lost!
-                    socket.write_message(tungstenite::Message::Binary(msg))?;
-                }
+                self.priv_write_send_queue(info, &mut *socket)?;
 
                 match socket.read_message() {
                     Err(tungstenite::error::Error::Io(e)) => {
@@ -603,12 +760,7 @@ impl<T: Read + Write + std::fmt::Debug + std::marker::Sized> TransportWss<T> {
                 }
             }
             WebsocketStreamState::ReadyWss(mut socket) => {
-                // This seems to be wrong. Messages shouldn't be drained.
-                let msgs: Vec<Vec<u8>> = info.send_queue.drain(..).collect();
-                for msg in msgs {
-                    // TODO: fix this line! if there is an error, all the remaining messages will be lost!
-                    socket.write_message(tungstenite::Message::Binary(msg))?;
-                }
+                self.priv_write_send_queue(info, &mut *socket)?;
 
                 match socket.read_message() {
                     Err(tungstenite::error::Error::Io(e)) => {
@@ -644,6 +796,78 @@ impl<T: Read + Write + std::fmt::Debug + std::marker::Sized> TransportWss<T> {
         }
     }
 
+    // write the queued messages until the socket blocks,
+    // notify our owner if the queue drained after a congestion
+    fn priv_write_send_queue<S: Read + Write>(
+        &mut self,
+        info: &mut WssInfo<T>,
+        socket: &mut tungstenite::protocol::WebSocket<S>,
+    ) -> TransportResult<()> {
+        // a blocked message is kept by tungstenite, and written first
+        let mut is_blocked = would_block(socket.write_pending())?;
+        while !is_blocked {
+            let msg = match info.send_queue.pop() {
+                Some(msg) => msg,
+                None => break,
+            };
+            is_blocked = would_block(socket.write_message(tungstenite::Message::Binary(msg)))?;
+        }
+        if info.send_queue.take_drained(&self.send_queue_limits) {
+            self.event_queue
+                .push(TransportEvent::ConnectionDrained(info.id.clone()));
+        }
+        Ok(())
+    }
+
+    // TlsConfig::TransportIdCertificate state, error if set_transport_keys() was not called
+    fn priv_transport_id_tls(&self) -> TransportResult<&TransportIdTls> {
+        self.transport_id_tls.as_ref().ok_or_else(|| {
+            TransportError::new("TransportIdCertificate mode without transport keys".into())
+        })
+    }
+
+    // build the TlsConnector checking our peers' certificates as configured
+    fn priv_tls_connector(&self) -> TransportResult<native_tls::TlsConnector> {
+        let mut builder = native_tls::TlsConnector::builder();
+        match &self.tls_config {
+            TlsConfig::CaVerified { ca_bundle, .. } => {
+                for certificate in tls::ca_certificates(ca_bundle)? {
+                    builder.add_root_certificate(certificate);
+                }
+            }
+            // self-signed certificates, checked once the handshake is done
+            _ => {
+                builder
+                    .danger_accept_invalid_certs(true)
+                    .danger_accept_invalid_hostnames(true);
+            }
+        }
+        Ok(builder.build()?)
+    }
+
+    // pin the certificate of a peer we connected to to the TransportId of its uri,
+    // in TlsConfig::TransportIdCertificate mode
+    fn priv_check_peer_certificate(&self, url: &Url, socket: &TlsStream<T>) -> TransportResult<()> {
+        if self.tls_config != TlsConfig::TransportIdCertificate {
+            return Ok(());
+        }
+        let transport_id_tls = self.priv_transport_id_tls()?;
+        let transport_id = tls::expected_transport_id(url).ok_or_else(|| {
+            TransportError::new(format!(
+                "No TransportId to pin the certificate of {} to",
+                url
+            ))
+        })?;
+        let certificate = socket
+            .peer_certificate()?
+            .ok_or_else(|| TransportError::new(format!("No certificate from {}", url)))?;
+        tls::verify_transport_id_certificate(
+            transport_id_tls.crypto.as_crypto_system(),
+            &certificate.to_der()?,
+            &transport_id,
+        )
+    }
+
     // process tls handshaking
     fn priv_tls_handshake(
         &mut self,
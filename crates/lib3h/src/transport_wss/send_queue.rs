@@ -0,0 +1,123 @@
+//! Bounded queue of the messages waiting to be written to a websocket connection
+
+use std::collections::VecDeque;
+
+/// how many messages can wait in the send queue of a connection
+pub const DEFAULT_MAX_SEND_QUEUE_LEN: usize = 1024;
+
+/// how many bytes can wait in the send queue of a connection
+pub const DEFAULT_MAX_SEND_QUEUE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Limits of the send queue of each connection
+#[derive(Debug, PartialEq, Clone)]
+pub struct SendQueueLimits {
+    /// most messages waiting to be written
+    pub max_len: usize,
+    /// most bytes waiting to be written
+    pub max_bytes: usize,
+}
+
+impl Default for SendQueueLimits {
+    fn default() -> Self {
+        SendQueueLimits {
+            max_len: DEFAULT_MAX_SEND_QUEUE_LEN,
+            max_bytes: DEFAULT_MAX_SEND_QUEUE_BYTES,
+        }
+    }
+}
+
+/// Messages waiting to be written, refusing more once the limits are reached
+#[derive(Debug)]
+pub struct SendQueue {
+    messages: VecDeque<Vec<u8>>,
+    byte_count: usize,
+    /// a message was refused, and the queue did not drain since
+    is_congested: bool,
+}
+
+impl SendQueue {
+    pub fn new() -> Self {
+        SendQueue {
+            messages: VecDeque::new(),
+            byte_count: 0,
+            is_congested: false,
+        }
+    }
+
+    /// queue a message, false if that would exceed the limits.
+    /// An empty queue accepts any message, so bigger ones can still be sent.
+    pub fn push(&mut self, payload: &[u8], limits: &SendQueueLimits) -> bool {
+        if !self.messages.is_empty()
+            && (self.messages.len() >= limits.max_len
+                || self.byte_count + payload.len() > limits.max_bytes)
+        {
+            self.is_congested = true;
+            return false;
+        }
+        self.byte_count += payload.len();
+        self.messages.push_back(payload.to_vec());
+        true
+    }
+
+    /// take the oldest message out
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        let msg = self.messages.pop_front()?;
+        self.byte_count -= msg.len();
+        Some(msg)
+    }
+
+    /// true once after a congestion, when the queue is down to half its limits
+    pub fn take_drained(&mut self, limits: &SendQueueLimits) -> bool {
+        if self.is_congested
+            && self.messages.len() <= limits.max_len / 2
+            && self.byte_count <= limits.max_bytes / 2
+        {
+            self.is_congested = false;
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_refuse_past_its_limits() {
+        let limits = SendQueueLimits {
+            max_len: 2,
+            max_bytes: 10,
+        };
+        let mut queue = SendQueue::new();
+        // An empty queue takes anything
+        assert!(queue.push(&[0; 20], &limits));
+        assert!(!queue.push(&[1], &limits));
+        assert_eq!(queue.pop(), Some(vec![0; 20]));
+
+        assert!(queue.push(&[1; 6], &limits));
+        assert!(!queue.push(&[2; 6], &limits));
+        assert!(queue.push(&[2; 4], &limits));
+        assert!(!queue.push(&[], &limits));
+    }
+
+    #[test]
+    fn it_should_report_drained_once() {
+        let limits = SendQueueLimits {
+            max_len: 4,
+            max_bytes: 100,
+        };
+        let mut queue = SendQueue::new();
+        assert!(!queue.take_drained(&limits));
+        for i in 0..4 {
+            assert!(queue.push(&[i], &limits));
+        }
+        assert!(!queue.push(&[4], &limits));
+        queue.pop();
+        assert!(!queue.take_drained(&limits));
+        queue.pop();
+        assert!(queue.take_drained(&limits));
+        assert!(!queue.take_drained(&limits));
+        assert_eq!(queue.pop(), Some(vec![2]));
+    }
+}
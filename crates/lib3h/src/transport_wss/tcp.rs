@@ -2,13 +2,33 @@
 //! TcpStream specific functions
 
 use crate::{
-    transport::error::TransportResult,
+    transport::error::{TransportError, TransportResult},
     transport_wss::{
         Acceptor, Bind, ConnectionIdFactory, IdGenerator, TlsConfig, TransportWss, WssInfo,
+        CONNECT_TIMEOUT_MS,
     },
 };
 
-use std::net::{TcpListener, TcpStream};
+use std::{
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// Dial `host:port`, giving up on each of its addresses after CONNECT_TIMEOUT_MS
+/// instead of blocking on an unreachable host
+pub(crate) fn connect_with_timeout(host_port: &str) -> TransportResult<TcpStream> {
+    let mut last_error = None;
+    for address in host_port.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, Duration::from_millis(CONNECT_TIMEOUT_MS)) {
+            Ok(socket) => return Ok(socket),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(match last_error {
+        Some(e) => e.into(),
+        None => TransportError::new(format!("{} resolves to no address", host_port)),
+    })
+}
 
 impl TransportWss<std::net::TcpStream> {
     /// convenience constructor for creating a websocket "Transport"
@@ -17,7 +37,7 @@ impl TransportWss<std::net::TcpStream> {
         let bind: Bind<TcpStream> = Box::new(move |url| Self::tcp_bind(url));
         TransportWss::new(
             |uri| {
-                let socket = std::net::TcpStream::connect(uri)?;
+                let socket = connect_with_timeout(uri)?;
                 socket.set_nonblocking(true)?;
                 Ok(socket)
             },
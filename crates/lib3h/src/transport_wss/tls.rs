@@ -0,0 +1,321 @@
+//! TLS certificates of TransportWss bound to a TransportId:
+//! a self-signed certificate whose key is signed by our transport key,
+//! so that a peer expecting our TransportId can check it is talking to us.
+
+use crate::{
+    transport::error::{TransportError, TransportResult},
+    transport_wss::TlsCertificate,
+};
+use lib3h_crypto_api::{Buffer, CryptoSystem};
+use openssl::{
+    asn1::Asn1Time,
+    bn::BigNum,
+    ec::{EcGroup, EcKey},
+    hash::MessageDigest,
+    nid::Nid,
+    pkcs12::Pkcs12,
+    pkey::{PKey, Private},
+    x509::{X509Extension, X509NameBuilder, X509},
+};
+use url::Url;
+
+/// Last arc of the OID of the certificate extension holding the signature
+/// of the certificate key by a transport key (UUID based OID `2.25.<uuid>`, see ITU-T X.667)
+const TRANSPORT_ID_EXTENSION_UUID: u128 = 39_206_144_524_590_470_774_217_233_819_072_103_485;
+/// Prefix of the data signed by the transport key, followed by the DER encoded certificate key
+const TRANSPORT_ID_SIGNATURE_PREFIX: &[u8] = b"lib3h-tls-transport-id:";
+/// Query parameter of a peer URI holding the TransportId its certificate is pinned to
+pub const TRANSPORT_ID_QUERY_KEY: &str = "a";
+/// Validity of our generated certificates
+const CERTIFICATE_VALIDITY_DAYS: u32 = 365;
+
+const DER_BOOLEAN: u8 = 0x01;
+const DER_OCTET_STRING: u8 = 0x04;
+const DER_OID: u8 = 0x06;
+const DER_SEQUENCE: u8 = 0x30;
+const DER_VERSION: u8 = 0xa0;
+const DER_EXTENSIONS: u8 = 0xa3;
+
+impl TlsCertificate {
+    /// Generate a self-signed certificate bound to the transport keys of `transport_id`
+    pub fn with_transport_keys(
+        crypto: &dyn CryptoSystem,
+        transport_id: &str,
+        transport_secret_key: &Box<dyn Buffer>,
+    ) -> TransportResult<Self> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+
+        // Sign the certificate key with our transport key
+        let mut data = TRANSPORT_ID_SIGNATURE_PREFIX.to_vec();
+        data.extend_from_slice(&key.public_key_to_der()?);
+        let message: Box<dyn Buffer> = Box::new(data);
+        let mut signature: Box<dyn Buffer> = Box::new(vec![0; crypto.sign_bytes()]);
+        crypto.sign(&mut signature, &message, transport_secret_key)?;
+        let mut extension_value = vec![DER_OCTET_STRING, signature.len() as u8];
+        extension_value.extend_from_slice(&signature);
+        let extension = X509Extension::new(
+            None,
+            None,
+            &format!("2.25.{}", TRANSPORT_ID_EXTENSION_UUID),
+            &format!("DER:{}", to_hex(&extension_value)),
+        )?;
+        let certificate = self_signed_certificate(&key, transport_id, vec![extension])?;
+
+        let passphrase = nanoid::simple();
+        let pkcs12 = Pkcs12::builder().build(&passphrase, transport_id, &key, &certificate)?;
+        Ok(TlsCertificate {
+            pkcs12_data: pkcs12.to_der()?,
+            passphrase,
+        })
+    }
+}
+
+/// Self-signed certificate of `key`
+fn self_signed_certificate(
+    key: &PKey<Private>,
+    common_name: &str,
+    extension_list: Vec<X509Extension>,
+) -> TransportResult<X509> {
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_nid(Nid::COMMONNAME, common_name)?;
+    let name = name.build();
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    builder.set_serial_number(&BigNum::from_u32(1)?.to_asn1_integer()?)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(key)?;
+    builder.set_not_before(&Asn1Time::days_from_now(0)?)?;
+    builder.set_not_after(&Asn1Time::days_from_now(CERTIFICATE_VALIDITY_DAYS)?)?;
+    for extension in extension_list {
+        builder.append_extension(extension)?;
+    }
+    builder.sign(key, MessageDigest::sha256())?;
+    Ok(builder.build())
+}
+
+/// TransportId a peer URI expects its certificate to be bound to
+pub fn expected_transport_id(uri: &Url) -> Option<String> {
+    uri.query_pairs()
+        .find(|(key, _)| key == TRANSPORT_ID_QUERY_KEY)
+        .map(|(_, transport_id)| transport_id.into_owned())
+}
+
+/// Check that a DER encoded certificate is bound to the transport keys of `transport_id`
+pub fn verify_transport_id_certificate(
+    crypto: &dyn CryptoSystem,
+    certificate: &[u8],
+    transport_id: &str,
+) -> TransportResult<()> {
+    let (certificate_key, maybe_signature) = parse_certificate(certificate)
+        .ok_or_else(|| TransportError::new("Malformed peer certificate".to_string()))?;
+    let signature = maybe_signature.ok_or_else(|| {
+        TransportError::new("Peer certificate is not bound to a TransportId".to_string())
+    })?;
+    let hcm0 = hcid::HcidEncoding::with_kind("hcm0")?;
+    let public_key = hcm0.decode(transport_id)?;
+    if signature.len() != crypto.sign_bytes() || public_key.len() != crypto.sign_public_key_bytes()
+    {
+        return Err(TransportError::new(
+            "Malformed peer certificate signature".to_string(),
+        ));
+    }
+    let mut data = TRANSPORT_ID_SIGNATURE_PREFIX.to_vec();
+    data.extend_from_slice(certificate_key);
+    let message: Box<dyn Buffer> = Box::new(data);
+    let signature: Box<dyn Buffer> = Box::new(signature.to_vec());
+    let public_key: Box<dyn Buffer> = Box::new(public_key);
+    if !crypto.sign_verify(&signature, &message, &public_key)? {
+        return Err(TransportError::new(format!(
+            "Peer certificate is not bound to {}",
+            transport_id
+        )));
+    }
+    Ok(())
+}
+
+/// Certificates of a bundle of PEM encoded CA certificates
+pub fn ca_certificates(ca_bundle: &[u8]) -> TransportResult<Vec<native_tls::Certificate>> {
+    const PEM_END: &str = "-----END CERTIFICATE-----";
+    let ca_bundle = String::from_utf8_lossy(ca_bundle);
+    let mut certificate_list = Vec::new();
+    for pem in ca_bundle.split_terminator(PEM_END) {
+        if !pem.contains("-----BEGIN CERTIFICATE-----") {
+            continue;
+        }
+        let pem = format!("{}{}", pem, PEM_END);
+        certificate_list.push(native_tls::Certificate::from_pem(pem.as_bytes())?);
+    }
+    if certificate_list.is_empty() {
+        return Err(TransportError::new(
+            "No certificate in CA bundle".to_string(),
+        ));
+    }
+    Ok(certificate_list)
+}
+
+/// Lowercase hex encoding
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// DER encoding of the OID of our certificate extension
+fn transport_id_extension_oid() -> Vec<u8> {
+    // First two arcs: 2 * 40 + 25
+    let mut oid = vec![105];
+    let mut base128 = Vec::new();
+    let mut value = TRANSPORT_ID_EXTENSION_UUID;
+    loop {
+        base128.push((value & 0x7f) as u8);
+        value >>= 7;
+        if value == 0 {
+            break;
+        }
+    }
+    for (i, byte) in base128.iter().enumerate().rev() {
+        oid.push(if i == 0 { *byte } else { byte | 0x80 });
+    }
+    oid
+}
+
+/// Read a DER element of tag `tag`, return its encoding, its content and what follows it
+fn read_der(input: &[u8], tag: u8) -> Option<(&[u8], &[u8], &[u8])> {
+    let (&first, rest) = input.split_first()?;
+    if first != tag {
+        return None;
+    }
+    let (&length, mut rest) = rest.split_first()?;
+    let length = if length < 0x80 {
+        length as usize
+    } else {
+        let count = (length & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let length = rest[..count]
+            .iter()
+            .fold(0, |length, &b| (length << 8) | b as usize);
+        rest = &rest[count..];
+        length
+    };
+    if rest.len() < length {
+        return None;
+    }
+    let encoded_len = input.len() - rest.len() + length;
+    Some((&input[..encoded_len], &rest[..length], &rest[length..]))
+}
+
+/// Skip a DER element of any tag
+fn skip_der(input: &[u8]) -> Option<&[u8]> {
+    let tag = *input.first()?;
+    read_der(input, tag).map(|(_, _, rest)| rest)
+}
+
+/// DER encoded key of a certificate, and the signature of our extension if it has one
+fn parse_certificate(certificate: &[u8]) -> Option<(&[u8], Option<&[u8]>)> {
+    let (_, certificate, _) = read_der(certificate, DER_SEQUENCE)?;
+    let (_, mut tbs, _) = read_der(certificate, DER_SEQUENCE)?;
+    if let Some((_, _, rest)) = read_der(tbs, DER_VERSION) {
+        tbs = rest;
+    }
+    // serialNumber, signature, issuer, validity, subject
+    for _ in 0..5 {
+        tbs = skip_der(tbs)?;
+    }
+    let (certificate_key, _, mut tbs) = read_der(tbs, DER_SEQUENCE)?;
+    // issuerUniqueID, subjectUniqueID
+    while !tbs.is_empty() && tbs[0] != DER_EXTENSIONS {
+        tbs = skip_der(tbs)?;
+    }
+    if tbs.is_empty() {
+        return Some((certificate_key, None));
+    }
+    let (_, extensions, _) = read_der(tbs, DER_EXTENSIONS)?;
+    let (_, mut extensions, _) = read_der(extensions, DER_SEQUENCE)?;
+    let our_oid = transport_id_extension_oid();
+    while !extensions.is_empty() {
+        let (_, extension, rest) = read_der(extensions, DER_SEQUENCE)?;
+        extensions = rest;
+        let (_, oid, mut extension) = read_der(extension, DER_OID)?;
+        if oid != our_oid.as_slice() {
+            continue;
+        }
+        if let Some((_, _, rest)) = read_der(extension, DER_BOOLEAN) {
+            extension = rest;
+        }
+        let (_, value, _) = read_der(extension, DER_OCTET_STRING)?;
+        let (_, signature, _) = read_der(value, DER_OCTET_STRING)?;
+        return Some((certificate_key, Some(signature)));
+    }
+    Some((certificate_key, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lib3h_sodium::SodiumCryptoSystem;
+
+    fn transport_keys(crypto: &dyn CryptoSystem) -> (String, Box<dyn Buffer>) {
+        let mut public_key: Box<dyn Buffer> = Box::new(vec![0; crypto.sign_public_key_bytes()]);
+        let mut secret_key = crypto.buf_new_secure(crypto.sign_secret_key_bytes());
+        crypto
+            .sign_keypair(&mut public_key, &mut secret_key)
+            .unwrap();
+        let hcm0 = hcid::HcidEncoding::with_kind("hcm0").unwrap();
+        (hcm0.encode(&public_key).unwrap(), secret_key)
+    }
+
+    fn certificate_der(certificate: &TlsCertificate) -> Vec<u8> {
+        let pkcs12 = Pkcs12::from_der(&certificate.pkcs12_data).unwrap();
+        let parsed = pkcs12.parse(&certificate.passphrase).unwrap();
+        parsed.cert.to_der().unwrap()
+    }
+
+    #[test]
+    fn it_should_bind_certificate_to_transport_id() {
+        let crypto = SodiumCryptoSystem::new();
+        let (alex_id, alex_secret_key) = transport_keys(&crypto);
+        let (billy_id, _) = transport_keys(&crypto);
+        let certificate =
+            TlsCertificate::with_transport_keys(&crypto, &alex_id, &alex_secret_key).unwrap();
+        let der = certificate_der(&certificate);
+        assert!(verify_transport_id_certificate(&crypto, &der, &alex_id).is_ok());
+        // Cannot be passed off as someone else's
+        assert!(verify_transport_id_certificate(&crypto, &der, &billy_id).is_err());
+    }
+
+    #[test]
+    fn it_should_reject_unbound_certificate() {
+        let crypto = SodiumCryptoSystem::new();
+        let (alex_id, _) = transport_keys(&crypto);
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        let unbound = self_signed_certificate(&key, &alex_id, vec![]).unwrap();
+        let der = unbound.to_der().unwrap();
+        assert!(parse_certificate(&der).is_some());
+        assert!(verify_transport_id_certificate(&crypto, &der, &alex_id).is_err());
+        assert!(verify_transport_id_certificate(&crypto, &der[..der.len() / 2], &alex_id).is_err());
+    }
+
+    #[test]
+    fn it_should_read_ca_bundle() {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let mut ca_bundle = Vec::new();
+        for name in &["ca_1", "ca_2"] {
+            let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+            let ca = self_signed_certificate(&key, name, vec![]).unwrap();
+            ca_bundle.extend_from_slice(&ca.to_pem().unwrap());
+        }
+        assert_eq!(ca_certificates(&ca_bundle).unwrap().len(), 2);
+        assert!(ca_certificates(b"not a bundle").is_err());
+    }
+
+    #[test]
+    fn it_should_read_expected_transport_id() {
+        let uri = Url::parse("wss://127.0.0.1:64519/?a=HcMalex").unwrap();
+        assert_eq!(expected_transport_id(&uri), Some("HcMalex".to_string()));
+        let uri = Url::parse("wss://127.0.0.1:64519/").unwrap();
+        assert_eq!(expected_transport_id(&uri), None);
+    }
+}
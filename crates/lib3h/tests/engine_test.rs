@@ -70,11 +70,23 @@ fn basic_setup_mock(name: &str) -> RealEngine<MirrorDht> {
         socket_type: "mem".into(),
         bootstrap_nodes: vec![],
         work_dir: String::new(),
+        transport_keys_passphrase: String::new(),
         log_level: 'd',
         bind_url: Url::parse(format!("mem://{}", name).as_str()).unwrap(),
         dht_gossip_interval: 100,
         dht_timeout_threshold: 1000,
         dht_custom_config: vec![],
+        query_entry_quorum: 1,
+        accept_relay_duty: false,
+        request_relay: false,
+        gsp_arc_interval: 0,
+        network_id: NETWORK_A_ID.clone(),
+        enable_lan_discovery: false,
+        wss_heartbeat_ms: 2000,
+        wss_idle_timeout_ms: 5000,
+        wss_max_send_queue_len: 1024,
+        wss_max_send_queue_bytes: 16 * 1024 * 1024,
+        max_message_size: 64 * 1024 * 1024,
     };
     let engine = RealEngine::new_mock(
         Box::new(SodiumCryptoSystem::new()),
@@ -97,11 +109,23 @@ fn basic_setup_wss<'a>() -> RealEngine<'a, MirrorDht> {
         socket_type: "ws".into(),
         bootstrap_nodes: vec![],
         work_dir: String::new(),
+        transport_keys_passphrase: String::new(),
         log_level: 'd',
         bind_url: Url::parse("wss://127.0.0.1:64519").unwrap(),
         dht_gossip_interval: 200,
         dht_timeout_threshold: 2000,
         dht_custom_config: vec![],
+        query_entry_quorum: 1,
+        accept_relay_duty: false,
+        request_relay: false,
+        gsp_arc_interval: 0,
+        network_id: NETWORK_A_ID.clone(),
+        enable_lan_discovery: false,
+        wss_heartbeat_ms: 2000,
+        wss_idle_timeout_ms: 5000,
+        wss_max_send_queue_len: 1024,
+        wss_max_send_queue_bytes: 16 * 1024 * 1024,
+        max_message_size: 64 * 1024 * 1024,
     };
     let engine = RealEngine::new(
         Box::new(SodiumCryptoSystem::new()),
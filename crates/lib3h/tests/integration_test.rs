@@ -16,18 +16,29 @@ mod node_mock;
 mod test_suites;
 
 use lib3h::{
-    dht::mirror_dht::MirrorDht,
+    dht::{
+        mirror_dht::MirrorDht,
+        rrdht::{get_location, RrDht, RrDhtConfig, StorageArc, FULL_ARC_RADIUS},
+    },
     engine::{RealEngine, RealEngineConfig},
     error::Lib3hResult,
+    gateway::multiplex::FRAGMENT_SIZE,
     transport_wss::TlsConfig,
 };
-use lib3h_protocol::{network_engine::NetworkEngine, Address};
-use node_mock::NodeMock;
+use lib3h_protocol::{
+    data_types::{DirectMessageData, EntryData},
+    network_engine::NetworkEngine,
+    protocol_server::Lib3hServerProtocol,
+    Address,
+};
+use node_mock::{EngineFactory, NodeMock};
+use rmp_serde::Deserializer;
+use serde::Deserialize;
 use test_suites::{
     three_basic::*, two_basic::*, two_connection::*, two_get_lists::*, two_spaces::*,
 };
 use url::Url;
-use utils::constants::*;
+use utils::{constants::*, processor_harness::Lib3hServerProtocolEquals};
 
 //--------------------------------------------------------------------------------------------------
 // Logging
@@ -73,26 +84,61 @@ fn construct_mock_engine(
     Ok(Box::new(engine))
 }
 
+fn construct_rrdht_mock_engine(
+    config: &RealEngineConfig,
+    name: &str,
+) -> Lib3hResult<Box<dyn NetworkEngine>> {
+    let engine: RealEngine<RrDht> = RealEngine::new_mock(
+        Box::new(lib3h_sodium::SodiumCryptoSystem::new()),
+        config.clone(),
+        name.into(),
+        RrDht::new_with_config,
+    )
+    .unwrap();
+    Ok(Box::new(engine))
+}
+
 //--------------------------------------------------------------------------------------------------
 // Node Setup
 //--------------------------------------------------------------------------------------------------
 
 pub type NodeFactory = fn(name: &str, agent_id_arg: Address) -> NodeMock;
 
-fn setup_memory_node(name: &str, agent_id_arg: Address, fn_name: &str) -> NodeMock {
+fn setup_memory_node(
+    name: &str,
+    agent_id_arg: Address,
+    fn_name: &str,
+    engine_factory: EngineFactory,
+) -> NodeMock {
+    let config = memory_config(name, fn_name);
+    NodeMock::new_with_config(name, agent_id_arg, config, engine_factory)
+}
+
+fn memory_config(name: &str, fn_name: &str) -> RealEngineConfig {
     let fn_name = fn_name.replace("::", "__");
-    let config = RealEngineConfig {
+    RealEngineConfig {
         tls_config: TlsConfig::Unencrypted,
         socket_type: "mem".into(),
         bootstrap_nodes: vec![],
         work_dir: String::new(),
+        transport_keys_passphrase: String::new(),
         log_level: 'd',
         bind_url: Url::parse(format!("mem://{}/{}", fn_name, name).as_str()).unwrap(),
         dht_gossip_interval: 500,
         dht_timeout_threshold: 3000,
         dht_custom_config: vec![],
-    };
-    NodeMock::new_with_config(name, agent_id_arg, config, construct_mock_engine)
+        query_entry_quorum: 1,
+        accept_relay_duty: false,
+        request_relay: false,
+        gsp_arc_interval: 0,
+        network_id: NETWORK_A_ID.clone(),
+        enable_lan_discovery: false,
+        wss_heartbeat_ms: 2000,
+        wss_idle_timeout_ms: 5000,
+        wss_max_send_queue_len: 1024,
+        wss_max_send_queue_bytes: 16 * 1024 * 1024,
+        max_message_size: 64 * 1024 * 1024,
+    }
 }
 
 fn setup_wss_node(
@@ -105,7 +151,10 @@ fn setup_wss_node(
     let port = generate_port();
     let protocol = match tls_config {
         TlsConfig::Unencrypted => "ws",
-        TlsConfig::SuppliedCertificate(_) | TlsConfig::FakeServer => "wss",
+        TlsConfig::SuppliedCertificate(_)
+        | TlsConfig::FakeServer
+        | TlsConfig::TransportIdCertificate
+        | TlsConfig::CaVerified { .. } => "wss",
     };
     let bind_url = Url::parse(format!("{}://127.0.0.1:{}/{}", protocol, port, fn_name).as_str())
         .expect("invalid web socket url");
@@ -115,15 +164,36 @@ fn setup_wss_node(
         socket_type: protocol.into(),
         bootstrap_nodes: vec![],
         work_dir: String::new(),
+        transport_keys_passphrase: String::new(),
         log_level: 'd',
         bind_url,
         dht_gossip_interval: 500,
         dht_timeout_threshold: 3005,
         dht_custom_config: vec![],
+        query_entry_quorum: 1,
+        accept_relay_duty: false,
+        request_relay: false,
+        gsp_arc_interval: 0,
+        network_id: NETWORK_A_ID.clone(),
+        enable_lan_discovery: false,
+        wss_heartbeat_ms: 2000,
+        wss_idle_timeout_ms: 5000,
+        wss_max_send_queue_len: 1024,
+        wss_max_send_queue_bytes: 16 * 1024 * 1024,
+        max_message_size: 64 * 1024 * 1024,
     };
     NodeMock::new_with_config(name, agent_id_arg, config, construct_mock_engine)
 }
 
+/// Node on the framed TCP transport, picked by the `tcp://` scheme of its bind_url
+fn setup_tcp_node(name: &str, agent_id_arg: Address, fn_name: &str) -> NodeMock {
+    let mut config = memory_config(name, fn_name);
+    config.socket_type = "tcp".into();
+    config.bind_url = Url::parse(format!("tcp://127.0.0.1:{}", generate_port()).as_str())
+        .expect("invalid tcp url");
+    NodeMock::new_with_config(name, agent_id_arg, config, construct_mock_engine)
+}
+
 //--------------------------------------------------------------------------------------------------
 // Utils
 //--------------------------------------------------------------------------------------------------
@@ -154,7 +224,7 @@ fn print_test_name(print_str: &str, test_fn: *mut std::os::raw::c_void) {
 fn test_two_memory_nodes_basic_suite() {
     enable_logging_for_test(true);
     for (test_fn, can_setup) in TWO_NODES_BASIC_TEST_FNS.iter() {
-        launch_two_memory_nodes_test(*test_fn, *can_setup).unwrap();
+        launch_two_memory_nodes_test(*test_fn, construct_mock_engine, *can_setup).unwrap();
     }
 }
 
@@ -162,7 +232,7 @@ fn test_two_memory_nodes_basic_suite() {
 fn test_two_memory_nodes_get_lists_suite() {
     enable_logging_for_test(true);
     for (test_fn, can_setup) in TWO_NODES_GET_LISTS_TEST_FNS.iter() {
-        launch_two_memory_nodes_test(*test_fn, *can_setup).unwrap();
+        launch_two_memory_nodes_test(*test_fn, construct_mock_engine, *can_setup).unwrap();
     }
 }
 
@@ -170,7 +240,7 @@ fn test_two_memory_nodes_get_lists_suite() {
 fn test_two_memory_nodes_spaces_suite() {
     enable_logging_for_test(true);
     for (test_fn, can_setup) in TWO_NODES_SPACES_TEST_FNS.iter() {
-        launch_two_memory_nodes_test(*test_fn, *can_setup).unwrap();
+        launch_two_memory_nodes_test(*test_fn, construct_mock_engine, *can_setup).unwrap();
     }
 }
 
@@ -185,20 +255,515 @@ fn test_three_memory_nodes_basic_suite() {
 fn test_two_memory_nodes_connection_suite() {
     enable_logging_for_test(true);
     for (test_fn, can_setup) in TWO_NODES_CONNECTION_TEST_FNS.iter() {
-        launch_two_memory_nodes_test(*test_fn, *can_setup).unwrap();
+        launch_two_memory_nodes_test(*test_fn, construct_mock_engine, *can_setup).unwrap();
+    }
+}
+
+#[test]
+fn test_two_memory_nodes_rrdht_basic_suite() {
+    enable_logging_for_test(true);
+    for (test_fn, can_setup) in TWO_NODES_BASIC_TEST_FNS.iter() {
+        launch_two_memory_nodes_test(*test_fn, construct_rrdht_mock_engine, *can_setup).unwrap();
+    }
+}
+
+#[test]
+fn test_three_memory_nodes_rrdht_shrunk_arcs() {
+    enable_logging_for_test(true);
+    let fn_name = "test_three_memory_nodes_rrdht_shrunk_arcs";
+    // Billy and Camille only claim a quarter of the ring around their own location,
+    // which covers ENTRY_ADDRESS_1 for Camille but not for Billy
+    let radius = FULL_ARC_RADIUS / 4;
+    let entry_location = get_location(&ENTRY_ADDRESS_1.to_string());
+    assert!(
+        StorageArc::new(get_location(&CAMILLE_AGENT_ID.to_string()), radius)
+            .contains(entry_location)
+    );
+    assert!(
+        !StorageArc::new(get_location(&BILLY_AGENT_ID.to_string()), radius)
+            .contains(entry_location)
+    );
+    let shrunk_config = RrDhtConfig {
+        storage_arc_radius: radius,
+        max_entry_count: 0,
+    }
+    .to_raw();
+    let mut alex = setup_memory_node(
+        "alex",
+        ALEX_AGENT_ID.clone(),
+        fn_name,
+        construct_rrdht_mock_engine,
+    );
+    let mut billy_config = memory_config("billy", fn_name);
+    billy_config.dht_custom_config = shrunk_config.clone();
+    let mut billy = NodeMock::new_with_config(
+        "billy",
+        BILLY_AGENT_ID.clone(),
+        billy_config,
+        construct_rrdht_mock_engine,
+    );
+    let mut camille_config = memory_config("camille", fn_name);
+    camille_config.dht_custom_config = shrunk_config;
+    let mut camille = NodeMock::new_with_config(
+        "camille",
+        CAMILLE_AGENT_ID.clone(),
+        camille_config,
+        construct_rrdht_mock_engine,
+    );
+    setup_three_nodes(&mut alex, &mut billy, &mut camille);
+    // Let the arcs be gossiped
+    alex.wait_until_no_work();
+    billy.wait_until_no_work();
+    camille.wait_until_no_work();
+    alex.wait_until_no_work();
+
+    // Alex publishes an entry
+    alex.author_entry(&ENTRY_ADDRESS_1, vec![ASPECT_CONTENT_1.clone()], true)
+        .unwrap();
+    let (did_work, _srv_msg_list) = alex.process().unwrap();
+    assert!(did_work);
+
+    // Only Camille is asked to store it
+    let store_result = camille.wait(Box::new(one_is!(
+        Lib3hServerProtocol::HandleStoreEntryAspect(_)
+    )));
+    assert!(store_result.is_some());
+    let store_result = billy.wait_with_timeout(
+        Box::new(one_is!(Lib3hServerProtocol::HandleStoreEntryAspect(_))),
+        1000,
+    );
+    assert!(store_result.is_none());
+}
+
+#[test]
+fn test_three_memory_nodes_query_quorum() {
+    enable_logging_for_test(true);
+    let fn_name = "test_three_memory_nodes_query_quorum";
+    // Alex waits for two answers to its queries
+    let mut alex_config = memory_config("alex", fn_name);
+    alex_config.query_entry_quorum = 2;
+    let mut alex = NodeMock::new_with_config(
+        "alex",
+        ALEX_AGENT_ID.clone(),
+        alex_config,
+        construct_mock_engine,
+    );
+    let mut billy = setup_memory_node(
+        "billy",
+        BILLY_AGENT_ID.clone(),
+        fn_name,
+        construct_mock_engine,
+    );
+    let mut camille = setup_memory_node(
+        "camille",
+        CAMILLE_AGENT_ID.clone(),
+        fn_name,
+        construct_mock_engine,
+    );
+    setup_three_nodes(&mut alex, &mut billy, &mut camille);
+    alex.wait_until_no_work();
+    billy.wait_until_no_work();
+    camille.wait_until_no_work();
+    // Only Camille's Core has the entry, without telling its engine
+    let entry = camille
+        .hold_entry(&ENTRY_ADDRESS_1, vec![ASPECT_CONTENT_1.clone()], false)
+        .unwrap();
+
+    // Billy fails to answer and Camille answers: Alex gets Camille's result
+    // without waiting for its query to time out
+    let query_data = alex.request_entry(ENTRY_ADDRESS_1.clone());
+    let (did_work, _srv_msg_list) = alex.process().unwrap();
+    assert!(did_work);
+    assert!(billy_and_camille_answer(&mut billy, &mut camille));
+    let result = alex
+        .wait_with_timeout(
+            Box::new(one_is!(Lib3hServerProtocol::QueryEntryResult(_))),
+            1000,
+        )
+        .expect("Should have received a QueryEntryResult before the query timed out");
+    let result = unwrap_to!(result => Lib3hServerProtocol::QueryEntryResult);
+    assert_eq!(result.request_id, query_data.request_id);
+    assert_eq!(result.responder_agent_id, *CAMILLE_AGENT_ID);
+    let mut de = Deserializer::new(&result.query_result[..]);
+    let found_entry: EntryData = Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(found_entry, entry);
+
+    // Both fail to answer: Alex gets their failure
+    let query_data = alex.request_entry(ENTRY_ADDRESS_2.clone());
+    let (did_work, _srv_msg_list) = alex.process().unwrap();
+    assert!(did_work);
+    assert!(billy_and_camille_answer(&mut billy, &mut camille));
+    let result = alex
+        .wait_with_timeout(
+            Box::new(one_is!(Lib3hServerProtocol::FailureResult(_))),
+            1000,
+        )
+        .expect("Should have received a FailureResult before the query timed out");
+    let result = unwrap_to!(result => Lib3hServerProtocol::FailureResult);
+    assert_eq!(result.request_id, query_data.request_id);
+    assert_eq!(result.result_info, b"No entry found".to_vec());
+}
+
+#[test]
+fn test_three_memory_nodes_reach_a_node_through_its_relay() {
+    enable_logging_for_test(true);
+    let fn_name = "test_three_memory_nodes_reach_a_node_through_its_relay";
+    let mut alex = setup_memory_node(
+        "alex",
+        ALEX_AGENT_ID.clone(),
+        fn_name,
+        construct_mock_engine,
+    );
+    let mut billy_config = memory_config("billy", fn_name);
+    billy_config.accept_relay_duty = true;
+    let mut billy = NodeMock::new_with_config(
+        "billy",
+        BILLY_AGENT_ID.clone(),
+        billy_config,
+        construct_mock_engine,
+    );
+    // Camille is behind a NAT: it asks the first node it connects to to relay for it
+    let mut camille_config = memory_config("camille", fn_name);
+    camille_config.request_relay = true;
+    let mut camille = NodeMock::new_with_config(
+        "camille",
+        CAMILLE_AGENT_ID.clone(),
+        camille_config,
+        construct_mock_engine,
+    );
+    // Joining before connecting: each connection announces the space
+    for node in vec![&mut alex, &mut billy, &mut camille] {
+        node.join_space(&SPACE_ADDRESS_A, true).unwrap();
+        node.wait_until_no_work();
+    }
+    let connect_data = alex.connect_to(&billy.advertise()).unwrap();
+    wait_connect!(alex, connect_data, billy);
+    alex.wait_until_no_work();
+    billy.wait_until_no_work();
+    // Billy becomes the relay of camille before alex hears of camille
+    let connect_data = camille.connect_to(&billy.advertise()).unwrap();
+    wait_connect!(camille, connect_data, billy);
+    billy.wait_until_no_work();
+    camille.wait_until_no_work();
+    billy.wait_until_no_work();
+    // Alex holds camille's relay uri. The memory transport lets alex dial camille too,
+    // but that connection is not confirmed until camille answers it.
+    alex.wait_until_no_work();
+
+    // Alex's message to camille goes through billy
+    let req_id = alex.send_direct_message(&CAMILLE_AGENT_ID, b"through billy".to_vec());
+    let (did_work, srv_msg_list) = alex.process().unwrap();
+    assert!(did_work);
+    assert!(srv_msg_list.iter().any(|msg| match msg {
+        Lib3hServerProtocol::SuccessResult(response) => response.request_id == req_id,
+        _ => false,
+    }));
+    billy.wait_until_no_work();
+    let msg = camille
+        .wait(Box::new(one_is!(
+            Lib3hServerProtocol::HandleSendDirectMessage(_)
+        )))
+        .expect("Camille should receive alex's message from its relay");
+    let msg = unwrap_to!(msg => Lib3hServerProtocol::HandleSendDirectMessage);
+    assert_eq!(msg.request_id, req_id);
+    assert_eq!(msg.from_agent_id, *ALEX_AGENT_ID);
+    assert_eq!(msg.content, b"through billy".to_vec());
+}
+
+#[test]
+fn test_two_memory_nodes_send_fragmented_messages() {
+    enable_logging_for_test(true);
+    let fn_name = "test_two_memory_nodes_send_fragmented_messages";
+    let mut alex_config = memory_config("alex", fn_name);
+    alex_config.max_message_size = 128 * FRAGMENT_SIZE;
+    let mut alex = NodeMock::new_with_config(
+        "alex",
+        ALEX_AGENT_ID.clone(),
+        alex_config,
+        construct_mock_engine,
+    );
+    let mut billy_config = memory_config("billy", fn_name);
+    billy_config.max_message_size = 64 * FRAGMENT_SIZE;
+    let mut billy = NodeMock::new_with_config(
+        "billy",
+        BILLY_AGENT_ID.clone(),
+        billy_config,
+        construct_mock_engine,
+    );
+    setup_two_nodes(&mut alex, &mut billy);
+
+    // Several windows of fragments, each acknowledged by billy
+    let content: Vec<u8> = (0..48 * FRAGMENT_SIZE + 1).map(|i| i as u8).collect();
+    let req_id = alex.send_direct_message(&BILLY_AGENT_ID, content.clone());
+    let msg = exchange_until_direct_message(&mut alex, &mut billy)
+        .expect("Billy should receive the whole message");
+    assert_eq!(msg.request_id, req_id);
+    assert_eq!(msg.content, content);
+
+    // Bigger than what billy accepts: alex sends it but billy drops it
+    let req_id = alex.send_direct_message(&BILLY_AGENT_ID, vec![42; 64 * FRAGMENT_SIZE + 1]);
+    let (_, srv_msg_list) = alex.process().unwrap();
+    assert!(srv_msg_list.iter().any(|msg| match msg {
+        Lib3hServerProtocol::SuccessResult(response) => response.request_id == req_id,
+        _ => false,
+    }));
+    assert!(exchange_until_direct_message(&mut alex, &mut billy).is_none());
+
+    // Bigger than what alex accepts: alex refuses to send it
+    let req_id = alex.send_direct_message(&BILLY_AGENT_ID, vec![42; 128 * FRAGMENT_SIZE + 1]);
+    let (_, srv_msg_list) = alex.process().unwrap();
+    assert_eq!(srv_msg_list.len(), 1);
+    let msg = &srv_msg_list[0];
+    one_let!(Lib3hServerProtocol::FailureResult(response) = msg {
+        assert_eq!(response.request_id, req_id);
+    });
+}
+
+/// Process alex and billy in turn until billy receives a direct message
+fn exchange_until_direct_message(
+    alex: &mut NodeMock,
+    billy: &mut NodeMock,
+) -> Option<DirectMessageData> {
+    for _ in 0..100 {
+        alex.process().unwrap();
+        let (did_work, srv_msg_list) = billy.process().unwrap();
+        for msg in srv_msg_list {
+            if let Lib3hServerProtocol::HandleSendDirectMessage(msg) = msg {
+                return Some(msg);
+            }
+        }
+        if !did_work {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+    None
+}
+
+/// Billy and Camille each answer the HandleQueryEntry they receive, successfully or not
+fn billy_and_camille_answer(billy: &mut NodeMock, camille: &mut NodeMock) -> bool {
+    for node in vec![billy, camille] {
+        let maybe_request = node.wait(Box::new(one_is!(Lib3hServerProtocol::HandleQueryEntry(_))));
+        let request = match maybe_request {
+            Some(request) => request,
+            None => return false,
+        };
+        let query_data = unwrap_to!(request => Lib3hServerProtocol::HandleQueryEntry);
+        let _ = node.reply_to_HandleQueryEntry(&query_data);
+        node.process().unwrap();
+    }
+    true
+}
+
+#[test]
+fn test_two_memory_nodes_arc_gossip_catch_up() {
+    enable_logging_for_test(true);
+    let fn_name = "test_two_memory_nodes_arc_gossip_catch_up";
+    let mut alex_config = memory_config("alex", fn_name);
+    alex_config.gsp_arc_interval = 100;
+    let mut alex = NodeMock::new_with_config(
+        "alex",
+        ALEX_AGENT_ID.clone(),
+        alex_config,
+        construct_mock_engine,
+    );
+    // Alex publishes an entry while Billy is offline
+    alex.join_space(&SPACE_ADDRESS_A, true).unwrap();
+    alex.wait_until_no_work();
+    alex.author_entry(&ENTRY_ADDRESS_1, vec![ASPECT_CONTENT_1.clone()], true)
+        .unwrap();
+    alex.wait_until_no_work();
+
+    // Billy comes online and joins the space
+    let mut billy_config = memory_config("billy", fn_name);
+    billy_config.gsp_arc_interval = 100;
+    let mut billy = NodeMock::new_with_config(
+        "billy",
+        BILLY_AGENT_ID.clone(),
+        billy_config,
+        construct_mock_engine,
+    );
+    let connect_data = billy.connect_to(&alex.advertise()).unwrap();
+    wait_connect!(billy, connect_data, alex);
+    billy.join_space(&SPACE_ADDRESS_A, true).unwrap();
+
+    // Billy catches up through arc gossip
+    let mut maybe_stored_entry_address = None;
+    for _ in 0..50 {
+        let (_did_work, srv_msg_list) = billy.process().unwrap();
+        alex.process().unwrap();
+        for srv_msg in srv_msg_list {
+            if let Lib3hServerProtocol::HandleStoreEntryAspect(msg) = srv_msg {
+                maybe_stored_entry_address = Some(msg.entry_address);
+            }
+        }
+        if maybe_stored_entry_address.is_some() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    assert_eq!(maybe_stored_entry_address, Some(ENTRY_ADDRESS_1.clone()));
+}
+
+#[test]
+fn test_two_memory_nodes_bootstrap() {
+    enable_logging_for_test(true);
+    let fn_name = "test_two_memory_nodes_bootstrap";
+    let mut alex = setup_memory_node(
+        "alex",
+        ALEX_AGENT_ID.clone(),
+        fn_name,
+        construct_mock_engine,
+    );
+    // Billy only knows alex as bootstrap node
+    let mut billy_config = memory_config("billy", fn_name);
+    billy_config.bootstrap_nodes = vec![alex.advertise().to_string()];
+    let mut billy = NodeMock::new_with_config(
+        "billy",
+        BILLY_AGENT_ID.clone(),
+        billy_config,
+        construct_mock_engine,
+    );
+    let connect_data = ();
+    wait_connect!(billy, connect_data, alex);
+    // Billy should bootstrap again once alex comes back
+    alex.disconnect();
+    let disconnected = Box::new(Lib3hServerProtocolEquals(
+        Lib3hServerProtocol::Disconnected(lib3h_protocol::data_types::DisconnectedData {
+            network_id: NETWORK_A_ID.clone(),
+        }),
+    ));
+    assert_one_processed!(billy, alex, disconnected);
+    let connect_data = ();
+    wait_connect!(billy, connect_data, alex);
+}
+
+#[test]
+fn test_two_memory_nodes_bootstrap_past_a_dead_node() {
+    enable_logging_for_test(true);
+    let fn_name = "test_two_memory_nodes_bootstrap_past_a_dead_node";
+    let mut alex = setup_memory_node(
+        "alex",
+        ALEX_AGENT_ID.clone(),
+        fn_name,
+        construct_mock_engine,
+    );
+    // Billy's first bootstrap node is not running
+    let mut billy_config = memory_config("billy", fn_name);
+    billy_config.bootstrap_nodes = vec![
+        format!("mem://{}/dead", fn_name),
+        alex.advertise().to_string(),
+    ];
+    let mut billy = NodeMock::new_with_config(
+        "billy",
+        BILLY_AGENT_ID.clone(),
+        billy_config,
+        construct_mock_engine,
+    );
+    // It fails on its own without failing billy's processing
+    let mut is_connected = false;
+    for _ in 0..20 {
+        let (_, event_list) = billy
+            .process()
+            .expect("A dead bootstrap node should not fail process()");
+        is_connected = is_connected
+            || event_list.iter().any(|evt| match evt {
+                Lib3hServerProtocol::Connected(data) => data.uri == alex.advertise(),
+                _ => false,
+            });
+        alex.process().unwrap();
+        if is_connected {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!(is_connected);
+}
+
+#[test]
+fn test_memory_node_saves_dht_state_while_running() {
+    enable_logging_for_test(true);
+    let fn_name = "test_memory_node_saves_dht_state_while_running";
+    let work_dir = std::env::temp_dir().join(format!("lib3h_{}_{}", fn_name, std::process::id()));
+    let mut alex = setup_memory_node(
+        "alex",
+        ALEX_AGENT_ID.clone(),
+        fn_name,
+        construct_mock_engine,
+    );
+    let mut billy_config = memory_config("billy", fn_name);
+    billy_config.work_dir = work_dir.to_string_lossy().to_string();
+    billy_config.bootstrap_nodes = vec![alex.advertise().to_string()];
+    let mut billy = NodeMock::new_with_config(
+        "billy",
+        BILLY_AGENT_ID.clone(),
+        billy_config,
+        construct_mock_engine,
+    );
+    let connect_data = ();
+    wait_connect!(billy, connect_data, alex);
+    billy.wait_until_no_work();
+    // Stored without waiting for billy to shut down
+    assert!(work_dir.join("dht_state.bin").exists());
+    drop(billy);
+    std::fs::remove_dir_all(&work_dir).unwrap();
+}
+
+#[test]
+fn test_two_memory_nodes_of_different_networks() {
+    enable_logging_for_test(true);
+    let fn_name = "test_two_memory_nodes_of_different_networks";
+    let mut alex = setup_memory_node(
+        "alex",
+        ALEX_AGENT_ID.clone(),
+        fn_name,
+        construct_mock_engine,
+    );
+    // Billy dials alex for another network
+    let mut billy_config = memory_config("billy", fn_name);
+    billy_config.network_id = "net_B".to_string();
+    billy_config.bootstrap_nodes = vec![alex.advertise().to_string()];
+    let mut billy = NodeMock::new_with_config(
+        "billy",
+        BILLY_AGENT_ID.clone(),
+        billy_config,
+        construct_mock_engine,
+    );
+    // Handshakes are exchanged but neither side should consider itself connected
+    for _ in 0..10 {
+        let (_did_work, mut srv_msg_list) = billy.process().unwrap();
+        srv_msg_list.append(&mut alex.process().unwrap().1);
+        for srv_msg in srv_msg_list {
+            if let Lib3hServerProtocol::Connected(_) = srv_msg {
+                panic!("Nodes of different networks connected: {:?}", srv_msg);
+            }
+        }
     }
 }
 
 // Do general test with config
-fn launch_two_memory_nodes_test(test_fn: TwoNodesTestFn, can_setup: bool) -> Result<(), ()> {
+fn launch_two_memory_nodes_test(
+    test_fn: TwoNodesTestFn,
+    engine_factory: EngineFactory,
+    can_setup: bool,
+) -> Result<(), ()> {
     let test_fn_ptr = test_fn as *mut std::os::raw::c_void;
     println!("");
     print_test_name("IN-MEMORY TWO NODES TEST: ", test_fn_ptr);
     println!("========================");
 
     // Setup
-    let mut alex = setup_memory_node("alex", ALEX_AGENT_ID.clone(), &fn_name(test_fn_ptr));
-    let mut billy = setup_memory_node("billy", BILLY_AGENT_ID.clone(), &fn_name(test_fn_ptr));
+    let mut alex = setup_memory_node(
+        "alex",
+        ALEX_AGENT_ID.clone(),
+        &fn_name(test_fn_ptr),
+        engine_factory,
+    );
+    let mut billy = setup_memory_node(
+        "billy",
+        BILLY_AGENT_ID.clone(),
+        &fn_name(test_fn_ptr),
+        engine_factory,
+    );
     if can_setup {
         setup_two_nodes(&mut alex, &mut billy);
     }
@@ -222,9 +787,24 @@ fn launch_three_memory_nodes_test(test_fn: ThreeNodesTestFn, can_setup: bool) ->
     println!("==========================");
 
     // Setup
-    let mut alex = setup_memory_node("alex", ALEX_AGENT_ID.clone(), &fn_name(test_fn_ptr));
-    let mut billy = setup_memory_node("billy", BILLY_AGENT_ID.clone(), &fn_name(test_fn_ptr));
-    let mut camille = setup_memory_node("camille", CAMILLE_AGENT_ID.clone(), &fn_name(test_fn_ptr));
+    let mut alex = setup_memory_node(
+        "alex",
+        ALEX_AGENT_ID.clone(),
+        &fn_name(test_fn_ptr),
+        construct_mock_engine,
+    );
+    let mut billy = setup_memory_node(
+        "billy",
+        BILLY_AGENT_ID.clone(),
+        &fn_name(test_fn_ptr),
+        construct_mock_engine,
+    );
+    let mut camille = setup_memory_node(
+        "camille",
+        CAMILLE_AGENT_ID.clone(),
+        &fn_name(test_fn_ptr),
+        construct_mock_engine,
+    );
     if can_setup {
         setup_three_nodes(&mut alex, &mut billy, &mut camille);
     }
@@ -322,6 +902,14 @@ fn test_two_wss_tls_nodes_connection_suite() {
     }
 }
 
+#[test]
+fn test_two_wss_transport_id_tls_nodes_basic_suite() {
+    enable_logging_for_test(true);
+    for (test_fn, can_setup) in TWO_NODES_BASIC_TEST_FNS.iter() {
+        launch_two_wss_nodes_test(*test_fn, TlsConfig::TransportIdCertificate, *can_setup).unwrap();
+    }
+}
+
 // Do general test with config
 fn launch_two_wss_nodes_test(
     test_fn: TwoNodesTestFn,
@@ -418,3 +1006,38 @@ fn launch_three_wss_nodes_test(
     // Done
     Ok(())
 }
+
+// -- Framed Tcp Transport Tests --
+#[test]
+fn test_two_tcp_nodes_basic_suite() {
+    enable_logging_for_test(true);
+    for (test_fn, can_setup) in TWO_NODES_BASIC_TEST_FNS.iter() {
+        launch_two_tcp_nodes_test(*test_fn, *can_setup).unwrap();
+    }
+}
+
+// Do general test with config
+fn launch_two_tcp_nodes_test(test_fn: TwoNodesTestFn, can_setup: bool) -> Result<(), ()> {
+    let test_fn_ptr = test_fn as *mut std::os::raw::c_void;
+    println!("");
+    print_test_name("TCP TWO NODES TEST: ", test_fn_ptr);
+    println!("========================");
+
+    // Setup
+    let mut alex = setup_tcp_node("alex", ALEX_AGENT_ID.clone(), &fn_name(test_fn_ptr));
+    let mut billy = setup_tcp_node("billy", BILLY_AGENT_ID.clone(), &fn_name(test_fn_ptr));
+    assert_eq!(alex.advertise().scheme(), "tcp");
+    if can_setup {
+        setup_two_nodes(&mut alex, &mut billy);
+    }
+
+    // Execute test
+    test_fn(&mut alex, &mut billy);
+
+    // Wrap-up test
+    println!("========================");
+    print_test_name("TCP TWO NODES TEST END: ", test_fn_ptr);
+
+    // Done
+    Ok(())
+}
@@ -197,6 +197,11 @@ impl NodeMock {
     pub fn has_joined(&self, space_address: &Address) -> bool {
         self.joined_space_list.contains(space_address)
     }
+
+    /// Reject any HandleStoreEntryAspect of that aspect from now on
+    pub fn set_aspect_invalid(&mut self, aspect_address: &Address) {
+        self.invalid_aspect_set.insert(aspect_address.clone());
+    }
 }
 
 ///
@@ -332,6 +337,22 @@ impl NodeMock {
         query_data
     }
 
+    /// Node asks for some entry data, from the network if it does not hold it.
+    pub fn fetch_entry(&mut self, entry_address: Address) -> FetchEntryData {
+        let current_space = self.current_space.clone().expect("Current Space not set");
+        let fetch_data = FetchEntryData {
+            space_address: current_space,
+            entry_address,
+            request_id: self.generate_request_id(),
+            provider_agent_id: self.agent_id.clone(),
+            aspect_address_list: None,
+        };
+        self.engine
+            .post(Lib3hClientProtocol::FetchEntry(fetch_data.clone()).into())
+            .expect("Posting FetchEntry failed");
+        fetch_data
+    }
+
     ///
     pub fn reply_to_HandleQueryEntry(
         &mut self,
@@ -753,9 +774,25 @@ impl NodeMock {
                 // no-op
             }
             // HandleStoreEntryAspect: Network is asking us to store some aspect
-            // Accept if we joined that space and tell our Lib3h that we are holding it.
+            // Accept if we joined that space and the aspect is not invalid.
             Lib3hServerProtocol::HandleStoreEntryAspect(msg) => {
                 if self.has_joined(&msg.space_address) {
+                    let mut response = GenericResultData {
+                        space_address: msg.space_address.clone(),
+                        request_id: msg.request_id.clone(),
+                        to_agent_id: self.agent_id.clone(),
+                        result_info: Vec::new(),
+                    };
+                    if self
+                        .invalid_aspect_set
+                        .contains(&msg.entry_aspect.aspect_address)
+                    {
+                        response.result_info = "Invalid aspect".as_bytes().to_vec();
+                        self.engine
+                            .post(Lib3hClientProtocol::FailureResult(response))
+                            .expect("Engine.post() can't fail");
+                        return;
+                    }
                     // Store data in local datastore
                     let chain_store = self
                         .chain_store_list
@@ -769,16 +806,8 @@ impl NodeMock {
                         msg.entry_aspect.aspect_address,
                         res.is_ok()
                     );
-                    let provided_entry = ProvidedEntryData {
-                        space_address: msg.space_address.clone(),
-                        provider_agent_id: msg.provider_agent_id.clone(),
-                        entry: EntryData {
-                            entry_address: msg.entry_address.clone(),
-                            aspect_list: vec![msg.entry_aspect.clone()],
-                        },
-                    };
                     self.engine
-                        .post(Lib3hClientProtocol::HoldEntry(provided_entry))
+                        .post(Lib3hClientProtocol::SuccessResult(response))
                         .expect("Engine.post() can't fail");
                 }
             }
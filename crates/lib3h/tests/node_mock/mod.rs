@@ -49,6 +49,8 @@ pub struct NodeMock {
     joined_space_list: HashSet<Address>,
     /// Space currently in use
     pub current_space: Option<Address>,
+    /// Aspects to reject when asked to store them
+    invalid_aspect_set: HashSet<Address>,
 }
 
 /// Constructors
@@ -79,6 +81,7 @@ impl NodeMock {
             chain_store_list: HashMap::new(),
             joined_space_list: HashSet::new(),
             current_space: None,
+            invalid_aspect_set: HashSet::new(),
             my_advertise,
             name: name.to_string(),
             connected_list: HashSet::new(),
@@ -255,13 +255,11 @@ fn test_author_and_hold(alex: &mut NodeMock, billy: &mut NodeMock, camille: &mut
         camille.name(),
         enty_address_str
     );
-    let query_data = camille.request_entry(entry_1.entry_address.clone());
-    let (did_work, _srv_msg_list) = camille.process().unwrap();
-    assert!(did_work);
+    camille.request_entry(entry_1.entry_address.clone());
     // #fullsync
     // Billy sends that data back to the network
     println!("\n{} reply to own request:\n", camille.name());
-    let _ = camille.reply_to_HandleQueryEntry(&query_data).unwrap();
+    assert!(camille.wait_HandleQueryEntry_and_reply());
     let (did_work, srv_msg_list) = camille.process().unwrap();
     println!(
         "\n{} gets own response {:?}\n",
@@ -286,13 +284,11 @@ fn test_author_and_hold(alex: &mut NodeMock, billy: &mut NodeMock, camille: &mut
         camille.name(),
         enty_address_str
     );
-    let query_data = camille.request_entry(entry_2.entry_address.clone());
-    let (did_work, _srv_msg_list) = camille.process().unwrap();
-    assert!(did_work);
+    camille.request_entry(entry_2.entry_address.clone());
     // #fullsync
     // Billy sends that data back to the network
     println!("\n{} reply to own request:\n", camille.name());
-    let _ = camille.reply_to_HandleQueryEntry(&query_data).unwrap();
+    assert!(camille.wait_HandleQueryEntry_and_reply());
     let (did_work, srv_msg_list) = camille.process().unwrap();
     println!(
         "\n{} gets own response {:?}\n",
@@ -11,8 +11,10 @@ lazy_static! {
         (test_send_message, true),
         (test_send_message_fail, true),
         (test_hold_entry, true),
+        (test_fetch_remote_entry, true),
         (test_author_no_aspect, true),
         (test_author_one_aspect, true),
+        (test_reject_aspect, true),
         (test_author_two_aspects, true),
         (test_two_authors, true),
     ];
@@ -44,14 +46,12 @@ pub fn setup_two_nodes(mut alex: &mut NodeMock, mut billy: &mut NodeMock) {
 pub fn request_entry_ok(node: &mut NodeMock, entry: &EntryData) {
     let enty_address_str = &entry.entry_address;
     println!("\n{} requesting entry: {}\n", node.name(), enty_address_str);
-    let query_data = node.request_entry(entry.entry_address.clone());
-    let (did_work, _srv_msg_list) = node.process().unwrap();
-    assert!(did_work);
+    node.request_entry(entry.entry_address.clone());
 
     // #fullsync
     // Billy sends that data back to the network
     println!("\n{} reply to own request:\n", node.name());
-    let _ = node.reply_to_HandleQueryEntry(&query_data).unwrap();
+    assert!(node.wait_HandleQueryEntry_and_reply());
     let (did_work, srv_msg_list) = node.process().unwrap();
     println!("\n{} gets own response {:?}\n", node.name(), srv_msg_list);
     assert!(did_work);
@@ -200,6 +200,29 @@ pub fn test_author_one_aspect(alex: &mut NodeMock, billy: &mut NodeMock) {
     assert_eq!(res_info, "No entry found");
 }
 
+/// Test publish, Store rejected by Core
+fn test_reject_aspect(alex: &mut NodeMock, billy: &mut NodeMock) {
+    let entry = NodeMock::form_EntryData(&ENTRY_ADDRESS_1, vec![ASPECT_CONTENT_1.clone()]);
+    billy.set_aspect_invalid(&entry.aspect_list[0].aspect_address);
+    // Alex publish data on the network
+    let _ = alex
+        .author_entry(&ENTRY_ADDRESS_1, vec![ASPECT_CONTENT_1.clone()], true)
+        .unwrap();
+    let (did_work, srv_msg_list) = alex.process().unwrap();
+    assert!(did_work);
+    assert_eq!(srv_msg_list.len(), 0);
+
+    // Billy should receive the entry store request and reject it
+    let store_result = billy.wait(Box::new(one_is!(
+        Lib3hServerProtocol::HandleStoreEntryAspect(_)
+    )));
+    assert!(store_result.is_some());
+    // Process the FailureResult: aspect is not held so billy's DHT does not fetch it
+    let (did_work, srv_msg_list) = billy.process().unwrap();
+    assert!(did_work);
+    assert_eq!(srv_msg_list.len(), 0, "{:?}", srv_msg_list);
+}
+
 /// Test Hold & Query
 fn test_hold_entry(alex: &mut NodeMock, billy: &mut NodeMock) {
     // Alex holds an entry
@@ -237,6 +260,31 @@ fn test_hold_entry(alex: &mut NodeMock, billy: &mut NodeMock) {
     assert_eq!(res_info, "No entry found");
 }
 
+/// Test FetchEntry of an entry only the other node holds
+fn test_fetch_remote_entry(alex: &mut NodeMock, billy: &mut NodeMock) {
+    // Billy holds an entry but does not provide it for broadcasting
+    let entry = billy
+        .hold_entry(&ENTRY_ADDRESS_1, vec![ASPECT_CONTENT_1.clone()], true)
+        .unwrap();
+    let (did_work, _srv_msg_list) = billy.process().unwrap();
+    assert!(did_work);
+
+    // Alex does not hold it: the request goes to Billy
+    let fetch_data = alex.fetch_entry(ENTRY_ADDRESS_1.clone());
+    let (did_work, srv_msg_list) = alex.process().unwrap();
+    assert!(did_work);
+    assert_eq!(srv_msg_list.len(), 0, "{:?}", srv_msg_list);
+
+    // Billy's Core provides the entry
+    assert!(billy.wait_HandleFetchEntry_and_reply());
+
+    // Alex gets it back
+    let result = alex.wait(Box::new(one_is!(Lib3hServerProtocol::FetchEntryResult(_))));
+    let msg = unwrap_to!(result.unwrap() => Lib3hServerProtocol::FetchEntryResult);
+    assert_eq!(msg.request_id, fetch_data.request_id);
+    assert_eq!(msg.entry, entry);
+}
+
 /// Entry with no Aspect case: Should no-op
 fn test_author_no_aspect(alex: &mut NodeMock, billy: &mut NodeMock) {
     // Alex publish data on the network
@@ -1,7 +1,7 @@
 use crate::{
     node_mock::*,
     test_suites::two_basic::{test_author_one_aspect, test_send_message, TwoNodesTestFn},
-    utils::processor_harness::*,
+    utils::{constants::*, processor_harness::*},
 };
 use lib3h_protocol::protocol_server::Lib3hServerProtocol;
 
@@ -32,7 +32,7 @@ fn test_two_disconnect(alex: &mut NodeMock, billy: &mut NodeMock) {
     assert_eq!(srv_msg_list.len(), 1);
     let msg_1 = &srv_msg_list[0];
     one_let!(Lib3hServerProtocol::Disconnected(response) = msg_1 {
-        assert_eq!(response.network_id, "FIXME");
+        assert_eq!(response.network_id, *NETWORK_A_ID);
     });
 }
 
@@ -79,7 +79,7 @@ fn test_two_peer_timeout(alex: &mut NodeMock, billy: &mut NodeMock) {
     // Billy SHOULD send a PeerTimedOut message ...
     let processor = Box::new(Lib3hServerProtocolEquals(
         Lib3hServerProtocol::Disconnected(lib3h_protocol::data_types::DisconnectedData {
-            network_id: "FIXME".into(), // TODO
+            network_id: NETWORK_A_ID.clone(),
         }),
     ));
     assert_one_processed!(alex, billy, processor);
@@ -96,12 +96,12 @@ fn test_two_peer_timeout_reconnect(
     std::thread::sleep(std::time::Duration::from_millis(3100));
     let disconnect1 = Box::new(Lib3hServerProtocolEquals(
         Lib3hServerProtocol::Disconnected(lib3h_protocol::data_types::DisconnectedData {
-            network_id: "FIXME".into(), // TODO
+            network_id: NETWORK_A_ID.clone(),
         }),
     ));
     let disconnect2 = Box::new(Lib3hServerProtocolEquals(
         Lib3hServerProtocol::Disconnected(lib3h_protocol::data_types::DisconnectedData {
-            network_id: "FIXME".into(), // TODO
+            network_id: NETWORK_A_ID.clone(),
         }),
     ));
     assert_one_processed!(billy, alex, disconnect1);
@@ -134,7 +134,7 @@ fn test_two_reconnect(alex: &mut NodeMock, billy: &mut NodeMock) {
     assert_eq!(srv_msg_list.len(), 1);
     let msg_1 = &srv_msg_list[0];
     one_let!(Lib3hServerProtocol::Disconnected(response) = msg_1 {
-        assert_eq!(response.network_id, "FIXME");
+        assert_eq!(response.network_id, *NETWORK_A_ID);
     });
 
     println!("\n Reconnecting Alex...\n");
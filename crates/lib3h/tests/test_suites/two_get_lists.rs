@@ -89,13 +89,15 @@ pub fn empty_author_list_test(alex: &mut NodeMock, billy: &mut NodeMock) {
 
     // Billy asks for unpublished data.
     println!("\n{} requesting entry: ENTRY_ADDRESS_1\n", billy.name());
-    let query_data = billy.request_entry(ENTRY_ADDRESS_1.clone());
-    let (did_work, _srv_msg_list) = billy.process().unwrap();
-    assert!(did_work);
+    billy.request_entry(ENTRY_ADDRESS_1.clone());
 
     // #fullsync
     // Alex sends back a failureResult response to the network
     println!("\n{} looking for HandleQueryEntry\n", billy.name());
+    let request = billy
+        .wait(Box::new(one_is!(Lib3hServerProtocol::HandleQueryEntry(_))))
+        .expect("Should receive a HandleQueryEntry");
+    let query_data = unwrap_to!(request => Lib3hServerProtocol::HandleQueryEntry);
     let res = billy.reply_to_HandleQueryEntry(&query_data);
     println!("\n{} found: {:?}\n", billy.name(), res);
     assert!(res.is_err());
@@ -188,14 +190,12 @@ pub fn many_aspects_test(alex: &mut NodeMock, billy: &mut NodeMock) {
     // Billy asks for the entry
     // ========================
     println!("\n{} requesting entry: ENTRY_ADDRESS_1\n", billy.name());
-    let query_data = billy.request_entry(ENTRY_ADDRESS_1.clone());
-    let (did_work, _srv_msg_list) = billy.process().unwrap();
-    assert!(did_work);
+    billy.request_entry(ENTRY_ADDRESS_1.clone());
 
     // #fullsync
     // Billy sends that data back to the network
     println!("\n{} reply to own request:\n", billy.name());
-    let _ = billy.reply_to_HandleQueryEntry(&query_data).unwrap();
+    assert!(billy.wait_HandleQueryEntry_and_reply());
     let (did_work, srv_msg_list) = billy.process().unwrap();
     assert!(did_work);
     assert_eq!(srv_msg_list.len(), 1, "{:?}", srv_msg_list);
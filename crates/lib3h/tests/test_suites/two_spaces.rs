@@ -11,6 +11,7 @@ lazy_static! {
     pub static ref TWO_NODES_SPACES_TEST_FNS: Vec<(TwoNodesTestFn, bool)> = vec![
         (test_leave_space, true),
         (test_rejoining, true),
+        (test_leave_and_rejoin, true),
         (test_multispace_send, true),
         (test_multispace_dht, true),
     ];
@@ -100,6 +101,32 @@ pub fn test_rejoining(alex: &mut NodeMock, billy: &mut NodeMock) {
     });
 }
 
+/// A peer that left a space and joins it again should be reachable again
+pub fn test_leave_and_rejoin(alex: &mut NodeMock, billy: &mut NodeMock) {
+    // Alex LeaveSpace
+    let req_id = alex
+        .leave_current_space()
+        .expect("Failed sending LeaveSpace message on Alex");
+    assert_process_success!(alex, req_id);
+    // Billy receives the LeaveSpace notice
+    billy.wait_until_no_work();
+    // Alex re-joins
+    println!("\nAlex re-joins...\n");
+    let req_id = alex.join_space(&SPACE_ADDRESS_A.clone(), true).unwrap();
+    let (did_work, srv_msg_list) = alex.process().unwrap();
+    assert!(did_work);
+    assert_eq!(srv_msg_list.len(), 3);
+    let msg_1 = &srv_msg_list[0];
+    one_let!(Lib3hServerProtocol::SuccessResult(response) = msg_1 {
+        assert_eq!(response.request_id, req_id);
+    });
+    billy.wait_until_no_work();
+    alex.wait_until_no_work();
+    // Do some test
+    println!("\nTest send DirectMessage...\n");
+    test_send_message(alex, billy);
+}
+
 /// Sending a Message before doing a 'TrackDna' should fail
 pub fn test_multispace_send(alex: &mut NodeMock, billy: &mut NodeMock) {
     // Alex LeaveSpace
@@ -3,6 +3,7 @@
 /// times a necessary until success (up to a hard coded number of iterations, currently).
 use predicates::prelude::*;
 
+use crate::utils::constants::NETWORK_A_ID;
 use lib3h_protocol::{data_types::*, protocol_server::Lib3hServerProtocol};
 
 /// Represents all useful state after a single call to an engine's process function
@@ -345,6 +346,7 @@ pub fn is_connected(request_id: &str, uri: url::Url) -> Lib3hServerProtocolEqual
     Lib3hServerProtocolEquals(Lib3hServerProtocol::Connected(ConnectedData {
         request_id: request_id.into(),
         uri,
+        network_id: NETWORK_A_ID.clone(),
     }))
 }
 
@@ -400,6 +402,7 @@ macro_rules! wait_connect {
                 lib3h_protocol::data_types::ConnectedData {
                 uri: $other.advertise(),
                 request_id: "".to_string(), // TODO fix this bug and uncomment out! connect_data.clone().request_id
+                network_id: $crate::utils::constants::NETWORK_A_ID.clone(),
             });
             let predicate: Box<dyn $crate::utils::processor_harness::Processor> =
                 Box::new($crate::utils::processor_harness::Lib3hServerProtocolEquals(connected_data));
@@ -113,7 +113,8 @@ pub struct ConnectedData {
     /// The first uri we are connected to
     #[serde(with = "url_serde")]
     pub uri: Url,
-    // TODO #172 - Add network_id? Or let local client figure it out with the request_id?
+    /// The network we are connected to
+    pub network_id: String,
     // TODO #178 - Add some info on network state
     // pub peer_count: u32,
 }
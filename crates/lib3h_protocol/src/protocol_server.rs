@@ -36,6 +36,7 @@ pub enum Lib3hServerProtocol {
     /// Another node, or the network module itself is requesting data from us
     HandleFetchEntry(FetchEntryData),
     /// Store data on a node's dht arc.
+    /// Answer with a `SuccessResult` to hold it or a `FailureResult` to reject it.
     HandleStoreEntryAspect(StoreEntryAspectData),
     /// Local client does not need to hold that entry anymore.
     /// Local client doesn't 'have to' comply.
@@ -2,7 +2,29 @@
 
 use super::error::{MulticastDnsError, MulticastDnsResult};
 use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
-use std::io::Read;
+use std::{
+    collections::HashMap,
+    io::{Cursor, Read},
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+/// record type A
+const TYPE_A: u16 = 1;
+/// record type PTR
+const TYPE_PTR: u16 = 12;
+/// record type TXT
+const TYPE_TXT: u16 = 16;
+/// record type AAAA
+const TYPE_AAAA: u16 = 28;
+/// record type SRV
+const TYPE_SRV: u16 = 33;
+
+/// the two high bits of a label length mark a compression pointer
+const POINTER_MASK: u8 = 0xc0;
+/// compression pointers can only address the first 16k of a packet
+const MAX_POINTER_OFFSET: usize = 0x3fff;
+/// a compressed name cannot be made of more pointers than this
+const MAX_POINTER_JUMPS: usize = 32;
 
 /// SRV record within a question
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -21,11 +43,72 @@ pub struct SrvDataA {
     pub target: Vec<u8>,
 }
 
+/// PTR record within a question
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PtrDataQ {
+    pub name: Vec<u8>,
+}
+
+/// PTR record within an answer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PtrDataA {
+    pub name: Vec<u8>,
+    pub ttl_seconds: u32,
+    pub target: Vec<u8>,
+}
+
+/// TXT record within a question
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxtDataQ {
+    pub name: Vec<u8>,
+}
+
+/// TXT record within an answer
+/// each string is at most 255 bytes, usually of the form `key=value`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxtDataA {
+    pub name: Vec<u8>,
+    pub ttl_seconds: u32,
+    pub strings: Vec<Vec<u8>>,
+}
+
+/// A record within a question
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ADataQ {
+    pub name: Vec<u8>,
+}
+
+/// A record within an answer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ADataA {
+    pub name: Vec<u8>,
+    pub ttl_seconds: u32,
+    pub address: Ipv4Addr,
+}
+
+/// AAAA record within a question
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AaaaDataQ {
+    pub name: Vec<u8>,
+}
+
+/// AAAA record within an answer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AaaaDataA {
+    pub name: Vec<u8>,
+    pub ttl_seconds: u32,
+    pub address: Ipv6Addr,
+}
+
 /// query question
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Question {
     Unknown,
     Srv(SrvDataQ),
+    Ptr(PtrDataQ),
+    Txt(TxtDataQ),
+    A(ADataQ),
+    Aaaa(AaaaDataQ),
 }
 
 /// response answer
@@ -33,6 +116,76 @@ pub enum Question {
 pub enum Answer {
     Unknown(Vec<u8>),
     Srv(SrvDataA),
+    Ptr(PtrDataA),
+    Txt(TxtDataA),
+    A(ADataA),
+    Aaaa(AaaaDataA),
+}
+
+impl Question {
+    /// the name this question is about, None if unknown
+    pub fn name(&self) -> Option<&[u8]> {
+        match self {
+            Question::Unknown => None,
+            Question::Srv(q) => Some(&q.name),
+            Question::Ptr(q) => Some(&q.name),
+            Question::Txt(q) => Some(&q.name),
+            Question::A(q) => Some(&q.name),
+            Question::Aaaa(q) => Some(&q.name),
+        }
+    }
+
+    /// does `answer` answer this question
+    pub fn is_answered_by(&self, answer: &Answer) -> bool {
+        match (self, answer) {
+            (Question::Srv(q), Answer::Srv(a)) => q.name == a.name,
+            (Question::Ptr(q), Answer::Ptr(a)) => q.name == a.name,
+            (Question::Txt(q), Answer::Txt(a)) => q.name == a.name,
+            (Question::A(q), Answer::A(a)) => q.name == a.name,
+            (Question::Aaaa(q), Answer::Aaaa(a)) => q.name == a.name,
+            _ => false,
+        }
+    }
+}
+
+impl Answer {
+    /// the name of this record, None if unknown
+    pub fn name(&self) -> Option<&[u8]> {
+        match self {
+            Answer::Unknown(_) => None,
+            Answer::Srv(a) => Some(&a.name),
+            Answer::Ptr(a) => Some(&a.name),
+            Answer::Txt(a) => Some(&a.name),
+            Answer::A(a) => Some(&a.name),
+            Answer::Aaaa(a) => Some(&a.name),
+        }
+    }
+
+    /// how long this record can be cached, 0 if it is withdrawn
+    pub fn ttl_seconds(&self) -> u32 {
+        match self {
+            Answer::Unknown(_) => 0,
+            Answer::Srv(a) => a.ttl_seconds,
+            Answer::Ptr(a) => a.ttl_seconds,
+            Answer::Txt(a) => a.ttl_seconds,
+            Answer::A(a) => a.ttl_seconds,
+            Answer::Aaaa(a) => a.ttl_seconds,
+        }
+    }
+
+    /// copy of this record with another ttl
+    pub fn with_ttl_seconds(&self, ttl_seconds: u32) -> Self {
+        let mut out = self.clone();
+        match &mut out {
+            Answer::Unknown(_) => (),
+            Answer::Srv(a) => a.ttl_seconds = ttl_seconds,
+            Answer::Ptr(a) => a.ttl_seconds = ttl_seconds,
+            Answer::Txt(a) => a.ttl_seconds = ttl_seconds,
+            Answer::A(a) => a.ttl_seconds = ttl_seconds,
+            Answer::Aaaa(a) => a.ttl_seconds = ttl_seconds,
+        }
+        out
+    }
 }
 
 /// dns packet
@@ -42,6 +195,9 @@ pub struct Packet {
     pub is_query: bool,
     pub questions: Vec<Question>,
     pub answers: Vec<Answer>,
+    /// records a responder adds to help resolve its answers,
+    /// e.g. the SRV, TXT and A records of the service a PTR answer points to
+    pub additionals: Vec<Answer>,
 }
 
 impl Packet {
@@ -54,12 +210,13 @@ impl Packet {
             is_query: true,
             questions: vec![],
             answers: vec![],
+            additionals: vec![],
         }
     }
 
     /// parse a dns packet into a Packet struct
     pub fn with_raw(packet: &[u8]) -> MulticastDnsResult<Self> {
-        let mut read = std::io::Cursor::new(packet);
+        let mut read = Cursor::new(packet);
         let mut out = Packet::new();
 
         out.id = read.read_u16::<BigEndian>()?;
@@ -67,52 +224,37 @@ impl Packet {
 
         let question_count = read.read_u16::<BigEndian>()?;
         let answer_count = read.read_u16::<BigEndian>()?;
-
-        // nameserver count
-        read.read_u16::<BigEndian>()?;
-
-        // additional count
-        read.read_u16::<BigEndian>()?;
+        let nameserver_count = read.read_u16::<BigEndian>()?;
+        let additional_count = read.read_u16::<BigEndian>()?;
 
         for _ in 0..question_count {
-            let svc_name = read_qname(&mut read)?;
+            let name = read_qname(&mut read)?;
             let kind = read.read_u16::<BigEndian>()?;
             let _class = read.read_u16::<BigEndian>()?;
 
-            if kind == 33 {
-                out.questions
-                    .push(Question::Srv(SrvDataQ { name: svc_name }));
-            } else {
-                out.questions.push(Question::Unknown);
-            }
+            out.questions.push(match kind {
+                TYPE_SRV => Question::Srv(SrvDataQ { name }),
+                TYPE_PTR => Question::Ptr(PtrDataQ { name }),
+                TYPE_TXT => Question::Txt(TxtDataQ { name }),
+                TYPE_A => Question::A(ADataQ { name }),
+                TYPE_AAAA => Question::Aaaa(AaaaDataQ { name }),
+                _ => Question::Unknown,
+            });
         }
 
         for _ in 0..answer_count {
-            let svc_name = read_qname(&mut read)?;
-            let kind = read.read_u16::<BigEndian>()?;
-            let _class = read.read_u16::<BigEndian>()?;
-            let ttl_seconds = read.read_u32::<BigEndian>()?;
-
-            let enc_size = read.read_u16::<BigEndian>()? as usize;
-
-            if kind == 33 {
-                let priority = read.read_u16::<BigEndian>()?;
-                let weight = read.read_u16::<BigEndian>()?;
-                let port = read.read_u16::<BigEndian>()?;
-                let target = read_qname(&mut read)?;
-                out.answers.push(Answer::Srv(SrvDataA {
-                    name: svc_name,
-                    ttl_seconds,
-                    priority,
-                    weight,
-                    port,
-                    target,
-                }));
-            } else {
-                let mut raw = vec![0; enc_size];
-                read.read_exact(&mut raw)?;
-                out.answers.push(Answer::Unknown(raw));
-            }
+            let answer = read_answer(&mut read)?;
+            out.answers.push(answer);
+        }
+
+        // nameserver records are not used by mdns, skip them
+        for _ in 0..nameserver_count {
+            read_answer(&mut read)?;
+        }
+
+        for _ in 0..additional_count {
+            let answer = read_answer(&mut read)?;
+            out.additionals.push(answer);
         }
 
         Ok(out)
@@ -122,6 +264,9 @@ impl Packet {
     pub fn to_raw(&self) -> MulticastDnsResult<Vec<u8>> {
         let mut out = Vec::with_capacity(500);
 
+        // offset of the names already written, for compression
+        let mut names = HashMap::new();
+
         // id
         out.write_u16::<BigEndian>(self.id)?;
 
@@ -141,87 +286,228 @@ impl Packet {
         // unimplemented nameserver count
         out.write_u16::<BigEndian>(0)?;
 
-        // unimplemented additional count
-        out.write_u16::<BigEndian>(0)?;
+        // additional count
+        out.write_u16::<BigEndian>(self.additionals.len() as u16)?;
 
         // add questions
         for q in self.questions.iter() {
-            match q {
+            let (name, kind) = match q {
                 Question::Unknown => {
                     return Err(MulticastDnsError::Generic(
                         "unknown question type".to_string(),
                     ));
                 }
-                Question::Srv(q) => {
-                    write_qname(&mut out, &q.name)?;
-
-                    // type SRV
-                    out.write_u16::<BigEndian>(33)?;
-
-                    // class IN (prefer broadcast)
-                    out.write_u16::<BigEndian>(1)?;
-                    // class IN (prefer unicast)
-                    //out.write_u16::<BigEndian>(1 | 0x8000)?;
-                    // class Any (prefer broadcast)
-                    //out.write_u16::<BigEndian>(255)?;
-                }
+                Question::Srv(q) => (&q.name, TYPE_SRV),
+                Question::Ptr(q) => (&q.name, TYPE_PTR),
+                Question::Txt(q) => (&q.name, TYPE_TXT),
+                Question::A(q) => (&q.name, TYPE_A),
+                Question::Aaaa(q) => (&q.name, TYPE_AAAA),
+            };
+
+            write_compressed_qname(&mut out, name, &mut names)?;
+
+            out.write_u16::<BigEndian>(kind)?;
+
+            // class IN (prefer broadcast)
+            out.write_u16::<BigEndian>(1)?;
+            // class IN (prefer unicast)
+            //out.write_u16::<BigEndian>(1 | 0x8000)?;
+            // class Any (prefer broadcast)
+            //out.write_u16::<BigEndian>(255)?;
+        }
+
+        // add answers, then additional records
+        for a in self.answers.iter().chain(self.additionals.iter()) {
+            write_answer(&mut out, a, &mut names)?;
+        }
+
+        Ok(out)
+    }
+}
+
+/// parse a resource record
+fn read_answer(read: &mut Cursor<&[u8]>) -> MulticastDnsResult<Answer> {
+    let name = read_qname(read)?;
+    let kind = read.read_u16::<BigEndian>()?;
+    let _class = read.read_u16::<BigEndian>()?;
+    let ttl_seconds = read.read_u32::<BigEndian>()?;
+
+    let enc_size = read.read_u16::<BigEndian>()? as usize;
+    let data_start = read.position() as usize;
+    let data_end = data_start + enc_size;
+    if data_end > read.get_ref().len() {
+        return Err(MulticastDnsError::Generic(
+            "record data exceeds packet".to_string(),
+        ));
+    }
+
+    let answer = match kind {
+        TYPE_SRV => {
+            let priority = read.read_u16::<BigEndian>()?;
+            let weight = read.read_u16::<BigEndian>()?;
+            let port = read.read_u16::<BigEndian>()?;
+            let target = read_qname(read)?;
+            Answer::Srv(SrvDataA {
+                name,
+                ttl_seconds,
+                priority,
+                weight,
+                port,
+                target,
+            })
+        }
+        TYPE_PTR => {
+            let target = read_qname(read)?;
+            Answer::Ptr(PtrDataA {
+                name,
+                ttl_seconds,
+                target,
+            })
+        }
+        TYPE_TXT => {
+            let mut strings = Vec::new();
+            while (read.position() as usize) < data_end {
+                let len = read.read_u8()? as usize;
+                let mut string = vec![0; len];
+                read.read_exact(&mut string)?;
+                strings.push(string);
             }
+            Answer::Txt(TxtDataA {
+                name,
+                ttl_seconds,
+                strings,
+            })
+        }
+        TYPE_A => {
+            let mut octets = [0; 4];
+            read.read_exact(&mut octets)?;
+            Answer::A(ADataA {
+                name,
+                ttl_seconds,
+                address: Ipv4Addr::from(octets),
+            })
+        }
+        TYPE_AAAA => {
+            let mut octets = [0; 16];
+            read.read_exact(&mut octets)?;
+            Answer::Aaaa(AaaaDataA {
+                name,
+                ttl_seconds,
+                address: Ipv6Addr::from(octets),
+            })
+        }
+        _ => {
+            let mut raw = vec![0; enc_size];
+            read.read_exact(&mut raw)?;
+            Answer::Unknown(raw)
         }
+    };
 
-        // add answers
-        for a in self.answers.iter() {
-            match a {
-                Answer::Unknown(_) => {
-                    return Err(MulticastDnsError::Generic(
-                        "unknown question type".to_string(),
-                    ));
-                }
-                Answer::Srv(a) => {
-                    write_qname(&mut out, &a.name)?;
+    if read.position() as usize != data_end {
+        return Err(MulticastDnsError::Generic(
+            "record data length mismatch".to_string(),
+        ));
+    }
 
-                    // type SRV
-                    out.write_u16::<BigEndian>(33)?;
+    Ok(answer)
+}
 
-                    // class IN (prefer broadcast)
-                    out.write_u16::<BigEndian>(1)?;
-                    // class IN (prefer unicast)
-                    //out.write_u16::<BigEndian>(1 | 0x8000)?;
-                    // class Any (prefer broadcast)
-                    //out.write_u16::<BigEndian>(255)?;
+/// encode a resource record
+fn write_answer(
+    out: &mut Vec<u8>,
+    a: &Answer,
+    names: &mut HashMap<Vec<u8>, u16>,
+) -> MulticastDnsResult<()> {
+    let (name, kind, ttl_seconds) = match a {
+        Answer::Unknown(_) => {
+            return Err(MulticastDnsError::Generic(
+                "unknown answer type".to_string(),
+            ));
+        }
+        Answer::Srv(a) => (&a.name, TYPE_SRV, a.ttl_seconds),
+        Answer::Ptr(a) => (&a.name, TYPE_PTR, a.ttl_seconds),
+        Answer::Txt(a) => (&a.name, TYPE_TXT, a.ttl_seconds),
+        Answer::A(a) => (&a.name, TYPE_A, a.ttl_seconds),
+        Answer::Aaaa(a) => (&a.name, TYPE_AAAA, a.ttl_seconds),
+    };
+
+    write_compressed_qname(out, name, names)?;
+
+    out.write_u16::<BigEndian>(kind)?;
 
-                    // ttl
-                    out.write_u32::<BigEndian>(a.ttl_seconds)?;
+    // class IN (prefer broadcast)
+    out.write_u16::<BigEndian>(1)?;
+    // class IN (prefer unicast)
+    //out.write_u16::<BigEndian>(1 | 0x8000)?;
+    // class Any (prefer broadcast)
+    //out.write_u16::<BigEndian>(255)?;
 
-                    // srv len (will get set after write_qname)
-                    let len_offset = out.len();
-                    out.write_u16::<BigEndian>(0)?;
+    // ttl
+    out.write_u32::<BigEndian>(ttl_seconds)?;
 
-                    // priority
-                    out.write_u16::<BigEndian>(a.priority)?;
+    // data len (will get set after writing the data)
+    let len_offset = out.len();
+    out.write_u16::<BigEndian>(0)?;
 
-                    // weight
-                    out.write_u16::<BigEndian>(a.weight)?;
+    match a {
+        Answer::Unknown(_) => unreachable!(),
+        Answer::Srv(a) => {
+            // priority
+            out.write_u16::<BigEndian>(a.priority)?;
 
-                    // port
-                    out.write_u16::<BigEndian>(a.port)?;
+            // weight
+            out.write_u16::<BigEndian>(a.weight)?;
 
-                    // target
-                    let len = write_qname(&mut out, &a.target)?;
+            // port
+            out.write_u16::<BigEndian>(a.port)?;
 
-                    BigEndian::write_u16(&mut out[len_offset..len_offset + 2], len + 6);
+            // target (RFC 2782: no compression)
+            write_qname(out, &a.target)?;
+        }
+        Answer::Ptr(a) => {
+            write_compressed_qname(out, &a.target, names)?;
+        }
+        Answer::Txt(a) => {
+            for string in a.strings.iter() {
+                if string.len() > 255 {
+                    return Err(MulticastDnsError::Generic(
+                        "txt string too long".to_string(),
+                    ));
                 }
+                out.write_u8(string.len() as u8)?;
+                out.extend_from_slice(string);
+            }
+            // RFC 6763: a TXT record cannot be empty
+            if a.strings.is_empty() {
+                out.write_u8(0)?;
             }
         }
-
-        Ok(out)
+        Answer::A(a) => {
+            out.extend_from_slice(&a.address.octets());
+        }
+        Answer::Aaaa(a) => {
+            out.extend_from_slice(&a.address.octets());
+        }
     }
+
+    let len = out.len() - len_offset - 2;
+    BigEndian::write_u16(&mut out[len_offset..len_offset + 2], len as u16);
+
+    Ok(())
 }
 
 /// write a dot-notation dns name into bytecode parts
 fn write_qname<T: byteorder::WriteBytesExt>(out: &mut T, data: &[u8]) -> MulticastDnsResult<u16> {
     let mut len = 0;
 
+    // root name
+    if data.is_empty() {
+        out.write_u8(0)?;
+        return Ok(1);
+    }
+
     for part in data.split(|&c| c == b'.') {
+        check_label(part)?;
         out.write_u8(part.len() as u8)?;
         len += 1;
         for c in part.iter() {
@@ -236,12 +522,98 @@ fn write_qname<T: byteorder::WriteBytesExt>(out: &mut T, data: &[u8]) -> Multica
     Ok(len)
 }
 
+/// write a dot-notation dns name into bytecode parts,
+/// pointing to an already written name for its longest known suffix
+fn write_compressed_qname(
+    out: &mut Vec<u8>,
+    data: &[u8],
+    names: &mut HashMap<Vec<u8>, u16>,
+) -> MulticastDnsResult<()> {
+    let mut suffix = data;
+    // root name
+    if suffix.is_empty() {
+        out.write_u8(0)?;
+        return Ok(());
+    }
+    loop {
+        if let Some(offset) = names.get(suffix) {
+            out.write_u16::<BigEndian>(((POINTER_MASK as u16) << 8) | offset)?;
+            return Ok(());
+        }
+        if out.len() <= MAX_POINTER_OFFSET {
+            names.insert(suffix.to_vec(), out.len() as u16);
+        }
+        let (part, rest) = match suffix.iter().position(|&c| c == b'.') {
+            Some(dot) => (&suffix[..dot], Some(&suffix[dot + 1..])),
+            None => (suffix, None),
+        };
+        check_label(part)?;
+        out.write_u8(part.len() as u8)?;
+        out.extend_from_slice(part);
+        match rest {
+            Some(rest) => suffix = rest,
+            None => break,
+        }
+    }
+
+    out.write_u8(0)?;
+
+    Ok(())
+}
+
+/// a label must fit its length byte without looking like a pointer,
+/// and cannot be empty as an empty label ends the name
+fn check_label(part: &[u8]) -> MulticastDnsResult<()> {
+    if part.is_empty() || part.len() > 63 {
+        return Err(MulticastDnsError::Generic(format!(
+            "invalid dns label length: {}",
+            part.len()
+        )));
+    }
+    Ok(())
+}
+
 /// read raw dns bytecode part name into dot-notation Vec<u8>
-fn read_qname<T: byteorder::ReadBytesExt>(read: &mut T) -> MulticastDnsResult<Vec<u8>> {
+/// following compression pointers within the packet
+fn read_qname(read: &mut Cursor<&[u8]>) -> MulticastDnsResult<Vec<u8>> {
+    let packet = *read.get_ref();
     let mut out = Vec::with_capacity(500);
+    // where the cursor continues once we followed a pointer
+    let mut resume_at = None;
+    let mut pos = read.position() as usize;
+    let mut jump_count = 0;
 
     loop {
-        let len = read.read_u8()? as usize;
+        let len = *packet
+            .get(pos)
+            .ok_or_else(|| MulticastDnsError::Generic("name exceeds packet".to_string()))?;
+        pos += 1;
+
+        if len & POINTER_MASK == POINTER_MASK {
+            let low = *packet
+                .get(pos)
+                .ok_or_else(|| MulticastDnsError::Generic("name exceeds packet".to_string()))?;
+            pos += 1;
+            jump_count += 1;
+            if jump_count > MAX_POINTER_JUMPS {
+                return Err(MulticastDnsError::Generic(
+                    "too many name pointers".to_string(),
+                ));
+            }
+            if resume_at.is_none() {
+                resume_at = Some(pos);
+            }
+            pos = (((len & !POINTER_MASK) as usize) << 8) | low as usize;
+            continue;
+        }
+
+        if len & POINTER_MASK != 0 {
+            return Err(MulticastDnsError::Generic(
+                "unsupported label type".to_string(),
+            ));
+        }
+
+        let len = len as usize;
         if len == 0 {
             break;
         }
@@ -250,11 +622,15 @@ fn read_qname<T: byteorder::ReadBytesExt>(read: &mut T) -> MulticastDnsResult<Ve
             out.push(46);
         }
 
-        let olen = out.len();
-        out.resize(olen + len, 0);
-        read.read_exact(&mut out[olen..olen + len])?;
+        let label = packet
+            .get(pos..pos + len)
+            .ok_or_else(|| MulticastDnsError::Generic("name exceeds packet".to_string()))?;
+        out.extend_from_slice(label);
+        pos += len;
     }
 
+    read.set_position(resume_at.unwrap_or(pos) as u64);
+
     Ok(out)
 }
 
@@ -298,4 +674,103 @@ mod tests {
         );
         assert_eq!(packet, Packet::with_raw(&raw).unwrap());
     }
+
+    #[test]
+    fn it_should_ptr_txt_a_aaaa_q() {
+        let mut packet = Packet::new();
+        packet.id = 0xbdbd;
+        packet.is_query = true;
+        packet.questions.push(Question::Ptr(PtrDataQ {
+            name: b"_svc._udp.local".to_vec(),
+        }));
+        packet.questions.push(Question::Txt(TxtDataQ {
+            name: b"node._svc._udp.local".to_vec(),
+        }));
+        packet.questions.push(Question::A(ADataQ {
+            name: b"host.local".to_vec(),
+        }));
+        packet.questions.push(Question::Aaaa(AaaaDataQ {
+            name: b"host.local".to_vec(),
+        }));
+        let raw = packet.to_raw().unwrap();
+        assert_eq!(packet, Packet::with_raw(&raw).unwrap());
+    }
+
+    #[test]
+    fn it_should_ptr_txt_a_aaaa_a() {
+        let mut packet = Packet::new();
+        packet.id = 0xbdbd;
+        packet.is_query = false;
+        packet.answers.push(Answer::Ptr(PtrDataA {
+            name: b"_svc._udp.local".to_vec(),
+            ttl_seconds: 120,
+            target: b"node._svc._udp.local".to_vec(),
+        }));
+        packet.additionals.push(Answer::Txt(TxtDataA {
+            name: b"node._svc._udp.local".to_vec(),
+            ttl_seconds: 120,
+            strings: vec![b"tid=HcMnode".to_vec(), b"nid=test".to_vec(), vec![]],
+        }));
+        packet.additionals.push(Answer::A(ADataA {
+            name: b"host.local".to_vec(),
+            ttl_seconds: 120,
+            address: Ipv4Addr::new(192, 168, 0, 2),
+        }));
+        packet.additionals.push(Answer::Aaaa(AaaaDataA {
+            name: b"host.local".to_vec(),
+            ttl_seconds: 120,
+            address: "fe80::1".parse().unwrap(),
+        }));
+        let raw = packet.to_raw().unwrap();
+        assert_eq!(packet, Packet::with_raw(&raw).unwrap());
+    }
+
+    #[test]
+    fn it_should_compress_names() {
+        let mut packet = Packet::new();
+        packet.is_query = false;
+        packet.answers.push(Answer::Ptr(PtrDataA {
+            name: b"_svc._udp.local".to_vec(),
+            ttl_seconds: 120,
+            target: b"node._svc._udp.local".to_vec(),
+        }));
+        let raw = packet.to_raw().unwrap();
+        // "node" label followed by a pointer to offset 12, the owner name
+        assert!(raw.ends_with(&[0, 7, 4, b'n', b'o', b'd', b'e', 0xc0, 12]));
+        assert_eq!(packet, Packet::with_raw(&raw).unwrap());
+    }
+
+    #[test]
+    fn it_should_read_compressed_names() {
+        // answer whose SRV target points into its owner name
+        let raw = vec![
+            0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 0, // header
+            3, b's', b'v', b'c', 5, b'l', b'o', b'c', b'a', b'l', 0, // owner name
+            0, 33, 0, 1, 0, 0, 0, 120, 0, 11, // type, class, ttl, len
+            0, 0, 0, 0, 0x14, 0xe9, // priority, weight, port
+            2, b'h', b'o', 0xc0, 16, // target
+        ];
+        let packet = Packet::with_raw(&raw).unwrap();
+        assert_eq!(
+            packet.answers[0],
+            Answer::Srv(SrvDataA {
+                name: b"svc.local".to_vec(),
+                ttl_seconds: 120,
+                priority: 0,
+                weight: 0,
+                port: 5353,
+                target: b"ho.local".to_vec(),
+            })
+        );
+    }
+
+    #[test]
+    fn it_should_reject_pointer_loops() {
+        let raw = vec![
+            0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, // header
+            0xc0, 12, // name pointing to itself
+            0, 12, 0, 1,
+        ];
+        assert!(Packet::with_raw(&raw).is_err());
+    }
 }
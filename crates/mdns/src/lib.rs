@@ -13,8 +13,9 @@ const READ_BUF_SIZE: usize = 4_096;
 
 #[cfg(not(target_os = "windows"))]
 use net2::unix::UnixUdpBuilderExt;
+use net2::UdpSocketExt;
 
-use std::net::ToSocketAddrs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6};
 
 pub mod error;
 pub use error::{MulticastDnsError, MulticastDnsResult};
@@ -22,14 +23,28 @@ pub use error::{MulticastDnsError, MulticastDnsResult};
 pub mod dns;
 pub use dns::*;
 
+pub mod service;
+pub use service::{MdnsEvent, MdnsService, RecordCache};
+
+/// the mdns IPv4 multicast group
+pub const MULTICAST_ADDRESS_V4: &str = "224.0.0.251";
+
+/// the link-local mdns IPv6 multicast group
+pub const MULTICAST_ADDRESS_V6: &str = "ff02::fb";
+
 /// mdns configuration
 #[derive(Clone, Debug)]
 pub struct Config {
     pub bind_address: String,
     pub bind_port: u16,
     pub multicast_loop: bool,
+    /// ttl of IPv4 packets, hop limit of IPv6 packets
     pub multicast_ttl: u32,
     pub multicast_address: String,
+    /// index of the network interface to join the IPv6 group on, 0 for the default one
+    pub multicast_interface: u32,
+    /// also run mdns over the other IP version, on all interfaces with its default group
+    pub dual_stack: bool,
 }
 
 /// mdns builder
@@ -46,7 +61,9 @@ impl Builder {
                 bind_port: 5353,
                 multicast_loop: true,
                 multicast_ttl: 255,
-                multicast_address: "224.0.0.251".to_string(),
+                multicast_address: MULTICAST_ADDRESS_V4.to_string(),
+                multicast_interface: 0,
+                dual_stack: false,
             },
         }
     }
@@ -75,57 +92,131 @@ impl Builder {
         self
     }
 
-    /// set the multicast address
+    /// set the multicast address, of the same IP version as the bind address
     pub fn set_multicast_address(&mut self, address: &str) -> &mut Self {
         self.config.multicast_address = address.to_string();
         self
     }
 
+    /// specify the index of the network interface to join the IPv6 group on
+    pub fn set_multicast_interface(&mut self, interface: u32) -> &mut Self {
+        self.config.multicast_interface = interface;
+        self
+    }
+
+    /// should we also run over the other IP version?
+    pub fn set_dual_stack(&mut self, dual_stack: bool) -> &mut Self {
+        self.config.dual_stack = dual_stack;
+        self
+    }
+
     /// construct the actual mdns struct
     pub fn build(&mut self) -> Result<MulticastDns, MulticastDnsError> {
         MulticastDns::new(self.config.clone())
     }
 }
 
+/// a udp socket joined to a multicast group
+struct MulticastSocket {
+    socket: std::net::UdpSocket,
+    group: SocketAddr,
+}
+
+impl MulticastSocket {
+    /// bind and join `multicast_address`, both of the same IP version
+    fn new(
+        config: &Config,
+        bind_address: IpAddr,
+        multicast_address: IpAddr,
+    ) -> Result<Self, MulticastDnsError> {
+        let socket = create_socket(&bind_address, config.bind_port)?;
+        socket.set_nonblocking(true)?;
+
+        let group = match (bind_address, multicast_address) {
+            (IpAddr::V4(bind_address), IpAddr::V4(multicast_address)) => {
+                socket.set_multicast_loop_v4(config.multicast_loop)?;
+                socket.set_multicast_ttl_v4(config.multicast_ttl)?;
+                socket.join_multicast_v4(&multicast_address, &bind_address)?;
+                SocketAddr::new(multicast_address.into(), config.bind_port)
+            }
+            (IpAddr::V6(_), IpAddr::V6(multicast_address)) => {
+                socket.set_multicast_loop_v6(config.multicast_loop)?;
+                socket.set_multicast_hops_v6(config.multicast_ttl)?;
+                if config.multicast_interface != 0 {
+                    socket.set_multicast_if_v6(config.multicast_interface)?;
+                }
+                socket.join_multicast_v6(&multicast_address, config.multicast_interface)?;
+                // link-local groups need the interface as scope
+                SocketAddr::V6(SocketAddrV6::new(
+                    multicast_address,
+                    config.bind_port,
+                    0,
+                    config.multicast_interface,
+                ))
+            }
+            _ => {
+                return Err(MulticastDnsError::Generic(format!(
+                    "bind address {} and multicast address {} are not of the same IP version",
+                    bind_address, multicast_address,
+                )));
+            }
+        };
+
+        Ok(MulticastSocket { socket, group })
+    }
+}
+
 /// an mdns instance that can send and receive dns packets on LAN UDP multicast
 pub struct MulticastDns {
-    config: Config,
-    socket: std::net::UdpSocket,
+    /// one socket per IP version we run over
+    sockets: Vec<MulticastSocket>,
     read_buf: [u8; READ_BUF_SIZE],
 }
 
 impl MulticastDns {
     /// create a new mdns struct instance
     pub fn new(config: Config) -> Result<Self, MulticastDnsError> {
-        let socket = create_socket(&config.bind_address, config.bind_port)?;
-
-        socket.set_nonblocking(true)?;
-        socket.set_multicast_loop_v4(config.multicast_loop)?;
-        socket.set_multicast_ttl_v4(config.multicast_ttl)?;
-        socket.join_multicast_v4(
-            &config.multicast_address.parse()?,
-            &config.bind_address.parse()?,
-        )?;
+        let bind_address: IpAddr = config.bind_address.parse()?;
+        let multicast_address: IpAddr = config.multicast_address.parse()?;
+
+        let mut sockets = vec![MulticastSocket::new(
+            &config,
+            bind_address,
+            multicast_address,
+        )?];
+
+        if config.dual_stack {
+            let (other_bind_address, other_multicast_address) = match multicast_address {
+                IpAddr::V4(_) => (
+                    IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+                    MULTICAST_ADDRESS_V6.parse()?,
+                ),
+                IpAddr::V6(_) => (
+                    IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                    MULTICAST_ADDRESS_V4.parse()?,
+                ),
+            };
+            sockets.push(MulticastSocket::new(
+                &config,
+                other_bind_address,
+                other_multicast_address,
+            )?);
+        }
 
         Ok(MulticastDns {
-            config,
-            socket,
+            sockets,
             read_buf: [0; READ_BUF_SIZE],
         })
     }
 
     /// broadcast a dns packet
+    /// (on every IP version we run over)
     pub fn send(&mut self, packet: &Packet) -> Result<(), MulticastDnsError> {
-        let addr = (
-            self.config.multicast_address.as_ref(),
-            self.config.bind_port,
-        )
-            .to_socket_addrs()?
-            .next()?;
-
         let data = packet.to_raw()?;
 
-        self.socket.send_to(&data, &addr)?;
+        for s in self.sockets.iter() {
+            s.socket.send_to(&data, s.group)?;
+        }
 
         Ok(())
     }
@@ -133,40 +224,55 @@ impl MulticastDns {
     /// try to receive a dns packet
     /// will return None rather than blocking if none are queued
     pub fn recv(&mut self) -> Result<Option<Packet>, MulticastDnsError> {
-        let (read, _) = match self.socket.recv_from(&mut self.read_buf) {
-            Ok(r) => r,
-            Err(e) => {
-                if e.kind() == std::io::ErrorKind::WouldBlock {
-                    return Ok(None);
+        for s in self.sockets.iter() {
+            let (read, _) = match s.socket.recv_from(&mut self.read_buf) {
+                Ok(r) => r,
+                Err(e) => {
+                    if e.kind() == std::io::ErrorKind::WouldBlock {
+                        continue;
+                    }
+                    return Err(e.into());
                 }
-                return Err(e.into());
-            }
-        };
+            };
 
-        if read > 0 {
-            let packet = Packet::with_raw(&self.read_buf[0..read])?;
-            return Ok(Some(packet));
+            if read > 0 {
+                let packet = Packet::with_raw(&self.read_buf[0..read])?;
+                return Ok(Some(packet));
+            }
         }
 
         Ok(None)
     }
 }
 
+/// udp socket builder for the IP version of `addr`
+/// IPv6 sockets are IPv6 only, dual-stack uses one socket per version
+fn udp_builder(addr: &IpAddr) -> Result<net2::UdpBuilder, MulticastDnsError> {
+    match addr {
+        IpAddr::V4(_) => Ok(net2::UdpBuilder::new_v4()?),
+        IpAddr::V6(_) => {
+            let builder = net2::UdpBuilder::new_v6()?;
+            builder.only_v6(true)?;
+            Ok(builder)
+        }
+    }
+}
+
 /// non-windows udp socket bind
 #[cfg(not(target_os = "windows"))]
-fn create_socket(addr: &str, port: u16) -> Result<std::net::UdpSocket, MulticastDnsError> {
-    Ok(net2::UdpBuilder::new_v4()?
+fn create_socket(addr: &IpAddr, port: u16) -> Result<std::net::UdpSocket, MulticastDnsError> {
+    Ok(udp_builder(addr)?
         .reuse_address(true)?
         .reuse_port(true)?
-        .bind((addr, port))?)
+        .bind((*addr, port))?)
 }
 
 /// windows udp socket bind
 #[cfg(target_os = "windows")]
-fn create_socket(addr: &str, port: u16) -> Result<std::net::UdpSocket, MulticastDnsError> {
-    Ok(net2::UdpBuilder::new_v4()?
+fn create_socket(addr: &IpAddr, port: u16) -> Result<std::net::UdpSocket, MulticastDnsError> {
+    Ok(udp_builder(addr)?
         .reuse_address(true)?
-        .bind((addr, port))?)
+        .bind((*addr, port))?)
 }
 
 #[cfg(test)]
@@ -241,4 +347,67 @@ mod tests {
             _ => panic!("BAD TYPE"),
         }
     }
+
+    #[test]
+    fn it_should_loop_question_v6() {
+        let mut mdns = Builder::new()
+            .set_bind_address("::")
+            .set_bind_port(55002)
+            .set_multicast_loop(true)
+            .set_multicast_ttl(255)
+            .set_multicast_address(MULTICAST_ADDRESS_V6)
+            .set_multicast_interface(0)
+            .build()
+            .expect("build fail");
+
+        let mut packet = dns::Packet::new();
+        packet.is_query = true;
+        packet.questions.push(dns::Question::Srv(dns::SrvDataQ {
+            name: b"lib3h.test.service".to_vec(),
+        }));
+        mdns.send(&packet).expect("send fail");
+
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let resp = mdns.recv().expect("recv fail");
+
+        match resp.unwrap().questions[0] {
+            Question::Srv(ref q) => {
+                assert_eq!(b"lib3h.test.service".to_vec(), q.name);
+            }
+            _ => panic!("BAD TYPE"),
+        }
+    }
+
+    #[test]
+    fn it_should_loop_on_both_ip_versions() {
+        let mut mdns = Builder::new()
+            .set_bind_port(55003)
+            .set_multicast_loop(true)
+            .set_dual_stack(true)
+            .build()
+            .expect("build fail");
+
+        let mut packet = dns::Packet::new();
+        packet.is_query = true;
+        packet.questions.push(dns::Question::Srv(dns::SrvDataQ {
+            name: b"lib3h.test.service".to_vec(),
+        }));
+        mdns.send(&packet).expect("send fail");
+
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        // once per IP version
+        assert_eq!(Some(packet.clone()), mdns.recv().expect("recv fail"));
+        assert_eq!(Some(packet), mdns.recv().expect("recv fail"));
+        assert_eq!(None, mdns.recv().expect("recv fail"));
+    }
+
+    #[test]
+    fn it_should_reject_mixed_ip_versions() {
+        assert!(Builder::new()
+            .set_bind_address("0.0.0.0")
+            .set_bind_port(55004)
+            .set_multicast_address(MULTICAST_ADDRESS_V6)
+            .build()
+            .is_err());
+    }
 }
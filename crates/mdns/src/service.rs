@@ -0,0 +1,365 @@
+//! Continuous mdns service over a MulticastDns:
+//! a responder answering the queries for the records we register,
+//! and a browser periodically querying for the records we browse
+//! and caching the ones found until their ttl expires.
+
+use super::{dns, error::MulticastDnsResult, MulticastDns};
+use std::time::{Duration, Instant};
+
+/// most packets read by one `MdnsService::process()`
+const MAX_PACKETS_PER_PROCESS: usize = 64;
+
+/// event emitted by `MdnsService::process()`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MdnsEvent {
+    /// a record matching a browsed question was found
+    RecordAdded(dns::Answer),
+    /// a found record expired or was withdrawn by its owner
+    RecordRemoved(dns::Answer),
+}
+
+/// records found by browsing, kept until their ttl expires
+pub struct RecordCache {
+    /// records as last received, with their expiry
+    records: Vec<(dns::Answer, Instant)>,
+}
+
+impl RecordCache {
+    /// create an empty cache
+    pub fn new() -> Self {
+        RecordCache { records: vec![] }
+    }
+
+    /// add or refresh a record, a ttl of 0 removes it
+    pub fn insert(&mut self, answer: dns::Answer, now: Instant) -> Option<MdnsEvent> {
+        let key = answer.with_ttl_seconds(0);
+        let position = self
+            .records
+            .iter()
+            .position(|(cached, _)| cached.with_ttl_seconds(0) == key);
+
+        if answer.ttl_seconds() == 0 {
+            return position.map(|i| MdnsEvent::RecordRemoved(self.records.remove(i).0));
+        }
+
+        let expiry = now + Duration::from_secs(u64::from(answer.ttl_seconds()));
+        match position {
+            Some(i) => {
+                self.records[i] = (answer, expiry);
+                None
+            }
+            None => {
+                self.records.push((answer.clone(), expiry));
+                Some(MdnsEvent::RecordAdded(answer))
+            }
+        }
+    }
+
+    /// remove the records whose ttl expired
+    pub fn expire(&mut self, now: Instant) -> Vec<MdnsEvent> {
+        let (expired, records): (Vec<_>, Vec<_>) = self
+            .records
+            .drain(..)
+            .partition(|(_, expiry)| *expiry <= now);
+        self.records = records;
+        expired
+            .into_iter()
+            .map(|(answer, _)| MdnsEvent::RecordRemoved(answer))
+            .collect()
+    }
+
+    /// the records currently cached
+    pub fn records(&self) -> impl Iterator<Item = &dns::Answer> {
+        self.records.iter().map(|(answer, _)| answer)
+    }
+}
+
+/// mdns responder and browser
+pub struct MdnsService {
+    mdns: MulticastDns,
+    query_interval: Duration,
+    /// records we answer queries for
+    registered: Vec<dns::Answer>,
+    /// registered records not announced yet
+    to_announce: Vec<dns::Answer>,
+    /// questions we periodically send
+    browsed: Vec<dns::Question>,
+    /// when to send the browsed questions next, None for as soon as possible
+    next_query: Option<Instant>,
+    cache: RecordCache,
+}
+
+impl MdnsService {
+    /// create a service sending its browsed questions every `query_interval`
+    pub fn new(mdns: MulticastDns, query_interval: Duration) -> Self {
+        MdnsService {
+            mdns,
+            query_interval,
+            registered: vec![],
+            to_announce: vec![],
+            browsed: vec![],
+            next_query: None,
+            cache: RecordCache::new(),
+        }
+    }
+
+    /// answer the queries for `answer`, announcing it on next process()
+    pub fn register(&mut self, answer: dns::Answer) {
+        self.unregister_silently(&answer);
+        self.registered.push(answer.clone());
+        self.to_announce.push(answer);
+    }
+
+    /// stop answering the queries for the records named `name`
+    /// and tell the browsers to forget them
+    pub fn unregister(&mut self, name: &[u8]) -> MulticastDnsResult<()> {
+        let (goodbyes, registered): (Vec<_>, Vec<_>) = self
+            .registered
+            .drain(..)
+            .partition(|answer| answer.name() == Some(name));
+        self.registered = registered;
+        self.to_announce
+            .retain(|answer| answer.name() != Some(name));
+
+        if goodbyes.is_empty() {
+            return Ok(());
+        }
+
+        let mut packet = dns::Packet::new();
+        packet.is_query = false;
+        packet.answers = goodbyes
+            .iter()
+            .map(|answer| answer.with_ttl_seconds(0))
+            .collect();
+        self.mdns.send(&packet)
+    }
+
+    /// periodically query for `question`, caching the records answering it
+    /// and the records named after them
+    pub fn browse(&mut self, question: dns::Question) {
+        if !self.browsed.contains(&question) {
+            self.browsed.push(question);
+            self.next_query = None;
+        }
+    }
+
+    /// the records found by browsing
+    pub fn cache(&self) -> &RecordCache {
+        &self.cache
+    }
+
+    /// announce, query, answer and cache what is due
+    /// will not block, returns true in first position if it did any work
+    pub fn process(&mut self) -> MulticastDnsResult<(bool, Vec<MdnsEvent>)> {
+        let now = Instant::now();
+        let mut did_work = false;
+        let mut events = Vec::new();
+
+        if !self.to_announce.is_empty() {
+            let mut packet = dns::Packet::new();
+            packet.is_query = false;
+            packet.answers = self.to_announce.drain(..).collect();
+            self.mdns.send(&packet)?;
+            did_work = true;
+        }
+
+        let is_query_due = self.next_query.map(|next| now >= next).unwrap_or(true);
+        if !self.browsed.is_empty() && is_query_due {
+            let mut packet = dns::Packet::new();
+            packet.is_query = true;
+            packet.questions = self.browsed.clone();
+            self.mdns.send(&packet)?;
+            self.next_query = Some(now + self.query_interval);
+            did_work = true;
+        }
+
+        for _ in 0..MAX_PACKETS_PER_PROCESS {
+            let packet = match self.mdns.recv() {
+                Ok(Some(packet)) => packet,
+                Ok(None) => break,
+                // not all packets on the mdns port are for us to understand
+                Err(_) => continue,
+            };
+            did_work = true;
+
+            if packet.is_query {
+                self.respond(&packet)?;
+                continue;
+            }
+
+            for answer in packet.answers.iter().chain(packet.additionals.iter()) {
+                if !self.is_browsed(answer) || self.is_registered(answer) {
+                    continue;
+                }
+                if let Some(event) = self.cache.insert(answer.clone(), now) {
+                    events.push(event);
+                }
+            }
+        }
+
+        events.append(&mut self.cache.expire(now));
+        if !events.is_empty() {
+            did_work = true;
+        }
+
+        Ok((did_work, events))
+    }
+
+    /// answer `query` with the records we registered for it
+    fn respond(&mut self, query: &dns::Packet) -> MulticastDnsResult<()> {
+        let mut answers: Vec<dns::Answer> = Vec::new();
+        for question in query.questions.iter() {
+            for record in self.registered.iter() {
+                if question.is_answered_by(record) && !answers.contains(record) {
+                    answers.push(record.clone());
+                }
+            }
+        }
+
+        if answers.is_empty() {
+            return Ok(());
+        }
+
+        // help resolving the instances our PTR records point to
+        let mut additionals: Vec<dns::Answer> = Vec::new();
+        for answer in answers.iter() {
+            if let dns::Answer::Ptr(ptr) = answer {
+                for record in self.registered.iter() {
+                    if record.name() == Some(&ptr.target[..])
+                        && !answers.contains(record)
+                        && !additionals.contains(record)
+                    {
+                        additionals.push(record.clone());
+                    }
+                }
+            }
+        }
+
+        let mut packet = dns::Packet::new();
+        packet.id = query.id;
+        packet.is_query = false;
+        packet.answers = answers;
+        packet.additionals = additionals;
+        self.mdns.send(&packet)
+    }
+
+    /// is `answer` named after one of our browsed questions
+    fn is_browsed(&self, answer: &dns::Answer) -> bool {
+        let name = match answer.name() {
+            Some(name) => name,
+            None => return false,
+        };
+        self.browsed.iter().filter_map(|q| q.name()).any(|browsed| {
+            name == browsed
+                || (name.len() > browsed.len()
+                    && name.ends_with(browsed)
+                    && name[name.len() - browsed.len() - 1] == b'.')
+        })
+    }
+
+    /// is `answer` one of our own records, e.g. looped back to us
+    fn is_registered(&self, answer: &dns::Answer) -> bool {
+        let key = answer.with_ttl_seconds(0);
+        self.registered
+            .iter()
+            .any(|record| record.with_ttl_seconds(0) == key)
+    }
+
+    /// forget a registered record without telling the browsers
+    fn unregister_silently(&mut self, answer: &dns::Answer) {
+        let key = answer.with_ttl_seconds(0);
+        self.registered
+            .retain(|record| record.with_ttl_seconds(0) != key);
+        self.to_announce
+            .retain(|record| record.with_ttl_seconds(0) != key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Builder;
+
+    fn srv(name: &[u8], ttl_seconds: u32) -> dns::Answer {
+        dns::Answer::Srv(dns::SrvDataA {
+            name: name.to_vec(),
+            ttl_seconds,
+            priority: 0,
+            weight: 0,
+            port: 0x3333,
+            target: b"lib3h.test.target".to_vec(),
+        })
+    }
+
+    #[test]
+    fn it_should_cache_until_ttl_expires() {
+        let mut cache = RecordCache::new();
+        let now = Instant::now();
+
+        let record = srv(b"node.lib3h.test.service", 10);
+        assert_eq!(
+            Some(MdnsEvent::RecordAdded(record.clone())),
+            cache.insert(record.clone(), now)
+        );
+
+        // refreshing is silent, and pushes back the expiry
+        let refreshed = srv(b"node.lib3h.test.service", 20);
+        assert_eq!(None, cache.insert(refreshed.clone(), now));
+        assert!(cache.expire(now + Duration::from_secs(15)).is_empty());
+        assert_eq!(vec![&refreshed], cache.records().collect::<Vec<_>>());
+
+        assert_eq!(
+            vec![MdnsEvent::RecordRemoved(refreshed)],
+            cache.expire(now + Duration::from_secs(20))
+        );
+        assert_eq!(0, cache.records().count());
+    }
+
+    #[test]
+    fn it_should_remove_on_goodbye() {
+        let mut cache = RecordCache::new();
+        let now = Instant::now();
+
+        let record = srv(b"node.lib3h.test.service", 10);
+        cache.insert(record.clone(), now);
+        assert_eq!(
+            Some(MdnsEvent::RecordRemoved(record)),
+            cache.insert(srv(b"node.lib3h.test.service", 0), now)
+        );
+        assert_eq!(None, cache.insert(srv(b"node.lib3h.test.service", 0), now));
+    }
+
+    #[test]
+    fn it_should_find_and_lose_a_registered_record() {
+        let build = || {
+            Builder::new()
+                .set_bind_port(55005)
+                .set_multicast_loop(true)
+                .build()
+                .expect("build fail")
+        };
+        let mut responder = MdnsService::new(build(), Duration::from_millis(100));
+        let mut browser = MdnsService::new(build(), Duration::from_millis(100));
+
+        let record = srv(b"node.lib3h.test.service", 120);
+        responder.register(record.clone());
+        browser.browse(dns::Question::Srv(dns::SrvDataQ {
+            name: b"node.lib3h.test.service".to_vec(),
+        }));
+
+        let mut events = Vec::new();
+        for _ in 0..10 {
+            responder.process().expect("process fail");
+            events.append(&mut browser.process().expect("process fail").1);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert_eq!(vec![MdnsEvent::RecordAdded(record.clone())], events);
+
+        responder
+            .unregister(b"node.lib3h.test.service")
+            .expect("unregister fail");
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let (_, events) = browser.process().expect("process fail");
+        assert_eq!(vec![MdnsEvent::RecordRemoved(record)], events);
+    }
+}
@@ -0,0 +1,79 @@
+//! P2p Protocol custom error definition.
+
+use std::{error::Error as StdError, fmt, result};
+
+/// A type alias for `Result<T, P2pProtocolError>`.
+pub type P2pProtocolResult<T> = result::Result<T, P2pProtocolError>;
+
+/// An error that can occur when encoding or decoding wire messages.
+#[derive(Debug)]
+pub struct P2pProtocolError(Box<ErrorKind>);
+
+impl P2pProtocolError {
+    /// A constructor for `P2pProtocolError`.
+    pub fn new(kind: ErrorKind) -> Self {
+        P2pProtocolError(Box::new(kind))
+    }
+
+    /// Return the specific type of this error.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.0
+    }
+
+    /// Unwrap this error into its underlying type.
+    pub fn into_kind(self) -> ErrorKind {
+        *self.0
+    }
+}
+
+/// The specific type of an error.
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// Error originating from the [capnp] crate, usually a malformed frame.
+    Capnp(capnp::Error),
+    /// A union discriminant or enum value unknown to this version of the schema.
+    NotInSchema(u16),
+    /// Yet undefined error.
+    Other(String),
+    /// Hints that destructuring should not be exhaustive.
+    ///
+    /// This enum may grow additional variants, so this makes sure clients
+    /// don't count on exhaustive matching. (Otherwise, adding a new variant
+    /// could break existing code.)
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl StdError for P2pProtocolError {
+    /// The lower-level source of this error, if any.
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self.0 {
+            ErrorKind::Capnp(ref err) => Some(err),
+            ErrorKind::NotInSchema(_) | ErrorKind::Other(_) => None,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl fmt::Display for P2pProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.0 {
+            ErrorKind::Capnp(ref err) => err.fmt(f),
+            ErrorKind::NotInSchema(ref n) => write!(f, "Value not in schema: {}", n),
+            ErrorKind::Other(ref s) => write!(f, "Unknown error encountered: '{}'.", s),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl From<capnp::Error> for P2pProtocolError {
+    fn from(err: capnp::Error) -> Self {
+        P2pProtocolError::new(ErrorKind::Capnp(err))
+    }
+}
+
+impl From<capnp::NotInSchema> for P2pProtocolError {
+    fn from(err: capnp::NotInSchema) -> Self {
+        P2pProtocolError::new(ErrorKind::NotInSchema(err.0))
+    }
+}
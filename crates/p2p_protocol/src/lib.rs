@@ -5,6 +5,11 @@ extern crate serde;
 extern crate serde_derive;
 extern crate serde_json;
 
+pub mod error;
+pub mod multiplex;
+pub mod p2p;
+pub mod transit_encoding;
+
 #[allow(dead_code)]
 #[allow(clippy::all)]
 #[rustfmt::skip]
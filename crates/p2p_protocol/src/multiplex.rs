@@ -0,0 +1,287 @@
+//! Typed encoding / decoding of the channel multiplexing messages
+//! (see `protocol/multiplex.capnp`).
+
+use crate::{
+    error::P2pProtocolResult,
+    multiplex_capnp::multiplex_message::{self, msg_error},
+};
+use capnp::{message, serialize_packed};
+
+/// channelId of a MsgError not related to a specific channel
+pub const NO_CHANNEL_ID: u32 = 0xffff_ffff;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MultiplexErrorCode {
+    Unknown,
+    BadChannelId,
+    BadSpaceHash,
+    BadToId,
+    BadFromId,
+    MessageTooBig,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MultiplexMessage {
+    MsgError(MsgMultiplexError),
+    MsgChannelCreate(MsgChannelCreate),
+    MsgChannelClose(u32),
+    MsgChannelMessage(MsgChannelMessage),
+    MsgRelayRequest,
+    MsgRelayAccept,
+    MsgChannelFragment(MsgChannelFragment),
+    MsgFragmentResume(MsgFragmentResume),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgMultiplexError {
+    pub channel_id: u32,
+    pub error_code: MultiplexErrorCode,
+    pub error_text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgChannelCreate {
+    pub channel_id: u32,
+    pub space_hash: Vec<u8>,
+    pub to_id: Vec<u8>,
+    pub from_id: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgChannelMessage {
+    pub channel_id: u32,
+    pub content: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgChannelFragment {
+    pub channel_id: u32,
+    pub index: u32,
+    pub count: u32,
+    pub message_size: u64,
+    pub message_hash: Vec<u8>,
+    pub content: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgFragmentResume {
+    pub channel_id: u32,
+    pub message_hash: Vec<u8>,
+    pub next_index: u32,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Encoding / Decoding
+//--------------------------------------------------------------------------------------------------
+
+impl MultiplexMessage {
+    /// Encode as a packed capnp frame
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut message = message::Builder::new_default();
+        {
+            let mut root = message.init_root::<multiplex_message::Builder>();
+            // Note: use same order as the enum
+            match self {
+                MultiplexMessage::MsgError(msg) => msg.write(root.init_msg_error()),
+                MultiplexMessage::MsgChannelCreate(msg) => {
+                    let mut builder = root.init_msg_channel_create();
+                    builder.set_channel_id(msg.channel_id);
+                    builder.set_space_hash(&msg.space_hash);
+                    builder.set_to_id(&msg.to_id);
+                    builder.set_from_id(&msg.from_id);
+                }
+                MultiplexMessage::MsgChannelClose(channel_id) => {
+                    root.set_msg_channel_close(*channel_id)
+                }
+                MultiplexMessage::MsgChannelMessage(msg) => {
+                    let mut builder = root.init_msg_channel_message();
+                    builder.set_channel_id(msg.channel_id);
+                    builder.set_content(&msg.content);
+                }
+                MultiplexMessage::MsgRelayRequest => root.set_msg_relay_request(()),
+                MultiplexMessage::MsgRelayAccept => root.set_msg_relay_accept(()),
+                MultiplexMessage::MsgChannelFragment(msg) => {
+                    let mut builder = root.init_msg_channel_fragment();
+                    builder.set_channel_id(msg.channel_id);
+                    builder.set_index(msg.index);
+                    builder.set_count(msg.count);
+                    builder.set_message_size(msg.message_size);
+                    builder.set_message_hash(&msg.message_hash);
+                    builder.set_content(&msg.content);
+                }
+                MultiplexMessage::MsgFragmentResume(msg) => {
+                    let mut builder = root.init_msg_fragment_resume();
+                    builder.set_channel_id(msg.channel_id);
+                    builder.set_message_hash(&msg.message_hash);
+                    builder.set_next_index(msg.next_index);
+                }
+            }
+        }
+        let mut buf = Vec::new();
+        serialize_packed::write_message(&mut buf, &message)
+            .expect("Writing to a Vec should never fail");
+        buf
+    }
+
+    /// Decode a packed capnp frame
+    pub fn from_bytes(bytes: &[u8]) -> P2pProtocolResult<Self> {
+        Self::read(bytes, message::ReaderOptions::new())
+    }
+
+    /// Decode a packed capnp frame, refusing it before unpacking
+    /// if it would take more than `max_size` bytes once unpacked
+    pub fn from_bytes_limited(bytes: &[u8], max_size: usize) -> P2pProtocolResult<Self> {
+        let mut options = message::ReaderOptions::new();
+        options.traversal_limit_in_words((max_size / 8 + 1) as u64);
+        Self::read(bytes, options)
+    }
+
+    fn read(bytes: &[u8], options: message::ReaderOptions) -> P2pProtocolResult<Self> {
+        let reader = serialize_packed::read_message(&mut &bytes[..], options)?;
+        let root = reader.get_root::<multiplex_message::Reader>()?;
+        // Note: use same order as the enum
+        let msg = match root.which()? {
+            multiplex_message::MsgError(r) => {
+                MultiplexMessage::MsgError(MsgMultiplexError::read(r?)?)
+            }
+            multiplex_message::MsgChannelCreate(r) => {
+                let r = r?;
+                MultiplexMessage::MsgChannelCreate(MsgChannelCreate {
+                    channel_id: r.get_channel_id(),
+                    space_hash: r.get_space_hash()?.to_vec(),
+                    to_id: r.get_to_id()?.to_vec(),
+                    from_id: r.get_from_id()?.to_vec(),
+                })
+            }
+            multiplex_message::MsgChannelClose(channel_id) => {
+                MultiplexMessage::MsgChannelClose(channel_id)
+            }
+            multiplex_message::MsgChannelMessage(r) => {
+                let r = r?;
+                MultiplexMessage::MsgChannelMessage(MsgChannelMessage {
+                    channel_id: r.get_channel_id(),
+                    content: r.get_content()?.to_vec(),
+                })
+            }
+            multiplex_message::MsgRelayRequest(()) => MultiplexMessage::MsgRelayRequest,
+            multiplex_message::MsgRelayAccept(()) => MultiplexMessage::MsgRelayAccept,
+            multiplex_message::MsgChannelFragment(r) => {
+                let r = r?;
+                MultiplexMessage::MsgChannelFragment(MsgChannelFragment {
+                    channel_id: r.get_channel_id(),
+                    index: r.get_index(),
+                    count: r.get_count(),
+                    message_size: r.get_message_size(),
+                    message_hash: r.get_message_hash()?.to_vec(),
+                    content: r.get_content()?.to_vec(),
+                })
+            }
+            multiplex_message::MsgFragmentResume(r) => {
+                let r = r?;
+                MultiplexMessage::MsgFragmentResume(MsgFragmentResume {
+                    channel_id: r.get_channel_id(),
+                    message_hash: r.get_message_hash()?.to_vec(),
+                    next_index: r.get_next_index(),
+                })
+            }
+        };
+        Ok(msg)
+    }
+}
+
+impl MsgMultiplexError {
+    fn write(&self, mut builder: msg_error::Builder) {
+        builder.set_channel_id(self.channel_id);
+        builder.set_error_code(match self.error_code {
+            MultiplexErrorCode::Unknown => msg_error::ErrorCode::Unknown,
+            MultiplexErrorCode::BadChannelId => msg_error::ErrorCode::BadChannelId,
+            MultiplexErrorCode::BadSpaceHash => msg_error::ErrorCode::BadSpaceHash,
+            MultiplexErrorCode::BadToId => msg_error::ErrorCode::BadToId,
+            MultiplexErrorCode::BadFromId => msg_error::ErrorCode::BadFromId,
+            MultiplexErrorCode::MessageTooBig => msg_error::ErrorCode::MessageTooBig,
+        });
+        builder.set_error_text(&self.error_text);
+    }
+
+    fn read(reader: msg_error::Reader) -> P2pProtocolResult<Self> {
+        let error_code = match reader.get_error_code()? {
+            msg_error::ErrorCode::Unknown => MultiplexErrorCode::Unknown,
+            msg_error::ErrorCode::BadChannelId => MultiplexErrorCode::BadChannelId,
+            msg_error::ErrorCode::BadSpaceHash => MultiplexErrorCode::BadSpaceHash,
+            msg_error::ErrorCode::BadToId => MultiplexErrorCode::BadToId,
+            msg_error::ErrorCode::BadFromId => MultiplexErrorCode::BadFromId,
+            msg_error::ErrorCode::MessageTooBig => MultiplexErrorCode::MessageTooBig,
+        };
+        Ok(MsgMultiplexError {
+            channel_id: reader.get_channel_id(),
+            error_code,
+            error_text: reader.get_error_text()?.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(msg: MultiplexMessage) {
+        let decoded = MultiplexMessage::from_bytes(&msg.to_bytes()).unwrap();
+        assert_eq!(decoded, msg);
+    }
+
+    #[test]
+    fn it_should_refuse_frames_over_the_size_limit() {
+        let msg = MultiplexMessage::MsgChannelMessage(MsgChannelMessage {
+            channel_id: 42,
+            content: vec![0; 4096],
+        });
+        // Zeros pack to a much smaller frame
+        let frame = msg.to_bytes();
+        assert!(frame.len() < 1024);
+        assert!(MultiplexMessage::from_bytes_limited(&frame, 1024).is_err());
+        assert_eq!(
+            MultiplexMessage::from_bytes_limited(&frame, 8192).unwrap(),
+            msg
+        );
+    }
+
+    #[test]
+    fn it_should_round_trip_all_multiplex_messages() {
+        round_trip(MultiplexMessage::MsgError(MsgMultiplexError {
+            channel_id: NO_CHANNEL_ID,
+            error_code: MultiplexErrorCode::BadSpaceHash,
+            error_text: "unknown space".to_string(),
+        }));
+        round_trip(MultiplexMessage::MsgChannelCreate(MsgChannelCreate {
+            channel_id: 42,
+            space_hash: b"space".to_vec(),
+            to_id: b"to".to_vec(),
+            from_id: b"from".to_vec(),
+        }));
+        round_trip(MultiplexMessage::MsgChannelClose(42));
+        round_trip(MultiplexMessage::MsgChannelMessage(MsgChannelMessage {
+            channel_id: 42,
+            content: vec![1, 2, 3],
+        }));
+        round_trip(MultiplexMessage::MsgRelayRequest);
+        round_trip(MultiplexMessage::MsgRelayAccept);
+        round_trip(MultiplexMessage::MsgChannelFragment(MsgChannelFragment {
+            channel_id: 42,
+            index: 3,
+            count: 5,
+            message_size: 5 * 65536,
+            message_hash: vec![0xab; 32],
+            content: vec![4, 5, 6],
+        }));
+        round_trip(MultiplexMessage::MsgFragmentResume(MsgFragmentResume {
+            channel_id: 42,
+            message_hash: vec![0xab; 32],
+            next_index: 4,
+        }));
+        round_trip(MultiplexMessage::MsgError(MsgMultiplexError {
+            channel_id: 42,
+            error_code: MultiplexErrorCode::MessageTooBig,
+            error_text: "too big".to_string(),
+        }));
+    }
+}
@@ -4,7 +4,7 @@
 
 
 pub mod multiplex_message {
-  pub use self::Which::{MsgError,MsgChannelCreate,MsgChannelClose,MsgChannelMessage,MsgRelayRequest,MsgRelayAccept};
+  pub use self::Which::{MsgError,MsgChannelCreate,MsgChannelClose,MsgChannelMessage,MsgRelayRequest,MsgRelayAccept,MsgChannelFragment,MsgFragmentResume};
 
   #[derive(Copy, Clone)]
   pub struct Owned;
@@ -63,6 +63,14 @@ pub mod multiplex_message {
       if self.reader.get_data_field::<u16>(0) != 3 { return false; }
       !self.reader.get_pointer_field(0).is_null()
     }
+    pub fn has_msg_channel_fragment(&self) -> bool {
+      if self.reader.get_data_field::<u16>(0) != 6 { return false; }
+      !self.reader.get_pointer_field(0).is_null()
+    }
+    pub fn has_msg_fragment_resume(&self) -> bool {
+      if self.reader.get_data_field::<u16>(0) != 7 { return false; }
+      !self.reader.get_pointer_field(0).is_null()
+    }
     #[inline]
     pub fn which(self) -> ::std::result::Result<WhichReader<'a,>, ::capnp::NotInSchema> {
       match self.reader.get_data_field::<u16>(0) {
@@ -96,6 +104,16 @@ pub mod multiplex_message {
             ()
           ))
         }
+        6 => {
+          ::std::result::Result::Ok(MsgChannelFragment(
+            ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
+          ))
+        }
+        7 => {
+          ::std::result::Result::Ok(MsgFragmentResume(
+            ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
+          ))
+        }
         x => ::std::result::Result::Err(::capnp::NotInSchema(x))
       }
     }
@@ -205,6 +223,34 @@ pub mod multiplex_message {
       self.builder.set_data_field::<u16>(0, 5);
     }
     #[inline]
+    pub fn set_msg_channel_fragment<'b>(&mut self, value: crate::multiplex_capnp::multiplex_message::msg_channel_fragment::Reader<'b>) -> ::capnp::Result<()> {
+      self.builder.set_data_field::<u16>(0, 6);
+      ::capnp::traits::SetPointerBuilder::set_pointer_builder(self.builder.get_pointer_field(0), value, false)
+    }
+    #[inline]
+    pub fn init_msg_channel_fragment(self, ) -> crate::multiplex_capnp::multiplex_message::msg_channel_fragment::Builder<'a> {
+      self.builder.set_data_field::<u16>(0, 6);
+      ::capnp::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), 0)
+    }
+    pub fn has_msg_channel_fragment(&self) -> bool {
+      if self.builder.get_data_field::<u16>(0) != 6 { return false; }
+      !self.builder.get_pointer_field(0).is_null()
+    }
+    #[inline]
+    pub fn set_msg_fragment_resume<'b>(&mut self, value: crate::multiplex_capnp::multiplex_message::msg_fragment_resume::Reader<'b>) -> ::capnp::Result<()> {
+      self.builder.set_data_field::<u16>(0, 7);
+      ::capnp::traits::SetPointerBuilder::set_pointer_builder(self.builder.get_pointer_field(0), value, false)
+    }
+    #[inline]
+    pub fn init_msg_fragment_resume(self, ) -> crate::multiplex_capnp::multiplex_message::msg_fragment_resume::Builder<'a> {
+      self.builder.set_data_field::<u16>(0, 7);
+      ::capnp::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), 0)
+    }
+    pub fn has_msg_fragment_resume(&self) -> bool {
+      if self.builder.get_data_field::<u16>(0) != 7 { return false; }
+      !self.builder.get_pointer_field(0).is_null()
+    }
+    #[inline]
     pub fn which(self) -> ::std::result::Result<WhichBuilder<'a,>, ::capnp::NotInSchema> {
       match self.builder.get_data_field::<u16>(0) {
         0 => {
@@ -237,6 +283,16 @@ pub mod multiplex_message {
             ()
           ))
         }
+        6 => {
+          ::std::result::Result::Ok(MsgChannelFragment(
+            ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
+          ))
+        }
+        7 => {
+          ::std::result::Result::Ok(MsgFragmentResume(
+            ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
+          ))
+        }
         x => ::std::result::Result::Err(::capnp::NotInSchema(x))
       }
     }
@@ -255,16 +311,18 @@ pub mod multiplex_message {
     pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 1, pointers: 1 };
     pub const TYPE_ID: u64 = 0xfe42_1146_6097_93a5;
   }
-  pub enum Which<A0,A1,A2> {
+  pub enum Which<A0,A1,A2,A3,A4> {
     MsgError(A0),
     MsgChannelCreate(A1),
     MsgChannelClose(u32),
     MsgChannelMessage(A2),
     MsgRelayRequest(()),
     MsgRelayAccept(()),
+    MsgChannelFragment(A3),
+    MsgFragmentResume(A4),
   }
-  pub type WhichReader<'a,> = Which<::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_error::Reader<'a>>,::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_channel_create::Reader<'a>>,::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_channel_message::Reader<'a>>>;
-  pub type WhichBuilder<'a,> = Which<::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_error::Builder<'a>>,::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_channel_create::Builder<'a>>,::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_channel_message::Builder<'a>>>;
+  pub type WhichReader<'a,> = Which<::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_error::Reader<'a>>,::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_channel_create::Reader<'a>>,::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_channel_message::Reader<'a>>,::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_channel_fragment::Reader<'a>>,::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_fragment_resume::Reader<'a>>>;
+  pub type WhichBuilder<'a,> = Which<::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_error::Builder<'a>>,::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_channel_create::Builder<'a>>,::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_channel_message::Builder<'a>>,::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_channel_fragment::Builder<'a>>,::capnp::Result<crate::multiplex_capnp::multiplex_message::msg_fragment_resume::Builder<'a>>>;
 
   pub mod msg_error {
     #[derive(Copy, Clone)]
@@ -432,6 +490,7 @@ pub mod multiplex_message {
       BadSpaceHash = 2,
       BadToId = 3,
       BadFromId = 4,
+      MessageTooBig = 5,
     }
     impl ::capnp::traits::FromU16 for ErrorCode {
       #[inline]
@@ -442,6 +501,7 @@ pub mod multiplex_message {
           2 => ::std::result::Result::Ok(ErrorCode::BadSpaceHash),
           3 => ::std::result::Result::Ok(ErrorCode::BadToId),
           4 => ::std::result::Result::Ok(ErrorCode::BadFromId),
+          5 => ::std::result::Result::Ok(ErrorCode::MessageTooBig),
           n => ::std::result::Result::Err(::capnp::NotInSchema(n)),
         }
       }
@@ -793,4 +853,368 @@ pub mod multiplex_message {
       pub const TYPE_ID: u64 = 0xfa3a_e37a_767e_1231;
     }
   }
+
+  pub mod msg_channel_fragment {
+    #[derive(Copy, Clone)]
+    pub struct Owned;
+    impl <'a> ::capnp::traits::Owned<'a> for Owned { type Reader = Reader<'a>; type Builder = Builder<'a>; }
+    impl <'a> ::capnp::traits::OwnedStruct<'a> for Owned { type Reader = Reader<'a>; type Builder = Builder<'a>; }
+    impl ::capnp::traits::Pipelined for Owned { type Pipeline = Pipeline; }
+
+    #[derive(Clone, Copy)]
+    pub struct Reader<'a> { reader: ::capnp::private::layout::StructReader<'a> }
+
+    impl <'a,> ::capnp::traits::HasTypeId for Reader<'a,>  {
+      #[inline]
+      fn type_id() -> u64 { _private::TYPE_ID }
+    }
+    impl <'a,> ::capnp::traits::FromStructReader<'a> for Reader<'a,>  {
+      fn new(reader: ::capnp::private::layout::StructReader<'a>) -> Reader<'a,> {
+        Reader { reader: reader,  }
+      }
+    }
+
+    impl <'a,> ::capnp::traits::FromPointerReader<'a> for Reader<'a,>  {
+      fn get_from_pointer(reader: &::capnp::private::layout::PointerReader<'a>, default: ::std::option::Option<&'a [::capnp::Word]>) -> ::capnp::Result<Reader<'a,>> {
+        ::std::result::Result::Ok(::capnp::traits::FromStructReader::new(reader.get_struct(default)?))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::IntoInternalStructReader<'a> for Reader<'a,>  {
+      fn into_internal_struct_reader(self) -> ::capnp::private::layout::StructReader<'a> {
+        self.reader
+      }
+    }
+
+    impl <'a,> ::capnp::traits::Imbue<'a> for Reader<'a,>  {
+      fn imbue(&mut self, cap_table: &'a ::capnp::private::layout::CapTable) {
+        self.reader.imbue(::capnp::private::layout::CapTableReader::Plain(cap_table))
+      }
+    }
+
+    impl <'a,> Reader<'a,>  {
+      pub fn reborrow(&self) -> Reader<> {
+        Reader { .. *self }
+      }
+
+      pub fn total_size(&self) -> ::capnp::Result<::capnp::MessageSize> {
+        self.reader.total_size()
+      }
+      #[inline]
+      pub fn get_channel_id(self) -> u32 {
+        self.reader.get_data_field::<u32>(0)
+      }
+      #[inline]
+      pub fn get_index(self) -> u32 {
+        self.reader.get_data_field::<u32>(1)
+      }
+      #[inline]
+      pub fn get_count(self) -> u32 {
+        self.reader.get_data_field::<u32>(2)
+      }
+      #[inline]
+      pub fn get_message_size(self) -> u64 {
+        self.reader.get_data_field::<u64>(2)
+      }
+      #[inline]
+      pub fn get_message_hash(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
+      }
+      pub fn has_message_hash(&self) -> bool {
+        !self.reader.get_pointer_field(0).is_null()
+      }
+      #[inline]
+      pub fn get_content(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(1), ::std::option::Option::None)
+      }
+      pub fn has_content(&self) -> bool {
+        !self.reader.get_pointer_field(1).is_null()
+      }
+    }
+
+    pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
+    impl <'a,> ::capnp::traits::HasStructSize for Builder<'a,>  {
+      #[inline]
+      fn struct_size() -> ::capnp::private::layout::StructSize { _private::STRUCT_SIZE }
+    }
+    impl <'a,> ::capnp::traits::HasTypeId for Builder<'a,>  {
+      #[inline]
+      fn type_id() -> u64 { _private::TYPE_ID }
+    }
+    impl <'a,> ::capnp::traits::FromStructBuilder<'a> for Builder<'a,>  {
+      fn new(builder: ::capnp::private::layout::StructBuilder<'a>) -> Builder<'a, > {
+        Builder { builder: builder,  }
+      }
+    }
+
+    impl <'a,> ::capnp::traits::ImbueMut<'a> for Builder<'a,>  {
+      fn imbue_mut(&mut self, cap_table: &'a mut ::capnp::private::layout::CapTable) {
+        self.builder.imbue(::capnp::private::layout::CapTableBuilder::Plain(cap_table))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::FromPointerBuilder<'a> for Builder<'a,>  {
+      fn init_pointer(builder: ::capnp::private::layout::PointerBuilder<'a>, _size: u32) -> Builder<'a,> {
+        ::capnp::traits::FromStructBuilder::new(builder.init_struct(_private::STRUCT_SIZE))
+      }
+      fn get_from_pointer(builder: ::capnp::private::layout::PointerBuilder<'a>, default: ::std::option::Option<&'a [::capnp::Word]>) -> ::capnp::Result<Builder<'a,>> {
+        ::std::result::Result::Ok(::capnp::traits::FromStructBuilder::new(builder.get_struct(_private::STRUCT_SIZE, default)?))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::SetPointerBuilder<Builder<'a,>> for Reader<'a,>  {
+      fn set_pointer_builder<'b>(pointer: ::capnp::private::layout::PointerBuilder<'b>, value: Reader<'a,>, canonicalize: bool) -> ::capnp::Result<()> { pointer.set_struct(&value.reader, canonicalize) }
+    }
+
+    impl <'a,> Builder<'a,>  {
+      pub fn into_reader(self) -> Reader<'a,> {
+        ::capnp::traits::FromStructReader::new(self.builder.into_reader())
+      }
+      pub fn reborrow(&mut self) -> Builder<> {
+        Builder { .. *self }
+      }
+      pub fn reborrow_as_reader(&self) -> Reader<> {
+        ::capnp::traits::FromStructReader::new(self.builder.into_reader())
+      }
+
+      pub fn total_size(&self) -> ::capnp::Result<::capnp::MessageSize> {
+        self.builder.into_reader().total_size()
+      }
+      #[inline]
+      pub fn get_channel_id(self) -> u32 {
+        self.builder.get_data_field::<u32>(0)
+      }
+      #[inline]
+      pub fn set_channel_id(&mut self, value: u32)  {
+        self.builder.set_data_field::<u32>(0, value);
+      }
+      #[inline]
+      pub fn get_index(self) -> u32 {
+        self.builder.get_data_field::<u32>(1)
+      }
+      #[inline]
+      pub fn set_index(&mut self, value: u32)  {
+        self.builder.set_data_field::<u32>(1, value);
+      }
+      #[inline]
+      pub fn get_count(self) -> u32 {
+        self.builder.get_data_field::<u32>(2)
+      }
+      #[inline]
+      pub fn set_count(&mut self, value: u32)  {
+        self.builder.set_data_field::<u32>(2, value);
+      }
+      #[inline]
+      pub fn get_message_size(self) -> u64 {
+        self.builder.get_data_field::<u64>(2)
+      }
+      #[inline]
+      pub fn set_message_size(&mut self, value: u64)  {
+        self.builder.set_data_field::<u64>(2, value);
+      }
+      #[inline]
+      pub fn get_message_hash(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_message_hash(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(0).set_data(value);
+      }
+      #[inline]
+      pub fn init_message_hash(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(0).init_data(size)
+      }
+      pub fn has_message_hash(&self) -> bool {
+        !self.builder.get_pointer_field(0).is_null()
+      }
+      #[inline]
+      pub fn get_content(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(1), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_content(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(1).set_data(value);
+      }
+      #[inline]
+      pub fn init_content(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(1).init_data(size)
+      }
+      pub fn has_content(&self) -> bool {
+        !self.builder.get_pointer_field(1).is_null()
+      }
+    }
+
+    pub struct Pipeline { _typeless: ::capnp::any_pointer::Pipeline }
+    impl ::capnp::capability::FromTypelessPipeline for Pipeline {
+      fn new(typeless: ::capnp::any_pointer::Pipeline) -> Pipeline {
+        Pipeline { _typeless: typeless,  }
+      }
+    }
+    impl Pipeline  {
+    }
+    mod _private {
+      use capnp::private::layout;
+      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 3, pointers: 2 };
+      pub const TYPE_ID: u64 = 0xc254_7ecb_6d42_3def;
+    }
+  }
+
+  pub mod msg_fragment_resume {
+    #[derive(Copy, Clone)]
+    pub struct Owned;
+    impl <'a> ::capnp::traits::Owned<'a> for Owned { type Reader = Reader<'a>; type Builder = Builder<'a>; }
+    impl <'a> ::capnp::traits::OwnedStruct<'a> for Owned { type Reader = Reader<'a>; type Builder = Builder<'a>; }
+    impl ::capnp::traits::Pipelined for Owned { type Pipeline = Pipeline; }
+
+    #[derive(Clone, Copy)]
+    pub struct Reader<'a> { reader: ::capnp::private::layout::StructReader<'a> }
+
+    impl <'a,> ::capnp::traits::HasTypeId for Reader<'a,>  {
+      #[inline]
+      fn type_id() -> u64 { _private::TYPE_ID }
+    }
+    impl <'a,> ::capnp::traits::FromStructReader<'a> for Reader<'a,>  {
+      fn new(reader: ::capnp::private::layout::StructReader<'a>) -> Reader<'a,> {
+        Reader { reader: reader,  }
+      }
+    }
+
+    impl <'a,> ::capnp::traits::FromPointerReader<'a> for Reader<'a,>  {
+      fn get_from_pointer(reader: &::capnp::private::layout::PointerReader<'a>, default: ::std::option::Option<&'a [::capnp::Word]>) -> ::capnp::Result<Reader<'a,>> {
+        ::std::result::Result::Ok(::capnp::traits::FromStructReader::new(reader.get_struct(default)?))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::IntoInternalStructReader<'a> for Reader<'a,>  {
+      fn into_internal_struct_reader(self) -> ::capnp::private::layout::StructReader<'a> {
+        self.reader
+      }
+    }
+
+    impl <'a,> ::capnp::traits::Imbue<'a> for Reader<'a,>  {
+      fn imbue(&mut self, cap_table: &'a ::capnp::private::layout::CapTable) {
+        self.reader.imbue(::capnp::private::layout::CapTableReader::Plain(cap_table))
+      }
+    }
+
+    impl <'a,> Reader<'a,>  {
+      pub fn reborrow(&self) -> Reader<> {
+        Reader { .. *self }
+      }
+
+      pub fn total_size(&self) -> ::capnp::Result<::capnp::MessageSize> {
+        self.reader.total_size()
+      }
+      #[inline]
+      pub fn get_channel_id(self) -> u32 {
+        self.reader.get_data_field::<u32>(0)
+      }
+      #[inline]
+      pub fn get_message_hash(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
+      }
+      pub fn has_message_hash(&self) -> bool {
+        !self.reader.get_pointer_field(0).is_null()
+      }
+      #[inline]
+      pub fn get_next_index(self) -> u32 {
+        self.reader.get_data_field::<u32>(1)
+      }
+    }
+
+    pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
+    impl <'a,> ::capnp::traits::HasStructSize for Builder<'a,>  {
+      #[inline]
+      fn struct_size() -> ::capnp::private::layout::StructSize { _private::STRUCT_SIZE }
+    }
+    impl <'a,> ::capnp::traits::HasTypeId for Builder<'a,>  {
+      #[inline]
+      fn type_id() -> u64 { _private::TYPE_ID }
+    }
+    impl <'a,> ::capnp::traits::FromStructBuilder<'a> for Builder<'a,>  {
+      fn new(builder: ::capnp::private::layout::StructBuilder<'a>) -> Builder<'a, > {
+        Builder { builder: builder,  }
+      }
+    }
+
+    impl <'a,> ::capnp::traits::ImbueMut<'a> for Builder<'a,>  {
+      fn imbue_mut(&mut self, cap_table: &'a mut ::capnp::private::layout::CapTable) {
+        self.builder.imbue(::capnp::private::layout::CapTableBuilder::Plain(cap_table))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::FromPointerBuilder<'a> for Builder<'a,>  {
+      fn init_pointer(builder: ::capnp::private::layout::PointerBuilder<'a>, _size: u32) -> Builder<'a,> {
+        ::capnp::traits::FromStructBuilder::new(builder.init_struct(_private::STRUCT_SIZE))
+      }
+      fn get_from_pointer(builder: ::capnp::private::layout::PointerBuilder<'a>, default: ::std::option::Option<&'a [::capnp::Word]>) -> ::capnp::Result<Builder<'a,>> {
+        ::std::result::Result::Ok(::capnp::traits::FromStructBuilder::new(builder.get_struct(_private::STRUCT_SIZE, default)?))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::SetPointerBuilder<Builder<'a,>> for Reader<'a,>  {
+      fn set_pointer_builder<'b>(pointer: ::capnp::private::layout::PointerBuilder<'b>, value: Reader<'a,>, canonicalize: bool) -> ::capnp::Result<()> { pointer.set_struct(&value.reader, canonicalize) }
+    }
+
+    impl <'a,> Builder<'a,>  {
+      pub fn into_reader(self) -> Reader<'a,> {
+        ::capnp::traits::FromStructReader::new(self.builder.into_reader())
+      }
+      pub fn reborrow(&mut self) -> Builder<> {
+        Builder { .. *self }
+      }
+      pub fn reborrow_as_reader(&self) -> Reader<> {
+        ::capnp::traits::FromStructReader::new(self.builder.into_reader())
+      }
+
+      pub fn total_size(&self) -> ::capnp::Result<::capnp::MessageSize> {
+        self.builder.into_reader().total_size()
+      }
+      #[inline]
+      pub fn get_channel_id(self) -> u32 {
+        self.builder.get_data_field::<u32>(0)
+      }
+      #[inline]
+      pub fn set_channel_id(&mut self, value: u32)  {
+        self.builder.set_data_field::<u32>(0, value);
+      }
+      #[inline]
+      pub fn get_message_hash(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_message_hash(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(0).set_data(value);
+      }
+      #[inline]
+      pub fn init_message_hash(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(0).init_data(size)
+      }
+      pub fn has_message_hash(&self) -> bool {
+        !self.builder.get_pointer_field(0).is_null()
+      }
+      #[inline]
+      pub fn get_next_index(self) -> u32 {
+        self.builder.get_data_field::<u32>(1)
+      }
+      #[inline]
+      pub fn set_next_index(&mut self, value: u32)  {
+        self.builder.set_data_field::<u32>(1, value);
+      }
+    }
+
+    pub struct Pipeline { _typeless: ::capnp::any_pointer::Pipeline }
+    impl ::capnp::capability::FromTypelessPipeline for Pipeline {
+      fn new(typeless: ::capnp::any_pointer::Pipeline) -> Pipeline {
+        Pipeline { _typeless: typeless,  }
+      }
+    }
+    impl Pipeline  {
+    }
+    mod _private {
+      use capnp::private::layout;
+      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 1, pointers: 1 };
+      pub const TYPE_ID: u64 = 0x913e_fdca_2f1d_1344;
+    }
+  }
 }
@@ -0,0 +1,807 @@
+//! Typed encoding / decoding of the `P2pMessage` wire protocol (see `protocol/p2p.capnp`).
+
+use crate::{error::P2pProtocolResult, p2p_capnp::p2p_message};
+use capnp::{message, serialize_packed};
+
+/// All message types of the node-to-node p2p protocol.
+/// Note: use same order as the capnp union
+#[derive(Debug, Clone, PartialEq)]
+pub enum P2pMessage {
+    MsgError(MsgError),
+    MsgHandshake(MsgHandshake),
+    MsgGspArcRequest(MsgGspArc),
+    MsgGspArcResponse(MsgGspArc),
+    MsgGspAspectDataRequest(MsgGspAspectDataRequest),
+    MsgGspAspectDataResponse(MsgGspAspectDataResponse),
+    MsgGspAspectBroadcast(MsgGspAspectBroadcast),
+    MsgDirectRequest(MsgDirect),
+    MsgDirectResponse(MsgDirect),
+    MsgQueryRequest(MsgQuery),
+    MsgQueryResponse(MsgQuery),
+    MsgJoinSpace(MsgJoinSpace),
+    MsgDhtGossip(MsgDhtGossip),
+    MsgLeaveSpace(MsgLeaveSpace),
+    MsgDhtGossipAck(MsgDhtGossip),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+    Unknown,
+    BadNetworkId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgError {
+    pub error_code: ErrorCode,
+    pub error_text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgHandshake {
+    pub store_arc_radius: u32,
+    pub query_arc_radius: u32,
+    pub space_address: Vec<u8>,
+    pub peer_address: Vec<u8>,
+    pub timestamp: u64,
+    pub signer_pub_key: Vec<u8>,
+    pub signature: Vec<u8>,
+    pub network_id: Vec<u8>,
+    pub nonce: Vec<u8>,
+    /// Empty until the remote's nonce is known
+    pub remote_nonce: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgGspArc {
+    pub aspect_constraint_arc: AspectConstraintArc,
+    pub aspect_constraint_time: AspectConstraintTime,
+    pub aspect_constraint_count: AspectConstraintCount,
+    pub aspect_hash_list: Vec<AspectHashList>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgGspAspectDataRequest {
+    pub aspect_hash_list: Vec<AspectHashList>,
+    pub request_id: String,
+    pub space_address: Vec<u8>,
+    pub to_agent_id: Vec<u8>,
+    pub from_agent_id: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgGspAspectDataResponse {
+    pub aspect_data_list: Vec<AspectDataList>,
+    pub request_id: String,
+    pub space_address: Vec<u8>,
+    pub to_agent_id: Vec<u8>,
+    pub from_agent_id: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgGspAspectBroadcast {
+    pub aspect_data_list: Vec<AspectDataList>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgDirect {
+    pub request_id: String,
+    pub data: Vec<u8>,
+    pub space_address: Vec<u8>,
+    pub to_agent_id: Vec<u8>,
+    pub from_agent_id: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgQuery {
+    pub request_id: String,
+    pub entry_address: Vec<u8>,
+    pub data: Vec<u8>,
+    pub space_address: Vec<u8>,
+    pub to_agent_id: Vec<u8>,
+    pub from_agent_id: Vec<u8>,
+    /// Response only: the destination could not answer, `data` holds the reason
+    pub failed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgJoinSpace {
+    pub peer_list: Vec<PeerInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgDhtGossip {
+    pub space_address: Vec<u8>,
+    pub to_peer_address: Vec<u8>,
+    pub from_peer_address: Vec<u8>,
+    pub bundle: Vec<u8>,
+    /// 0 if no acknowledgement is expected
+    pub gossip_id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgLeaveSpace {
+    pub space_address: Vec<u8>,
+    pub peer_address: Vec<u8>,
+    pub peer_uri: String,
+    /// Must be newer than the last PeerInfo of the peer
+    pub timestamp: u64,
+    pub signer_pub_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AspectHashList {
+    pub entry_address: Vec<u8>,
+    pub aspect_hash_list: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AspectDataList {
+    pub entry_address: Vec<u8>,
+    pub aspect_data_list: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AspectConstraintArc {
+    pub arc_start: u32,
+    pub arc_end: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AspectConstraintTime {
+    pub gte_epoch_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AspectConstraintCount {
+    pub gte_local_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerInfo {
+    pub space_address: Vec<u8>,
+    pub peer_address: Vec<u8>,
+    pub peer_uri: String,
+    pub timestamp: u64,
+    pub signer_pub_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Signing
+//--------------------------------------------------------------------------------------------------
+
+/// Append a length-prefixed field to a signing buffer
+fn push_signed_field(buf: &mut Vec<u8>, field: &[u8]) {
+    buf.extend_from_slice(&(field.len() as u32).to_le_bytes());
+    buf.extend_from_slice(field);
+}
+
+impl MsgHandshake {
+    /// Canonical bytes covered by `signature`
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        push_signed_field(&mut buf, &self.space_address);
+        push_signed_field(&mut buf, &self.peer_address);
+        buf.extend_from_slice(&self.timestamp.to_le_bytes());
+        push_signed_field(&mut buf, &self.signer_pub_key);
+        push_signed_field(&mut buf, &self.network_id);
+        push_signed_field(&mut buf, &self.nonce);
+        push_signed_field(&mut buf, &self.remote_nonce);
+        buf
+    }
+}
+
+impl PeerInfo {
+    /// Canonical bytes covered by `signature`
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        push_signed_field(&mut buf, &self.space_address);
+        push_signed_field(&mut buf, &self.peer_address);
+        push_signed_field(&mut buf, self.peer_uri.as_bytes());
+        buf.extend_from_slice(&self.timestamp.to_le_bytes());
+        push_signed_field(&mut buf, &self.signer_pub_key);
+        buf
+    }
+}
+
+/// Domain tag that keeps a signed PeerInfo from being replayed as a leave
+const LEAVE_SIGNING_TAG: &[u8] = b"leave";
+
+impl MsgLeaveSpace {
+    /// Canonical bytes covered by `signature`
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        push_signed_field(&mut buf, LEAVE_SIGNING_TAG);
+        push_signed_field(&mut buf, &self.space_address);
+        push_signed_field(&mut buf, &self.peer_address);
+        push_signed_field(&mut buf, self.peer_uri.as_bytes());
+        buf.extend_from_slice(&self.timestamp.to_le_bytes());
+        push_signed_field(&mut buf, &self.signer_pub_key);
+        buf
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Encoding / Decoding
+//--------------------------------------------------------------------------------------------------
+
+impl P2pMessage {
+    /// Encode into a packed capnp frame
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut message = message::Builder::new_default();
+        {
+            let root = message.init_root::<p2p_message::Builder>();
+            // Note: use same order as the enum
+            match self {
+                P2pMessage::MsgError(msg) => msg.write(root.init_msg_error()),
+                P2pMessage::MsgHandshake(msg) => msg.write(root.init_msg_handshake()),
+                P2pMessage::MsgGspArcRequest(msg) => msg.write(root.init_msg_gsp_arc_request()),
+                P2pMessage::MsgGspArcResponse(msg) => msg.write(root.init_msg_gsp_arc_response()),
+                P2pMessage::MsgGspAspectDataRequest(msg) => {
+                    msg.write(root.init_msg_gsp_aspect_data_request())
+                }
+                P2pMessage::MsgGspAspectDataResponse(msg) => {
+                    msg.write(root.init_msg_gsp_aspect_data_response())
+                }
+                P2pMessage::MsgGspAspectBroadcast(msg) => {
+                    msg.write(root.init_msg_gsp_aspect_broadcast())
+                }
+                P2pMessage::MsgDirectRequest(msg) => msg.write(root.init_msg_direct_request()),
+                P2pMessage::MsgDirectResponse(msg) => msg.write(root.init_msg_direct_response()),
+                P2pMessage::MsgQueryRequest(msg) => msg.write(root.init_msg_query_request()),
+                P2pMessage::MsgQueryResponse(msg) => msg.write(root.init_msg_query_response()),
+                P2pMessage::MsgJoinSpace(msg) => msg.write(root.init_msg_join_space()),
+                P2pMessage::MsgDhtGossip(msg) => msg.write(root.init_msg_dht_gossip()),
+                P2pMessage::MsgLeaveSpace(msg) => msg.write(root.init_msg_leave_space()),
+                P2pMessage::MsgDhtGossipAck(msg) => msg.write(root.init_msg_dht_gossip_ack()),
+            }
+        }
+        let mut buf = Vec::new();
+        serialize_packed::write_message(&mut buf, &message)
+            .expect("Writing to a Vec should never fail");
+        buf
+    }
+
+    /// Decode a packed capnp frame
+    pub fn from_bytes(bytes: &[u8]) -> P2pProtocolResult<Self> {
+        let reader =
+            serialize_packed::read_message(&mut &bytes[..], message::ReaderOptions::new())?;
+        let root = reader.get_root::<p2p_message::Reader>()?;
+        // Note: use same order as the enum
+        let msg = match root.which()? {
+            p2p_message::MsgError(r) => P2pMessage::MsgError(MsgError::read(r?)?),
+            p2p_message::MsgHandshake(r) => P2pMessage::MsgHandshake(MsgHandshake::read(r?)?),
+            p2p_message::MsgGspArcRequest(r) => P2pMessage::MsgGspArcRequest(MsgGspArc::read(r?)?),
+            p2p_message::MsgGspArcResponse(r) => {
+                P2pMessage::MsgGspArcResponse(MsgGspArc::read(r?)?)
+            }
+            p2p_message::MsgGspAspectDataRequest(r) => {
+                P2pMessage::MsgGspAspectDataRequest(MsgGspAspectDataRequest::read(r?)?)
+            }
+            p2p_message::MsgGspAspectDataResponse(r) => {
+                P2pMessage::MsgGspAspectDataResponse(MsgGspAspectDataResponse::read(r?)?)
+            }
+            p2p_message::MsgGspAspectBroadcast(r) => {
+                P2pMessage::MsgGspAspectBroadcast(MsgGspAspectBroadcast::read(r?)?)
+            }
+            p2p_message::MsgDirectRequest(r) => P2pMessage::MsgDirectRequest(MsgDirect::read(r?)?),
+            p2p_message::MsgDirectResponse(r) => {
+                P2pMessage::MsgDirectResponse(MsgDirect::read(r?)?)
+            }
+            p2p_message::MsgQueryRequest(r) => P2pMessage::MsgQueryRequest(MsgQuery::read(r?)?),
+            p2p_message::MsgQueryResponse(r) => P2pMessage::MsgQueryResponse(MsgQuery::read(r?)?),
+            p2p_message::MsgJoinSpace(r) => P2pMessage::MsgJoinSpace(MsgJoinSpace::read(r?)?),
+            p2p_message::MsgDhtGossip(r) => P2pMessage::MsgDhtGossip(MsgDhtGossip::read(r?)?),
+            p2p_message::MsgLeaveSpace(r) => P2pMessage::MsgLeaveSpace(MsgLeaveSpace::read(r?)?),
+            p2p_message::MsgDhtGossipAck(r) => P2pMessage::MsgDhtGossipAck(MsgDhtGossip::read(r?)?),
+        };
+        Ok(msg)
+    }
+}
+
+impl MsgError {
+    fn write(&self, mut builder: p2p_message::msg_error::Builder) {
+        builder.set_error_code(match self.error_code {
+            ErrorCode::Unknown => p2p_message::msg_error::ErrorCode::Unknown,
+            ErrorCode::BadNetworkId => p2p_message::msg_error::ErrorCode::BadNetworkId,
+        });
+        builder.set_error_text(&self.error_text);
+    }
+
+    fn read(reader: p2p_message::msg_error::Reader) -> P2pProtocolResult<Self> {
+        let error_code = match reader.get_error_code()? {
+            p2p_message::msg_error::ErrorCode::Unknown => ErrorCode::Unknown,
+            p2p_message::msg_error::ErrorCode::BadNetworkId => ErrorCode::BadNetworkId,
+        };
+        Ok(MsgError {
+            error_code,
+            error_text: reader.get_error_text()?.to_string(),
+        })
+    }
+}
+
+impl MsgHandshake {
+    fn write(&self, mut builder: p2p_message::msg_handshake::Builder) {
+        builder.set_store_arc_radius(self.store_arc_radius);
+        builder.set_query_arc_radius(self.query_arc_radius);
+        builder.set_space_address(&self.space_address);
+        builder.set_peer_address(&self.peer_address);
+        builder.set_timestamp(self.timestamp);
+        builder.set_signer_pub_key(&self.signer_pub_key);
+        builder.set_signature(&self.signature);
+        builder.set_network_id(&self.network_id);
+        builder.set_nonce(&self.nonce);
+        builder.set_remote_nonce(&self.remote_nonce);
+    }
+
+    fn read(reader: p2p_message::msg_handshake::Reader) -> P2pProtocolResult<Self> {
+        Ok(MsgHandshake {
+            store_arc_radius: reader.get_store_arc_radius(),
+            query_arc_radius: reader.get_query_arc_radius(),
+            space_address: reader.get_space_address()?.to_vec(),
+            peer_address: reader.get_peer_address()?.to_vec(),
+            timestamp: reader.get_timestamp(),
+            signer_pub_key: reader.get_signer_pub_key()?.to_vec(),
+            signature: reader.get_signature()?.to_vec(),
+            network_id: reader.get_network_id()?.to_vec(),
+            nonce: reader.get_nonce()?.to_vec(),
+            remote_nonce: reader.get_remote_nonce()?.to_vec(),
+        })
+    }
+}
+
+impl MsgGspArc {
+    fn write(&self, mut builder: p2p_message::msg_gsp_arc::Builder) {
+        {
+            let mut arc = builder.reborrow().init_aspect_constraint_arc();
+            arc.set_arc_start(self.aspect_constraint_arc.arc_start);
+            arc.set_arc_end(self.aspect_constraint_arc.arc_end);
+        }
+        builder
+            .reborrow()
+            .init_aspect_constraint_time()
+            .set_gte_epoch_ms(self.aspect_constraint_time.gte_epoch_ms);
+        builder
+            .reborrow()
+            .init_aspect_constraint_count()
+            .set_gte_local_count(self.aspect_constraint_count.gte_local_count);
+        let mut list = builder.init_aspect_hash_list(self.aspect_hash_list.len() as u32);
+        for (i, item) in self.aspect_hash_list.iter().enumerate() {
+            item.write(list.reborrow().get(i as u32));
+        }
+    }
+
+    fn read(reader: p2p_message::msg_gsp_arc::Reader) -> P2pProtocolResult<Self> {
+        let arc = reader.get_aspect_constraint_arc()?;
+        let list = reader.get_aspect_hash_list()?;
+        let mut aspect_hash_list = Vec::with_capacity(list.len() as usize);
+        for i in 0..list.len() {
+            aspect_hash_list.push(AspectHashList::read(list.get(i))?);
+        }
+        Ok(MsgGspArc {
+            aspect_constraint_arc: AspectConstraintArc {
+                arc_start: arc.get_arc_start(),
+                arc_end: arc.get_arc_end(),
+            },
+            aspect_constraint_time: AspectConstraintTime {
+                gte_epoch_ms: reader.get_aspect_constraint_time()?.get_gte_epoch_ms(),
+            },
+            aspect_constraint_count: AspectConstraintCount {
+                gte_local_count: reader.get_aspect_constraint_count()?.get_gte_local_count(),
+            },
+            aspect_hash_list,
+        })
+    }
+}
+
+impl MsgGspAspectDataRequest {
+    fn write(&self, mut builder: p2p_message::msg_gsp_aspect_data_request::Builder) {
+        {
+            let mut list = builder
+                .reborrow()
+                .init_aspect_hash_list(self.aspect_hash_list.len() as u32);
+            for (i, item) in self.aspect_hash_list.iter().enumerate() {
+                item.write(list.reborrow().get(i as u32));
+            }
+        }
+        builder.set_request_id(&self.request_id);
+        builder.set_space_address(&self.space_address);
+        builder.set_to_agent_id(&self.to_agent_id);
+        builder.set_from_agent_id(&self.from_agent_id);
+    }
+
+    fn read(reader: p2p_message::msg_gsp_aspect_data_request::Reader) -> P2pProtocolResult<Self> {
+        let list = reader.get_aspect_hash_list()?;
+        let mut aspect_hash_list = Vec::with_capacity(list.len() as usize);
+        for i in 0..list.len() {
+            aspect_hash_list.push(AspectHashList::read(list.get(i))?);
+        }
+        Ok(MsgGspAspectDataRequest {
+            aspect_hash_list,
+            request_id: reader.get_request_id()?.to_string(),
+            space_address: reader.get_space_address()?.to_vec(),
+            to_agent_id: reader.get_to_agent_id()?.to_vec(),
+            from_agent_id: reader.get_from_agent_id()?.to_vec(),
+        })
+    }
+}
+
+impl MsgGspAspectDataResponse {
+    fn write(&self, mut builder: p2p_message::msg_gsp_aspect_data_response::Builder) {
+        {
+            let mut list = builder
+                .reborrow()
+                .init_aspect_data_list(self.aspect_data_list.len() as u32);
+            for (i, item) in self.aspect_data_list.iter().enumerate() {
+                item.write(list.reborrow().get(i as u32));
+            }
+        }
+        builder.set_request_id(&self.request_id);
+        builder.set_space_address(&self.space_address);
+        builder.set_to_agent_id(&self.to_agent_id);
+        builder.set_from_agent_id(&self.from_agent_id);
+    }
+
+    fn read(reader: p2p_message::msg_gsp_aspect_data_response::Reader) -> P2pProtocolResult<Self> {
+        let list = reader.get_aspect_data_list()?;
+        let mut aspect_data_list = Vec::with_capacity(list.len() as usize);
+        for i in 0..list.len() {
+            aspect_data_list.push(AspectDataList::read(list.get(i))?);
+        }
+        Ok(MsgGspAspectDataResponse {
+            aspect_data_list,
+            request_id: reader.get_request_id()?.to_string(),
+            space_address: reader.get_space_address()?.to_vec(),
+            to_agent_id: reader.get_to_agent_id()?.to_vec(),
+            from_agent_id: reader.get_from_agent_id()?.to_vec(),
+        })
+    }
+}
+
+impl MsgGspAspectBroadcast {
+    fn write(&self, builder: p2p_message::msg_gsp_aspect_broadcast::Builder) {
+        let mut list = builder.init_aspect_data_list(self.aspect_data_list.len() as u32);
+        for (i, item) in self.aspect_data_list.iter().enumerate() {
+            item.write(list.reborrow().get(i as u32));
+        }
+    }
+
+    fn read(reader: p2p_message::msg_gsp_aspect_broadcast::Reader) -> P2pProtocolResult<Self> {
+        let list = reader.get_aspect_data_list()?;
+        let mut aspect_data_list = Vec::with_capacity(list.len() as usize);
+        for i in 0..list.len() {
+            aspect_data_list.push(AspectDataList::read(list.get(i))?);
+        }
+        Ok(MsgGspAspectBroadcast { aspect_data_list })
+    }
+}
+
+impl MsgDirect {
+    fn write(&self, mut builder: p2p_message::msg_direct::Builder) {
+        builder.set_request_id(&self.request_id);
+        builder.set_data(&self.data);
+        builder.set_space_address(&self.space_address);
+        builder.set_to_agent_id(&self.to_agent_id);
+        builder.set_from_agent_id(&self.from_agent_id);
+    }
+
+    fn read(reader: p2p_message::msg_direct::Reader) -> P2pProtocolResult<Self> {
+        Ok(MsgDirect {
+            request_id: reader.get_request_id()?.to_string(),
+            data: reader.get_data()?.to_vec(),
+            space_address: reader.get_space_address()?.to_vec(),
+            to_agent_id: reader.get_to_agent_id()?.to_vec(),
+            from_agent_id: reader.get_from_agent_id()?.to_vec(),
+        })
+    }
+}
+
+impl MsgQuery {
+    fn write(&self, mut builder: p2p_message::msg_query::Builder) {
+        builder.set_request_id(&self.request_id);
+        builder.set_entry_address(&self.entry_address);
+        builder.set_data(&self.data);
+        builder.set_space_address(&self.space_address);
+        builder.set_to_agent_id(&self.to_agent_id);
+        builder.set_from_agent_id(&self.from_agent_id);
+        builder.set_failed(self.failed);
+    }
+
+    fn read(reader: p2p_message::msg_query::Reader) -> P2pProtocolResult<Self> {
+        Ok(MsgQuery {
+            request_id: reader.get_request_id()?.to_string(),
+            entry_address: reader.get_entry_address()?.to_vec(),
+            data: reader.get_data()?.to_vec(),
+            space_address: reader.get_space_address()?.to_vec(),
+            to_agent_id: reader.get_to_agent_id()?.to_vec(),
+            from_agent_id: reader.get_from_agent_id()?.to_vec(),
+            failed: reader.get_failed(),
+        })
+    }
+}
+
+impl MsgJoinSpace {
+    fn write(&self, builder: p2p_message::msg_join_space::Builder) {
+        let mut list = builder.init_peer_list(self.peer_list.len() as u32);
+        for (i, item) in self.peer_list.iter().enumerate() {
+            item.write(list.reborrow().get(i as u32));
+        }
+    }
+
+    fn read(reader: p2p_message::msg_join_space::Reader) -> P2pProtocolResult<Self> {
+        let list = reader.get_peer_list()?;
+        let mut peer_list = Vec::with_capacity(list.len() as usize);
+        for i in 0..list.len() {
+            peer_list.push(PeerInfo::read(list.get(i))?);
+        }
+        Ok(MsgJoinSpace { peer_list })
+    }
+}
+
+impl MsgDhtGossip {
+    fn write(&self, mut builder: p2p_message::msg_dht_gossip::Builder) {
+        builder.set_space_address(&self.space_address);
+        builder.set_to_peer_address(&self.to_peer_address);
+        builder.set_from_peer_address(&self.from_peer_address);
+        builder.set_bundle(&self.bundle);
+        builder.set_gossip_id(self.gossip_id);
+    }
+
+    fn read(reader: p2p_message::msg_dht_gossip::Reader) -> P2pProtocolResult<Self> {
+        Ok(MsgDhtGossip {
+            space_address: reader.get_space_address()?.to_vec(),
+            to_peer_address: reader.get_to_peer_address()?.to_vec(),
+            from_peer_address: reader.get_from_peer_address()?.to_vec(),
+            bundle: reader.get_bundle()?.to_vec(),
+            gossip_id: reader.get_gossip_id(),
+        })
+    }
+}
+
+impl MsgLeaveSpace {
+    fn write(&self, mut builder: p2p_message::msg_leave_space::Builder) {
+        builder.set_space_address(&self.space_address);
+        builder.set_peer_address(&self.peer_address);
+        builder.set_peer_uri(&self.peer_uri);
+        builder.set_timestamp(self.timestamp);
+        builder.set_signer_pub_key(&self.signer_pub_key);
+        builder.set_signature(&self.signature);
+    }
+
+    fn read(reader: p2p_message::msg_leave_space::Reader) -> P2pProtocolResult<Self> {
+        Ok(MsgLeaveSpace {
+            space_address: reader.get_space_address()?.to_vec(),
+            peer_address: reader.get_peer_address()?.to_vec(),
+            peer_uri: reader.get_peer_uri()?.to_string(),
+            timestamp: reader.get_timestamp(),
+            signer_pub_key: reader.get_signer_pub_key()?.to_vec(),
+            signature: reader.get_signature()?.to_vec(),
+        })
+    }
+}
+
+impl AspectHashList {
+    fn write(&self, mut builder: p2p_message::aspect_hash_list::Builder) {
+        builder.set_entry_address(&self.entry_address);
+        let mut list = builder.init_aspect_hash_list(self.aspect_hash_list.len() as u32);
+        for (i, item) in self.aspect_hash_list.iter().enumerate() {
+            list.set(i as u32, item);
+        }
+    }
+
+    fn read(reader: p2p_message::aspect_hash_list::Reader) -> P2pProtocolResult<Self> {
+        let list = reader.get_aspect_hash_list()?;
+        let mut aspect_hash_list = Vec::with_capacity(list.len() as usize);
+        for i in 0..list.len() {
+            aspect_hash_list.push(list.get(i)?.to_vec());
+        }
+        Ok(AspectHashList {
+            entry_address: reader.get_entry_address()?.to_vec(),
+            aspect_hash_list,
+        })
+    }
+}
+
+impl AspectDataList {
+    fn write(&self, mut builder: p2p_message::aspect_data_list::Builder) {
+        builder.set_entry_address(&self.entry_address);
+        let mut list = builder.init_aspect_data_list(self.aspect_data_list.len() as u32);
+        for (i, item) in self.aspect_data_list.iter().enumerate() {
+            list.set(i as u32, item);
+        }
+    }
+
+    fn read(reader: p2p_message::aspect_data_list::Reader) -> P2pProtocolResult<Self> {
+        let list = reader.get_aspect_data_list()?;
+        let mut aspect_data_list = Vec::with_capacity(list.len() as usize);
+        for i in 0..list.len() {
+            aspect_data_list.push(list.get(i)?.to_vec());
+        }
+        Ok(AspectDataList {
+            entry_address: reader.get_entry_address()?.to_vec(),
+            aspect_data_list,
+        })
+    }
+}
+
+impl PeerInfo {
+    fn write(&self, mut builder: p2p_message::peer_info::Builder) {
+        builder.set_space_address(&self.space_address);
+        builder.set_peer_address(&self.peer_address);
+        builder.set_peer_uri(&self.peer_uri);
+        builder.set_timestamp(self.timestamp);
+        builder.set_signer_pub_key(&self.signer_pub_key);
+        builder.set_signature(&self.signature);
+    }
+
+    fn read(reader: p2p_message::peer_info::Reader) -> P2pProtocolResult<Self> {
+        Ok(PeerInfo {
+            space_address: reader.get_space_address()?.to_vec(),
+            peer_address: reader.get_peer_address()?.to_vec(),
+            peer_uri: reader.get_peer_uri()?.to_string(),
+            timestamp: reader.get_timestamp(),
+            signer_pub_key: reader.get_signer_pub_key()?.to_vec(),
+            signature: reader.get_signature()?.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aspect_hash_list() -> Vec<AspectHashList> {
+        vec![AspectHashList {
+            entry_address: b"entry_addr_1".to_vec(),
+            aspect_hash_list: vec![b"aspect_addr_1".to_vec(), b"aspect_addr_2".to_vec()],
+        }]
+    }
+
+    fn aspect_data_list() -> Vec<AspectDataList> {
+        vec![AspectDataList {
+            entry_address: b"entry_addr_1".to_vec(),
+            aspect_data_list: vec![b"hello-1".to_vec(), vec![]],
+        }]
+    }
+
+    fn check_round_trip(msg: P2pMessage) {
+        let bytes = msg.to_bytes();
+        let decoded = P2pMessage::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, msg);
+    }
+
+    #[test]
+    fn it_should_round_trip_every_message_type() {
+        let arc_msg = MsgGspArc {
+            aspect_constraint_arc: AspectConstraintArc {
+                arc_start: 42,
+                arc_end: 0xffff_0000,
+            },
+            aspect_constraint_time: AspectConstraintTime { gte_epoch_ms: 1234 },
+            aspect_constraint_count: AspectConstraintCount { gte_local_count: 7 },
+            aspect_hash_list: aspect_hash_list(),
+        };
+        let direct_msg = MsgDirect {
+            request_id: "req_1".to_string(),
+            data: b"hello".to_vec(),
+            space_address: b"space_1".to_vec(),
+            to_agent_id: b"billy".to_vec(),
+            from_agent_id: b"alex".to_vec(),
+        };
+        let query_msg = MsgQuery {
+            request_id: "req_2".to_string(),
+            entry_address: b"entry_addr_1".to_vec(),
+            data: b"query".to_vec(),
+            space_address: b"space_1".to_vec(),
+            to_agent_id: b"billy".to_vec(),
+            from_agent_id: b"alex".to_vec(),
+            failed: false,
+        };
+        let peer_list = vec![PeerInfo {
+            space_address: b"space_1".to_vec(),
+            peer_address: b"alex".to_vec(),
+            peer_uri: "transportId:hcm0_alex".to_string(),
+            timestamp: 1_563_000_000_000,
+            signer_pub_key: vec![1; 32],
+            signature: vec![2; 64],
+        }];
+        let msg_list = vec![
+            P2pMessage::MsgError(MsgError {
+                error_code: ErrorCode::Unknown,
+                error_text: "oops".to_string(),
+            }),
+            P2pMessage::MsgError(MsgError {
+                error_code: ErrorCode::BadNetworkId,
+                error_text: "not in network_1".to_string(),
+            }),
+            P2pMessage::MsgHandshake(MsgHandshake {
+                store_arc_radius: 0x8000_0000,
+                query_arc_radius: 12,
+                space_address: b"__network__".to_vec(),
+                peer_address: b"hcm0_alex".to_vec(),
+                timestamp: 1_563_000_000_000,
+                signer_pub_key: vec![1; 32],
+                signature: vec![2; 64],
+                network_id: b"network_1".to_vec(),
+                nonce: vec![3; 32],
+                remote_nonce: vec![4; 32],
+            }),
+            P2pMessage::MsgGspArcRequest(arc_msg.clone()),
+            P2pMessage::MsgGspArcResponse(arc_msg),
+            P2pMessage::MsgGspAspectDataRequest(MsgGspAspectDataRequest {
+                aspect_hash_list: aspect_hash_list(),
+                request_id: "req_2".to_string(),
+                space_address: b"space_1".to_vec(),
+                to_agent_id: b"billy".to_vec(),
+                from_agent_id: b"alex".to_vec(),
+            }),
+            P2pMessage::MsgGspAspectDataResponse(MsgGspAspectDataResponse {
+                aspect_data_list: aspect_data_list(),
+                request_id: "req_2".to_string(),
+                space_address: b"space_1".to_vec(),
+                to_agent_id: b"billy".to_vec(),
+                from_agent_id: b"alex".to_vec(),
+            }),
+            P2pMessage::MsgGspAspectBroadcast(MsgGspAspectBroadcast {
+                aspect_data_list: aspect_data_list(),
+            }),
+            P2pMessage::MsgDirectRequest(direct_msg.clone()),
+            P2pMessage::MsgDirectResponse(direct_msg),
+            P2pMessage::MsgQueryRequest(query_msg.clone()),
+            P2pMessage::MsgQueryResponse(MsgQuery {
+                failed: true,
+                ..query_msg
+            }),
+            P2pMessage::MsgJoinSpace(MsgJoinSpace { peer_list }),
+            P2pMessage::MsgDhtGossip(MsgDhtGossip {
+                space_address: b"space_1".to_vec(),
+                to_peer_address: b"billy".to_vec(),
+                from_peer_address: b"alex".to_vec(),
+                bundle: vec![0, 1, 2, 3],
+                gossip_id: 42,
+            }),
+            P2pMessage::MsgLeaveSpace(leave_msg()),
+            P2pMessage::MsgDhtGossipAck(MsgDhtGossip {
+                space_address: b"space_1".to_vec(),
+                to_peer_address: b"alex".to_vec(),
+                from_peer_address: b"billy".to_vec(),
+                bundle: vec![],
+                gossip_id: 42,
+            }),
+        ];
+        for msg in msg_list {
+            check_round_trip(msg);
+        }
+    }
+
+    fn leave_msg() -> MsgLeaveSpace {
+        MsgLeaveSpace {
+            space_address: b"space_1".to_vec(),
+            peer_address: b"alex".to_vec(),
+            peer_uri: "transportId:hcm0_alex".to_string(),
+            timestamp: 1_563_000_000_001,
+            signer_pub_key: vec![1; 32],
+            signature: vec![2; 64],
+        }
+    }
+
+    #[test]
+    fn it_should_not_sign_a_leave_like_a_peer_info() {
+        let leave = leave_msg();
+        let peer_info = PeerInfo {
+            space_address: leave.space_address.clone(),
+            peer_address: leave.peer_address.clone(),
+            peer_uri: leave.peer_uri.clone(),
+            timestamp: leave.timestamp,
+            signer_pub_key: leave.signer_pub_key.clone(),
+            signature: leave.signature.clone(),
+        };
+        assert_ne!(leave.signed_bytes(), peer_info.signed_bytes());
+    }
+
+    #[test]
+    fn it_should_fail_on_garbage() {
+        assert!(P2pMessage::from_bytes(&[]).is_err());
+    }
+}
@@ -4,7 +4,7 @@
 
 
 pub mod p2p_message {
-  pub use self::Which::{MsgError,MsgHandshake,MsgGspArcRequest,MsgGspArcResponse,MsgGspAspectDataRequest,MsgGspAspectDataResponse,MsgGspAspectBroadcast,MsgDirectRequest,MsgDirectResponse,MsgQueryRequest,MsgQueryResponse};
+  pub use self::Which::{MsgError,MsgHandshake,MsgGspArcRequest,MsgGspArcResponse,MsgGspAspectDataRequest,MsgGspAspectDataResponse,MsgGspAspectBroadcast,MsgDirectRequest,MsgDirectResponse,MsgQueryRequest,MsgQueryResponse,MsgJoinSpace,MsgDhtGossip,MsgLeaveSpace,MsgDhtGossipAck};
 
   #[derive(Copy, Clone)]
   pub struct Owned;
@@ -95,6 +95,22 @@ pub mod p2p_message {
       if self.reader.get_data_field::<u16>(0) != 10 { return false; }
       !self.reader.get_pointer_field(0).is_null()
     }
+    pub fn has_msg_join_space(&self) -> bool {
+      if self.reader.get_data_field::<u16>(0) != 11 { return false; }
+      !self.reader.get_pointer_field(0).is_null()
+    }
+    pub fn has_msg_dht_gossip(&self) -> bool {
+      if self.reader.get_data_field::<u16>(0) != 12 { return false; }
+      !self.reader.get_pointer_field(0).is_null()
+    }
+    pub fn has_msg_leave_space(&self) -> bool {
+      if self.reader.get_data_field::<u16>(0) != 13 { return false; }
+      !self.reader.get_pointer_field(0).is_null()
+    }
+    pub fn has_msg_dht_gossip_ack(&self) -> bool {
+      if self.reader.get_data_field::<u16>(0) != 14 { return false; }
+      !self.reader.get_pointer_field(0).is_null()
+    }
     #[inline]
     pub fn which(self) -> ::std::result::Result<WhichReader<'a,>, ::capnp::NotInSchema> {
       match self.reader.get_data_field::<u16>(0) {
@@ -153,6 +169,26 @@ pub mod p2p_message {
             ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
           ))
         }
+        11 => {
+          ::std::result::Result::Ok(MsgJoinSpace(
+            ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
+          ))
+        }
+        12 => {
+          ::std::result::Result::Ok(MsgDhtGossip(
+            ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
+          ))
+        }
+        13 => {
+          ::std::result::Result::Ok(MsgLeaveSpace(
+            ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
+          ))
+        }
+        14 => {
+          ::std::result::Result::Ok(MsgDhtGossipAck(
+            ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
+          ))
+        }
         x => ::std::result::Result::Err(::capnp::NotInSchema(x))
       }
     }
@@ -361,6 +397,62 @@ pub mod p2p_message {
       !self.builder.get_pointer_field(0).is_null()
     }
     #[inline]
+    pub fn set_msg_join_space<'b>(&mut self, value: crate::p2p_capnp::p2p_message::msg_join_space::Reader<'b>) -> ::capnp::Result<()> {
+      self.builder.set_data_field::<u16>(0, 11);
+      ::capnp::traits::SetPointerBuilder::set_pointer_builder(self.builder.get_pointer_field(0), value, false)
+    }
+    #[inline]
+    pub fn init_msg_join_space(self, ) -> crate::p2p_capnp::p2p_message::msg_join_space::Builder<'a> {
+      self.builder.set_data_field::<u16>(0, 11);
+      ::capnp::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), 0)
+    }
+    pub fn has_msg_join_space(&self) -> bool {
+      if self.builder.get_data_field::<u16>(0) != 11 { return false; }
+      !self.builder.get_pointer_field(0).is_null()
+    }
+    #[inline]
+    pub fn set_msg_dht_gossip<'b>(&mut self, value: crate::p2p_capnp::p2p_message::msg_dht_gossip::Reader<'b>) -> ::capnp::Result<()> {
+      self.builder.set_data_field::<u16>(0, 12);
+      ::capnp::traits::SetPointerBuilder::set_pointer_builder(self.builder.get_pointer_field(0), value, false)
+    }
+    #[inline]
+    pub fn init_msg_dht_gossip(self, ) -> crate::p2p_capnp::p2p_message::msg_dht_gossip::Builder<'a> {
+      self.builder.set_data_field::<u16>(0, 12);
+      ::capnp::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), 0)
+    }
+    pub fn has_msg_dht_gossip(&self) -> bool {
+      if self.builder.get_data_field::<u16>(0) != 12 { return false; }
+      !self.builder.get_pointer_field(0).is_null()
+    }
+    #[inline]
+    pub fn set_msg_leave_space<'b>(&mut self, value: crate::p2p_capnp::p2p_message::msg_leave_space::Reader<'b>) -> ::capnp::Result<()> {
+      self.builder.set_data_field::<u16>(0, 13);
+      ::capnp::traits::SetPointerBuilder::set_pointer_builder(self.builder.get_pointer_field(0), value, false)
+    }
+    #[inline]
+    pub fn init_msg_leave_space(self, ) -> crate::p2p_capnp::p2p_message::msg_leave_space::Builder<'a> {
+      self.builder.set_data_field::<u16>(0, 13);
+      ::capnp::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), 0)
+    }
+    pub fn has_msg_leave_space(&self) -> bool {
+      if self.builder.get_data_field::<u16>(0) != 13 { return false; }
+      !self.builder.get_pointer_field(0).is_null()
+    }
+    #[inline]
+    pub fn set_msg_dht_gossip_ack<'b>(&mut self, value: crate::p2p_capnp::p2p_message::msg_dht_gossip::Reader<'b>) -> ::capnp::Result<()> {
+      self.builder.set_data_field::<u16>(0, 14);
+      ::capnp::traits::SetPointerBuilder::set_pointer_builder(self.builder.get_pointer_field(0), value, false)
+    }
+    #[inline]
+    pub fn init_msg_dht_gossip_ack(self, ) -> crate::p2p_capnp::p2p_message::msg_dht_gossip::Builder<'a> {
+      self.builder.set_data_field::<u16>(0, 14);
+      ::capnp::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), 0)
+    }
+    pub fn has_msg_dht_gossip_ack(&self) -> bool {
+      if self.builder.get_data_field::<u16>(0) != 14 { return false; }
+      !self.builder.get_pointer_field(0).is_null()
+    }
+    #[inline]
     pub fn which(self) -> ::std::result::Result<WhichBuilder<'a,>, ::capnp::NotInSchema> {
       match self.builder.get_data_field::<u16>(0) {
         0 => {
@@ -418,6 +510,26 @@ pub mod p2p_message {
             ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
           ))
         }
+        11 => {
+          ::std::result::Result::Ok(MsgJoinSpace(
+            ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
+          ))
+        }
+        12 => {
+          ::std::result::Result::Ok(MsgDhtGossip(
+            ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
+          ))
+        }
+        13 => {
+          ::std::result::Result::Ok(MsgLeaveSpace(
+            ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
+          ))
+        }
+        14 => {
+          ::std::result::Result::Ok(MsgDhtGossipAck(
+            ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
+          ))
+        }
         x => ::std::result::Result::Err(::capnp::NotInSchema(x))
       }
     }
@@ -436,7 +548,7 @@ pub mod p2p_message {
     pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 1, pointers: 1 };
     pub const TYPE_ID: u64 = 0x8525_e0f5_2956_47a2;
   }
-  pub enum Which<A0,A1,A2,A3,A4,A5,A6,A7,A8,A9,A10> {
+  pub enum Which<A0,A1,A2,A3,A4,A5,A6,A7,A8,A9,A10,A11,A12,A13,A14> {
     MsgError(A0),
     MsgHandshake(A1),
     MsgGspArcRequest(A2),
@@ -448,9 +560,13 @@ pub mod p2p_message {
     MsgDirectResponse(A8),
     MsgQueryRequest(A9),
     MsgQueryResponse(A10),
+    MsgJoinSpace(A11),
+    MsgDhtGossip(A12),
+    MsgLeaveSpace(A13),
+    MsgDhtGossipAck(A14),
   }
-  pub type WhichReader<'a,> = Which<::capnp::Result<crate::p2p_capnp::p2p_message::msg_error::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_handshake::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_arc::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_arc::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_aspect_data_request::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_aspect_data_response::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_aspect_broadcast::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_direct::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_direct::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_query::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_query::Reader<'a>>>;
-  pub type WhichBuilder<'a,> = Which<::capnp::Result<crate::p2p_capnp::p2p_message::msg_error::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_handshake::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_arc::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_arc::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_aspect_data_request::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_aspect_data_response::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_aspect_broadcast::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_direct::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_direct::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_query::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_query::Builder<'a>>>;
+  pub type WhichReader<'a,> = Which<::capnp::Result<crate::p2p_capnp::p2p_message::msg_error::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_handshake::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_arc::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_arc::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_aspect_data_request::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_aspect_data_response::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_aspect_broadcast::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_direct::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_direct::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_query::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_query::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_join_space::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_dht_gossip::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_leave_space::Reader<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_dht_gossip::Reader<'a>>>;
+  pub type WhichBuilder<'a,> = Which<::capnp::Result<crate::p2p_capnp::p2p_message::msg_error::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_handshake::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_arc::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_arc::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_aspect_data_request::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_aspect_data_response::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_gsp_aspect_broadcast::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_direct::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_direct::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_query::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_query::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_join_space::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_dht_gossip::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_leave_space::Builder<'a>>,::capnp::Result<crate::p2p_capnp::p2p_message::msg_dht_gossip::Builder<'a>>>;
 
   pub mod msg_error {
     #[derive(Copy, Clone)]
@@ -602,12 +718,14 @@ pub mod p2p_message {
     #[derive(Clone, Copy, PartialEq)]
     pub enum ErrorCode {
       Unknown = 0,
+      BadNetworkId = 1,
     }
     impl ::capnp::traits::FromU16 for ErrorCode {
       #[inline]
       fn from_u16(value: u16) -> ::std::result::Result<ErrorCode, ::capnp::NotInSchema> {
         match value {
           0 => ::std::result::Result::Ok(ErrorCode::Unknown),
+          1 => ::std::result::Result::Ok(ErrorCode::BadNetworkId),
           n => ::std::result::Result::Err(::capnp::NotInSchema(n)),
         }
       }
@@ -676,6 +794,59 @@ pub mod p2p_message {
       pub fn get_query_arc_radius(self) -> u32 {
         self.reader.get_data_field::<u32>(1)
       }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.reader.get_pointer_field(0).is_null()
+      }
+      #[inline]
+      pub fn get_peer_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(1), ::std::option::Option::None)
+      }
+      pub fn has_peer_address(&self) -> bool {
+        !self.reader.get_pointer_field(1).is_null()
+      }
+      #[inline]
+      pub fn get_timestamp(self) -> u64 {
+        self.reader.get_data_field::<u64>(1)
+      }
+      #[inline]
+      pub fn get_signer_pub_key(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(2), ::std::option::Option::None)
+      }
+      pub fn has_signer_pub_key(&self) -> bool {
+        !self.reader.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_signature(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(3), ::std::option::Option::None)
+      }
+      pub fn has_signature(&self) -> bool {
+        !self.reader.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_network_id(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(4), ::std::option::Option::None)
+      }
+      pub fn has_network_id(&self) -> bool {
+        !self.reader.get_pointer_field(4).is_null()
+      }
+      #[inline]
+      pub fn get_nonce(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(5), ::std::option::Option::None)
+      }
+      pub fn has_nonce(&self) -> bool {
+        !self.reader.get_pointer_field(5).is_null()
+      }
+      #[inline]
+      pub fn get_remote_nonce(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(6), ::std::option::Option::None)
+      }
+      pub fn has_remote_nonce(&self) -> bool {
+        !self.reader.get_pointer_field(6).is_null()
+      }
     }
 
     pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
@@ -742,6 +913,119 @@ pub mod p2p_message {
       pub fn set_query_arc_radius(&mut self, value: u32)  {
         self.builder.set_data_field::<u32>(1, value);
       }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_space_address(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(0).set_data(value);
+      }
+      #[inline]
+      pub fn init_space_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(0).init_data(size)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.builder.get_pointer_field(0).is_null()
+      }
+      #[inline]
+      pub fn get_peer_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(1), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_peer_address(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(1).set_data(value);
+      }
+      #[inline]
+      pub fn init_peer_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(1).init_data(size)
+      }
+      pub fn has_peer_address(&self) -> bool {
+        !self.builder.get_pointer_field(1).is_null()
+      }
+      #[inline]
+      pub fn get_timestamp(self) -> u64 {
+        self.builder.get_data_field::<u64>(1)
+      }
+      #[inline]
+      pub fn set_timestamp(&mut self, value: u64)  {
+        self.builder.set_data_field::<u64>(1, value);
+      }
+      #[inline]
+      pub fn get_signer_pub_key(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(2), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_signer_pub_key(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(2).set_data(value);
+      }
+      #[inline]
+      pub fn init_signer_pub_key(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(2).init_data(size)
+      }
+      pub fn has_signer_pub_key(&self) -> bool {
+        !self.builder.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_signature(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(3), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_signature(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(3).set_data(value);
+      }
+      #[inline]
+      pub fn init_signature(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(3).init_data(size)
+      }
+      pub fn has_signature(&self) -> bool {
+        !self.builder.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_network_id(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(4), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_network_id(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(4).set_data(value);
+      }
+      #[inline]
+      pub fn init_network_id(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(4).init_data(size)
+      }
+      pub fn has_network_id(&self) -> bool {
+        !self.builder.get_pointer_field(4).is_null()
+      }
+      #[inline]
+      pub fn get_nonce(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(5), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_nonce(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(5).set_data(value);
+      }
+      #[inline]
+      pub fn init_nonce(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(5).init_data(size)
+      }
+      pub fn has_nonce(&self) -> bool {
+        !self.builder.get_pointer_field(5).is_null()
+      }
+      #[inline]
+      pub fn get_remote_nonce(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(6), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_remote_nonce(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(6).set_data(value);
+      }
+      #[inline]
+      pub fn init_remote_nonce(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(6).init_data(size)
+      }
+      pub fn has_remote_nonce(&self) -> bool {
+        !self.builder.get_pointer_field(6).is_null()
+      }
     }
 
     pub struct Pipeline { _typeless: ::capnp::any_pointer::Pipeline }
@@ -754,7 +1038,7 @@ pub mod p2p_message {
     }
     mod _private {
       use capnp::private::layout;
-      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 1, pointers: 0 };
+      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 2, pointers: 7 };
       pub const TYPE_ID: u64 = 0x8be4_5ddf_6bb6_74ce;
     }
   }
@@ -1022,6 +1306,34 @@ pub mod p2p_message {
       pub fn has_aspect_hash_list(&self) -> bool {
         !self.reader.get_pointer_field(0).is_null()
       }
+      #[inline]
+      pub fn get_request_id(self) -> ::capnp::Result<::capnp::text::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(1), ::std::option::Option::None)
+      }
+      pub fn has_request_id(&self) -> bool {
+        !self.reader.get_pointer_field(1).is_null()
+      }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(2), ::std::option::Option::None)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.reader.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_to_agent_id(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(3), ::std::option::Option::None)
+      }
+      pub fn has_to_agent_id(&self) -> bool {
+        !self.reader.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_from_agent_id(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(4), ::std::option::Option::None)
+      }
+      pub fn has_from_agent_id(&self) -> bool {
+        !self.reader.get_pointer_field(4).is_null()
+      }
     }
 
     pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
@@ -1087,6 +1399,66 @@ pub mod p2p_message {
       pub fn has_aspect_hash_list(&self) -> bool {
         !self.builder.get_pointer_field(0).is_null()
       }
+      #[inline]
+      pub fn get_request_id(self) -> ::capnp::Result<::capnp::text::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(1), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_request_id(&mut self, value: ::capnp::text::Reader)  {
+        self.builder.get_pointer_field(1).set_text(value);
+      }
+      #[inline]
+      pub fn init_request_id(self, size: u32) -> ::capnp::text::Builder<'a> {
+        self.builder.get_pointer_field(1).init_text(size)
+      }
+      pub fn has_request_id(&self) -> bool {
+        !self.builder.get_pointer_field(1).is_null()
+      }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(2), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_space_address(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(2).set_data(value);
+      }
+      #[inline]
+      pub fn init_space_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(2).init_data(size)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.builder.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_to_agent_id(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(3), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_to_agent_id(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(3).set_data(value);
+      }
+      #[inline]
+      pub fn init_to_agent_id(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(3).init_data(size)
+      }
+      pub fn has_to_agent_id(&self) -> bool {
+        !self.builder.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_from_agent_id(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(4), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_from_agent_id(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(4).set_data(value);
+      }
+      #[inline]
+      pub fn init_from_agent_id(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(4).init_data(size)
+      }
+      pub fn has_from_agent_id(&self) -> bool {
+        !self.builder.get_pointer_field(4).is_null()
+      }
     }
 
     pub struct Pipeline { _typeless: ::capnp::any_pointer::Pipeline }
@@ -1099,7 +1471,7 @@ pub mod p2p_message {
     }
     mod _private {
       use capnp::private::layout;
-      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 0, pointers: 1 };
+      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 0, pointers: 5 };
       pub const TYPE_ID: u64 = 0x8f64_86ad_dab6_7ae7;
     }
   }
@@ -1157,6 +1529,34 @@ pub mod p2p_message {
       pub fn has_aspect_data_list(&self) -> bool {
         !self.reader.get_pointer_field(0).is_null()
       }
+      #[inline]
+      pub fn get_request_id(self) -> ::capnp::Result<::capnp::text::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(1), ::std::option::Option::None)
+      }
+      pub fn has_request_id(&self) -> bool {
+        !self.reader.get_pointer_field(1).is_null()
+      }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(2), ::std::option::Option::None)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.reader.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_to_agent_id(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(3), ::std::option::Option::None)
+      }
+      pub fn has_to_agent_id(&self) -> bool {
+        !self.reader.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_from_agent_id(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(4), ::std::option::Option::None)
+      }
+      pub fn has_from_agent_id(&self) -> bool {
+        !self.reader.get_pointer_field(4).is_null()
+      }
     }
 
     pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
@@ -1222,19 +1622,79 @@ pub mod p2p_message {
       pub fn has_aspect_data_list(&self) -> bool {
         !self.builder.get_pointer_field(0).is_null()
       }
-    }
-
-    pub struct Pipeline { _typeless: ::capnp::any_pointer::Pipeline }
-    impl ::capnp::capability::FromTypelessPipeline for Pipeline {
-      fn new(typeless: ::capnp::any_pointer::Pipeline) -> Pipeline {
-        Pipeline { _typeless: typeless,  }
+      #[inline]
+      pub fn get_request_id(self) -> ::capnp::Result<::capnp::text::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(1), ::std::option::Option::None)
       }
-    }
-    impl Pipeline  {
-    }
+      #[inline]
+      pub fn set_request_id(&mut self, value: ::capnp::text::Reader)  {
+        self.builder.get_pointer_field(1).set_text(value);
+      }
+      #[inline]
+      pub fn init_request_id(self, size: u32) -> ::capnp::text::Builder<'a> {
+        self.builder.get_pointer_field(1).init_text(size)
+      }
+      pub fn has_request_id(&self) -> bool {
+        !self.builder.get_pointer_field(1).is_null()
+      }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(2), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_space_address(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(2).set_data(value);
+      }
+      #[inline]
+      pub fn init_space_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(2).init_data(size)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.builder.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_to_agent_id(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(3), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_to_agent_id(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(3).set_data(value);
+      }
+      #[inline]
+      pub fn init_to_agent_id(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(3).init_data(size)
+      }
+      pub fn has_to_agent_id(&self) -> bool {
+        !self.builder.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_from_agent_id(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(4), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_from_agent_id(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(4).set_data(value);
+      }
+      #[inline]
+      pub fn init_from_agent_id(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(4).init_data(size)
+      }
+      pub fn has_from_agent_id(&self) -> bool {
+        !self.builder.get_pointer_field(4).is_null()
+      }
+    }
+
+    pub struct Pipeline { _typeless: ::capnp::any_pointer::Pipeline }
+    impl ::capnp::capability::FromTypelessPipeline for Pipeline {
+      fn new(typeless: ::capnp::any_pointer::Pipeline) -> Pipeline {
+        Pipeline { _typeless: typeless,  }
+      }
+    }
+    impl Pipeline  {
+    }
     mod _private {
       use capnp::private::layout;
-      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 0, pointers: 1 };
+      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 0, pointers: 5 };
       pub const TYPE_ID: u64 = 0xd90d_9ffa_6c31_b0ff;
     }
   }
@@ -1434,6 +1894,27 @@ pub mod p2p_message {
       pub fn has_data(&self) -> bool {
         !self.reader.get_pointer_field(1).is_null()
       }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(2), ::std::option::Option::None)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.reader.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_to_agent_id(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(3), ::std::option::Option::None)
+      }
+      pub fn has_to_agent_id(&self) -> bool {
+        !self.reader.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_from_agent_id(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(4), ::std::option::Option::None)
+      }
+      pub fn has_from_agent_id(&self) -> bool {
+        !self.reader.get_pointer_field(4).is_null()
+      }
     }
 
     pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
@@ -1508,11 +1989,661 @@ pub mod p2p_message {
         self.builder.get_pointer_field(1).set_data(value);
       }
       #[inline]
-      pub fn init_data(self, size: u32) -> ::capnp::data::Builder<'a> {
-        self.builder.get_pointer_field(1).init_data(size)
-      }
-      pub fn has_data(&self) -> bool {
-        !self.builder.get_pointer_field(1).is_null()
+      pub fn init_data(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(1).init_data(size)
+      }
+      pub fn has_data(&self) -> bool {
+        !self.builder.get_pointer_field(1).is_null()
+      }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(2), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_space_address(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(2).set_data(value);
+      }
+      #[inline]
+      pub fn init_space_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(2).init_data(size)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.builder.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_to_agent_id(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(3), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_to_agent_id(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(3).set_data(value);
+      }
+      #[inline]
+      pub fn init_to_agent_id(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(3).init_data(size)
+      }
+      pub fn has_to_agent_id(&self) -> bool {
+        !self.builder.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_from_agent_id(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(4), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_from_agent_id(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(4).set_data(value);
+      }
+      #[inline]
+      pub fn init_from_agent_id(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(4).init_data(size)
+      }
+      pub fn has_from_agent_id(&self) -> bool {
+        !self.builder.get_pointer_field(4).is_null()
+      }
+    }
+
+    pub struct Pipeline { _typeless: ::capnp::any_pointer::Pipeline }
+    impl ::capnp::capability::FromTypelessPipeline for Pipeline {
+      fn new(typeless: ::capnp::any_pointer::Pipeline) -> Pipeline {
+        Pipeline { _typeless: typeless,  }
+      }
+    }
+    impl Pipeline  {
+    }
+    mod _private {
+      use capnp::private::layout;
+      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 0, pointers: 5 };
+      pub const TYPE_ID: u64 = 0xcaf3_3a76_558f_07e7;
+    }
+  }
+
+  pub mod msg_query {
+    #[derive(Copy, Clone)]
+    pub struct Owned;
+    impl <'a> ::capnp::traits::Owned<'a> for Owned { type Reader = Reader<'a>; type Builder = Builder<'a>; }
+    impl <'a> ::capnp::traits::OwnedStruct<'a> for Owned { type Reader = Reader<'a>; type Builder = Builder<'a>; }
+    impl ::capnp::traits::Pipelined for Owned { type Pipeline = Pipeline; }
+
+    #[derive(Clone, Copy)]
+    pub struct Reader<'a> { reader: ::capnp::private::layout::StructReader<'a> }
+
+    impl <'a,> ::capnp::traits::HasTypeId for Reader<'a,>  {
+      #[inline]
+      fn type_id() -> u64 { _private::TYPE_ID }
+    }
+    impl <'a,> ::capnp::traits::FromStructReader<'a> for Reader<'a,>  {
+      fn new(reader: ::capnp::private::layout::StructReader<'a>) -> Reader<'a,> {
+        Reader { reader: reader,  }
+      }
+    }
+
+    impl <'a,> ::capnp::traits::FromPointerReader<'a> for Reader<'a,>  {
+      fn get_from_pointer(reader: &::capnp::private::layout::PointerReader<'a>, default: ::std::option::Option<&'a [::capnp::Word]>) -> ::capnp::Result<Reader<'a,>> {
+        ::std::result::Result::Ok(::capnp::traits::FromStructReader::new(reader.get_struct(default)?))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::IntoInternalStructReader<'a> for Reader<'a,>  {
+      fn into_internal_struct_reader(self) -> ::capnp::private::layout::StructReader<'a> {
+        self.reader
+      }
+    }
+
+    impl <'a,> ::capnp::traits::Imbue<'a> for Reader<'a,>  {
+      fn imbue(&mut self, cap_table: &'a ::capnp::private::layout::CapTable) {
+        self.reader.imbue(::capnp::private::layout::CapTableReader::Plain(cap_table))
+      }
+    }
+
+    impl <'a,> Reader<'a,>  {
+      pub fn reborrow(&self) -> Reader<> {
+        Reader { .. *self }
+      }
+
+      pub fn total_size(&self) -> ::capnp::Result<::capnp::MessageSize> {
+        self.reader.total_size()
+      }
+      #[inline]
+      pub fn get_request_id(self) -> ::capnp::Result<::capnp::text::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
+      }
+      pub fn has_request_id(&self) -> bool {
+        !self.reader.get_pointer_field(0).is_null()
+      }
+      #[inline]
+      pub fn get_entry_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(1), ::std::option::Option::None)
+      }
+      pub fn has_entry_address(&self) -> bool {
+        !self.reader.get_pointer_field(1).is_null()
+      }
+      #[inline]
+      pub fn get_data(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(2), ::std::option::Option::None)
+      }
+      pub fn has_data(&self) -> bool {
+        !self.reader.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(3), ::std::option::Option::None)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.reader.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_to_agent_id(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(4), ::std::option::Option::None)
+      }
+      pub fn has_to_agent_id(&self) -> bool {
+        !self.reader.get_pointer_field(4).is_null()
+      }
+      #[inline]
+      pub fn get_from_agent_id(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(5), ::std::option::Option::None)
+      }
+      pub fn has_from_agent_id(&self) -> bool {
+        !self.reader.get_pointer_field(5).is_null()
+      }
+      #[inline]
+      pub fn get_failed(self) -> bool {
+        self.reader.get_bool_field(0)
+      }
+    }
+
+    pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
+    impl <'a,> ::capnp::traits::HasStructSize for Builder<'a,>  {
+      #[inline]
+      fn struct_size() -> ::capnp::private::layout::StructSize { _private::STRUCT_SIZE }
+    }
+    impl <'a,> ::capnp::traits::HasTypeId for Builder<'a,>  {
+      #[inline]
+      fn type_id() -> u64 { _private::TYPE_ID }
+    }
+    impl <'a,> ::capnp::traits::FromStructBuilder<'a> for Builder<'a,>  {
+      fn new(builder: ::capnp::private::layout::StructBuilder<'a>) -> Builder<'a, > {
+        Builder { builder: builder,  }
+      }
+    }
+
+    impl <'a,> ::capnp::traits::ImbueMut<'a> for Builder<'a,>  {
+      fn imbue_mut(&mut self, cap_table: &'a mut ::capnp::private::layout::CapTable) {
+        self.builder.imbue(::capnp::private::layout::CapTableBuilder::Plain(cap_table))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::FromPointerBuilder<'a> for Builder<'a,>  {
+      fn init_pointer(builder: ::capnp::private::layout::PointerBuilder<'a>, _size: u32) -> Builder<'a,> {
+        ::capnp::traits::FromStructBuilder::new(builder.init_struct(_private::STRUCT_SIZE))
+      }
+      fn get_from_pointer(builder: ::capnp::private::layout::PointerBuilder<'a>, default: ::std::option::Option<&'a [::capnp::Word]>) -> ::capnp::Result<Builder<'a,>> {
+        ::std::result::Result::Ok(::capnp::traits::FromStructBuilder::new(builder.get_struct(_private::STRUCT_SIZE, default)?))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::SetPointerBuilder<Builder<'a,>> for Reader<'a,>  {
+      fn set_pointer_builder<'b>(pointer: ::capnp::private::layout::PointerBuilder<'b>, value: Reader<'a,>, canonicalize: bool) -> ::capnp::Result<()> { pointer.set_struct(&value.reader, canonicalize) }
+    }
+
+    impl <'a,> Builder<'a,>  {
+      pub fn into_reader(self) -> Reader<'a,> {
+        ::capnp::traits::FromStructReader::new(self.builder.into_reader())
+      }
+      pub fn reborrow(&mut self) -> Builder<> {
+        Builder { .. *self }
+      }
+      pub fn reborrow_as_reader(&self) -> Reader<> {
+        ::capnp::traits::FromStructReader::new(self.builder.into_reader())
+      }
+
+      pub fn total_size(&self) -> ::capnp::Result<::capnp::MessageSize> {
+        self.builder.into_reader().total_size()
+      }
+      #[inline]
+      pub fn get_request_id(self) -> ::capnp::Result<::capnp::text::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_request_id(&mut self, value: ::capnp::text::Reader)  {
+        self.builder.get_pointer_field(0).set_text(value);
+      }
+      #[inline]
+      pub fn init_request_id(self, size: u32) -> ::capnp::text::Builder<'a> {
+        self.builder.get_pointer_field(0).init_text(size)
+      }
+      pub fn has_request_id(&self) -> bool {
+        !self.builder.get_pointer_field(0).is_null()
+      }
+      #[inline]
+      pub fn get_entry_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(1), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_entry_address(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(1).set_data(value);
+      }
+      #[inline]
+      pub fn init_entry_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(1).init_data(size)
+      }
+      pub fn has_entry_address(&self) -> bool {
+        !self.builder.get_pointer_field(1).is_null()
+      }
+      #[inline]
+      pub fn get_data(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(2), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_data(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(2).set_data(value);
+      }
+      #[inline]
+      pub fn init_data(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(2).init_data(size)
+      }
+      pub fn has_data(&self) -> bool {
+        !self.builder.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(3), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_space_address(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(3).set_data(value);
+      }
+      #[inline]
+      pub fn init_space_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(3).init_data(size)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.builder.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_to_agent_id(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(4), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_to_agent_id(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(4).set_data(value);
+      }
+      #[inline]
+      pub fn init_to_agent_id(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(4).init_data(size)
+      }
+      pub fn has_to_agent_id(&self) -> bool {
+        !self.builder.get_pointer_field(4).is_null()
+      }
+      #[inline]
+      pub fn get_from_agent_id(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(5), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_from_agent_id(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(5).set_data(value);
+      }
+      #[inline]
+      pub fn init_from_agent_id(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(5).init_data(size)
+      }
+      pub fn has_from_agent_id(&self) -> bool {
+        !self.builder.get_pointer_field(5).is_null()
+      }
+      #[inline]
+      pub fn get_failed(self) -> bool {
+        self.builder.get_bool_field(0)
+      }
+      #[inline]
+      pub fn set_failed(&mut self, value: bool)  {
+        self.builder.set_bool_field(0, value);
+      }
+    }
+
+    pub struct Pipeline { _typeless: ::capnp::any_pointer::Pipeline }
+    impl ::capnp::capability::FromTypelessPipeline for Pipeline {
+      fn new(typeless: ::capnp::any_pointer::Pipeline) -> Pipeline {
+        Pipeline { _typeless: typeless,  }
+      }
+    }
+    impl Pipeline  {
+    }
+    mod _private {
+      use capnp::private::layout;
+      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 1, pointers: 6 };
+      pub const TYPE_ID: u64 = 0xa7e5_ad12_a54c_dcdd;
+    }
+  }
+
+  pub mod msg_join_space {
+    #[derive(Copy, Clone)]
+    pub struct Owned;
+    impl <'a> ::capnp::traits::Owned<'a> for Owned { type Reader = Reader<'a>; type Builder = Builder<'a>; }
+    impl <'a> ::capnp::traits::OwnedStruct<'a> for Owned { type Reader = Reader<'a>; type Builder = Builder<'a>; }
+    impl ::capnp::traits::Pipelined for Owned { type Pipeline = Pipeline; }
+
+    #[derive(Clone, Copy)]
+    pub struct Reader<'a> { reader: ::capnp::private::layout::StructReader<'a> }
+
+    impl <'a,> ::capnp::traits::HasTypeId for Reader<'a,>  {
+      #[inline]
+      fn type_id() -> u64 { _private::TYPE_ID }
+    }
+    impl <'a,> ::capnp::traits::FromStructReader<'a> for Reader<'a,>  {
+      fn new(reader: ::capnp::private::layout::StructReader<'a>) -> Reader<'a,> {
+        Reader { reader: reader,  }
+      }
+    }
+
+    impl <'a,> ::capnp::traits::FromPointerReader<'a> for Reader<'a,>  {
+      fn get_from_pointer(reader: &::capnp::private::layout::PointerReader<'a>, default: ::std::option::Option<&'a [::capnp::Word]>) -> ::capnp::Result<Reader<'a,>> {
+        ::std::result::Result::Ok(::capnp::traits::FromStructReader::new(reader.get_struct(default)?))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::IntoInternalStructReader<'a> for Reader<'a,>  {
+      fn into_internal_struct_reader(self) -> ::capnp::private::layout::StructReader<'a> {
+        self.reader
+      }
+    }
+
+    impl <'a,> ::capnp::traits::Imbue<'a> for Reader<'a,>  {
+      fn imbue(&mut self, cap_table: &'a ::capnp::private::layout::CapTable) {
+        self.reader.imbue(::capnp::private::layout::CapTableReader::Plain(cap_table))
+      }
+    }
+
+    impl <'a,> Reader<'a,>  {
+      pub fn reborrow(&self) -> Reader<> {
+        Reader { .. *self }
+      }
+
+      pub fn total_size(&self) -> ::capnp::Result<::capnp::MessageSize> {
+        self.reader.total_size()
+      }
+      #[inline]
+      pub fn get_peer_list(self) -> ::capnp::Result<::capnp::struct_list::Reader<'a,crate::p2p_capnp::p2p_message::peer_info::Owned>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
+      }
+      pub fn has_peer_list(&self) -> bool {
+        !self.reader.get_pointer_field(0).is_null()
+      }
+    }
+
+    pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
+    impl <'a,> ::capnp::traits::HasStructSize for Builder<'a,>  {
+      #[inline]
+      fn struct_size() -> ::capnp::private::layout::StructSize { _private::STRUCT_SIZE }
+    }
+    impl <'a,> ::capnp::traits::HasTypeId for Builder<'a,>  {
+      #[inline]
+      fn type_id() -> u64 { _private::TYPE_ID }
+    }
+    impl <'a,> ::capnp::traits::FromStructBuilder<'a> for Builder<'a,>  {
+      fn new(builder: ::capnp::private::layout::StructBuilder<'a>) -> Builder<'a, > {
+        Builder { builder: builder,  }
+      }
+    }
+
+    impl <'a,> ::capnp::traits::ImbueMut<'a> for Builder<'a,>  {
+      fn imbue_mut(&mut self, cap_table: &'a mut ::capnp::private::layout::CapTable) {
+        self.builder.imbue(::capnp::private::layout::CapTableBuilder::Plain(cap_table))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::FromPointerBuilder<'a> for Builder<'a,>  {
+      fn init_pointer(builder: ::capnp::private::layout::PointerBuilder<'a>, _size: u32) -> Builder<'a,> {
+        ::capnp::traits::FromStructBuilder::new(builder.init_struct(_private::STRUCT_SIZE))
+      }
+      fn get_from_pointer(builder: ::capnp::private::layout::PointerBuilder<'a>, default: ::std::option::Option<&'a [::capnp::Word]>) -> ::capnp::Result<Builder<'a,>> {
+        ::std::result::Result::Ok(::capnp::traits::FromStructBuilder::new(builder.get_struct(_private::STRUCT_SIZE, default)?))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::SetPointerBuilder<Builder<'a,>> for Reader<'a,>  {
+      fn set_pointer_builder<'b>(pointer: ::capnp::private::layout::PointerBuilder<'b>, value: Reader<'a,>, canonicalize: bool) -> ::capnp::Result<()> { pointer.set_struct(&value.reader, canonicalize) }
+    }
+
+    impl <'a,> Builder<'a,>  {
+      pub fn into_reader(self) -> Reader<'a,> {
+        ::capnp::traits::FromStructReader::new(self.builder.into_reader())
+      }
+      pub fn reborrow(&mut self) -> Builder<> {
+        Builder { .. *self }
+      }
+      pub fn reborrow_as_reader(&self) -> Reader<> {
+        ::capnp::traits::FromStructReader::new(self.builder.into_reader())
+      }
+
+      pub fn total_size(&self) -> ::capnp::Result<::capnp::MessageSize> {
+        self.builder.into_reader().total_size()
+      }
+      #[inline]
+      pub fn get_peer_list(self) -> ::capnp::Result<::capnp::struct_list::Builder<'a,crate::p2p_capnp::p2p_message::peer_info::Owned>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_peer_list(&mut self, value: ::capnp::struct_list::Reader<'a,crate::p2p_capnp::p2p_message::peer_info::Owned>) -> ::capnp::Result<()> {
+        ::capnp::traits::SetPointerBuilder::set_pointer_builder(self.builder.get_pointer_field(0), value, false)
+      }
+      #[inline]
+      pub fn init_peer_list(self, size: u32) -> ::capnp::struct_list::Builder<'a,crate::p2p_capnp::p2p_message::peer_info::Owned> {
+        ::capnp::traits::FromPointerBuilder::init_pointer(self.builder.get_pointer_field(0), size)
+      }
+      pub fn has_peer_list(&self) -> bool {
+        !self.builder.get_pointer_field(0).is_null()
+      }
+    }
+
+    pub struct Pipeline { _typeless: ::capnp::any_pointer::Pipeline }
+    impl ::capnp::capability::FromTypelessPipeline for Pipeline {
+      fn new(typeless: ::capnp::any_pointer::Pipeline) -> Pipeline {
+        Pipeline { _typeless: typeless,  }
+      }
+    }
+    impl Pipeline  {
+    }
+    mod _private {
+      use capnp::private::layout;
+      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 0, pointers: 1 };
+      pub const TYPE_ID: u64 = 0x93a4_9de3_9bc3_a64b;
+    }
+  }
+
+  pub mod msg_dht_gossip {
+    #[derive(Copy, Clone)]
+    pub struct Owned;
+    impl <'a> ::capnp::traits::Owned<'a> for Owned { type Reader = Reader<'a>; type Builder = Builder<'a>; }
+    impl <'a> ::capnp::traits::OwnedStruct<'a> for Owned { type Reader = Reader<'a>; type Builder = Builder<'a>; }
+    impl ::capnp::traits::Pipelined for Owned { type Pipeline = Pipeline; }
+
+    #[derive(Clone, Copy)]
+    pub struct Reader<'a> { reader: ::capnp::private::layout::StructReader<'a> }
+
+    impl <'a,> ::capnp::traits::HasTypeId for Reader<'a,>  {
+      #[inline]
+      fn type_id() -> u64 { _private::TYPE_ID }
+    }
+    impl <'a,> ::capnp::traits::FromStructReader<'a> for Reader<'a,>  {
+      fn new(reader: ::capnp::private::layout::StructReader<'a>) -> Reader<'a,> {
+        Reader { reader: reader,  }
+      }
+    }
+
+    impl <'a,> ::capnp::traits::FromPointerReader<'a> for Reader<'a,>  {
+      fn get_from_pointer(reader: &::capnp::private::layout::PointerReader<'a>, default: ::std::option::Option<&'a [::capnp::Word]>) -> ::capnp::Result<Reader<'a,>> {
+        ::std::result::Result::Ok(::capnp::traits::FromStructReader::new(reader.get_struct(default)?))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::IntoInternalStructReader<'a> for Reader<'a,>  {
+      fn into_internal_struct_reader(self) -> ::capnp::private::layout::StructReader<'a> {
+        self.reader
+      }
+    }
+
+    impl <'a,> ::capnp::traits::Imbue<'a> for Reader<'a,>  {
+      fn imbue(&mut self, cap_table: &'a ::capnp::private::layout::CapTable) {
+        self.reader.imbue(::capnp::private::layout::CapTableReader::Plain(cap_table))
+      }
+    }
+
+    impl <'a,> Reader<'a,>  {
+      pub fn reborrow(&self) -> Reader<> {
+        Reader { .. *self }
+      }
+
+      pub fn total_size(&self) -> ::capnp::Result<::capnp::MessageSize> {
+        self.reader.total_size()
+      }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.reader.get_pointer_field(0).is_null()
+      }
+      #[inline]
+      pub fn get_to_peer_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(1), ::std::option::Option::None)
+      }
+      pub fn has_to_peer_address(&self) -> bool {
+        !self.reader.get_pointer_field(1).is_null()
+      }
+      #[inline]
+      pub fn get_from_peer_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(2), ::std::option::Option::None)
+      }
+      pub fn has_from_peer_address(&self) -> bool {
+        !self.reader.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_bundle(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(3), ::std::option::Option::None)
+      }
+      pub fn has_bundle(&self) -> bool {
+        !self.reader.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_gossip_id(self) -> u64 {
+        self.reader.get_data_field::<u64>(0)
+      }
+    }
+
+    pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
+    impl <'a,> ::capnp::traits::HasStructSize for Builder<'a,>  {
+      #[inline]
+      fn struct_size() -> ::capnp::private::layout::StructSize { _private::STRUCT_SIZE }
+    }
+    impl <'a,> ::capnp::traits::HasTypeId for Builder<'a,>  {
+      #[inline]
+      fn type_id() -> u64 { _private::TYPE_ID }
+    }
+    impl <'a,> ::capnp::traits::FromStructBuilder<'a> for Builder<'a,>  {
+      fn new(builder: ::capnp::private::layout::StructBuilder<'a>) -> Builder<'a, > {
+        Builder { builder: builder,  }
+      }
+    }
+
+    impl <'a,> ::capnp::traits::ImbueMut<'a> for Builder<'a,>  {
+      fn imbue_mut(&mut self, cap_table: &'a mut ::capnp::private::layout::CapTable) {
+        self.builder.imbue(::capnp::private::layout::CapTableBuilder::Plain(cap_table))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::FromPointerBuilder<'a> for Builder<'a,>  {
+      fn init_pointer(builder: ::capnp::private::layout::PointerBuilder<'a>, _size: u32) -> Builder<'a,> {
+        ::capnp::traits::FromStructBuilder::new(builder.init_struct(_private::STRUCT_SIZE))
+      }
+      fn get_from_pointer(builder: ::capnp::private::layout::PointerBuilder<'a>, default: ::std::option::Option<&'a [::capnp::Word]>) -> ::capnp::Result<Builder<'a,>> {
+        ::std::result::Result::Ok(::capnp::traits::FromStructBuilder::new(builder.get_struct(_private::STRUCT_SIZE, default)?))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::SetPointerBuilder<Builder<'a,>> for Reader<'a,>  {
+      fn set_pointer_builder<'b>(pointer: ::capnp::private::layout::PointerBuilder<'b>, value: Reader<'a,>, canonicalize: bool) -> ::capnp::Result<()> { pointer.set_struct(&value.reader, canonicalize) }
+    }
+
+    impl <'a,> Builder<'a,>  {
+      pub fn into_reader(self) -> Reader<'a,> {
+        ::capnp::traits::FromStructReader::new(self.builder.into_reader())
+      }
+      pub fn reborrow(&mut self) -> Builder<> {
+        Builder { .. *self }
+      }
+      pub fn reborrow_as_reader(&self) -> Reader<> {
+        ::capnp::traits::FromStructReader::new(self.builder.into_reader())
+      }
+
+      pub fn total_size(&self) -> ::capnp::Result<::capnp::MessageSize> {
+        self.builder.into_reader().total_size()
+      }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_space_address(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(0).set_data(value);
+      }
+      #[inline]
+      pub fn init_space_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(0).init_data(size)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.builder.get_pointer_field(0).is_null()
+      }
+      #[inline]
+      pub fn get_to_peer_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(1), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_to_peer_address(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(1).set_data(value);
+      }
+      #[inline]
+      pub fn init_to_peer_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(1).init_data(size)
+      }
+      pub fn has_to_peer_address(&self) -> bool {
+        !self.builder.get_pointer_field(1).is_null()
+      }
+      #[inline]
+      pub fn get_from_peer_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(2), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_from_peer_address(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(2).set_data(value);
+      }
+      #[inline]
+      pub fn init_from_peer_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(2).init_data(size)
+      }
+      pub fn has_from_peer_address(&self) -> bool {
+        !self.builder.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_bundle(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(3), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_bundle(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(3).set_data(value);
+      }
+      #[inline]
+      pub fn init_bundle(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(3).init_data(size)
+      }
+      pub fn has_bundle(&self) -> bool {
+        !self.builder.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_gossip_id(self) -> u64 {
+        self.builder.get_data_field::<u64>(0)
+      }
+      #[inline]
+      pub fn set_gossip_id(&mut self, value: u64)  {
+        self.builder.set_data_field::<u64>(0, value);
       }
     }
 
@@ -1526,12 +2657,12 @@ pub mod p2p_message {
     }
     mod _private {
       use capnp::private::layout;
-      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 0, pointers: 2 };
-      pub const TYPE_ID: u64 = 0xcaf3_3a76_558f_07e7;
+      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 1, pointers: 4 };
+      pub const TYPE_ID: u64 = 0xd029_b04e_d80c_2ac4;
     }
   }
 
-  pub mod msg_query {
+  pub mod msg_leave_space {
     #[derive(Copy, Clone)]
     pub struct Owned;
     impl <'a> ::capnp::traits::Owned<'a> for Owned { type Reader = Reader<'a>; type Builder = Builder<'a>; }
@@ -1578,26 +2709,44 @@ pub mod p2p_message {
         self.reader.total_size()
       }
       #[inline]
-      pub fn get_request_id(self) -> ::capnp::Result<::capnp::text::Reader<'a>> {
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
         ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
       }
-      pub fn has_request_id(&self) -> bool {
+      pub fn has_space_address(&self) -> bool {
         !self.reader.get_pointer_field(0).is_null()
       }
       #[inline]
-      pub fn get_entry_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+      pub fn get_peer_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
         ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(1), ::std::option::Option::None)
       }
-      pub fn has_entry_address(&self) -> bool {
+      pub fn has_peer_address(&self) -> bool {
         !self.reader.get_pointer_field(1).is_null()
       }
       #[inline]
-      pub fn get_data(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+      pub fn get_peer_uri(self) -> ::capnp::Result<::capnp::text::Reader<'a>> {
         ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(2), ::std::option::Option::None)
       }
-      pub fn has_data(&self) -> bool {
+      pub fn has_peer_uri(&self) -> bool {
         !self.reader.get_pointer_field(2).is_null()
       }
+      #[inline]
+      pub fn get_timestamp(self) -> u64 {
+        self.reader.get_data_field::<u64>(0)
+      }
+      #[inline]
+      pub fn get_signer_pub_key(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(3), ::std::option::Option::None)
+      }
+      pub fn has_signer_pub_key(&self) -> bool {
+        !self.reader.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_signature(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(4), ::std::option::Option::None)
+      }
+      pub fn has_signature(&self) -> bool {
+        !self.reader.get_pointer_field(4).is_null()
+      }
     }
 
     pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
@@ -1649,50 +2798,88 @@ pub mod p2p_message {
         self.builder.into_reader().total_size()
       }
       #[inline]
-      pub fn get_request_id(self) -> ::capnp::Result<::capnp::text::Builder<'a>> {
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
         ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
       }
       #[inline]
-      pub fn set_request_id(&mut self, value: ::capnp::text::Reader)  {
-        self.builder.get_pointer_field(0).set_text(value);
+      pub fn set_space_address(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(0).set_data(value);
       }
       #[inline]
-      pub fn init_request_id(self, size: u32) -> ::capnp::text::Builder<'a> {
-        self.builder.get_pointer_field(0).init_text(size)
+      pub fn init_space_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(0).init_data(size)
       }
-      pub fn has_request_id(&self) -> bool {
+      pub fn has_space_address(&self) -> bool {
         !self.builder.get_pointer_field(0).is_null()
       }
       #[inline]
-      pub fn get_entry_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+      pub fn get_peer_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
         ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(1), ::std::option::Option::None)
       }
       #[inline]
-      pub fn set_entry_address(&mut self, value: ::capnp::data::Reader)  {
+      pub fn set_peer_address(&mut self, value: ::capnp::data::Reader)  {
         self.builder.get_pointer_field(1).set_data(value);
       }
       #[inline]
-      pub fn init_entry_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+      pub fn init_peer_address(self, size: u32) -> ::capnp::data::Builder<'a> {
         self.builder.get_pointer_field(1).init_data(size)
       }
-      pub fn has_entry_address(&self) -> bool {
+      pub fn has_peer_address(&self) -> bool {
         !self.builder.get_pointer_field(1).is_null()
       }
       #[inline]
-      pub fn get_data(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+      pub fn get_peer_uri(self) -> ::capnp::Result<::capnp::text::Builder<'a>> {
         ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(2), ::std::option::Option::None)
       }
       #[inline]
-      pub fn set_data(&mut self, value: ::capnp::data::Reader)  {
-        self.builder.get_pointer_field(2).set_data(value);
+      pub fn set_peer_uri(&mut self, value: ::capnp::text::Reader)  {
+        self.builder.get_pointer_field(2).set_text(value);
       }
       #[inline]
-      pub fn init_data(self, size: u32) -> ::capnp::data::Builder<'a> {
-        self.builder.get_pointer_field(2).init_data(size)
+      pub fn init_peer_uri(self, size: u32) -> ::capnp::text::Builder<'a> {
+        self.builder.get_pointer_field(2).init_text(size)
       }
-      pub fn has_data(&self) -> bool {
+      pub fn has_peer_uri(&self) -> bool {
         !self.builder.get_pointer_field(2).is_null()
       }
+      #[inline]
+      pub fn get_timestamp(self) -> u64 {
+        self.builder.get_data_field::<u64>(0)
+      }
+      #[inline]
+      pub fn set_timestamp(&mut self, value: u64)  {
+        self.builder.set_data_field::<u64>(0, value);
+      }
+      #[inline]
+      pub fn get_signer_pub_key(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(3), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_signer_pub_key(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(3).set_data(value);
+      }
+      #[inline]
+      pub fn init_signer_pub_key(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(3).init_data(size)
+      }
+      pub fn has_signer_pub_key(&self) -> bool {
+        !self.builder.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_signature(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(4), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_signature(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(4).set_data(value);
+      }
+      #[inline]
+      pub fn init_signature(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(4).init_data(size)
+      }
+      pub fn has_signature(&self) -> bool {
+        !self.builder.get_pointer_field(4).is_null()
+      }
     }
 
     pub struct Pipeline { _typeless: ::capnp::any_pointer::Pipeline }
@@ -1705,8 +2892,8 @@ pub mod p2p_message {
     }
     mod _private {
       use capnp::private::layout;
-      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 0, pointers: 3 };
-      pub const TYPE_ID: u64 = 0xa7e5_ad12_a54c_dcdd;
+      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 1, pointers: 5 };
+      pub const TYPE_ID: u64 = 0x99c0_31a9_85f1_1fc3;
     }
   }
 
@@ -2410,4 +3597,239 @@ pub mod p2p_message {
       pub const TYPE_ID: u64 = 0x9c28_221c_ed2b_7a32;
     }
   }
+
+  pub mod peer_info {
+    #[derive(Copy, Clone)]
+    pub struct Owned;
+    impl <'a> ::capnp::traits::Owned<'a> for Owned { type Reader = Reader<'a>; type Builder = Builder<'a>; }
+    impl <'a> ::capnp::traits::OwnedStruct<'a> for Owned { type Reader = Reader<'a>; type Builder = Builder<'a>; }
+    impl ::capnp::traits::Pipelined for Owned { type Pipeline = Pipeline; }
+
+    #[derive(Clone, Copy)]
+    pub struct Reader<'a> { reader: ::capnp::private::layout::StructReader<'a> }
+
+    impl <'a,> ::capnp::traits::HasTypeId for Reader<'a,>  {
+      #[inline]
+      fn type_id() -> u64 { _private::TYPE_ID }
+    }
+    impl <'a,> ::capnp::traits::FromStructReader<'a> for Reader<'a,>  {
+      fn new(reader: ::capnp::private::layout::StructReader<'a>) -> Reader<'a,> {
+        Reader { reader: reader,  }
+      }
+    }
+
+    impl <'a,> ::capnp::traits::FromPointerReader<'a> for Reader<'a,>  {
+      fn get_from_pointer(reader: &::capnp::private::layout::PointerReader<'a>, default: ::std::option::Option<&'a [::capnp::Word]>) -> ::capnp::Result<Reader<'a,>> {
+        ::std::result::Result::Ok(::capnp::traits::FromStructReader::new(reader.get_struct(default)?))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::IntoInternalStructReader<'a> for Reader<'a,>  {
+      fn into_internal_struct_reader(self) -> ::capnp::private::layout::StructReader<'a> {
+        self.reader
+      }
+    }
+
+    impl <'a,> ::capnp::traits::Imbue<'a> for Reader<'a,>  {
+      fn imbue(&mut self, cap_table: &'a ::capnp::private::layout::CapTable) {
+        self.reader.imbue(::capnp::private::layout::CapTableReader::Plain(cap_table))
+      }
+    }
+
+    impl <'a,> Reader<'a,>  {
+      pub fn reborrow(&self) -> Reader<> {
+        Reader { .. *self }
+      }
+
+      pub fn total_size(&self) -> ::capnp::Result<::capnp::MessageSize> {
+        self.reader.total_size()
+      }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(0), ::std::option::Option::None)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.reader.get_pointer_field(0).is_null()
+      }
+      #[inline]
+      pub fn get_peer_address(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(1), ::std::option::Option::None)
+      }
+      pub fn has_peer_address(&self) -> bool {
+        !self.reader.get_pointer_field(1).is_null()
+      }
+      #[inline]
+      pub fn get_peer_uri(self) -> ::capnp::Result<::capnp::text::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(2), ::std::option::Option::None)
+      }
+      pub fn has_peer_uri(&self) -> bool {
+        !self.reader.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_timestamp(self) -> u64 {
+        self.reader.get_data_field::<u64>(0)
+      }
+      #[inline]
+      pub fn get_signer_pub_key(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(3), ::std::option::Option::None)
+      }
+      pub fn has_signer_pub_key(&self) -> bool {
+        !self.reader.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_signature(self) -> ::capnp::Result<::capnp::data::Reader<'a>> {
+        ::capnp::traits::FromPointerReader::get_from_pointer(&self.reader.get_pointer_field(4), ::std::option::Option::None)
+      }
+      pub fn has_signature(&self) -> bool {
+        !self.reader.get_pointer_field(4).is_null()
+      }
+    }
+
+    pub struct Builder<'a> { builder: ::capnp::private::layout::StructBuilder<'a> }
+    impl <'a,> ::capnp::traits::HasStructSize for Builder<'a,>  {
+      #[inline]
+      fn struct_size() -> ::capnp::private::layout::StructSize { _private::STRUCT_SIZE }
+    }
+    impl <'a,> ::capnp::traits::HasTypeId for Builder<'a,>  {
+      #[inline]
+      fn type_id() -> u64 { _private::TYPE_ID }
+    }
+    impl <'a,> ::capnp::traits::FromStructBuilder<'a> for Builder<'a,>  {
+      fn new(builder: ::capnp::private::layout::StructBuilder<'a>) -> Builder<'a, > {
+        Builder { builder: builder,  }
+      }
+    }
+
+    impl <'a,> ::capnp::traits::ImbueMut<'a> for Builder<'a,>  {
+      fn imbue_mut(&mut self, cap_table: &'a mut ::capnp::private::layout::CapTable) {
+        self.builder.imbue(::capnp::private::layout::CapTableBuilder::Plain(cap_table))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::FromPointerBuilder<'a> for Builder<'a,>  {
+      fn init_pointer(builder: ::capnp::private::layout::PointerBuilder<'a>, _size: u32) -> Builder<'a,> {
+        ::capnp::traits::FromStructBuilder::new(builder.init_struct(_private::STRUCT_SIZE))
+      }
+      fn get_from_pointer(builder: ::capnp::private::layout::PointerBuilder<'a>, default: ::std::option::Option<&'a [::capnp::Word]>) -> ::capnp::Result<Builder<'a,>> {
+        ::std::result::Result::Ok(::capnp::traits::FromStructBuilder::new(builder.get_struct(_private::STRUCT_SIZE, default)?))
+      }
+    }
+
+    impl <'a,> ::capnp::traits::SetPointerBuilder<Builder<'a,>> for Reader<'a,>  {
+      fn set_pointer_builder<'b>(pointer: ::capnp::private::layout::PointerBuilder<'b>, value: Reader<'a,>, canonicalize: bool) -> ::capnp::Result<()> { pointer.set_struct(&value.reader, canonicalize) }
+    }
+
+    impl <'a,> Builder<'a,>  {
+      pub fn into_reader(self) -> Reader<'a,> {
+        ::capnp::traits::FromStructReader::new(self.builder.into_reader())
+      }
+      pub fn reborrow(&mut self) -> Builder<> {
+        Builder { .. *self }
+      }
+      pub fn reborrow_as_reader(&self) -> Reader<> {
+        ::capnp::traits::FromStructReader::new(self.builder.into_reader())
+      }
+
+      pub fn total_size(&self) -> ::capnp::Result<::capnp::MessageSize> {
+        self.builder.into_reader().total_size()
+      }
+      #[inline]
+      pub fn get_space_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(0), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_space_address(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(0).set_data(value);
+      }
+      #[inline]
+      pub fn init_space_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(0).init_data(size)
+      }
+      pub fn has_space_address(&self) -> bool {
+        !self.builder.get_pointer_field(0).is_null()
+      }
+      #[inline]
+      pub fn get_peer_address(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(1), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_peer_address(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(1).set_data(value);
+      }
+      #[inline]
+      pub fn init_peer_address(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(1).init_data(size)
+      }
+      pub fn has_peer_address(&self) -> bool {
+        !self.builder.get_pointer_field(1).is_null()
+      }
+      #[inline]
+      pub fn get_peer_uri(self) -> ::capnp::Result<::capnp::text::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(2), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_peer_uri(&mut self, value: ::capnp::text::Reader)  {
+        self.builder.get_pointer_field(2).set_text(value);
+      }
+      #[inline]
+      pub fn init_peer_uri(self, size: u32) -> ::capnp::text::Builder<'a> {
+        self.builder.get_pointer_field(2).init_text(size)
+      }
+      pub fn has_peer_uri(&self) -> bool {
+        !self.builder.get_pointer_field(2).is_null()
+      }
+      #[inline]
+      pub fn get_timestamp(self) -> u64 {
+        self.builder.get_data_field::<u64>(0)
+      }
+      #[inline]
+      pub fn set_timestamp(&mut self, value: u64)  {
+        self.builder.set_data_field::<u64>(0, value);
+      }
+      #[inline]
+      pub fn get_signer_pub_key(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(3), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_signer_pub_key(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(3).set_data(value);
+      }
+      #[inline]
+      pub fn init_signer_pub_key(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(3).init_data(size)
+      }
+      pub fn has_signer_pub_key(&self) -> bool {
+        !self.builder.get_pointer_field(3).is_null()
+      }
+      #[inline]
+      pub fn get_signature(self) -> ::capnp::Result<::capnp::data::Builder<'a>> {
+        ::capnp::traits::FromPointerBuilder::get_from_pointer(self.builder.get_pointer_field(4), ::std::option::Option::None)
+      }
+      #[inline]
+      pub fn set_signature(&mut self, value: ::capnp::data::Reader)  {
+        self.builder.get_pointer_field(4).set_data(value);
+      }
+      #[inline]
+      pub fn init_signature(self, size: u32) -> ::capnp::data::Builder<'a> {
+        self.builder.get_pointer_field(4).init_data(size)
+      }
+      pub fn has_signature(&self) -> bool {
+        !self.builder.get_pointer_field(4).is_null()
+      }
+    }
+
+    pub struct Pipeline { _typeless: ::capnp::any_pointer::Pipeline }
+    impl ::capnp::capability::FromTypelessPipeline for Pipeline {
+      fn new(typeless: ::capnp::any_pointer::Pipeline) -> Pipeline {
+        Pipeline { _typeless: typeless,  }
+      }
+    }
+    impl Pipeline  {
+    }
+    mod _private {
+      use capnp::private::layout;
+      pub const STRUCT_SIZE: layout::StructSize = layout::StructSize { data: 1, pointers: 5 };
+      pub const TYPE_ID: u64 = 0xfb8f_c795_1ebc_73a8;
+    }
+  }
 }
@@ -0,0 +1,423 @@
+//! Typed encoding / decoding of the transit encoding handshake messages
+//! (see `protocol/transit_encoding.capnp`).
+//! These are all separate root structs: each message knows how to pack / unpack itself.
+
+use crate::{error::P2pProtocolResult, transit_encoding_capnp as te};
+use capnp::{message, serialize::OwnedSegments, serialize_packed};
+
+/// Protocol Identifier expected in MsgStep1FromConnect
+pub const TRANSIT_ENCODING_MAGIC: u16 = 0xa86c;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HaltReasonCode {
+    Unspecified,
+    BadMagic,
+    BadEncoding,
+    BadNetworkId,
+    KxDecodeFail,
+    UnexpectedSigPubKey,
+    BadSignature,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Halt {
+    pub reason_code: HaltReasonCode,
+    pub reason_text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Unknown,
+    OpenJson,
+    OpenPacked,
+    SodiumJson,
+    SodiumPacked,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgStep1FromConnect {
+    pub magic: u16,
+    pub encoding: Encoding,
+    pub network_id: Vec<u8>,
+    pub kx_pub_key: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MsgStep2FromListen {
+    Halt(Halt),
+    Continue(MsgStep2Continue),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgStep2Continue {
+    pub kx_pub_key: Vec<u8>,
+    pub kx_nonce: Vec<u8>,
+    pub kx_secret: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgStep2FromListenKxEncoded {
+    pub padding: Vec<u8>,
+    pub sig_pub_key: Vec<u8>,
+    pub l2c_session_key: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MsgStep3FromConnect {
+    Halt(Halt),
+    Continue(MsgStep3Continue),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgStep3Continue {
+    pub kx_nonce: Vec<u8>,
+    pub kx_secret: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgStep3FromConnectKxEncoded {
+    pub padding: Vec<u8>,
+    pub sig_pub_key: Vec<u8>,
+    pub c2l_session_key: Vec<u8>,
+    pub c2l_signature: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgStep4FromListenEncoded {
+    pub padding: Vec<u8>,
+    pub l2c_signature: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsgStep5FromConnectEncoded {
+    pub padding: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedMessage {
+    pub padding: Vec<u8>,
+    pub content: Vec<u8>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Encoding / Decoding
+//--------------------------------------------------------------------------------------------------
+
+fn pack(message: &message::Builder<message::HeapAllocator>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    serialize_packed::write_message(&mut buf, message).expect("Writing to a Vec should never fail");
+    buf
+}
+
+fn unpack(bytes: &[u8]) -> P2pProtocolResult<message::Reader<OwnedSegments>> {
+    Ok(serialize_packed::read_message(
+        &mut &bytes[..],
+        message::ReaderOptions::new(),
+    )?)
+}
+
+impl Halt {
+    fn write(&self, mut builder: te::halt::Builder) {
+        builder.set_reason_code(match self.reason_code {
+            HaltReasonCode::Unspecified => te::halt::ReasonCode::Unspecified,
+            HaltReasonCode::BadMagic => te::halt::ReasonCode::BadMagic,
+            HaltReasonCode::BadEncoding => te::halt::ReasonCode::BadEncoding,
+            HaltReasonCode::BadNetworkId => te::halt::ReasonCode::BadNetworkId,
+            HaltReasonCode::KxDecodeFail => te::halt::ReasonCode::KxDecodeFail,
+            HaltReasonCode::UnexpectedSigPubKey => te::halt::ReasonCode::UnexpectedSigPubKey,
+            HaltReasonCode::BadSignature => te::halt::ReasonCode::BadSignature,
+        });
+        builder.set_reason_text(&self.reason_text);
+    }
+
+    fn read(reader: te::halt::Reader) -> P2pProtocolResult<Self> {
+        let reason_code = match reader.get_reason_code()? {
+            te::halt::ReasonCode::Unspecified => HaltReasonCode::Unspecified,
+            te::halt::ReasonCode::BadMagic => HaltReasonCode::BadMagic,
+            te::halt::ReasonCode::BadEncoding => HaltReasonCode::BadEncoding,
+            te::halt::ReasonCode::BadNetworkId => HaltReasonCode::BadNetworkId,
+            te::halt::ReasonCode::KxDecodeFail => HaltReasonCode::KxDecodeFail,
+            te::halt::ReasonCode::UnexpectedSigPubKey => HaltReasonCode::UnexpectedSigPubKey,
+            te::halt::ReasonCode::BadSignature => HaltReasonCode::BadSignature,
+        };
+        Ok(Halt {
+            reason_code,
+            reason_text: reader.get_reason_text()?.to_string(),
+        })
+    }
+}
+
+impl MsgStep1FromConnect {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut message = message::Builder::new_default();
+        {
+            let mut builder = message.init_root::<te::msg_step1_from_connect::Builder>();
+            builder.set_magic(self.magic);
+            builder.set_encoding(match self.encoding {
+                Encoding::Unknown => te::msg_step1_from_connect::Encoding::Unknown,
+                Encoding::OpenJson => te::msg_step1_from_connect::Encoding::OpenJson,
+                Encoding::OpenPacked => te::msg_step1_from_connect::Encoding::OpenPacked,
+                Encoding::SodiumJson => te::msg_step1_from_connect::Encoding::SodiumJson,
+                Encoding::SodiumPacked => te::msg_step1_from_connect::Encoding::SodiumPacked,
+            });
+            builder.set_network_id(&self.network_id);
+            builder.set_kx_pub_key(&self.kx_pub_key);
+        }
+        pack(&message)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> P2pProtocolResult<Self> {
+        let message = unpack(bytes)?;
+        let reader = message.get_root::<te::msg_step1_from_connect::Reader>()?;
+        let encoding = match reader.get_encoding()? {
+            te::msg_step1_from_connect::Encoding::Unknown => Encoding::Unknown,
+            te::msg_step1_from_connect::Encoding::OpenJson => Encoding::OpenJson,
+            te::msg_step1_from_connect::Encoding::OpenPacked => Encoding::OpenPacked,
+            te::msg_step1_from_connect::Encoding::SodiumJson => Encoding::SodiumJson,
+            te::msg_step1_from_connect::Encoding::SodiumPacked => Encoding::SodiumPacked,
+        };
+        Ok(MsgStep1FromConnect {
+            magic: reader.get_magic(),
+            encoding,
+            network_id: reader.get_network_id()?.to_vec(),
+            kx_pub_key: reader.get_kx_pub_key()?.to_vec(),
+        })
+    }
+}
+
+impl MsgStep2FromListen {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut message = message::Builder::new_default();
+        {
+            let builder = message.init_root::<te::msg_step2_from_listen::Builder>();
+            match self {
+                MsgStep2FromListen::Halt(halt) => halt.write(builder.init_halt()),
+                MsgStep2FromListen::Continue(msg) => {
+                    let mut builder = builder.init_continue();
+                    builder.set_kx_pub_key(&msg.kx_pub_key);
+                    builder.set_kx_nonce(&msg.kx_nonce);
+                    builder.set_kx_secret(&msg.kx_secret);
+                }
+            }
+        }
+        pack(&message)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> P2pProtocolResult<Self> {
+        let message = unpack(bytes)?;
+        let reader = message.get_root::<te::msg_step2_from_listen::Reader>()?;
+        let msg = match reader.which()? {
+            te::msg_step2_from_listen::Halt(r) => MsgStep2FromListen::Halt(Halt::read(r?)?),
+            te::msg_step2_from_listen::Continue(r) => {
+                let r = r?;
+                MsgStep2FromListen::Continue(MsgStep2Continue {
+                    kx_pub_key: r.get_kx_pub_key()?.to_vec(),
+                    kx_nonce: r.get_kx_nonce()?.to_vec(),
+                    kx_secret: r.get_kx_secret()?.to_vec(),
+                })
+            }
+        };
+        Ok(msg)
+    }
+}
+
+impl MsgStep2FromListenKxEncoded {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut message = message::Builder::new_default();
+        {
+            let mut builder = message.init_root::<te::msg_step2_from_listen_kx_encoded::Builder>();
+            builder.set_padding(&self.padding);
+            builder.set_sig_pub_key(&self.sig_pub_key);
+            builder.set_l2c_session_key(&self.l2c_session_key);
+        }
+        pack(&message)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> P2pProtocolResult<Self> {
+        let message = unpack(bytes)?;
+        let reader = message.get_root::<te::msg_step2_from_listen_kx_encoded::Reader>()?;
+        Ok(MsgStep2FromListenKxEncoded {
+            padding: reader.get_padding()?.to_vec(),
+            sig_pub_key: reader.get_sig_pub_key()?.to_vec(),
+            l2c_session_key: reader.get_l2c_session_key()?.to_vec(),
+        })
+    }
+}
+
+impl MsgStep3FromConnect {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut message = message::Builder::new_default();
+        {
+            let builder = message.init_root::<te::msg_step3_from_connect::Builder>();
+            match self {
+                MsgStep3FromConnect::Halt(halt) => halt.write(builder.init_halt()),
+                MsgStep3FromConnect::Continue(msg) => {
+                    let mut builder = builder.init_continue();
+                    builder.set_kx_nonce(&msg.kx_nonce);
+                    builder.set_kx_secret(&msg.kx_secret);
+                }
+            }
+        }
+        pack(&message)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> P2pProtocolResult<Self> {
+        let message = unpack(bytes)?;
+        let reader = message.get_root::<te::msg_step3_from_connect::Reader>()?;
+        let msg = match reader.which()? {
+            te::msg_step3_from_connect::Halt(r) => MsgStep3FromConnect::Halt(Halt::read(r?)?),
+            te::msg_step3_from_connect::Continue(r) => {
+                let r = r?;
+                MsgStep3FromConnect::Continue(MsgStep3Continue {
+                    kx_nonce: r.get_kx_nonce()?.to_vec(),
+                    kx_secret: r.get_kx_secret()?.to_vec(),
+                })
+            }
+        };
+        Ok(msg)
+    }
+}
+
+impl MsgStep3FromConnectKxEncoded {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut message = message::Builder::new_default();
+        {
+            let mut builder =
+                message.init_root::<te::msg_step3_from_connect_kx_encoded::Builder>();
+            builder.set_padding(&self.padding);
+            builder.set_sig_pub_key(&self.sig_pub_key);
+            builder.set_c2l_session_key(&self.c2l_session_key);
+            builder.set_c2l_signature(&self.c2l_signature);
+        }
+        pack(&message)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> P2pProtocolResult<Self> {
+        let message = unpack(bytes)?;
+        let reader = message.get_root::<te::msg_step3_from_connect_kx_encoded::Reader>()?;
+        Ok(MsgStep3FromConnectKxEncoded {
+            padding: reader.get_padding()?.to_vec(),
+            sig_pub_key: reader.get_sig_pub_key()?.to_vec(),
+            c2l_session_key: reader.get_c2l_session_key()?.to_vec(),
+            c2l_signature: reader.get_c2l_signature()?.to_vec(),
+        })
+    }
+}
+
+impl MsgStep4FromListenEncoded {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut message = message::Builder::new_default();
+        {
+            let mut builder = message.init_root::<te::msg_step4_from_listen_encoded::Builder>();
+            builder.set_padding(&self.padding);
+            builder.set_l2c_signature(&self.l2c_signature);
+        }
+        pack(&message)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> P2pProtocolResult<Self> {
+        let message = unpack(bytes)?;
+        let reader = message.get_root::<te::msg_step4_from_listen_encoded::Reader>()?;
+        Ok(MsgStep4FromListenEncoded {
+            padding: reader.get_padding()?.to_vec(),
+            l2c_signature: reader.get_l2c_signature()?.to_vec(),
+        })
+    }
+}
+
+impl MsgStep5FromConnectEncoded {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut message = message::Builder::new_default();
+        message
+            .init_root::<te::msg_step5_from_connect_encoded::Builder>()
+            .set_padding(&self.padding);
+        pack(&message)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> P2pProtocolResult<Self> {
+        let message = unpack(bytes)?;
+        let reader = message.get_root::<te::msg_step5_from_connect_encoded::Reader>()?;
+        Ok(MsgStep5FromConnectEncoded {
+            padding: reader.get_padding()?.to_vec(),
+        })
+    }
+}
+
+impl EncodedMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut message = message::Builder::new_default();
+        {
+            let mut builder = message.init_root::<te::encoded_message::Builder>();
+            builder.set_padding(&self.padding);
+            builder.set_content(&self.content);
+        }
+        pack(&message)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> P2pProtocolResult<Self> {
+        let message = unpack(bytes)?;
+        let reader = message.get_root::<te::encoded_message::Reader>()?;
+        Ok(EncodedMessage {
+            padding: reader.get_padding()?.to_vec(),
+            content: reader.get_content()?.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_round_trip_step1() {
+        let msg = MsgStep1FromConnect {
+            magic: TRANSIT_ENCODING_MAGIC,
+            encoding: Encoding::SodiumPacked,
+            network_id: b"test_network".to_vec(),
+            kx_pub_key: vec![1; 32],
+        };
+        assert_eq!(MsgStep1FromConnect::from_bytes(&msg.to_bytes()).unwrap(), msg);
+    }
+
+    #[test]
+    fn it_should_round_trip_step2_and_step3_unions() {
+        let halt = Halt {
+            reason_code: HaltReasonCode::BadNetworkId,
+            reason_text: "wrong network".to_string(),
+        };
+        let msg = MsgStep2FromListen::Halt(halt.clone());
+        assert_eq!(MsgStep2FromListen::from_bytes(&msg.to_bytes()).unwrap(), msg);
+        let msg = MsgStep2FromListen::Continue(MsgStep2Continue {
+            kx_pub_key: vec![2; 32],
+            kx_nonce: vec![3; 24],
+            kx_secret: vec![4; 100],
+        });
+        assert_eq!(MsgStep2FromListen::from_bytes(&msg.to_bytes()).unwrap(), msg);
+        let msg = MsgStep3FromConnect::Halt(halt);
+        assert_eq!(MsgStep3FromConnect::from_bytes(&msg.to_bytes()).unwrap(), msg);
+        let msg = MsgStep3FromConnect::Continue(MsgStep3Continue {
+            kx_nonce: vec![3; 24],
+            kx_secret: vec![5; 150],
+        });
+        assert_eq!(MsgStep3FromConnect::from_bytes(&msg.to_bytes()).unwrap(), msg);
+    }
+
+    #[test]
+    fn it_should_round_trip_encoded_messages() {
+        let msg = MsgStep3FromConnectKxEncoded {
+            padding: vec![],
+            sig_pub_key: vec![6; 32],
+            c2l_session_key: vec![7; 32],
+            c2l_signature: vec![8; 64],
+        };
+        assert_eq!(
+            MsgStep3FromConnectKxEncoded::from_bytes(&msg.to_bytes()).unwrap(),
+            msg
+        );
+        let msg = EncodedMessage {
+            padding: vec![0; 4],
+            content: b"hello".to_vec(),
+        };
+        assert_eq!(EncodedMessage::from_bytes(&msg.to_bytes()).unwrap(), msg);
+    }
+}
@@ -190,6 +190,7 @@ pub fn dump_lib3h_protocol_as_json_for_n3h() {
     test_server(Lib3hServerProtocol::Connected(ConnectedData {
         request_id: "rid".to_string(),
         uri: url::Url::parse("hc:id").unwrap(),
+        network_id: "nid".to_string(),
     }));
 
     test_server(Lib3hServerProtocol::Disconnected(DisconnectedData {